@@ -0,0 +1,610 @@
+//! JiLang内置模块的过程宏
+//!
+//! 在模块类型的impl块上使用 `#[jilang_module]`，并用 `#[jilang_fn]` 标记要导出的函数：
+//!
+//! ```ignore
+//! /// 文本处理
+//! #[jilang_module(name = "text")]
+//! impl TextModule {
+//!     /// 把字符串重复若干次
+//!     ///
+//!     /// # 参数
+//!     /// - text: 要重复的字符串
+//!     /// - times: 重复次数，默认为2
+//!     ///
+//!     /// # 示例
+//!     /// {"text.repeat": ["ab", 3]}
+//!     #[jilang_fn]
+//!     fn repeat(text: String, times: Option<usize>) -> String {
+//!         text.repeat(times.unwrap_or(2))
+//!     }
+//! }
+//! ```
+//!
+//! 宏为类型实现 `Module`（名称、函数列表和 `as_any`）以及 `sdk::ModuleInfo`（模块元数据）。
+//! 导出函数有两种写法：
+//!
+//! - 有类型的参数：参数通过 `sdk::FromArg` 从JSON值转换，转换失败时输出带参数名的错误并返回null；
+//!   返回值通过 `sdk::IntoValue` 转换，返回 `Result` 时 `Err` 同样作为错误输出。
+//!   类型为 `&mut Context` 或 `&Context` 的参数传入当前上下文，不占用JiLang参数的位置；
+//! - 原始签名 `fn(args: &[Value], context: &mut Context) -> Value`：函数自己处理参数，
+//!   参数的元数据只来自文档注释。
+//!
+//! 文档注释的第一段是函数描述，`# 参数` 段中的 `- 名称 (类型, 可选): 描述` 描述参数（类型和可选标记
+//! 只在原始签名的函数中需要），`# 返回` 段描述返回值，`# 示例` 段是示例代码。
+//! `#[jilang_module]` 的参数：`name`（默认为类型名去掉Module后的小写形式）、`version`（默认为
+//! 包版本）、`author` 和 `description`（默认为impl块上的文档注释）。
+//! `#[jilang_fn(name = "...")]` 可以使用和Rust函数名不同的导出名。
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Expr, FnArg, ImplItem, ImplItemFn, ItemImpl, Lit, LitStr, Meta, Pat, ReturnType, Type};
+
+/// 为impl块生成 `Module` 和 `sdk::ModuleInfo` 的实现
+#[proc_macro_attribute]
+pub fn jilang_module(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = ModuleArgs::default();
+    let parser = syn::meta::parser(|meta| {
+        let value = || -> syn::Result<String> { Ok(meta.value()?.parse::<LitStr>()?.value()) };
+        if meta.path.is_ident("name") {
+            args.name = Some(value()?);
+        } else if meta.path.is_ident("version") {
+            args.version = Some(value()?);
+        } else if meta.path.is_ident("author") {
+            args.author = Some(value()?);
+        } else if meta.path.is_ident("description") {
+            args.description = Some(value()?);
+        } else {
+            return Err(meta.error("未知的参数，可用参数: name, version, author, description"));
+        }
+        Ok(())
+    });
+    parse_macro_input!(attr with parser);
+    let item = parse_macro_input!(item as ItemImpl);
+
+    match expand_module(args, item) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// 标记 `#[jilang_module]` impl块中导出的函数，由 `#[jilang_module]` 读取
+#[proc_macro_attribute]
+pub fn jilang_fn(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    item
+}
+
+#[derive(Default)]
+struct ModuleArgs {
+    name: Option<String>,
+    version: Option<String>,
+    author: Option<String>,
+    description: Option<String>,
+}
+
+fn expand_module(args: ModuleArgs, item: ItemImpl) -> syn::Result<TokenStream2> {
+    if let Some((_, path, _)) = &item.trait_ {
+        return Err(syn::Error::new_spanned(path, "#[jilang_module] 只能用在类型自身的impl块上"));
+    }
+    let self_ty = &item.self_ty;
+    let module_name = match args.name {
+        Some(name) => name,
+        None => default_module_name(self_ty)?,
+    };
+    let version = match args.version {
+        Some(version) => quote!(#version),
+        None => quote!(env!("CARGO_PKG_VERSION")),
+    };
+    let author = args.author.unwrap_or_else(|| "JiLang".to_string());
+    let description = args.description.unwrap_or_else(|| Doc::parse(&item.attrs).description);
+
+    let mut entries = Vec::new();
+    let mut metadata = Vec::new();
+    for impl_item in &item.items {
+        let ImplItem::Fn(function) = impl_item else { continue };
+        let Some(export_name) = export_name(function)? else { continue };
+        let exported = ExportedFn::new(function, export_name)?;
+        entries.push(exported.entry(&module_name));
+        metadata.push(exported.metadata());
+    }
+
+    Ok(quote! {
+        #item
+
+        impl crate::modules::Module for #self_ty {
+            fn get_name(&self) -> &'static str {
+                #module_name
+            }
+
+            fn get_functions(&self) -> Vec<(&'static str, Box<dyn Fn(&[serde_json::Value], &mut crate::interpreter::context::Context) -> serde_json::Value + Send + Sync + 'static>)> {
+                vec![#(#entries),*]
+            }
+
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+        }
+
+        impl crate::modules::sdk::ModuleInfo for #self_ty {
+            fn module_metadata() -> crate::modules::external_module::ModuleMetadata {
+                let mut functions = std::collections::HashMap::new();
+                #(#metadata)*
+                crate::modules::external_module::ModuleMetadata {
+                    name: #module_name.to_string(),
+                    version: #version.to_string(),
+                    description: #description.to_string(),
+                    author: #author.to_string(),
+                    functions,
+                }
+            }
+        }
+    })
+}
+
+// IoModule -> io
+fn default_module_name(self_ty: &Type) -> syn::Result<String> {
+    let Type::Path(path) = self_ty else {
+        return Err(syn::Error::new_spanned(self_ty, "无法从类型推断模块名，请使用 #[jilang_module(name = \"...\")]"));
+    };
+    let ident = path.path.segments.last().unwrap().ident.to_string();
+    Ok(ident.strip_suffix("Module").filter(|s| !s.is_empty()).unwrap_or(&ident).to_lowercase())
+}
+
+// 带有 #[jilang_fn] 的函数返回导出名
+fn export_name(function: &ImplItemFn) -> syn::Result<Option<String>> {
+    let Some(attr) = function.attrs.iter().find(|attr| attr.path().is_ident("jilang_fn")) else {
+        return Ok(None);
+    };
+    let mut name = function.sig.ident.to_string();
+    if let Meta::List(_) = attr.meta {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("未知的参数，可用参数: name"))
+            }
+        })?;
+    }
+    Ok(Some(name))
+}
+
+enum Param {
+    // 当前上下文，mutable表示 &mut Context
+    Context { mutable: bool },
+    Value { ident: syn::Ident, ty: Box<Type> },
+}
+
+struct ExportedFn {
+    ident: syn::Ident,
+    name: String,
+    // None表示原始签名 fn(&[Value], &mut Context) -> Value
+    params: Option<Vec<Param>>,
+    output: Option<Type>,
+    doc: Doc,
+}
+
+impl ExportedFn {
+    fn new(function: &ImplItemFn, name: String) -> syn::Result<Self> {
+        let sig = &function.sig;
+        let mut inputs = Vec::new();
+        for input in &sig.inputs {
+            match input {
+                FnArg::Receiver(receiver) => {
+                    return Err(syn::Error::new_spanned(receiver, "#[jilang_fn] 函数不能有self参数"));
+                }
+                FnArg::Typed(typed) => inputs.push(typed),
+            }
+        }
+
+        let raw = inputs.len() == 2 && is_value_slice(&inputs[0].ty) && context_kind(&inputs[1].ty) == Some(true);
+        let params = if raw {
+            None
+        } else {
+            let mut params = Vec::new();
+            for input in inputs {
+                if let Some(mutable) = context_kind(&input.ty) {
+                    params.push(Param::Context { mutable });
+                    continue;
+                }
+                let Pat::Ident(pat) = &*input.pat else {
+                    return Err(syn::Error::new_spanned(&input.pat, "#[jilang_fn] 函数的参数必须是简单的名称"));
+                };
+                params.push(Param::Value { ident: pat.ident.clone(), ty: input.ty.clone() });
+            }
+            Some(params)
+        };
+        let output = match &sig.output {
+            ReturnType::Default => None,
+            ReturnType::Type(_, ty) => Some((**ty).clone()),
+        };
+
+        Ok(Self { ident: sig.ident.clone(), name, params, output, doc: Doc::parse(&function.attrs) })
+    }
+
+    // get_functions中的一项
+    fn entry(&self, module_name: &str) -> TokenStream2 {
+        let (ident, name) = (&self.ident, &self.name);
+        let Some(params) = &self.params else {
+            return quote! {
+                (#name, Box::new(Self::#ident) as Box<dyn Fn(&[serde_json::Value], &mut crate::interpreter::context::Context) -> serde_json::Value + Send + Sync + 'static>)
+            };
+        };
+
+        let count = params.iter().filter(|param| matches!(param, Param::Value { .. })).count();
+        let mut conversions = Vec::new();
+        let mut call_args = Vec::new();
+        let mut index = 0usize;
+        for param in params {
+            match param {
+                Param::Context { mutable: true } => call_args.push(quote!(&mut *context)),
+                Param::Context { mutable: false } => call_args.push(quote!(&*context)),
+                Param::Value { ident, ty } => {
+                    let param_name = ident.to_string();
+                    conversions.push(quote! {
+                        let #ident: #ty = crate::modules::sdk::arg(args, #index, #param_name, context)?;
+                    });
+                    call_args.push(quote!(#ident));
+                    index += 1;
+                }
+            }
+        }
+        let call = quote!(Self::#ident(#(#call_args),*));
+        let convert = match &self.output {
+            None => quote! { #call; Ok(serde_json::Value::Null) },
+            Some(ty) if result_inner(ty).is_some() => quote! {
+                #call.map(crate::modules::sdk::IntoValue::into_value).map_err(|e| e.to_string())
+            },
+            Some(_) => quote! { Ok(crate::modules::sdk::IntoValue::into_value(#call)) },
+        };
+
+        quote! {
+            (#name, Box::new(|args: &[serde_json::Value], context: &mut crate::interpreter::context::Context| -> serde_json::Value {
+                let result = (|| -> std::result::Result<serde_json::Value, String> {
+                    if args.len() > #count {
+                        return Err(format!("最多接受 {} 个参数，实际为 {} 个", #count, args.len()));
+                    }
+                    #(#conversions)*
+                    #convert
+                })();
+                crate::modules::sdk::report(#module_name, #name, result)
+            }) as Box<dyn Fn(&[serde_json::Value], &mut crate::interpreter::context::Context) -> serde_json::Value + Send + Sync + 'static>)
+        }
+    }
+
+    // 插入函数元数据的语句
+    fn metadata(&self) -> TokenStream2 {
+        let name = &self.name;
+        let description = &self.doc.description;
+        let example = &self.doc.example;
+
+        let parameters: Vec<TokenStream2> = match &self.params {
+            // 原始签名的函数只有文档中的参数
+            None => self.doc.params.iter().map(|param| {
+                let (type_name, optional) = (param.type_name.as_deref().unwrap_or("Any"), param.optional);
+                parameter_tokens(&param.name, &param.description, quote!(#type_name), quote!(#optional))
+            }).collect(),
+            Some(params) => params.iter().filter_map(|param| {
+                let Param::Value { ident, ty } = param else { return None };
+                let param_name = ident.to_string();
+                let doc = self.doc.params.iter().find(|p| p.name == param_name);
+                let description = doc.map(|p| p.description.clone()).unwrap_or_default();
+                let type_name = match doc.and_then(|p| p.type_name.as_deref()) {
+                    Some(type_name) => quote!(#type_name),
+                    None => quote!(<#ty as crate::modules::sdk::FromArg>::TYPE_NAME),
+                };
+                Some(parameter_tokens(&param_name, &description, type_name, quote!(<#ty as crate::modules::sdk::FromArg>::OPTIONAL)))
+            }).collect(),
+        };
+
+        // 返回值：有文档时为 "类型（描述）"
+        let returns = &self.doc.returns;
+        let type_name = match (&self.params, &self.output) {
+            (None, _) => None,
+            (Some(_), None) => Some(quote!("Null")),
+            (Some(_), Some(ty)) => {
+                let ty = result_inner(ty).unwrap_or(ty);
+                Some(quote!(<#ty as crate::modules::sdk::IntoValue>::TYPE_NAME))
+            }
+        };
+        let return_type = match type_name {
+            None if returns.is_empty() => quote!("Any".to_string()),
+            None => quote!(#returns.to_string()),
+            Some(type_name) if returns.is_empty() => quote!(#type_name.to_string()),
+            Some(type_name) => quote!(format!("{}（{}）", #type_name, #returns)),
+        };
+
+        quote! {
+            functions.insert(#name.to_string(), crate::modules::external_module::FunctionMetadata {
+                name: #name.to_string(),
+                description: #description.to_string(),
+                parameters: vec![#(#parameters),*],
+                return_type: #return_type,
+                example: #example.to_string(),
+            });
+        }
+    }
+}
+
+fn parameter_tokens(name: &str, description: &str, type_name: TokenStream2, optional: TokenStream2) -> TokenStream2 {
+    quote! {
+        crate::modules::external_module::ParameterMetadata {
+            name: #name.to_string(),
+            description: #description.to_string(),
+            type_description: #type_name.to_string(),
+            optional: #optional,
+            default_value: None,
+        }
+    }
+}
+
+// &[Value]
+fn is_value_slice(ty: &Type) -> bool {
+    let Type::Reference(reference) = ty else { return false };
+    let Type::Slice(slice) = &*reference.elem else { return false };
+    last_segment(&slice.elem).is_some_and(|segment| segment.ident == "Value")
+}
+
+// &mut Context 返回Some(true)，&Context 返回Some(false)
+fn context_kind(ty: &Type) -> Option<bool> {
+    let Type::Reference(reference) = ty else { return None };
+    last_segment(&reference.elem).filter(|segment| segment.ident == "Context")?;
+    Some(reference.mutability.is_some())
+}
+
+// Result<T, E> 中的T
+fn result_inner(ty: &Type) -> Option<&Type> {
+    let segment = last_segment(ty).filter(|segment| segment.ident == "Result")?;
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else { return None };
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+fn last_segment(ty: &Type) -> Option<&syn::PathSegment> {
+    match ty {
+        Type::Path(path) => path.path.segments.last(),
+        _ => None,
+    }
+}
+
+/// 从文档注释中解析出的函数说明
+#[derive(Default)]
+struct Doc {
+    description: String,
+    params: Vec<DocParam>,
+    returns: String,
+    example: String,
+}
+
+struct DocParam {
+    name: String,
+    type_name: Option<String>,
+    optional: bool,
+    description: String,
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Description,
+    Params,
+    Returns,
+    Example,
+    Other,
+}
+
+impl Doc {
+    fn parse(attrs: &[Attribute]) -> Self {
+        let mut doc = Doc::default();
+        let mut description = Vec::new();
+        let mut returns = Vec::new();
+        let mut example = Vec::new();
+        let mut section = Section::Description;
+
+        for line in doc_lines(attrs) {
+            let trimmed = line.trim();
+            if let Some(title) = trimmed.strip_prefix('#') {
+                section = match title.trim_start_matches('#').trim() {
+                    "参数" | "Arguments" | "Parameters" => Section::Params,
+                    "返回" | "返回值" | "Returns" => Section::Returns,
+                    "示例" | "例子" | "Example" | "Examples" => Section::Example,
+                    _ => Section::Other,
+                };
+                continue;
+            }
+            match section {
+                Section::Description => description.push(trimmed.to_string()),
+                Section::Params => {
+                    if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+                        doc.params.extend(DocParam::parse(item));
+                    } else if let (Some(param), false) = (doc.params.last_mut(), trimmed.is_empty()) {
+                        // 缩进的续行
+                        param.description = format!("{} {}", param.description, trimmed).trim().to_string();
+                    }
+                }
+                Section::Returns => returns.push(trimmed.to_string()),
+                // 代码块的围栏不属于示例，示例保留原来的缩进
+                Section::Example if trimmed.starts_with("```") => {}
+                Section::Example => example.push(line),
+                Section::Other => {}
+            }
+        }
+
+        doc.description = description.join("\n").trim().to_string();
+        doc.returns = returns.join(" ").trim().to_string();
+        doc.example = example.join("\n").trim_matches('\n').to_string();
+        doc
+    }
+}
+
+impl DocParam {
+    // `名称 (类型, 可选): 描述`，类型和可选标记都可以省略
+    fn parse(item: &str) -> Option<Self> {
+        let (head, description) = match item.split_once([':', '：']) {
+            Some((head, description)) => (head, description.trim()),
+            None => (item, ""),
+        };
+        let (name, spec) = match head.split_once(['(', '（']) {
+            Some((name, spec)) => (name.trim(), spec.trim_end().trim_end_matches([')', '）'])),
+            None => (head.trim(), ""),
+        };
+        let name = name.trim_matches('`');
+        if name.is_empty() {
+            return None;
+        }
+        let mut type_name = None;
+        let mut optional = false;
+        for part in spec.split([',', '，']).map(str::trim).filter(|part| !part.is_empty()) {
+            match part {
+                "可选" | "optional" => optional = true,
+                _ => type_name = Some(part.to_string()),
+            }
+        }
+        Some(Self { name: name.to_string(), type_name, optional, description: description.to_string() })
+    }
+}
+
+fn doc_lines(attrs: &[Attribute]) -> Vec<String> {
+    attrs.iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(text) => Some(text.value()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .flat_map(|text| text.lines().map(|line| line.strip_prefix(' ').unwrap_or(line).to_string()).collect::<Vec<_>>())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn doc_param(item: &str) -> (String, Option<String>, bool, String) {
+        let param = DocParam::parse(item).unwrap();
+        (param.name, param.type_name, param.optional, param.description)
+    }
+
+    #[test]
+    fn doc_sections() {
+        let function: ImplItemFn = parse_quote! {
+            /// 第一行
+            /// 第二行
+            ///
+            /// # 参数
+            /// - a: 第一个参数
+            ///   续行
+            /// * b (Number): 第二个参数
+            ///
+            /// ## Returns
+            /// 两个数的和
+            ///
+            /// # 注意
+            /// 不属于任何字段
+            ///
+            /// # 示例
+            /// ```json
+            /// {"m.add": [
+            ///     1, 2
+            /// ]}
+            /// ```
+            fn add() {}
+        };
+        let doc = Doc::parse(&function.attrs);
+        assert_eq!(doc.description, "第一行\n第二行");
+        assert_eq!(doc.returns, "两个数的和");
+        assert_eq!(doc.example, "{\"m.add\": [\n    1, 2\n]}");
+        let params: Vec<_> = doc.params.iter().map(|p| (p.name.as_str(), p.type_name.as_deref(), p.description.as_str())).collect();
+        assert_eq!(params, [("a", None, "第一个参数 续行"), ("b", Some("Number"), "第二个参数")]);
+    }
+
+    #[test]
+    fn doc_param_forms() {
+        assert_eq!(doc_param("x"), ("x".to_string(), None, false, String::new()));
+        assert_eq!(doc_param("`x`: 描述"), ("x".to_string(), None, false, "描述".to_string()));
+        assert_eq!(doc_param("x (String, 可选): 描述"), ("x".to_string(), Some("String".to_string()), true, "描述".to_string()));
+        assert_eq!(doc_param("x（可选，Array）：描述"), ("x".to_string(), Some("Array".to_string()), true, "描述".to_string()));
+        assert_eq!(doc_param("x (optional)"), ("x".to_string(), None, true, String::new()));
+        assert!(DocParam::parse(": 没有名称").is_none());
+    }
+
+    #[test]
+    fn module_name_from_type() {
+        assert_eq!(default_module_name(&parse_quote!(IoModule)).unwrap(), "io");
+        assert_eq!(default_module_name(&parse_quote!(crate::Text)).unwrap(), "text");
+        assert_eq!(default_module_name(&parse_quote!(Module)).unwrap(), "module");
+        assert!(default_module_name(&parse_quote!([u8; 2])).is_err());
+    }
+
+    #[test]
+    fn signature_types() {
+        assert!(is_value_slice(&parse_quote!(&[serde_json::Value])));
+        assert!(!is_value_slice(&parse_quote!(Vec<Value>)));
+        assert_eq!(context_kind(&parse_quote!(&mut Context)), Some(true));
+        assert_eq!(context_kind(&parse_quote!(&crate::interpreter::context::Context)), Some(false));
+        assert_eq!(context_kind(&parse_quote!(Context)), None);
+        let ty: Type = parse_quote!(Result<Vec<i64>, String>);
+        assert_eq!(result_inner(&ty).map(|ty| quote!(#ty).to_string()).as_deref(), Some("Vec < i64 >"));
+        assert!(result_inner(&parse_quote!(Option<i64>)).is_none());
+    }
+
+    #[test]
+    fn exported_function_parameters() {
+        let function: ImplItemFn = parse_quote! {
+            #[jilang_fn(name = "other")]
+            fn f(context: &Context, a: String, b: Option<i64>) -> Result<String, String> {}
+        };
+        let name = export_name(&function).unwrap().unwrap();
+        assert_eq!(name, "other");
+        let exported = ExportedFn::new(&function, name).unwrap();
+        let params = exported.params.unwrap();
+        assert!(matches!(params[0], Param::Context { mutable: false }));
+        assert!(matches!(&params[2], Param::Value { ident, .. } if ident == "b"));
+
+        // 原始签名不按参数转换
+        let function: ImplItemFn = parse_quote! {
+            #[jilang_fn]
+            fn raw(args: &[Value], context: &mut Context) -> Value {}
+        };
+        assert_eq!(export_name(&function).unwrap().as_deref(), Some("raw"));
+        assert!(ExportedFn::new(&function, "raw".to_string()).unwrap().params.is_none());
+
+        let function: ImplItemFn = parse_quote!(fn helper() {});
+        assert!(export_name(&function).unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_functions_are_rejected() {
+        let function: ImplItemFn = parse_quote!(#[jilang_fn(alias = "x")] fn f() {});
+        assert!(export_name(&function).unwrap_err().to_string().contains("可用参数: name"));
+        let function: ImplItemFn = parse_quote!(fn f(&self) {});
+        assert!(ExportedFn::new(&function, "f".to_string()).is_err());
+        let function: ImplItemFn = parse_quote!(fn f((a, b): (i64, i64)) {});
+        assert!(ExportedFn::new(&function, "f".to_string()).is_err());
+        let item: ItemImpl = parse_quote!(impl Module for IoModule {});
+        assert!(expand_module(ModuleArgs::default(), item).is_err());
+    }
+
+    #[test]
+    fn metadata_uses_documented_types_first() {
+        let function: ImplItemFn = parse_quote! {
+            /// 描述
+            ///
+            /// # 参数
+            /// - a (Number): 数字
+            #[jilang_fn]
+            fn f(a: Value, b: i64) {}
+        };
+        let tokens = ExportedFn::new(&function, "f".to_string()).unwrap().metadata().to_string();
+        assert!(tokens.contains("\"Number\" . to_string ()"), "{}", tokens);
+        assert!(tokens.contains("< i64 as crate :: modules :: sdk :: FromArg > :: TYPE_NAME"), "{}", tokens);
+        assert!(tokens.contains("return_type : \"Null\" . to_string ()"), "{}", tokens);
+    }
+}
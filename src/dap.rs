@@ -0,0 +1,646 @@
+// 调试适配器协议（Debug Adapter Protocol）服务器
+//
+// 通过标准输入输出与编辑器（如VS Code）通信，基于解释器的执行钩子实现
+// 断点、单步执行、调用栈和变量查看。
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufReader, Write};
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use serde_json::{json, Value};
+use crate::interpreter::Interpreter;
+use crate::interpreter::context::Context;
+use crate::interpreter::error::{InterpreterError, Result};
+use crate::interpreter::hooks::{ExecutionHook, Location};
+use crate::interpreter::source_map::SourceMap;
+use crate::interpreter::variable_reference::VariableReference;
+use crate::protocol::{read_message, write_message};
+
+// 调试会话只有一个线程
+const THREAD_ID: i64 = 1;
+
+// 固定的作用域引用编号，嵌套值的引用从DYNAMIC_REF_BASE开始分配
+const SCOPE_VARIABLES: i64 = 1;
+const SCOPE_CONSTANTS: i64 = 2;
+const SCOPE_MODULE_META: i64 = 3;
+const DYNAMIC_REF_BASE: i64 = 1000;
+
+/// 协议连接，负责按DAP格式发送消息
+#[derive(Clone)]
+struct Connection {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    seq: Arc<Mutex<i64>>,
+}
+
+impl Connection {
+    fn new(writer: Box<dyn Write + Send>) -> Self {
+        Connection {
+            writer: Arc::new(Mutex::new(writer)),
+            seq: Arc::new(Mutex::new(1)),
+        }
+    }
+
+    fn send(&self, mut message: Value) {
+        {
+            let mut seq = self.seq.lock().unwrap();
+            message["seq"] = json!(*seq);
+            *seq += 1;
+        }
+        let mut writer = self.writer.lock().unwrap();
+        let _ = write_message(&mut *writer, &message);
+    }
+
+    fn respond(&self, request: &Value, body: Value) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.get("seq").cloned().unwrap_or(Value::Null),
+            "success": true,
+            "command": request.get("command").cloned().unwrap_or(Value::Null),
+            "body": body,
+        }));
+    }
+
+    fn respond_error(&self, request: &Value, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.get("seq").cloned().unwrap_or(Value::Null),
+            "success": false,
+            "command": request.get("command").cloned().unwrap_or(Value::Null),
+            "message": message,
+        }));
+    }
+
+    fn event(&self, event: &str, body: Value) {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }));
+    }
+
+    fn output(&self, category: &str, text: &str) {
+        self.event("output", json!({ "category": category, "output": text }));
+    }
+}
+
+// 单步执行模式
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StepMode {
+    // 运行直到遇到断点
+    Continue,
+    // 在下一条语句处暂停
+    Pause(&'static str),
+    // 在调用栈深度不超过指定值的下一条语句处暂停
+    StepOver(usize),
+    // 在调用栈深度小于指定值的下一条语句处暂停
+    StepOut(usize),
+}
+
+// 处理请求后的控制结果
+enum Control {
+    // 继续处理下一条请求
+    Handled,
+    // 恢复程序执行
+    Resume,
+    // 终止调试会话
+    Terminate,
+}
+
+/// 调试会话状态，同时作为解释器的执行钩子
+struct DebugSession {
+    conn: Connection,
+    requests: Rc<mpsc::Receiver<Value>>,
+    breakpoints: HashMap<String, HashSet<usize>>,
+    source_maps: HashMap<String, SourceMap>,
+    canonical_paths: HashMap<String, String>,
+    step: StepMode,
+    // 暂停期间分配的嵌套变量引用
+    variable_refs: Vec<Value>,
+    // 每次进入函数时调用方的变量，函数结束后变量会恢复成这个样子
+    caller_variables: Vec<HashMap<String, Value>>,
+    launch_args: Option<Value>,
+    configured: bool,
+}
+
+impl DebugSession {
+    fn new(conn: Connection, requests: Rc<mpsc::Receiver<Value>>) -> Self {
+        DebugSession {
+            conn,
+            requests,
+            breakpoints: HashMap::new(),
+            source_maps: HashMap::new(),
+            canonical_paths: HashMap::new(),
+            step: StepMode::Continue,
+            variable_refs: Vec::new(),
+            caller_variables: Vec::new(),
+            launch_args: None,
+            configured: false,
+        }
+    }
+
+    // 统一源文件路径的写法，便于与编辑器传来的路径比较
+    fn canonical(&mut self, path: &str) -> String {
+        if let Some(canonical) = self.canonical_paths.get(path) {
+            return canonical.clone();
+        }
+        let canonical = std::fs::canonicalize(path)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.to_string());
+        self.canonical_paths.insert(path.to_string(), canonical.clone());
+        canonical
+    }
+
+    fn source_map(&mut self, source: &str) -> &SourceMap {
+        let key = self.canonical(source);
+        self.source_maps.entry(key.clone()).or_insert_with(|| {
+            let text = std::fs::read_to_string(&key).unwrap_or_default();
+            SourceMap::from_source(&text)
+        })
+    }
+
+    // 获取位置对应的源码行号
+    fn line_of(&mut self, location: &Location) -> Option<usize> {
+        let source = location.source.clone()?;
+        self.source_map(&source).position(&location.path).map(|p| p.line)
+    }
+
+    // 从内到外列出调用栈上每一帧的名称和当前位置
+    fn frames(context: &Context) -> Vec<(String, Location)> {
+        let locations = context.locations();
+        let mut frames = Vec::new();
+        let mut top = locations.len();
+        for frame in context.call_frames().iter().rev() {
+            if top > frame.location_depth {
+                frames.push((frame.name.clone(), locations[top - 1].clone()));
+            }
+            top = frame.location_depth;
+        }
+        if top > 0 {
+            frames.push(("main".to_string(), locations[top - 1].clone()));
+        }
+        frames
+    }
+
+    // 处理一条请求，context仅在程序运行后可用
+    fn handle_request(&mut self, request: &Value, context: Option<&Context>) -> Control {
+        let command = request.get("command").and_then(|c| c.as_str()).unwrap_or("");
+        let arguments = request.get("arguments").cloned().unwrap_or(Value::Null);
+
+        match command {
+            "initialize" => {
+                self.conn.respond(request, json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsEvaluateForHovers": true,
+                    "supportsTerminateRequest": true,
+                }));
+                self.conn.event("initialized", json!({}));
+            },
+            "launch" => {
+                if arguments.get("program").and_then(|p| p.as_str()).is_none() {
+                    self.conn.respond_error(request, "launch 请求缺少 'program' 参数");
+                } else {
+                    if arguments.get("stopOnEntry").and_then(|v| v.as_bool()).unwrap_or(false) {
+                        self.step = StepMode::Pause("entry");
+                    }
+                    self.launch_args = Some(arguments);
+                    self.conn.respond(request, json!({}));
+                }
+            },
+            "setBreakpoints" => {
+                let path = arguments.get("source")
+                    .and_then(|s| s.get("path"))
+                    .and_then(|p| p.as_str())
+                    .unwrap_or("")
+                    .to_string();
+                let key = self.canonical(&path);
+                let requested: Vec<usize> = arguments.get("breakpoints")
+                    .and_then(|b| b.as_array())
+                    .map(|arr| arr.iter()
+                        .filter_map(|bp| bp.get("line").and_then(|l| l.as_u64()))
+                        .map(|l| l as usize)
+                        .collect())
+                    .unwrap_or_default();
+
+                // 将断点移动到该行或其后的第一条语句上
+                let mut lines = HashSet::new();
+                let mut results = Vec::new();
+                for line in requested {
+                    match self.source_map(&key).statement_line_at_or_after(line) {
+                        Some(actual) => {
+                            lines.insert(actual);
+                            results.push(json!({ "verified": true, "line": actual }));
+                        },
+                        None => {
+                            results.push(json!({ "verified": false, "line": line, "message": "该行之后没有可执行的语句" }));
+                        },
+                    }
+                }
+                self.breakpoints.insert(key, lines);
+                self.conn.respond(request, json!({ "breakpoints": results }));
+            },
+            "setExceptionBreakpoints" => {
+                self.conn.respond(request, json!({}));
+            },
+            "configurationDone" => {
+                self.configured = true;
+                self.conn.respond(request, json!({}));
+            },
+            "threads" => {
+                self.conn.respond(request, json!({
+                    "threads": [{ "id": THREAD_ID, "name": "main" }]
+                }));
+            },
+            "stackTrace" => {
+                let frames = context.map(Self::frames).unwrap_or_default();
+                let mut stack_frames = Vec::new();
+                for (id, (name, location)) in frames.iter().enumerate() {
+                    let line = self.line_of(location).unwrap_or(0);
+                    let source = location.source.as_ref().map(|path| json!({
+                        "name": std::path::Path::new(path).file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_else(|| path.clone()),
+                        "path": path,
+                    }));
+                    stack_frames.push(json!({
+                        "id": id,
+                        "name": name,
+                        "source": source,
+                        "line": line,
+                        "column": 1,
+                    }));
+                }
+                self.conn.respond(request, json!({
+                    "stackFrames": stack_frames,
+                    "totalFrames": frames.len(),
+                }));
+            },
+            "scopes" => {
+                // 最内层的帧使用当前变量，外层的帧使用进入下一层函数时保存的变量
+                let frame_id = arguments.get("frameId").and_then(|f| f.as_u64()).unwrap_or(0) as usize;
+                let variables_ref = match self.caller_variables.len().checked_sub(frame_id) {
+                    Some(index) if frame_id > 0 => {
+                        let variables = self.caller_variables[index].clone().into_iter().collect();
+                        self.allocate_ref(Value::Object(variables))
+                    },
+                    _ => SCOPE_VARIABLES,
+                };
+                self.conn.respond(request, json!({
+                    "scopes": [
+                        { "name": "variables", "variablesReference": variables_ref, "expensive": false },
+                        { "name": "constants", "variablesReference": SCOPE_CONSTANTS, "expensive": false },
+                        { "name": "module_meta", "variablesReference": SCOPE_MODULE_META, "expensive": false },
+                    ]
+                }));
+            },
+            "variables" => {
+                let reference = arguments.get("variablesReference").and_then(|r| r.as_i64()).unwrap_or(0);
+                let entries: Vec<(String, Value)> = match (reference, context) {
+                    (SCOPE_VARIABLES, Some(ctx)) => sorted_entries(ctx.variables.iter()),
+                    (SCOPE_CONSTANTS, Some(ctx)) => sorted_entries(ctx.constants.iter()),
+                    (SCOPE_MODULE_META, Some(ctx)) => sorted_entries(ctx.module_meta.iter()),
+                    (r, _) if r >= DYNAMIC_REF_BASE => {
+                        match self.variable_refs.get((r - DYNAMIC_REF_BASE) as usize) {
+                            Some(Value::Object(obj)) => obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+                            Some(Value::Array(arr)) => arr.iter().enumerate().map(|(i, v)| (i.to_string(), v.clone())).collect(),
+                            _ => Vec::new(),
+                        }
+                    },
+                    _ => Vec::new(),
+                };
+                let variables: Vec<Value> = entries.into_iter()
+                    .map(|(name, value)| self.variable_json(&name, value))
+                    .collect();
+                self.conn.respond(request, json!({ "variables": variables }));
+            },
+            "evaluate" => {
+                let expression = arguments.get("expression").and_then(|e| e.as_str()).unwrap_or("");
+                let value = context.and_then(|ctx| {
+                    if VariableReference::is_reference(expression) {
+                        ctx.get_value(expression)
+                    } else {
+                        ctx.variables.get(expression).cloned()
+                    }
+                });
+                match value {
+                    Some(value) => {
+                        let variable = self.variable_json(expression, value);
+                        self.conn.respond(request, json!({
+                            "result": variable["value"],
+                            "type": variable["type"],
+                            "variablesReference": variable["variablesReference"],
+                        }));
+                    },
+                    None => self.conn.respond_error(request, &format!("无法求值 '{}'", expression)),
+                }
+            },
+            "continue" => {
+                self.step = StepMode::Continue;
+                self.conn.respond(request, json!({ "allThreadsContinued": true }));
+                return Control::Resume;
+            },
+            "next" => {
+                self.step = StepMode::StepOver(context.map(|c| c.call_frames().len()).unwrap_or(0));
+                self.conn.respond(request, json!({}));
+                return Control::Resume;
+            },
+            "stepIn" => {
+                self.step = StepMode::Pause("step");
+                self.conn.respond(request, json!({}));
+                return Control::Resume;
+            },
+            "stepOut" => {
+                self.step = StepMode::StepOut(context.map(|c| c.call_frames().len()).unwrap_or(0));
+                self.conn.respond(request, json!({}));
+                return Control::Resume;
+            },
+            "pause" => {
+                self.step = StepMode::Pause("pause");
+                self.conn.respond(request, json!({}));
+            },
+            "disconnect" | "terminate" => {
+                self.conn.respond(request, json!({}));
+                return Control::Terminate;
+            },
+            _ => {
+                self.conn.respond_error(request, &format!("不支持的请求: {}", command));
+            },
+        }
+        Control::Handled
+    }
+
+    // 将值转换为DAP的variable对象，对象和数组分配可展开的引用
+    fn variable_json(&mut self, name: &str, value: Value) -> Value {
+        let (display, type_name) = match &value {
+            Value::Null => ("null".to_string(), "null"),
+            Value::Bool(b) => (b.to_string(), "boolean"),
+            Value::Number(n) => (n.to_string(), "number"),
+            Value::String(s) => (Value::String(s.clone()).to_string(), "string"),
+            Value::Array(arr) => (format!("<array>[{}]", arr.len()), "array"),
+            Value::Object(obj) => (format!("<object>{{{} keys}}", obj.len()), "object"),
+        };
+        let reference = match &value {
+            Value::Array(arr) if !arr.is_empty() => self.allocate_ref(value.clone()),
+            Value::Object(obj) if !obj.is_empty() => self.allocate_ref(value.clone()),
+            _ => 0,
+        };
+        json!({
+            "name": name,
+            "value": display,
+            "type": type_name,
+            "variablesReference": reference,
+        })
+    }
+
+    fn allocate_ref(&mut self, value: Value) -> i64 {
+        self.variable_refs.push(value);
+        DYNAMIC_REF_BASE + self.variable_refs.len() as i64 - 1
+    }
+
+    // 判断当前语句是否需要暂停，返回暂停原因
+    fn stop_reason(&mut self, context: &Context) -> Option<&'static str> {
+        let depth = context.call_frames().len();
+        match self.step {
+            StepMode::Pause(reason) => return Some(reason),
+            StepMode::StepOver(max_depth) if depth <= max_depth => return Some("step"),
+            StepMode::StepOut(from_depth) if depth < from_depth => return Some("step"),
+            _ => {}
+        }
+
+        let location = context.current_location()?.clone();
+        let source = self.canonical(location.source.as_deref()?);
+        let has_breakpoints = self.breakpoints.get(&source).map(|lines| !lines.is_empty()).unwrap_or(false);
+        if !has_breakpoints {
+            return None;
+        }
+        let line = self.line_of(&location)?;
+        if self.breakpoints.get(&source).map(|lines| lines.contains(&line)).unwrap_or(false) {
+            Some("breakpoint")
+        } else {
+            None
+        }
+    }
+
+    // 暂停执行并处理请求，直到收到恢复或终止的请求
+    fn pause(&mut self, reason: &str, context: &Context) -> Result<()> {
+        self.variable_refs.clear();
+        let _ = io::stdout().flush();
+        self.conn.event("stopped", json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        }));
+
+        let requests = Rc::clone(&self.requests);
+        loop {
+            let request = requests.recv().map_err(|_| session_terminated())?;
+            match self.handle_request(&request, Some(context)) {
+                Control::Handled => {},
+                Control::Resume => return Ok(()),
+                Control::Terminate => return Err(session_terminated()),
+            }
+        }
+    }
+}
+
+impl ExecutionHook for DebugSession {
+    fn before_statement(&mut self, _stmt_type: &str, context: &Context) -> Result<()> {
+        // 程序运行期间也要及时响应设置断点、暂停等请求
+        let requests = Rc::clone(&self.requests);
+        while let Ok(request) = requests.try_recv() {
+            if let Control::Terminate = self.handle_request(&request, Some(context)) {
+                return Err(session_terminated());
+            }
+        }
+
+        if let Some(reason) = self.stop_reason(context) {
+            self.step = StepMode::Continue;
+            self.pause(reason, context)?;
+        }
+        Ok(())
+    }
+
+    fn enter_function(&mut self, _name: &str, context: &Context) {
+        self.caller_variables.push(context.variables.clone());
+    }
+
+    fn leave_function(&mut self, _name: &str, _context: &Context) {
+        self.caller_variables.pop();
+    }
+}
+
+fn session_terminated() -> InterpreterError {
+    InterpreterError::RuntimeError("调试会话已终止".to_string())
+}
+
+fn sorted_entries<'a>(entries: impl Iterator<Item = (&'a String, &'a Value)>) -> Vec<(String, Value)> {
+    let mut result: Vec<(String, Value)> = entries.map(|(k, v)| (k.clone(), v.clone())).collect();
+    result.sort_by(|a, b| a.0.cmp(&b.0));
+    result
+}
+
+/// 将进程的标准输出重定向到管道，程序输出以output事件转发给编辑器
+///
+/// 返回用于发送协议消息的原始标准输出。
+#[cfg(unix)]
+mod stdout_redirect {
+    use std::fs::File;
+    use std::io::{self, Read, Write};
+    use std::os::unix::io::FromRawFd;
+    use std::thread::JoinHandle;
+
+    pub struct Redirect {
+        forwarder: Option<JoinHandle<()>>,
+    }
+
+    pub fn redirect(on_output: impl Fn(&str) + Send + 'static) -> io::Result<(Box<dyn Write + Send>, Redirect)> {
+        unsafe {
+            let protocol_fd = libc::dup(libc::STDOUT_FILENO);
+            if protocol_fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut fds = [0; 2];
+            if libc::pipe(fds.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::dup2(fds[1], libc::STDOUT_FILENO) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            libc::close(fds[1]);
+
+            let mut reader = File::from_raw_fd(fds[0]);
+            let forwarder = std::thread::spawn(move || {
+                let mut buffer = [0u8; 4096];
+                while let Ok(n) = reader.read(&mut buffer) {
+                    if n == 0 {
+                        break;
+                    }
+                    on_output(&String::from_utf8_lossy(&buffer[..n]));
+                }
+            });
+
+            Ok((Box::new(File::from_raw_fd(protocol_fd)), Redirect { forwarder: Some(forwarder) }))
+        }
+    }
+
+    impl Redirect {
+        // 关闭管道写端并等待剩余输出转发完毕
+        pub fn finish(&mut self) {
+            let _ = io::stdout().flush();
+            unsafe {
+                let null = libc::open(c"/dev/null".as_ptr(), libc::O_WRONLY);
+                if null >= 0 {
+                    libc::dup2(null, libc::STDOUT_FILENO);
+                    libc::close(null);
+                }
+            }
+            if let Some(forwarder) = self.forwarder.take() {
+                let _ = forwarder.join();
+            }
+        }
+    }
+}
+
+/// 非Unix平台不重定向标准输出，程序输出需避免写入标准输出
+#[cfg(not(unix))]
+mod stdout_redirect {
+    use std::io::{self, Write};
+
+    pub struct Redirect;
+
+    pub fn redirect(_on_output: impl Fn(&str) + Send + 'static) -> io::Result<(Box<dyn Write + Send>, Redirect)> {
+        Ok((Box::new(io::stdout()), Redirect))
+    }
+
+    impl Redirect {
+        pub fn finish(&mut self) {}
+    }
+}
+
+/// 启动DAP服务器，在标准输入输出上处理一次调试会话
+pub fn run_dap_server(extra_module_paths: Vec<String>) {
+    // 先创建连接占位，重定向后再替换为真实的协议输出
+    let output_conn: Arc<Mutex<Option<Connection>>> = Arc::new(Mutex::new(None));
+    let forward_conn = Arc::clone(&output_conn);
+    let (protocol_out, mut redirect) = match stdout_redirect::redirect(move |text| {
+        if let Some(conn) = forward_conn.lock().unwrap().as_ref() {
+            conn.output("stdout", text);
+        }
+    }) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("错误: 无法重定向标准输出: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let conn = Connection::new(protocol_out);
+    *output_conn.lock().unwrap() = Some(conn.clone());
+
+    // 读取线程：把所有请求放入通道，由解释器线程处理
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut reader = BufReader::new(io::stdin());
+        while let Some(message) = read_message(&mut reader) {
+            if message.get("type").and_then(|t| t.as_str()) == Some("request") && sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    let requests = Rc::new(receiver);
+    let mut session = DebugSession::new(conn.clone(), Rc::clone(&requests));
+
+    // 配置阶段：等待launch和configurationDone
+    while session.launch_args.is_none() || !session.configured {
+        let request = match requests.recv() {
+            Ok(request) => request,
+            Err(_) => return,
+        };
+        if let Control::Terminate = session.handle_request(&request, None) {
+            return;
+        }
+    }
+
+    let launch_args = session.launch_args.clone().unwrap_or(Value::Null);
+    let program_path = launch_args.get("program").and_then(|p| p.as_str()).unwrap_or("").to_string();
+    let mut module_paths = extra_module_paths;
+    if let Some(paths) = launch_args.get("modulePaths").and_then(|p| p.as_array()) {
+        module_paths.extend(paths.iter().filter_map(|p| p.as_str()).map(|p| p.to_string()));
+    }
+
+    let exit_code = match crate::load_program(&program_path, module_paths) {
+        Ok(loaded) => match Interpreter::new(loaded.program, loaded.modules) {
+            Ok(mut interpreter) => {
+                interpreter.set_source_path(&loaded.path);
+                interpreter.add_hook(Box::new(session));
+                match interpreter.run() {
+                    Ok(()) => 0,
+                    Err(e) => {
+                        conn.output("stderr", &format!("错误: {}\n", e));
+                        1
+                    }
+                }
+            },
+            Err(e) => {
+                conn.output("stderr", &format!("初始化错误: {}\n", e));
+                1
+            }
+        },
+        Err(e) => {
+            conn.output("stderr", &format!("{}\n", e));
+            1
+        }
+    };
+
+    redirect.finish();
+    conn.event("exited", json!({ "exitCode": exit_code }));
+    conn.event("terminated", json!({}));
+
+    // 等待编辑器断开连接
+    while let Ok(request) = requests.recv() {
+        let command = request.get("command").and_then(|c| c.as_str()).unwrap_or("");
+        if command == "disconnect" || command == "terminate" {
+            conn.respond(&request, json!({}));
+            break;
+        }
+        conn.respond_error(&request, "程序已结束");
+    }
+}
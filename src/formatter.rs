@@ -0,0 +1,503 @@
+// JiLang源码格式化器
+//
+// 解析时保留 `//`、`/* */` 注释和空行，按统一布局重新输出：
+// 两空格缩进，较短的语句对象保持在一行，顶层键按固定顺序排列。
+// JSON5写法（单引号字符串、未加引号的键、十六进制数字等）会被改写为标准JSON，尾随逗号会被去掉。
+use std::fmt::Write as _;
+
+// 单行输出的最大宽度（包括缩进）
+const MAX_WIDTH: usize = 100;
+
+const INDENT: &str = "  ";
+
+// 顶层键的规范顺序，其余键保持原有顺序排在后面
+const ROOT_KEY_ORDER: &[&str] = &["module_meta", "include", "const", "program", "tests"];
+
+// 语句块字段，这些数组总是展开为多行
+const BLOCK_FIELDS: &[&str] = &["body", "then", "else", "do", "default", "try", "catch", "finally", "cases"];
+
+/// 保留注释的语法树节点
+#[derive(Debug)]
+enum Node {
+    Object(Vec<Entry>, Vec<String>),
+    Array(Vec<Entry>, Vec<String>),
+    // 数字、字符串、true/false/null的规范文本
+    Scalar(String),
+}
+
+/// 对象成员或数组元素
+#[derive(Debug)]
+struct Entry {
+    // 对象键的规范文本（包括双引号），数组元素为None
+    key: Option<String>,
+    value: Node,
+    // 位于前面的独占一行的注释
+    leading: Vec<String>,
+    // 同一行末尾的注释
+    trailing: Option<String>,
+    // 与上一个成员之间是否有空行
+    blank_before: bool,
+}
+
+// 两个记号之间的注释和空行
+struct Trivia {
+    // (注释文本, 注释前是否换行)
+    comments: Vec<(String, bool)>,
+    blank_line: bool,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> String {
+        format!("第 {} 行第 {} 列: {}", self.line, self.column, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.get(self.pos).copied()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_trivia(&mut self) -> Trivia {
+        let mut trivia = Trivia { comments: Vec::new(), blank_line: false };
+        let mut newlines = 0;
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                newlines += 1;
+                if newlines >= 2 {
+                    trivia.blank_line = true;
+                }
+                self.bump();
+            } else if c.is_whitespace() {
+                self.bump();
+            } else if c == '/' && self.chars.get(self.pos + 1) == Some(&'*') {
+                let mut comment = String::new();
+                while let Some(c) = self.bump() {
+                    comment.push(c);
+                    if comment.len() > 3 && comment.ends_with("*/") {
+                        break;
+                    }
+                }
+                trivia.comments.push((comment, newlines > 0));
+                newlines = 0;
+            } else if c == '/' && self.chars.get(self.pos + 1) == Some(&'/') {
+                let mut comment = String::new();
+                while let Some(c) = self.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    comment.push(c);
+                    self.bump();
+                }
+                trivia.comments.push((comment.trim_end().to_string(), newlines > 0));
+                newlines = 0;
+            } else {
+                break;
+            }
+        }
+        trivia
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.peek() == Some(expected) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.error(&format!("应为 '{}'", expected)))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some('{') => self.parse_container('}', true),
+            Some('[') => self.parse_container(']', false),
+            Some('"') | Some('\'') => Ok(Node::Scalar(self.parse_string()?)),
+            Some(_) => {
+                let mut text = String::new();
+                while let Some(c) = self.peek() {
+                    if c == ',' || c == '}' || c == ']' || c.is_whitespace() || c == '/' {
+                        break;
+                    }
+                    text.push(c);
+                    self.bump();
+                }
+                if text.is_empty() {
+                    return Err(self.error("意外的字符"));
+                }
+                Ok(Node::Scalar(canonical(&text)))
+            },
+            None => Err(self.error("意外的文件结尾")),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        let quote = self.bump().unwrap_or('"');
+        let mut text = String::new();
+        text.push(quote);
+        let mut escaped = false;
+        while let Some(c) = self.bump() {
+            text.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == quote {
+                return Ok(canonical(&text));
+            } else if c == '\n' {
+                break;
+            }
+        }
+        Err(self.error("字符串未结束"))
+    }
+
+    fn parse_container(&mut self, close: char, is_object: bool) -> Result<Node, String> {
+        self.bump();
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut pending: Vec<String> = Vec::new();
+        let mut blank = false;
+
+        loop {
+            let trivia = self.skip_trivia();
+            blank |= trivia.blank_line && !entries.is_empty();
+            for (comment, after_newline) in trivia.comments {
+                match entries.last_mut() {
+                    Some(last) if !after_newline && pending.is_empty() && last.trailing.is_none() => {
+                        last.trailing = Some(comment);
+                    },
+                    _ => pending.push(comment),
+                }
+            }
+
+            match self.peek() {
+                Some(c) if c == close => {
+                    self.bump();
+                    return Ok(if is_object {
+                        Node::Object(entries, pending)
+                    } else {
+                        Node::Array(entries, pending)
+                    });
+                },
+                Some(',') => {
+                    self.bump();
+                    continue;
+                },
+                None => return Err(self.error(&format!("缺少 '{}'", close))),
+                _ => {}
+            }
+
+            let key = if is_object {
+                let key = match self.peek() {
+                    Some('"') | Some('\'') => self.parse_string()?,
+                    _ => {
+                        // 未加引号的键
+                        let mut name = String::new();
+                        while let Some(c) = self.peek() {
+                            if c == ':' || c.is_whitespace() || c == '/' {
+                                break;
+                            }
+                            name.push(c);
+                            self.bump();
+                        }
+                        serde_json::to_string(&name).unwrap_or_default()
+                    }
+                };
+                pending.extend(self.skip_trivia().comments.into_iter().map(|(c, _)| c));
+                self.expect(':')?;
+                pending.extend(self.skip_trivia().comments.into_iter().map(|(c, _)| c));
+                Some(key)
+            } else {
+                None
+            };
+
+            let value = self.parse_value()?;
+            entries.push(Entry {
+                key,
+                value,
+                leading: std::mem::take(&mut pending),
+                trailing: None,
+                blank_before: blank,
+            });
+            blank = false;
+        }
+    }
+}
+
+// 标量的规范写法：标准JSON保持原样，JSON5写法转换为标准JSON
+fn canonical(text: &str) -> String {
+    if serde_json::from_str::<serde_json::Value>(text).is_ok() {
+        return text.to_string();
+    }
+    match crate::jsonc::parse(text) {
+        Ok(value) => value.to_string(),
+        Err(_) => text.to_string(),
+    }
+}
+
+// 对象键去掉引号后的内容
+fn key_name(key: &str) -> &str {
+    key.trim_matches('"')
+}
+
+fn has_comments(node: &Node) -> bool {
+    match node {
+        Node::Object(entries, dangling) | Node::Array(entries, dangling) => {
+            !dangling.is_empty() || entries.iter().any(|e| {
+                !e.leading.is_empty() || e.trailing.is_some() || has_comments(&e.value)
+            })
+        },
+        Node::Scalar(_) => false,
+    }
+}
+
+// 节点是否包含必须展开的语句块
+fn has_block(node: &Node) -> bool {
+    match node {
+        Node::Object(entries, _) => entries.iter().any(|e| {
+            let is_block = e.key.as_deref().map(|k| BLOCK_FIELDS.contains(&key_name(k))).unwrap_or(false)
+                && matches!(&e.value, Node::Array(items, _) if !items.is_empty());
+            is_block || has_block(&e.value)
+        }),
+        Node::Array(entries, _) => entries.iter().any(|e| has_block(&e.value)),
+        Node::Scalar(_) => false,
+    }
+}
+
+// 单行形式，无法单行输出时返回None
+fn inline(node: &Node) -> Option<String> {
+    if has_comments(node) || has_block(node) {
+        return None;
+    }
+    Some(inline_unchecked(node))
+}
+
+fn inline_unchecked(node: &Node) -> String {
+    match node {
+        Node::Object(entries, _) => {
+            let members: Vec<String> = entries.iter()
+                .map(|e| format!("{}: {}", e.key.as_deref().unwrap_or("\"\""), inline_unchecked(&e.value)))
+                .collect();
+            format!("{{{}}}", members.join(", "))
+        },
+        Node::Array(entries, _) => {
+            let items: Vec<String> = entries.iter().map(|e| inline_unchecked(&e.value)).collect();
+            format!("[{}]", items.join(", "))
+        },
+        Node::Scalar(text) => text.clone(),
+    }
+}
+
+// 容器在程序结构中的角色，决定子节点是否强制展开
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    Root,
+    Program,
+    Other,
+}
+
+struct Printer {
+    out: String,
+}
+
+impl Printer {
+    fn indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    // 输出值；`prefix_width` 是当前行已占用的宽度，`expand` 强制展开
+    fn write_value(&mut self, node: &Node, depth: usize, prefix_width: usize, expand: bool, role: Role) {
+        if !expand {
+            if let Some(text) = inline(node) {
+                // 预留逗号的空间
+                if prefix_width + text.chars().count() < MAX_WIDTH {
+                    self.out.push_str(&text);
+                    return;
+                }
+            }
+        }
+
+        match node {
+            Node::Object(entries, dangling) => self.write_container(('{', '}'), entries, dangling, depth, role),
+            Node::Array(entries, dangling) => self.write_container(('[', ']'), entries, dangling, depth, role),
+            Node::Scalar(text) => self.out.push_str(text),
+        }
+    }
+
+    fn write_container(&mut self, brackets: (char, char), entries: &[Entry], dangling: &[String], depth: usize, role: Role) {
+        let (open, close) = brackets;
+        if entries.is_empty() && dangling.is_empty() {
+            self.out.push(open);
+            self.out.push(close);
+            return;
+        }
+
+        let ordered: Vec<&Entry> = if role == Role::Root {
+            let mut ordered: Vec<&Entry> = Vec::new();
+            for name in ROOT_KEY_ORDER {
+                ordered.extend(entries.iter().filter(|e| e.key.as_deref().map(key_name) == Some(*name)));
+            }
+            ordered.extend(entries.iter().filter(|e| {
+                !ROOT_KEY_ORDER.contains(&e.key.as_deref().map(key_name).unwrap_or(""))
+            }));
+            ordered
+        } else {
+            entries.iter().collect()
+        };
+
+        self.out.push(open);
+        self.out.push('\n');
+        for (i, entry) in ordered.iter().enumerate() {
+            if entry.blank_before && i > 0 {
+                self.out.push('\n');
+            }
+            for comment in &entry.leading {
+                self.indent(depth + 1);
+                self.out.push_str(comment);
+                self.out.push('\n');
+            }
+            self.indent(depth + 1);
+            let mut width = (depth + 1) * INDENT.len();
+            if let Some(key) = &entry.key {
+                let _ = write!(self.out, "{}: ", key);
+                width += key.chars().count() + 2;
+            }
+
+            // program和tests对象、函数定义和语句块总是展开
+            let name = entry.key.as_deref().map(key_name);
+            let is_program = role == Role::Root && matches!(name, Some("program") | Some("tests"));
+            let is_block = name.map(|n| BLOCK_FIELDS.contains(&n)).unwrap_or(false)
+                && matches!(entry.value, Node::Array(..));
+            let child_role = if is_program { Role::Program } else { Role::Other };
+            self.write_value(&entry.value, depth + 1, width, is_program || is_block || role == Role::Program, child_role);
+
+            if i + 1 < ordered.len() {
+                self.out.push(',');
+            }
+            if let Some(comment) = &entry.trailing {
+                self.out.push(' ');
+                self.out.push_str(comment);
+            }
+            self.out.push('\n');
+        }
+        for comment in dangling {
+            self.indent(depth + 1);
+            self.out.push_str(comment);
+            self.out.push('\n');
+        }
+        self.indent(depth);
+        self.out.push(close);
+    }
+}
+
+/// 格式化JiLang源码，保留注释
+pub fn format_source(source: &str) -> Result<String, String> {
+    let before = crate::jsonc::parse(source).map_err(|e| e.to_string())?;
+
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+
+    let header = parser.skip_trivia();
+    let root = parser.parse_value()?;
+    let footer = parser.skip_trivia();
+    if parser.peek().is_some() {
+        return Err(parser.error("程序结尾有多余的内容"));
+    }
+
+    let mut printer = Printer { out: String::new() };
+    for (comment, _) in &header.comments {
+        printer.out.push_str(comment);
+        printer.out.push('\n');
+    }
+    printer.write_value(&root, 0, 0, matches!(root, Node::Object(..)), Role::Root);
+    for (i, (comment, after_newline)) in footer.comments.iter().enumerate() {
+        printer.out.push(if i == 0 && !after_newline { ' ' } else { '\n' });
+        printer.out.push_str(comment);
+    }
+    printer.out.push('\n');
+
+    // 格式化不能改变程序的含义
+    let after = crate::jsonc::parse(&printer.out)
+        .map_err(|e| format!("格式化结果无法解析: {}", e))?;
+    if before != after {
+        return Err("格式化结果与原程序不一致".to_string());
+    }
+
+    Ok(printer.out)
+}
+
+/// 执行 `jlang fmt [--check] 文件...`，返回进程退出码
+pub fn run_fmt(args: &[String]) -> i32 {
+    let mut check = false;
+    let mut files = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ => files.push(arg.clone()),
+        }
+    }
+
+    if files.is_empty() {
+        eprintln!("错误: 请指定要格式化的JiLang文件");
+        eprintln!("用法: jlang fmt [--check] 文件名...");
+        return 2;
+    }
+
+    let mut exit_code = 0;
+    for file in &files {
+        let source = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("错误: 无法读取文件 '{}': {}", file, e);
+                exit_code = 2;
+                continue;
+            }
+        };
+
+        let formatted = match format_source(&source) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("错误: 无法格式化 '{}': {}", file, e);
+                exit_code = 2;
+                continue;
+            }
+        };
+
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            println!("需要格式化: {}", file);
+            if exit_code == 0 {
+                exit_code = 1;
+            }
+        } else if let Err(e) = std::fs::write(file, &formatted) {
+            eprintln!("错误: 无法写入文件 '{}': {}", file, e);
+            exit_code = 2;
+        } else {
+            println!("已格式化: {}", file);
+        }
+    }
+    exit_code
+}
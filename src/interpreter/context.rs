@@ -5,8 +5,15 @@ use crate::modules::external_module::{ExternalModule, ExternalModuleOptions};
 use super::error::{InterpreterError, Result};
 use super::error::error_messages::context as error_msg;
 use super::variable_reference::{VariableReference, ReferenceType};
+use super::hooks::{CallFrame, ExecutionHook, Location};
+use super::profiler;
 use crate::is_print_full_values;  // 导入新函数
 use std::collections::BTreeMap;
+use rand::{RngCore, SeedableRng};
+use rand::rngs::StdRng;
+use std::rc::Rc;
+use crate::modules::lua_module::InlineLua;
+use crate::modules::module_cache::ModuleWatcher;
 
 // 上下文选项结构体
 #[derive(Debug, Clone, Default)]
@@ -19,16 +26,28 @@ pub struct Context {
     pub variables: HashMap<String, Value>,
     pub constants: HashMap<String, Value>,
     pub program: Value,
-    pub modules: HashMap<String, Box<dyn modules::Module>>,
+    pub modules: HashMap<String, modules::SharedModule>,
     pub module_meta: HashMap<String, Value>,
     pub current_path: Option<String>,
     pub options: ContextOptions,
     return_value: Option<Value>,
     is_returning: bool,
+    hooks: Vec<Box<dyn ExecutionHook>>,
+    locations: Vec<Location>,
+    call_frames: Vec<CallFrame>,
+    output_capture: Option<String>,
+    // 设置种子后使用的确定性随机数生成器，未设置时使用线程随机数生成器
+    rng: Option<StdRng>,
+    // 内联Lua代码块共用的Lua状态，第一次执行lua语句时创建
+    inline_lua: Option<Rc<InlineLua>>,
+    // --watch-modules 时监视模块文件，文件修改后重新加载模块
+    module_watcher: Option<ModuleWatcher>,
+    // 正在进行的模块函数调用层数，大于0时模块仍被借用，不能重新加载
+    module_call_depth: usize,
 }
 
 impl Context {
-    pub fn new(program: Value, modules: Vec<Box<dyn modules::Module>>) -> Result<Self> {
+    pub fn new(program: Value, modules: Vec<modules::SharedModule>) -> Result<Self> {
         let mut context = Context {
             variables: HashMap::new(),
             constants: HashMap::new(),
@@ -39,6 +58,14 @@ impl Context {
             options: ContextOptions::default(),
             return_value: None,
             is_returning: false,
+            hooks: Vec::new(),
+            locations: Vec::new(),
+            call_frames: Vec::new(),
+            output_capture: None,
+            rng: None,
+            inline_lua: None,
+            module_watcher: None,
+            module_call_depth: 0,
         };
 
         // 验证程序结构
@@ -62,7 +89,8 @@ impl Context {
         }
 
         // 加载模块
-        for module in modules {
+        for shared in modules {
+            let module = shared.borrow();
             let name = module.get_name().to_string();
             
             // 加载模块元数据（如果有）
@@ -80,24 +108,24 @@ impl Context {
                         println!("已加载JL模块 '{}' 的元数据", name);
                     }
                 }
+            } else if let Some(wasm_module) = module.as_any().downcast_ref::<crate::modules::wasm_module::WasmModule>() {
+                if let Some(meta_value) = wasm_module.get_module_meta_value() {
+                    context.module_meta.insert(name.clone(), meta_value.clone());
+                    if crate::is_debug_mode() {
+                        println!("已加载WASM模块 '{}' 的元数据", name);
+                    }
+                }
+            } else if let Some(metadata) = crate::modules::builtin_metadata(&name) {
+                // 内置模块没有自定义元数据，使用生成的模块元数据
+                context.module_meta.insert(name.clone(), metadata.to_json());
             }
             
-            context.modules.insert(name, module);
+            drop(module);
+            context.modules.insert(name, shared);
         }
 
         // 创建特殊的module_meta变量
-        if !context.module_meta.is_empty() {
-            let meta_obj = Value::Object(
-                context.module_meta.iter()
-                    .map(|(k, v)| (k.clone(), v.clone()))
-                    .collect()
-            );
-            context.variables.insert("module_meta".to_string(), meta_obj);
-            
-            if crate::is_debug_mode() {
-                println!("已创建module_meta全局变量，包含 {} 个模块的元数据", context.module_meta.len());
-            }
-        }
+        context.update_module_meta_variable();
 
         // 检查函数名冲突
         if let Some(program_obj) = program.get("program") {
@@ -117,7 +145,7 @@ impl Context {
                             let module_name = parts[0];
                             let function_name = parts[1];
                             if let Some(module) = context.modules.get(module_name) {
-                                for (fname, _) in module.get_functions() {
+                                for (fname, _) in module.borrow().get_functions() {
                                     if fname == function_name {
                                         return Err(InterpreterError::FunctionError(
                                             error_msg::function_name_conflict_module(func_name)
@@ -189,7 +217,10 @@ impl Context {
                 }
             },
             Value::Object(obj) => {
-                if crate::is_print_full_values() {
+                if let Some(text) = modules::bignum::decimal_text(value) {
+                    // 十进制小数直接显示数值
+                    text.to_string()
+                } else if crate::is_print_full_values() {
                     // 完整打印对象内容
                     let mut result = String::new();
                     result.push('{');
@@ -277,7 +308,85 @@ impl Context {
             .replace("\\r", "\r")
     }
 
+    // 用module_meta更新同名的全局变量
+    fn update_module_meta_variable(&mut self) {
+        if self.module_meta.is_empty() {
+            self.variables.remove("module_meta");
+            return;
+        }
+        let meta_obj = Value::Object(
+            self.module_meta.iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        );
+        self.variables.insert("module_meta".to_string(), meta_obj);
+        
+        if crate::is_debug_mode() {
+            println!("已创建module_meta全局变量，包含 {} 个模块的元数据", self.module_meta.len());
+        }
+    }
+
+    /// 开始监视已加载的外部模块文件，文件修改后在下一条语句执行前重新加载
+    pub fn watch_modules(&mut self) {
+        let mut watcher = ModuleWatcher::default();
+        for (name, module) in self.modules.iter() {
+            let mut module = module.borrow_mut();
+            if let Some(path) = modules::as_external_mut(module.as_mut()).and_then(|m| m.source_path()) {
+                watcher.watch(name, path);
+            }
+        }
+        self.module_watcher = Some(watcher);
+    }
+
+    /// 关闭所有外部模块，在不经过析构直接退出进程之前调用
+    pub fn destroy_modules(&mut self) {
+        modules::destroy_modules(self.modules.values());
+    }
+
+    /// 重新加载文件修改过的模块，失败时保留原来的模块继续使用。
+    /// 在语句之间调用；模块函数执行期间（包括其中回调执行的语句）不重新加载
+    pub fn reload_changed_modules(&mut self) {
+        if self.module_call_depth > 0 {
+            return;
+        }
+        let changed = match self.module_watcher.as_mut() {
+            Some(watcher) => watcher.changed(),
+            None => return,
+        };
+        for name in changed {
+            let Some(shared) = self.modules.get(&name).cloned() else {
+                continue;
+            };
+            let mut module = shared.borrow_mut();
+            let Some(module) = modules::as_external_mut(module.as_mut()) else {
+                continue;
+            };
+            match module.reload() {
+                Ok(()) => {
+                    // 其他include共用这个实例，登记重新加载后的修改时间
+                    if let Some(path) = module.source_path() {
+                        modules::module_cache::share(path, &shared);
+                    }
+                    eprintln!("已重新加载模块 '{}'", name);
+                    match module.get_module_meta_value() {
+                        Some(meta_value) => self.module_meta.insert(name, meta_value.clone()),
+                        None => self.module_meta.remove(&name),
+                    };
+                    self.update_module_meta_variable();
+                }
+                Err(e) => eprintln!("警告: 重新加载模块 '{}' 失败: {}", name, e),
+            }
+        }
+    }
+
     pub fn call_module_function(&mut self, module_name: &str, function_name: &str, args: &[Value]) -> Result<Value> {
+        self.module_call_depth += 1;
+        let result = self.dispatch_module_function(module_name, function_name, args);
+        self.module_call_depth -= 1;
+        result
+    }
+
+    fn dispatch_module_function(&mut self, module_name: &str, function_name: &str, args: &[Value]) -> Result<Value> {
         // 使用clone避免借用冲突
         let module_name = module_name.to_string();
         let function_name = function_name.to_string();
@@ -290,10 +399,14 @@ impl Context {
             ));
         }
         
+        // 调用期间借用共享的模块实例，self可以传给模块函数
+        let shared = self.modules[&module_name].clone();
+        let module = shared.borrow();
+        
         // 首先尝试普通模块函数调用
-        let module = self.modules.get(&module_name).unwrap();
         for (fname, func) in module.get_functions() {
             if fname == function_name {
+                let _span = profiler::span(profiler::SpanKind::Native, &format!("{}.{}", module_name, function_name));
                 return Ok(func(&args, self));
             }
         }
@@ -301,27 +414,27 @@ impl Context {
         // 备份模块类型
         let is_lua_module = module.as_any().downcast_ref::<crate::modules::lua_module::LuaModule>().is_some();
         let is_jlang_module = module.as_any().downcast_ref::<crate::modules::external_module::JLangExternalModule>().is_some();
+        let is_wasm_module = module.as_any().downcast_ref::<crate::modules::wasm_module::WasmModule>().is_some();
+        #[cfg(unix)]
+        let is_native_module = module.as_any().downcast_ref::<crate::modules::native_module::NativeModule>().is_some();
+        #[cfg(not(unix))]
+        let is_native_module = false;
+        let is_plugin_module = module.as_any().downcast_ref::<crate::modules::plugin_module::PluginModule>().is_some();
         
-        // 使用unsafe处理借用冲突问题
         if is_lua_module {
-            let module_ptr = self.modules.get_mut(&module_name).unwrap() as *mut Box<dyn modules::Module>;
-            let module_ref = unsafe { &*module_ptr };
-            
-            if let Some(lua_module) = module_ref.as_any().downcast_ref::<crate::modules::lua_module::LuaModule>() {
+            if let Some(lua_module) = module.as_any().downcast_ref::<crate::modules::lua_module::LuaModule>() {
                 if lua_module.has_function(&function_name) {
                     if crate::is_debug_mode() {
                         println!("调用Lua模块 '{}' 中的函数: '{}'", module_name, function_name);
                     }
                     
+                    let _span = profiler::span(profiler::SpanKind::Lua, &format!("{}.{}", module_name, function_name));
                     let result = lua_module.call_function(&function_name, &args, self);
                     return result;
                 }
             }
         } else if is_jlang_module {
-            let module_ptr = self.modules.get_mut(&module_name).unwrap() as *mut Box<dyn modules::Module>;
-            let module_ref = unsafe { &*module_ptr };
-            
-            if let Some(jlang_module) = module_ref.as_any().downcast_ref::<crate::modules::external_module::JLangExternalModule>() {
+            if let Some(jlang_module) = module.as_any().downcast_ref::<crate::modules::external_module::JLangExternalModule>() {
                 if jlang_module.has_function(&function_name) {
                     if crate::is_debug_mode() {
                         println!("调用JLang外部模块 '{}' 中的函数: '{}'", module_name, function_name);
@@ -331,6 +444,40 @@ impl Context {
                     return result;
                 }
             }
+        } else if is_wasm_module {
+            if let Some(wasm_module) = module.as_any().downcast_ref::<crate::modules::wasm_module::WasmModule>() {
+                if wasm_module.has_function(&function_name) {
+                    if crate::is_debug_mode() {
+                        println!("调用WASM模块 '{}' 中的函数: '{}'", module_name, function_name);
+                    }
+                    
+                    let _span = profiler::span(profiler::SpanKind::Wasm, &format!("{}.{}", module_name, function_name));
+                    return wasm_module.call_function(&function_name, &args, self);
+                }
+            }
+        } else if is_native_module {
+            #[cfg(unix)]
+            if let Some(native_module) = module.as_any().downcast_ref::<crate::modules::native_module::NativeModule>() {
+                if native_module.has_function(&function_name) {
+                    if crate::is_debug_mode() {
+                        println!("调用原生模块 '{}' 中的函数: '{}'", module_name, function_name);
+                    }
+                    
+                    let _span = profiler::span(profiler::SpanKind::NativeModule, &format!("{}.{}", module_name, function_name));
+                    return native_module.call_function(&function_name, &args, self);
+                }
+            }
+        } else if is_plugin_module {
+            if let Some(plugin_module) = module.as_any().downcast_ref::<crate::modules::plugin_module::PluginModule>() {
+                if plugin_module.has_function(&function_name) {
+                    if crate::is_debug_mode() {
+                        println!("调用插件模块 '{}' 中的函数: '{}'", module_name, function_name);
+                    }
+                    
+                    let _span = profiler::span(profiler::SpanKind::Plugin, &format!("{}.{}", module_name, function_name));
+                    return plugin_module.call_function(&function_name, &args, self);
+                }
+            }
         }
         
         // 如果到这里还没有返回，说明没有找到函数
@@ -372,4 +519,157 @@ impl Context {
             _ => Ok(value.clone()),
         }
     }
-} 
\ No newline at end of file
+
+    // 输出文本：捕获输出时写入缓冲区，否则打印到标准输出
+    pub fn write_output(&mut self, text: &str) {
+        match &mut self.output_capture {
+            Some(buffer) => buffer.push_str(text),
+            None => print!("{}", text),
+        }
+    }
+
+    // 开始捕获echo等语句的输出（用于测试）
+    pub fn start_output_capture(&mut self) {
+        self.output_capture = Some(String::new());
+    }
+
+    // 获取已捕获的输出，未捕获时为None
+    pub fn captured_output(&self) -> Option<&str> {
+        self.output_capture.as_deref()
+    }
+
+    // 设置随机数种子，之后的随机数序列是确定的
+    pub fn seed_random(&mut self, seed: u64) {
+        self.rng = Some(StdRng::seed_from_u64(seed));
+    }
+
+    // 使用当前的随机数生成器，所有随机函数都通过这里获取随机数
+    pub fn with_rng<T>(&mut self, f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        match &mut self.rng {
+            Some(rng) => f(rng),
+            None => f(&mut rand::thread_rng()),
+        }
+    }
+
+    // lua语句使用的Lua状态，整个解释器共用一个，第一次使用时创建
+    pub fn inline_lua(&mut self) -> Result<Rc<InlineLua>> {
+        if let Some(runtime) = &self.inline_lua {
+            return Ok(runtime.clone());
+        }
+        let runtime = Rc::new(InlineLua::new()?);
+        self.inline_lua = Some(runtime.clone());
+        Ok(runtime)
+    }
+
+    // 注册执行钩子（调试器、性能分析等）
+    pub fn add_hook(&mut self, hook: Box<dyn ExecutionHook>) {
+        self.hooks.push(hook);
+    }
+
+    // 是否注册了执行钩子，没有钩子时不记录执行位置
+    pub fn has_hooks(&self) -> bool {
+        !self.hooks.is_empty()
+    }
+
+    // 进入子位置：以'/'开头的片段为绝对路径，否则相对当前位置
+    pub fn push_location(&mut self, segment: &str) {
+        if !self.has_hooks() {
+            return;
+        }
+        let location = match self.locations.last() {
+            Some(parent) if !segment.starts_with('/') => Location {
+                source: parent.source.clone(),
+                path: format!("{}/{}", parent.path, segment),
+            },
+            Some(parent) => Location {
+                source: parent.source.clone(),
+                path: segment.to_string(),
+            },
+            None => Location {
+                source: self.current_path.clone(),
+                path: if segment.starts_with('/') { segment.to_string() } else { format!("/{}", segment) },
+            },
+        };
+        self.locations.push(location);
+    }
+
+    // 离开当前位置
+    pub fn pop_location(&mut self) {
+        if self.has_hooks() {
+            self.locations.pop();
+        }
+    }
+
+    // 获取当前执行位置
+    pub fn current_location(&self) -> Option<&Location> {
+        self.locations.last()
+    }
+
+    // 获取完整的位置栈
+    pub fn locations(&self) -> &[Location] {
+        &self.locations
+    }
+
+    // 获取用户函数调用栈（不包含main）
+    pub fn call_frames(&self) -> &[CallFrame] {
+        &self.call_frames
+    }
+
+    // 进入用户函数，source为函数所在文件，path为函数定义的JSON路径
+    pub fn enter_function(&mut self, name: &str, source: Option<String>, path: String) {
+        if !self.has_hooks() {
+            return;
+        }
+        self.call_frames.push(CallFrame {
+            name: name.to_string(),
+            location_depth: self.locations.len(),
+        });
+        self.locations.push(Location { source, path });
+
+        let mut hooks = std::mem::take(&mut self.hooks);
+        for hook in hooks.iter_mut() {
+            hook.enter_function(name, self);
+        }
+        self.hooks = hooks;
+    }
+
+    // 离开用户函数，恢复调用前的位置栈
+    pub fn leave_function(&mut self) {
+        if !self.has_hooks() {
+            return;
+        }
+        if let Some(frame) = self.call_frames.last().cloned() {
+            let mut hooks = std::mem::take(&mut self.hooks);
+            for hook in hooks.iter_mut() {
+                hook.leave_function(&frame.name, self);
+            }
+            self.hooks = hooks;
+
+            self.call_frames.pop();
+            self.locations.truncate(frame.location_depth);
+        }
+    }
+
+    // 语句执行前调用所有钩子
+    pub fn run_before_hooks(&mut self, stmt_type: &str) -> Result<()> {
+        let mut hooks = std::mem::take(&mut self.hooks);
+        let mut result = Ok(());
+        for hook in hooks.iter_mut() {
+            result = hook.before_statement(stmt_type, self);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.hooks = hooks;
+        result
+    }
+
+    // 语句执行后调用所有钩子
+    pub fn run_after_hooks(&mut self, stmt_type: &str, result: &Result<Value>) {
+        let mut hooks = std::mem::take(&mut self.hooks);
+        for hook in hooks.iter_mut() {
+            hook.after_statement(stmt_type, self, result);
+        }
+        self.hooks = hooks;
+    }
+}
@@ -0,0 +1,332 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use serde_json::{json, Value};
+use super::context::Context;
+use super::error::Result;
+use super::hooks::ExecutionHook;
+use super::source_map::{self, escape_segment, SourceMap};
+use crate::source_format::{self, SourceFormat};
+
+// 源文件的程序结构和位置映射（只有JSON源码有行号）
+struct SourceInfo {
+    program: Value,
+    map: Option<SourceMap>,
+}
+
+/// 覆盖率数据：按源文件和JSON路径记录语句和函数的执行次数
+#[derive(Default)]
+pub struct Coverage {
+    sources: BTreeMap<String, Option<SourceInfo>>,
+    statement_hits: HashMap<(String, String), u64>,
+    function_hits: HashMap<(String, String), u64>,
+}
+
+/// 一条语句的覆盖情况
+pub struct StatementCoverage {
+    pub path: String,
+    pub line: Option<usize>,
+    pub hits: u64,
+}
+
+/// 一个函数的覆盖情况
+pub struct FunctionCoverage {
+    pub name: String,
+    pub line: Option<usize>,
+    pub hits: u64,
+}
+
+/// 一个分支（then/else、case、default、catch）的覆盖情况
+pub struct BranchCoverage {
+    /// 分支块的路径，例如 `/program/main/body/2/if/else`
+    pub path: String,
+    /// 所属语句的行号
+    pub line: Option<usize>,
+    /// 所属语句在文件中的编号
+    pub block: usize,
+    /// 分支在所属语句中的编号
+    pub branch: usize,
+    /// 所属语句未执行时为None
+    pub taken: Option<u64>,
+}
+
+/// 单个源文件的覆盖率报告
+pub struct FileCoverage {
+    pub path: String,
+    pub statements: Vec<StatementCoverage>,
+    pub functions: Vec<FunctionCoverage>,
+    pub branches: Vec<BranchCoverage>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记需要统计的源文件，未执行的语句也会出现在报告中
+    pub fn add_source(&mut self, path: &str, source: &str) {
+        let info = source_format::parse_file(path, source).ok().map(|program| SourceInfo {
+            map: match SourceFormat::from_path(path) {
+                SourceFormat::Json => Some(SourceMap::from_source(source)),
+                _ => None,
+            },
+            program,
+        });
+        self.sources.insert(path.to_string(), info);
+    }
+
+    fn record_statement(&mut self, context: &Context) {
+        if let Some(location) = context.current_location() {
+            let source = location.source.clone().unwrap_or_default();
+            *self.statement_hits.entry((source, location.path.clone())).or_default() += 1;
+        }
+    }
+
+    fn record_function(&mut self, context: &Context) {
+        if let Some(location) = context.current_location() {
+            let source = location.source.clone().unwrap_or_default();
+            *self.function_hits.entry((source, location.path.clone())).or_default() += 1;
+        }
+    }
+
+    /// 生成各源文件的覆盖率报告
+    pub fn report(&mut self) -> Vec<FileCoverage> {
+        // 运行时才加载的模块文件也要统计
+        let executed: Vec<String> = self.statement_hits.keys()
+            .chain(self.function_hits.keys())
+            .map(|(source, _)| source.clone())
+            .filter(|source| !source.is_empty() && !self.sources.contains_key(source))
+            .collect();
+        for source in executed {
+            match std::fs::read_to_string(&source) {
+                Ok(text) => self.add_source(&source, &text),
+                Err(_) => { self.sources.insert(source, None); },
+            }
+        }
+
+        self.sources.iter()
+            .map(|(path, info)| self.file_report(path, info.as_ref()))
+            .collect()
+    }
+
+    fn file_report(&self, path: &str, info: Option<&SourceInfo>) -> FileCoverage {
+        let line_of = |json_path: &str| info
+            .and_then(|info| info.map.as_ref())
+            .and_then(|map| map.position(json_path))
+            .map(|pos| pos.line);
+
+        let mut paths: Vec<String> = info.map(|info| source_map::statement_paths(&info.program)).unwrap_or_default();
+        for (source, stmt_path) in self.statement_hits.keys() {
+            if source == path && !paths.contains(stmt_path) {
+                paths.push(stmt_path.clone());
+            }
+        }
+        let hits_of = |stmt_path: &str| self.statement_hits
+            .get(&(path.to_string(), stmt_path.to_string()))
+            .copied()
+            .unwrap_or(0);
+
+        let mut statements: Vec<StatementCoverage> = paths.iter()
+            .map(|stmt_path| StatementCoverage { path: stmt_path.clone(), line: line_of(stmt_path), hits: hits_of(stmt_path) })
+            .collect();
+        statements.sort_by(|a, b| a.line.cmp(&b.line).then_with(|| a.path.cmp(&b.path)));
+
+        // main是程序入口，不作为函数统计
+        let functions = info
+            .and_then(|info| info.program.get("program"))
+            .and_then(|program| program.as_object())
+            .map(|program| program.keys()
+                .filter(|name| name.as_str() != "main")
+                .map(|name| {
+                    let fn_path = format!("/program/{}", escape_segment(name));
+                    FunctionCoverage {
+                        name: name.clone(),
+                        line: line_of(&fn_path),
+                        hits: self.function_hits.get(&(path.to_string(), fn_path.clone())).copied().unwrap_or(0),
+                    }
+                })
+                .collect())
+            .unwrap_or_default();
+
+        // 每个分支块以其第一条语句的执行次数作为分支的执行次数
+        let mut branches: Vec<BranchCoverage> = Vec::new();
+        let mut owners: Vec<String> = Vec::new();
+        for stmt in &statements {
+            let block_path = match stmt.path.strip_suffix("/0") {
+                Some(block_path) => block_path,
+                None => continue,
+            };
+            let owner = match branch_owner(block_path) {
+                Some(owner) => owner,
+                None => continue,
+            };
+            let block = match owners.iter().position(|o| o == owner) {
+                Some(index) => index,
+                None => {
+                    owners.push(owner.to_string());
+                    owners.len() - 1
+                }
+            };
+            let branch = branches.iter().filter(|b| b.block == block).count();
+            let owner_hits = hits_of(owner);
+            branches.push(BranchCoverage {
+                path: block_path.to_string(),
+                line: line_of(owner),
+                block,
+                branch,
+                taken: if owner_hits > 0 { Some(stmt.hits) } else { None },
+            });
+        }
+
+        FileCoverage { path: path.to_string(), statements, functions, branches }
+    }
+}
+
+// 如果块是分支（then/else、case、default、catch），返回所属语句的路径
+fn branch_owner(block_path: &str) -> Option<&str> {
+    for suffix in ["/if/then", "/if/else", "/switch/default", "/try/catch"] {
+        if let Some(owner) = block_path.strip_suffix(suffix) {
+            return Some(owner);
+        }
+    }
+    // case块的路径形如 所属语句/switch/cases/序号/do
+    let (rest, index) = block_path.strip_suffix("/do")?.rsplit_once('/')?;
+    index.parse::<usize>().ok()?;
+    rest.strip_suffix("/switch/cases")
+}
+
+impl FileCoverage {
+    fn statement_summary(&self) -> (usize, usize) {
+        (self.statements.iter().filter(|s| s.hits > 0).count(), self.statements.len())
+    }
+
+    fn function_summary(&self) -> (usize, usize) {
+        (self.functions.iter().filter(|f| f.hits > 0).count(), self.functions.len())
+    }
+
+    fn branch_summary(&self) -> (usize, usize) {
+        (self.branches.iter().filter(|b| b.taken.unwrap_or(0) > 0).count(), self.branches.len())
+    }
+}
+
+fn percent((covered, total): (usize, usize)) -> f64 {
+    if total == 0 { 100.0 } else { covered as f64 * 100.0 / total as f64 }
+}
+
+fn summary_json(summary: (usize, usize)) -> Value {
+    json!({"covered": summary.0, "total": summary.1, "percent": percent(summary)})
+}
+
+/// 生成LCOV格式的覆盖率报告，没有行号的语句（YAML/TOML源码）不会出现在DA记录中
+pub fn lcov(files: &[FileCoverage]) -> String {
+    let mut out = String::from("TN:\n");
+    for file in files {
+        out.push_str(&format!("SF:{}\n", file.path));
+        for function in &file.functions {
+            out.push_str(&format!("FN:{},{}\n", function.line.unwrap_or(1), function.name));
+        }
+        for function in &file.functions {
+            out.push_str(&format!("FNDA:{},{}\n", function.hits, function.name));
+        }
+        let (hit, found) = file.function_summary();
+        out.push_str(&format!("FNF:{}\nFNH:{}\n", found, hit));
+
+        for branch in &file.branches {
+            if let Some(line) = branch.line {
+                let taken = branch.taken.map(|t| t.to_string()).unwrap_or_else(|| "-".to_string());
+                out.push_str(&format!("BRDA:{},{},{},{}\n", line, branch.block, branch.branch, taken));
+            }
+        }
+        let (hit, found) = file.branch_summary();
+        out.push_str(&format!("BRF:{}\nBRH:{}\n", found, hit));
+
+        // 同一行有多条语句时取最大执行次数
+        let mut lines: BTreeMap<usize, u64> = BTreeMap::new();
+        for stmt in &file.statements {
+            if let Some(line) = stmt.line {
+                let hits = lines.entry(line).or_default();
+                *hits = (*hits).max(stmt.hits);
+            }
+        }
+        for (line, hits) in &lines {
+            out.push_str(&format!("DA:{},{}\n", line, hits));
+        }
+        out.push_str(&format!("LF:{}\nLH:{}\n", lines.len(), lines.values().filter(|h| **h > 0).count()));
+        out.push_str("end_of_record\n");
+    }
+    out
+}
+
+/// 生成JSON格式的覆盖率报告
+pub fn json_report(files: &[FileCoverage]) -> Value {
+    let mut totals = [(0, 0); 3];
+    let file_reports: Vec<Value> = files.iter().map(|file| {
+        let summaries = [file.statement_summary(), file.branch_summary(), file.function_summary()];
+        for (total, summary) in totals.iter_mut().zip(summaries.iter()) {
+            total.0 += summary.0;
+            total.1 += summary.1;
+        }
+        json!({
+            "path": file.path,
+            "statements": summary_json(summaries[0]),
+            "branches": summary_json(summaries[1]),
+            "functions": summary_json(summaries[2]),
+            "details": {
+                "statements": file.statements.iter()
+                    .map(|s| json!({"path": s.path, "line": s.line, "hits": s.hits}))
+                    .collect::<Vec<_>>(),
+                "branches": file.branches.iter()
+                    .map(|b| json!({"path": b.path, "line": b.line, "taken": b.taken}))
+                    .collect::<Vec<_>>(),
+                "functions": file.functions.iter()
+                    .map(|f| json!({"name": f.name, "line": f.line, "hits": f.hits}))
+                    .collect::<Vec<_>>(),
+            },
+        })
+    }).collect();
+    json!({
+        "totals": {
+            "statements": summary_json(totals[0]),
+            "branches": summary_json(totals[1]),
+            "functions": summary_json(totals[2]),
+        },
+        "files": file_reports,
+    })
+}
+
+/// 生成覆盖率摘要表格
+pub fn summary_table(files: &[FileCoverage]) -> String {
+    let mut out = String::from("\n=== 覆盖率 ===\n");
+    out.push_str(&format!("{:<40} {:>16} {:>16} {:>16}\n", "文件", "语句", "分支", "函数"));
+    for file in files {
+        let cell = |summary: (usize, usize)| format!("{}/{} {:.1}%", summary.0, summary.1, percent(summary));
+        let name = std::path::Path::new(&file.path).file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| file.path.clone());
+        out.push_str(&format!("{:<40} {:>16} {:>16} {:>16}\n",
+            name, cell(file.statement_summary()), cell(file.branch_summary()), cell(file.function_summary())));
+    }
+    out
+}
+
+/// 记录语句和函数执行次数的执行钩子
+pub struct CoverageHook {
+    data: Rc<RefCell<Coverage>>,
+}
+
+impl CoverageHook {
+    pub fn new(data: Rc<RefCell<Coverage>>) -> Self {
+        Self { data }
+    }
+}
+
+impl ExecutionHook for CoverageHook {
+    fn before_statement(&mut self, _stmt_type: &str, context: &Context) -> Result<()> {
+        self.data.borrow_mut().record_statement(context);
+        Ok(())
+    }
+
+    fn enter_function(&mut self, _name: &str, context: &Context) {
+        self.data.borrow_mut().record_function(context);
+    }
+}
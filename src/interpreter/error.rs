@@ -5,6 +5,7 @@ pub enum InterpreterError {
     FunctionError(String),
     ModuleError(String),
     RuntimeError(String),
+    AssertionError(String),
 }
 
 impl std::fmt::Display for InterpreterError {
@@ -15,6 +16,21 @@ impl std::fmt::Display for InterpreterError {
             Self::FunctionError(msg) => write!(f, "函数错误: {}。笨蛋！函数不是这样用的！", msg),
             Self::ModuleError(msg) => write!(f, "模块错误: {}。哼！模块加载失败了啦～", msg),
             Self::RuntimeError(msg) => write!(f, "运行时错误: {}。啊啦～程序员君不行呢～", msg),
+            Self::AssertionError(msg) => write!(f, "断言失败: {}", msg),
+        }
+    }
+}
+
+impl InterpreterError {
+    // 不带前后缀的错误信息
+    pub fn message(&self) -> &str {
+        match self {
+            Self::InvalidProgramStructure(msg)
+            | Self::VariableError(msg)
+            | Self::FunctionError(msg)
+            | Self::ModuleError(msg)
+            | Self::RuntimeError(msg)
+            | Self::AssertionError(msg) => msg,
         }
     }
 }
@@ -208,6 +224,14 @@ pub mod error_messages {
                 format!("执行命令失败: {}，哎哟～命令执行炸了～", err)
             }
         }
+        
+        // 内联Lua代码块错误
+        pub mod lua {
+            pub const ARGS_NOT_OBJ: &str = "'lua' 语句的参数必须是一个对象，喵呜～参数不是对象啦～";
+            pub const MISSING_CODE: &str = "'lua' 语句缺少 'code' 字段，诶？代码去哪了？";
+            pub const CODE_NOT_STRING: &str = "'lua' 语句的 'code' 必须是字符串或字符串数组（每个元素一行）";
+            pub const ARGS_MUST_BE_OBJ: &str = "'lua' 语句的 'args' 必须是一个对象";
+        }
     }
 
     // 添加数学运算相关的错误消息
@@ -0,0 +1,39 @@
+use serde_json::Value;
+use super::context::Context;
+use super::error::Result;
+
+/// 语句的执行位置：所在源文件和JSON路径
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    /// 源文件路径（主程序或JL模块文件）
+    pub source: Option<String>,
+    /// JSON Pointer格式的路径，例如 `/program/main/body/0`
+    pub path: String,
+}
+
+/// 用户函数调用帧
+#[derive(Clone, Debug)]
+pub struct CallFrame {
+    /// 函数名称（模块函数为 `模块.函数`）
+    pub name: String,
+    /// 进入函数时位置栈的深度，函数内的位置都在这之上
+    pub location_depth: usize,
+}
+
+/// 执行钩子特征
+///
+/// 解释器在执行每条带位置的语句前后调用已注册的钩子。
+/// 调试器、性能分析器和覆盖率统计都基于这些钩子实现。
+pub trait ExecutionHook {
+    /// 语句执行前调用，返回错误会中止执行
+    fn before_statement(&mut self, stmt_type: &str, context: &Context) -> Result<()>;
+
+    /// 语句执行后调用
+    fn after_statement(&mut self, _stmt_type: &str, _context: &Context, _result: &Result<Value>) {}
+
+    /// 进入用户函数或JL模块函数时调用
+    fn enter_function(&mut self, _name: &str, _context: &Context) {}
+
+    /// 离开用户函数或JL模块函数时调用
+    fn leave_function(&mut self, _name: &str, _context: &Context) {}
+}
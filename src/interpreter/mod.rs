@@ -1,26 +1,76 @@
 pub mod context;
+pub mod coverage;
 pub mod error;
+pub mod hooks;
+pub mod profiler;
+pub mod source_map;
 pub mod statements;
+pub mod trace;
 pub mod variable_reference;
 
 use serde_json::Value;
-use crate::modules::Module;
+use crate::modules::SharedModule;
 use crate::{is_ignore_non_critical_errors, is_check_only, is_check_all};
 use context::Context;
 use error::{InterpreterError, Result};
 use error::error_messages::interpreter;
-use statements::execute_statement;
+use statements::{execute_statement, execute_statement_at};
+use hooks::ExecutionHook;
 
 pub struct Interpreter {
     context: Context,
 }
 
 impl Interpreter {
-    pub fn new(program: Value, modules: Vec<Box<dyn Module>>) -> Result<Self> {
+    pub fn new(program: Value, modules: Vec<SharedModule>) -> Result<Self> {
         let context = Context::new(program, modules)?;
         Ok(Self { context })
     }
 
+    // 设置主程序文件路径，用于定位语句所在的源文件
+    pub fn set_source_path(&mut self, path: &str) {
+        self.context.current_path = Some(path.to_string());
+    }
+
+    // 注册执行钩子
+    pub fn add_hook(&mut self, hook: Box<dyn ExecutionHook>) {
+        self.context.add_hook(hook);
+    }
+
+    // 执行不带参数的函数定义（测试用例等），path为函数定义的JSON路径
+    pub fn run_function(&mut self, name: &str, func_def: &Value, path: &str) -> Result<Value> {
+        let source = self.context.current_path.clone();
+        self.context.enter_function(name, source, path.to_string());
+        let result = statements::execute_function(func_def, &mut self.context, None);
+        self.context.leave_function();
+        result
+    }
+
+    // 设置随机数种子（--seed）
+    pub fn seed_random(&mut self, seed: u64) {
+        self.context.seed_random(seed);
+    }
+
+    // 监视模块文件，修改后重新加载（--watch-modules）
+    pub fn watch_modules(&mut self) {
+        self.context.watch_modules();
+    }
+
+    // 关闭外部模块，调用std::process::exit之前使用
+    pub fn destroy_modules(&mut self) {
+        self.context.destroy_modules();
+    }
+
+    // 开始捕获程序输出
+    pub fn start_output_capture(&mut self) {
+        self.context.start_output_capture();
+    }
+
+    // 获取已捕获的输出
+    pub fn captured_output(&self) -> Option<&str> {
+        self.context.captured_output()
+    }
+
     pub fn run(&mut self) -> Result<()> {
         // 获取主程序体
         let program_body = self.context.program.get("program")
@@ -30,8 +80,8 @@ impl Interpreter {
                 interpreter::MISSING_PROGRAM_MAIN_BODY.to_string()
             ))?;
 
-        // 验证主程序体是数组
-        let statements = program_body.as_array()
+        // 验证主程序体是数组，复制一份以便执行时修改上下文
+        let statements = program_body.as_array().cloned()
             .ok_or_else(|| InterpreterError::InvalidProgramStructure(
                 interpreter::PROGRAM_MAIN_BODY_NOT_ARRAY.to_string()
             ))?;
@@ -42,11 +92,12 @@ impl Interpreter {
         }
 
         // 执行每个语句
-        for stmt in statements.to_vec() {
+        for (i, stmt) in statements.into_iter().enumerate() {
             if let Some(obj) = stmt.as_object() {
                 if let Some((stmt_type, args)) = obj.iter().next() {
                     // 将完整语句对象传递给execute_statement
-                    match execute_statement(stmt_type, args, &mut self.context, Some(&stmt)) {
+                    let location = format!("/program/main/body/{}", i);
+                    match execute_statement_at(&location, stmt_type, args, &mut self.context, Some(&stmt)) {
                         Ok(_) => {},
                         Err(e) => {
                             // 在全面检查模式下，只收集错误而不终止
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use super::context::Context;
+use super::error::Result;
+use super::hooks::ExecutionHook;
+
+/// 被计时的操作类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SpanKind {
+    /// 语句（按语句类型统计）
+    Statement,
+    /// 用户函数
+    Function,
+    /// 内置模块函数（io、math、http）
+    Native,
+    /// JL模块函数
+    JlModule,
+    /// Lua模块函数
+    Lua,
+    /// 创建Lua状态并加载模块代码
+    LuaState,
+    /// WASM模块函数
+    Wasm,
+    /// 原生共享库模块函数
+    NativeModule,
+    /// 进程外插件模块函数
+    Plugin,
+    /// HTTP请求
+    Http,
+    /// 外部命令
+    Exec,
+}
+
+impl SpanKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpanKind::Statement => "statement",
+            SpanKind::Function => "function",
+            SpanKind::Native => "native",
+            SpanKind::JlModule => "jl",
+            SpanKind::Lua => "lua",
+            SpanKind::LuaState => "lua_state",
+            SpanKind::Wasm => "wasm",
+            SpanKind::NativeModule => "native_module",
+            SpanKind::Plugin => "plugin",
+            SpanKind::Http => "http",
+            SpanKind::Exec => "exec",
+        }
+    }
+}
+
+/// 单项统计
+#[derive(Clone, Debug, Default)]
+pub struct SpanStats {
+    pub count: u64,
+    /// 总时间（递归调用只计算最外层）
+    pub total: Duration,
+    /// 自身时间（不含子操作）
+    pub self_time: Duration,
+}
+
+struct Frame {
+    kind: SpanKind,
+    name: String,
+    start: Instant,
+    child_time: Duration,
+}
+
+/// 性能分析器：记录嵌套的计时区间
+#[derive(Default)]
+pub struct Profiler {
+    stack: Vec<Frame>,
+    stats: HashMap<(SpanKind, String), SpanStats>,
+    // 调用栈（以;分隔）到自身时间的映射，用于生成火焰图
+    folded: HashMap<String, Duration>,
+    started: Option<Instant>,
+}
+
+// 快速判断是否启用，避免未启用时加锁
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PROFILER: Mutex<Option<Profiler>> = Mutex::new(None);
+
+/// 启用性能分析
+pub fn enable() {
+    let profiler = Profiler { started: Some(Instant::now()), ..Default::default() };
+    *PROFILER.lock().unwrap_or_else(|e| e.into_inner()) = Some(profiler);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 开始一个计时区间
+pub fn enter(kind: SpanKind, name: &str) {
+    if !is_enabled() {
+        return;
+    }
+    if let Some(profiler) = PROFILER.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        profiler.stack.push(Frame { kind, name: name.to_string(), start: Instant::now(), child_time: Duration::ZERO });
+    }
+}
+
+/// 结束最近开始的计时区间
+pub fn leave() {
+    if !is_enabled() {
+        return;
+    }
+    if let Some(profiler) = PROFILER.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        profiler.leave();
+    }
+}
+
+/// 开始计时区间，返回的守卫在离开作用域时结束区间
+pub fn span(kind: SpanKind, name: &str) -> SpanGuard {
+    let active = is_enabled();
+    if active {
+        enter(kind, name);
+    }
+    SpanGuard { active }
+}
+
+pub struct SpanGuard {
+    active: bool,
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        if self.active {
+            leave();
+        }
+    }
+}
+
+/// 停止分析并取出结果
+pub fn finish() -> Option<Profiler> {
+    if !is_enabled() {
+        return None;
+    }
+    ENABLED.store(false, Ordering::Relaxed);
+    let mut profiler = PROFILER.lock().unwrap_or_else(|e| e.into_inner()).take()?;
+    // 出错时可能有未结束的区间
+    while !profiler.stack.is_empty() {
+        profiler.leave();
+    }
+    Some(profiler)
+}
+
+impl Profiler {
+    fn leave(&mut self) {
+        let frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        let elapsed = frame.start.elapsed();
+        let self_time = elapsed.saturating_sub(frame.child_time);
+
+        let mut labels: Vec<String> = vec!["main".to_string()];
+        labels.extend(self.stack.iter().map(frame_label));
+        labels.push(frame_label(&frame));
+        *self.folded.entry(labels.join(";")).or_default() += self_time;
+
+        // 递归调用时只在最外层计入总时间
+        let recursive = self.stack.iter().any(|f| f.kind == frame.kind && f.name == frame.name);
+        let stats = self.stats.entry((frame.kind, frame.name)).or_default();
+        stats.count += 1;
+        stats.self_time += self_time;
+        if !recursive {
+            stats.total += elapsed;
+        }
+
+        if let Some(parent) = self.stack.last_mut() {
+            parent.child_time += elapsed;
+        }
+    }
+
+    /// 按自身时间从高到低排列的统计结果
+    pub fn sorted_stats(&self) -> Vec<(&(SpanKind, String), &SpanStats)> {
+        let mut entries: Vec<_> = self.stats.iter().collect();
+        entries.sort_by(|a, b| b.1.self_time.cmp(&a.1.self_time).then_with(|| a.0.cmp(b.0)));
+        entries
+    }
+
+    fn wall_time(&self) -> Duration {
+        self.started.map(|s| s.elapsed()).unwrap_or_default()
+    }
+
+    /// 生成文本表格
+    pub fn table(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("\n=== 性能分析 (总耗时 {:.3} ms) ===\n", ms(self.wall_time())));
+        out.push_str(&format!("{:<10} {:<32} {:>8} {:>12} {:>12} {:>12}\n", "类型", "名称", "调用次数", "总时间(ms)", "自身(ms)", "平均(ms)"));
+        for ((kind, name), stats) in self.sorted_stats() {
+            let average = if stats.count > 0 { ms(stats.total) / stats.count as f64 } else { 0.0 };
+            out.push_str(&format!("{:<10} {:<32} {:>8} {:>12.3} {:>12.3} {:>12.3}\n",
+                kind.name(), truncate(name, 32), stats.count, ms(stats.total), ms(stats.self_time), average));
+        }
+        let lua_state = self.kind_total(SpanKind::LuaState);
+        if lua_state.count > 0 {
+            out.push_str(&format!("Lua状态创建开销: {} 次，共 {:.3} ms\n", lua_state.count, ms(lua_state.total)));
+        }
+        out
+    }
+
+    fn kind_total(&self, kind: SpanKind) -> SpanStats {
+        self.stats.iter()
+            .filter(|((k, _), _)| *k == kind)
+            .fold(SpanStats::default(), |mut acc, (_, stats)| {
+                acc.count += stats.count;
+                acc.total += stats.total;
+                acc.self_time += stats.self_time;
+                acc
+            })
+    }
+
+    /// 生成折叠栈格式（每行：栈 自身时间微秒），可用于flamegraph.pl、speedscope等工具
+    pub fn folded_stacks(&self) -> String {
+        let mut lines: Vec<String> = self.folded.iter()
+            .filter(|(_, time)| time.as_micros() > 0)
+            .map(|(stack, time)| format!("{} {}", stack, time.as_micros()))
+            .collect();
+        lines.sort();
+        lines.join("\n") + "\n"
+    }
+
+    /// 生成JSON报告
+    pub fn json_report(&self) -> Value {
+        let entries: Vec<Value> = self.sorted_stats().into_iter()
+            .map(|((kind, name), stats)| json!({
+                "kind": kind.name(),
+                "name": name,
+                "count": stats.count,
+                "total_ms": ms(stats.total),
+                "self_ms": ms(stats.self_time),
+            }))
+            .collect();
+        let lua_state = self.kind_total(SpanKind::LuaState);
+        json!({
+            "wall_time_ms": ms(self.wall_time()),
+            "lua_state_overhead": {
+                "count": lua_state.count,
+                "total_ms": ms(lua_state.total),
+            },
+            "entries": entries,
+        })
+    }
+}
+
+fn frame_label(frame: &Frame) -> String {
+    // 折叠栈格式用;分隔帧，名称中不能包含;和空格
+    let name = frame.name.replace([';', ' '], "_");
+    match frame.kind {
+        SpanKind::Statement => name,
+        kind => format!("{}:{}", kind.name(), name),
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else {
+        let mut short: String = text.chars().take(width - 1).collect();
+        short.push('…');
+        short
+    }
+}
+
+/// 把语句和函数调用记录到性能分析器的执行钩子
+pub struct ProfilerHook;
+
+impl ExecutionHook for ProfilerHook {
+    fn before_statement(&mut self, stmt_type: &str, _context: &Context) -> Result<()> {
+        enter(SpanKind::Statement, stmt_type);
+        Ok(())
+    }
+
+    fn after_statement(&mut self, _stmt_type: &str, _context: &Context, _result: &Result<Value>) {
+        leave();
+    }
+
+    fn enter_function(&mut self, name: &str, _context: &Context) {
+        // 模块函数名形如 模块.函数
+        let kind = if name.contains('.') { SpanKind::JlModule } else { SpanKind::Function };
+        enter(kind, name);
+    }
+
+    fn leave_function(&mut self, _name: &str, _context: &Context) {
+        leave();
+    }
+}
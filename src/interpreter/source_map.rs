@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use serde_json::Value;
+
+/// 源码位置（行号和列号均从1开始，列号按UTF-16编码单元计算）
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SourcePosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// 源码中的字符串字面量（对象键或字符串值）
+#[derive(Clone, Debug)]
+pub struct StringToken {
+    /// 去掉引号后的内容
+    pub value: String,
+    /// 值的JSON路径；对象键使用其对应值的路径
+    pub path: String,
+    /// 是否是对象键
+    pub is_key: bool,
+    /// 开始引号的位置
+    pub start: SourcePosition,
+    /// 结束引号之后的位置
+    pub end: SourcePosition,
+}
+
+/// JSON路径到源码位置的映射
+///
+/// 路径使用JSON Pointer格式，例如 `/program/main/body/0`。
+/// 扫描时会跳过 `//` 和 `/* */` 注释，因此可以直接使用未经预处理的源码。
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    positions: HashMap<String, SourcePosition>,
+    strings: Vec<StringToken>,
+}
+
+impl SourceMap {
+    /// 从源码文本构建映射，遇到语法错误时保留已扫描到的部分
+    pub fn from_source(text: &str) -> Self {
+        let mut scanner = Scanner {
+            chars: text.chars().collect(),
+            pos: 0,
+            line: 1,
+            column: 1,
+            positions: HashMap::new(),
+            strings: Vec::new(),
+        };
+        scanner.skip_trivia();
+        scanner.scan_value(String::new());
+        SourceMap { positions: scanner.positions, strings: scanner.strings }
+    }
+
+    /// 获取指定路径的值在源码中的位置
+    pub fn position(&self, path: &str) -> Option<SourcePosition> {
+        self.positions.get(path).copied()
+    }
+
+    /// 获取所有字符串字面量，按出现顺序排列
+    pub fn strings(&self) -> &[StringToken] {
+        &self.strings
+    }
+
+    /// 查找包含指定位置的字符串字面量（包括两端的引号）
+    pub fn string_at(&self, position: SourcePosition) -> Option<&StringToken> {
+        self.strings.iter().find(|token| token.start <= position && position <= token.end)
+    }
+
+    /// 获取所有语句（块数组中的元素）的路径和位置，按行号排序
+    pub fn statements(&self) -> Vec<(&str, SourcePosition)> {
+        let mut result: Vec<(&str, SourcePosition)> = self.positions.iter()
+            .filter(|(path, _)| is_statement_path(path))
+            .map(|(path, pos)| (path.as_str(), *pos))
+            .collect();
+        result.sort_by_key(|(_, pos)| (pos.line, pos.column));
+        result
+    }
+
+    /// 查找位于指定行或其后的第一条语句所在的行号
+    pub fn statement_line_at_or_after(&self, line: usize) -> Option<usize> {
+        self.statements().into_iter()
+            .map(|(_, pos)| pos.line)
+            .find(|l| *l >= line)
+    }
+}
+
+/// 将对象键转义为JSON Pointer片段
+pub fn escape_segment(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+/// 获取程序结构中所有语句的路径，不依赖源码格式
+pub fn statement_paths(value: &Value) -> Vec<String> {
+    let mut paths = Vec::new();
+    collect_statement_paths(value, String::new(), &mut paths);
+    paths
+}
+
+fn collect_statement_paths(value: &Value, path: String, paths: &mut Vec<String>) {
+    if is_statement_path(&path) {
+        paths.push(path.clone());
+    }
+    match value {
+        Value::Object(map) => {
+            for (key, item) in map {
+                collect_statement_paths(item, format!("{}/{}", path, escape_segment(key)), paths);
+            }
+        },
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_statement_paths(item, format!("{}/{}", path, i), paths);
+            }
+        },
+        _ => {},
+    }
+}
+
+// 判断路径是否指向一条会被执行的语句：函数或测试的body中的语句，
+// 以及其中控制流语句的语句块里的语句，数据中同名的数组不算
+fn is_statement_path(path: &str) -> bool {
+    let segments: Vec<&str> = path.split('/').collect();
+    match segments.as_slice() {
+        ["", "program" | "tests", _, "body", index, rest @ ..] => is_index(index) && is_nested_statement(rest),
+        _ => false,
+    }
+}
+
+// rest为语句之后的路径片段：空表示语句本身，否则必须是语句类型加它的语句块中的一条语句
+fn is_nested_statement(rest: &[&str]) -> bool {
+    match rest {
+        [] => true,
+        ["if", "then" | "else", index, rest @ ..]
+        | ["while" | "for", "body", index, rest @ ..]
+        | ["switch", "default", index, rest @ ..]
+        | ["try", "try" | "catch", index, rest @ ..]
+        | ["assert.throws", "do", index, rest @ ..] => is_index(index) && is_nested_statement(rest),
+        ["switch", "cases", case, "do", index, rest @ ..] => is_index(case) && is_index(index) && is_nested_statement(rest),
+        _ => false,
+    }
+}
+
+fn is_index(segment: &str) -> bool {
+    segment.parse::<usize>().is_ok()
+}
+
+struct Scanner {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+    positions: HashMap<String, SourcePosition>,
+    strings: Vec<StringToken>,
+}
+
+impl Scanner {
+    fn position(&self) -> SourcePosition {
+        SourcePosition { line: self.line, column: self.column }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_next(&self) -> Option<char> {
+        self.chars.get(self.pos + 1).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.get(self.pos).copied()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += c.len_utf16();
+        }
+        Some(c)
+    }
+
+    // 跳过空白和注释
+    fn skip_trivia(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if c == '/' && self.peek_next() == Some('/') {
+                while let Some(c) = self.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    self.bump();
+                }
+            } else if c == '/' && self.peek_next() == Some('*') {
+                self.bump();
+                self.bump();
+                while let Some(c) = self.bump() {
+                    if c == '*' && self.peek() == Some('/') {
+                        self.bump();
+                        break;
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn scan_value(&mut self, path: String) -> bool {
+        self.positions.insert(path.clone(), self.position());
+        match self.peek() {
+            Some('{') => self.scan_object(path),
+            Some('[') => self.scan_array(path),
+            Some('"') | Some('\'') => self.scan_string(path, false).is_some(),
+            Some(_) => {
+                // 数字、true、false、null等标量
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    if c == ',' || c == '}' || c == ']' || c.is_whitespace() || c == '/' {
+                        break;
+                    }
+                    self.bump();
+                }
+                self.pos > start
+            },
+            None => false,
+        }
+    }
+
+    fn scan_object(&mut self, path: String) -> bool {
+        self.bump();
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                Some('}') => {
+                    self.bump();
+                    return true;
+                },
+                Some(',') => {
+                    self.bump();
+                    continue;
+                },
+                None => return false,
+                _ => {}
+            }
+
+            let key = match self.peek() {
+                Some('"') | Some('\'') => match self.scan_string(path.clone(), true) {
+                    Some(key) => key,
+                    None => return false,
+                },
+                _ => {
+                    // 未加引号的键
+                    let mut key = String::new();
+                    while let Some(c) = self.peek() {
+                        if c == ':' || c.is_whitespace() {
+                            break;
+                        }
+                        key.push(c);
+                        self.bump();
+                    }
+                    key
+                }
+            };
+
+            self.skip_trivia();
+            if self.peek() != Some(':') {
+                return false;
+            }
+            self.bump();
+            self.skip_trivia();
+
+            let child = format!("{}/{}", path, escape_segment(&key));
+            if let Some(token) = self.strings.last_mut() {
+                if token.is_key && token.path == path {
+                    token.path = child.clone();
+                }
+            }
+            if !self.scan_value(child) {
+                return false;
+            }
+        }
+    }
+
+    fn scan_array(&mut self, path: String) -> bool {
+        self.bump();
+        let mut index = 0;
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                Some(']') => {
+                    self.bump();
+                    return true;
+                },
+                Some(',') => {
+                    self.bump();
+                    continue;
+                },
+                None => return false,
+                _ => {}
+            }
+
+            let child = format!("{}/{}", path, index);
+            if !self.scan_value(child) {
+                return false;
+            }
+            index += 1;
+        }
+    }
+
+    fn scan_string(&mut self, path: String, is_key: bool) -> Option<String> {
+        let start = self.position();
+        let quote = self.bump()?;
+        let mut value = String::new();
+        while let Some(c) = self.bump() {
+            if c == '\\' {
+                if let Some(escaped) = self.bump() {
+                    value.push(escaped);
+                }
+            } else if c == quote {
+                self.strings.push(StringToken {
+                    value: value.clone(),
+                    path,
+                    is_key,
+                    start,
+                    end: self.position(),
+                });
+                return Some(value);
+            } else {
+                value.push(c);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn statement_paths_skip_data_arrays() {
+        let program = json!({"program": {"main": {"body": [
+            {"var": {"payload": {"body": [1, 2], "then": [3]}}},
+            {"if": {"condition": true, "then": [{"echo": ["a"]}], "else": [{"while": {"condition": false, "body": [{"echo": ["b"]}]}}]}},
+            {"switch": {"value": 1, "cases": [{"value": 1, "do": [{"echo": ["c"]}]}], "default": [{"echo": ["d"]}]}},
+            {"try": {"try": [{"echo": ["e"]}], "catch": [{"echo": ["f"]}]}}
+        ]}}, "tests": {"t": {"body": [{"assert.throws": {"do": [{"echo": ["g"]}]}}]}}});
+        let mut paths = statement_paths(&program);
+        paths.sort();
+        assert_eq!(paths, vec![
+            "/program/main/body/0",
+            "/program/main/body/1",
+            "/program/main/body/1/if/else/0",
+            "/program/main/body/1/if/else/0/while/body/0",
+            "/program/main/body/1/if/then/0",
+            "/program/main/body/2",
+            "/program/main/body/2/switch/cases/0/do/0",
+            "/program/main/body/2/switch/default/0",
+            "/program/main/body/3",
+            "/program/main/body/3/try/catch/0",
+            "/program/main/body/3/try/try/0",
+            "/tests/t/body/0",
+            "/tests/t/body/0/assert.throws/do/0",
+        ]);
+    }
+}
@@ -0,0 +1,315 @@
+use serde_json::Value;
+use regex::Regex;
+use super::super::context::Context;
+use super::super::error::{InterpreterError, Result};
+use super::{execute_statement_at, evaluate_condition, get_number_value};
+
+// approx默认允许的误差
+const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+// 获取数组形式的参数，不足min个时报错
+fn positional_args<'a>(name: &str, args: &'a Value, min: usize, usage: &str) -> Result<&'a Vec<Value>> {
+    match args.as_array() {
+        Some(items) if items.len() >= min => Ok(items),
+        _ => Err(InterpreterError::RuntimeError(
+            format!("杂鱼~'{}' 的参数必须是数组：{}", name, usage)
+        )),
+    }
+}
+
+// 可选的断言说明，放在失败信息的开头
+fn failure(name: &str, message: Option<&Value>, context: &Context, detail: String) -> InterpreterError {
+    let message = message.map(|m| context.resolve_value(m)).filter(|m| !m.is_empty());
+    match message {
+        Some(message) => InterpreterError::AssertionError(format!("{}: {}\n{}", name, message, detail)),
+        None => InterpreterError::AssertionError(format!("{}\n{}", name, detail)),
+    }
+}
+
+/// 严格比较两个值：类型必须相同，数字按数值比较（1 与 1.0 相等）
+pub fn deep_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        (Value::Array(x), Value::Array(y)) => x.len() == y.len() && x.iter().zip(y).all(|(a, b)| deep_equal(a, b)),
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(k, v)| y.get(k).is_some_and(|w| deep_equal(v, w)))
+        },
+        _ => a == b,
+    }
+}
+
+// 宽松比较：字符串与数字、布尔值按文本形式比较，例如 "1" 与 1 相等
+fn loose_equal(a: &Value, b: &Value) -> bool {
+    if deep_equal(a, b) {
+        return true;
+    }
+    match (a, b) {
+        (Value::String(s), other) | (other, Value::String(s)) if !other.is_string() => match other {
+            Value::Number(n) => s.trim().parse::<f64>().ok() == n.as_f64(),
+            Value::Bool(_) | Value::Null => s == &other.to_string(),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// 值的单行JSON表示，过长时使用多行格式
+fn show(value: &Value) -> String {
+    let compact = value.to_string();
+    if compact.chars().count() <= 80 {
+        compact
+    } else {
+        serde_json::to_string_pretty(value).unwrap_or(compact)
+            .replace('\n', "\n        ")
+    }
+}
+
+// 收集期望值和实际值之间的差异，路径使用JSON Pointer格式
+fn collect_differences(expected: &Value, actual: &Value, path: &str, out: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            for (key, value) in e {
+                let child = format!("{}/{}", path, super::escape_segment(key));
+                match a.get(key) {
+                    Some(other) => collect_differences(value, other, &child, out),
+                    None => out.push(format!("{}: 缺少键，期望 {}", child, value)),
+                }
+            }
+            for (key, value) in a {
+                if !e.contains_key(key) {
+                    out.push(format!("{}/{}: 多余的键，实际 {}", path, super::escape_segment(key), value));
+                }
+            }
+        },
+        (Value::Array(e), Value::Array(a)) => {
+            for i in 0..e.len().max(a.len()) {
+                let child = format!("{}/{}", path, i);
+                match (e.get(i), a.get(i)) {
+                    (Some(x), Some(y)) => collect_differences(x, y, &child, out),
+                    (Some(x), None) => out.push(format!("{}: 缺少元素，期望 {}", child, x)),
+                    (None, Some(y)) => out.push(format!("{}: 多余的元素，实际 {}", child, y)),
+                    (None, None) => {},
+                }
+            }
+        },
+        _ if !deep_equal(expected, actual) => {
+            let path = if path.is_empty() { "/" } else { path };
+            out.push(format!("{}: 期望 {}，实际 {}", path, expected, actual));
+        },
+        _ => {},
+    }
+}
+
+/// 生成期望值和实际值的对比信息
+pub fn diff(expected: &Value, actual: &Value) -> String {
+    let mut text = format!("  期望: {}\n  实际: {}", show(expected), show(actual));
+    if expected.is_object() || expected.is_array() {
+        let mut differences = Vec::new();
+        collect_differences(expected, actual, "", &mut differences);
+        if !differences.is_empty() {
+            text.push_str("\n  差异:");
+            for difference in differences {
+                text.push_str(&format!("\n    {}", difference));
+            }
+        }
+    }
+    text
+}
+
+// 文本的差异：指出第一个不同的行
+fn text_diff(expected: &str, actual: &str) -> String {
+    let mut text = format!("  期望: {}\n  实际: {}", Value::from(expected), Value::from(actual));
+    let expected_lines: Vec<&str> = expected.split('\n').collect();
+    let actual_lines: Vec<&str> = actual.split('\n').collect();
+    for i in 0..expected_lines.len().max(actual_lines.len()) {
+        let (e, a) = (expected_lines.get(i), actual_lines.get(i));
+        if e != a {
+            text.push_str(&format!("\n  第 {} 行不同:\n    - {}\n    + {}",
+                i + 1, e.copied().unwrap_or("<无>"), a.copied().unwrap_or("<无>")));
+            break;
+        }
+    }
+    text
+}
+
+// 值是否为真：false、0、null、空字符串、"false"、空数组和空对象为假
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty() && s != "false",
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+// execute_assert_equal - 断言两个值相等（字符串与数字按文本比较）
+pub fn execute_assert_equal(args: &Value, context: &mut Context) -> Result<Value> {
+    let items = positional_args("assert.equal", args, 2, "[实际值, 期望值, 说明?]")?;
+    let actual = context.resolve_value_raw(&items[0])?;
+    let expected = context.resolve_value_raw(&items[1])?;
+    if loose_equal(&actual, &expected) {
+        Ok(Value::Bool(true))
+    } else {
+        Err(failure("assert.equal", items.get(2), context, diff(&expected, &actual)))
+    }
+}
+
+// execute_assert_deep_equal - 断言两个值的结构和类型完全相同
+pub fn execute_assert_deep_equal(args: &Value, context: &mut Context) -> Result<Value> {
+    let items = positional_args("assert.deep_equal", args, 2, "[实际值, 期望值, 说明?]")?;
+    let actual = context.resolve_value_raw(&items[0])?;
+    let expected = context.resolve_value_raw(&items[1])?;
+    if deep_equal(&actual, &expected) {
+        Ok(Value::Bool(true))
+    } else {
+        Err(failure("assert.deep_equal", items.get(2), context, diff(&expected, &actual)))
+    }
+}
+
+// execute_assert_not_equal - 断言两个值不相等
+pub fn execute_assert_not_equal(args: &Value, context: &mut Context) -> Result<Value> {
+    let items = positional_args("assert.not_equal", args, 2, "[实际值, 不期望的值, 说明?]")?;
+    let actual = context.resolve_value_raw(&items[0])?;
+    let unexpected = context.resolve_value_raw(&items[1])?;
+    if loose_equal(&actual, &unexpected) {
+        Err(failure("assert.not_equal", items.get(2), context, format!("  两个值都是: {}", show(&actual))))
+    } else {
+        Ok(Value::Bool(true))
+    }
+}
+
+// execute_assert_true - 断言条件成立或值为真
+pub fn execute_assert_true(args: &Value, context: &mut Context) -> Result<Value> {
+    let items = positional_args("assert.true", args, 1, "[条件或值, 说明?]")?;
+    let passed = match &items[0] {
+        condition @ Value::Object(obj) if obj.contains_key("op") => evaluate_condition(condition, context),
+        value => is_truthy(&context.resolve_value_raw(value)?),
+    };
+    if passed {
+        Ok(Value::Bool(true))
+    } else {
+        Err(failure("assert.true", items.get(1), context, format!("  条件不成立: {}", show(&items[0]))))
+    }
+}
+
+// execute_assert_throws - 断言语句块执行时出错，可检查错误信息包含的文本
+pub fn execute_assert_throws(args: &Value, context: &mut Context) -> Result<Value> {
+    let obj = args.as_object().ok_or_else(|| InterpreterError::RuntimeError(
+        "杂鱼~'assert.throws' 的参数必须是对象：{\"do\": [语句...], \"error\": 错误信息包含的文本?, \"message\": 说明?}".to_string()
+    ))?;
+    let statements = obj.get("do").and_then(|d| d.as_array()).ok_or_else(|| InterpreterError::RuntimeError(
+        "杂鱼~'assert.throws' 缺少 'do' 语句数组".to_string()
+    ))?;
+
+    let mut error = None;
+    for (i, stmt) in statements.iter().enumerate() {
+        if let Some((stmt_type, stmt_args)) = stmt.as_object().and_then(|o| o.iter().next()) {
+            if let Err(e) = execute_statement_at(&format!("assert.throws/do/{}", i), stmt_type, stmt_args, context, Some(stmt)) {
+                error = Some(e);
+                break;
+            }
+        }
+    }
+
+    let error = match error {
+        Some(error) => error,
+        None => return Err(failure("assert.throws", obj.get("message"), context, "  语句块没有出错".to_string())),
+    };
+    let error_message = error.message().to_string();
+    if let Some(expected) = obj.get("error") {
+        let expected = context.resolve_value(expected);
+        if !error_message.contains(&expected) {
+            return Err(failure("assert.throws", obj.get("message"), context,
+                format!("  错误信息应包含: {}\n  实际错误信息: {}", Value::from(expected), Value::from(error_message.clone()))));
+        }
+    }
+
+    let result = Value::String(error_message);
+    if let Some(var_name) = obj.get("output").and_then(|o| o.as_str()) {
+        context.set_variable(var_name.to_string(), result.clone())?;
+    }
+    Ok(result)
+}
+
+// execute_assert_matches - 断言文本匹配正则表达式
+pub fn execute_assert_matches(args: &Value, context: &mut Context) -> Result<Value> {
+    let items = positional_args("assert.matches", args, 2, "[文本, 正则表达式, 说明?]")?;
+    let text = context.resolve_value(&items[0]);
+    let pattern = context.resolve_value(&items[1]);
+    let regex = Regex::new(&pattern).map_err(|e| InterpreterError::RuntimeError(
+        format!("杂鱼~正则表达式编译错误: {}", e)
+    ))?;
+    if regex.is_match(&text) {
+        Ok(Value::Bool(true))
+    } else {
+        Err(failure("assert.matches", items.get(2), context,
+            format!("  文本: {}\n  不匹配: /{}/", Value::from(text), pattern)))
+    }
+}
+
+// execute_assert_approx - 断言两个数在误差范围内相等
+pub fn execute_assert_approx(args: &Value, context: &mut Context) -> Result<Value> {
+    let items = positional_args("assert.approx", args, 2, "[实际值, 期望值, 误差?, 说明?]")?;
+    let number = |value: &Value| get_number_value(value, context).ok_or_else(|| InterpreterError::RuntimeError(
+        format!("杂鱼~'assert.approx' 需要数字，但得到了 {}", context.resolve_value(value))
+    ));
+    let actual = number(&items[0])?;
+    let expected = number(&items[1])?;
+    let tolerance = match items.get(2) {
+        Some(value) => number(value)?,
+        None => DEFAULT_TOLERANCE,
+    };
+    if (actual - expected).abs() <= tolerance {
+        Ok(Value::Bool(true))
+    } else {
+        Err(failure("assert.approx", items.get(3), context,
+            format!("  期望: {} ± {}\n  实际: {}（相差 {}）", expected, tolerance, actual, (actual - expected).abs())))
+    }
+}
+
+// execute_assert_output - 断言测试中捕获的输出，数组形式比较全部输出，对象形式可用equals、contains或matches
+pub fn execute_assert_output(args: &Value, context: &mut Context) -> Result<Value> {
+    let output = context.captured_output().map(|s| s.to_string()).ok_or_else(|| InterpreterError::RuntimeError(
+        "杂鱼~'assert.output' 只能在 jlang test 运行的测试中使用".to_string()
+    ))?;
+
+    if let Some(items) = args.as_array() {
+        let expected = items.first().map(|e| context.resolve_value(e)).unwrap_or_default();
+        return if output == expected {
+            Ok(Value::Bool(true))
+        } else {
+            Err(failure("assert.output", items.get(1), context, text_diff(&expected, &output)))
+        };
+    }
+
+    let obj = args.as_object().ok_or_else(|| InterpreterError::RuntimeError(
+        "杂鱼~'assert.output' 的参数必须是数组或包含 equals、contains、matches 之一的对象".to_string()
+    ))?;
+    let message = obj.get("message");
+    if let Some(expected) = obj.get("equals") {
+        let expected = context.resolve_value(expected);
+        if output != expected {
+            return Err(failure("assert.output", message, context, text_diff(&expected, &output)));
+        }
+    }
+    if let Some(expected) = obj.get("contains") {
+        let expected = context.resolve_value(expected);
+        if !output.contains(&expected) {
+            return Err(failure("assert.output", message, context,
+                format!("  输出应包含: {}\n  实际输出: {}", Value::from(expected), Value::from(output.clone()))));
+        }
+    }
+    if let Some(pattern) = obj.get("matches") {
+        let pattern = context.resolve_value(pattern);
+        let regex = Regex::new(&pattern).map_err(|e| InterpreterError::RuntimeError(
+            format!("杂鱼~正则表达式编译错误: {}", e)
+        ))?;
+        if !regex.is_match(&output) {
+            return Err(failure("assert.output", message, context,
+                format!("  输出: {}\n  不匹配: /{}/", Value::from(output.clone()), pattern)));
+        }
+    }
+    Ok(Value::Bool(true))
+}
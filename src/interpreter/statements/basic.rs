@@ -391,7 +391,7 @@ pub fn execute_echo_statement(args: &Value, context: &mut Context) -> Result<Val
             };
             
             output.push_str(&text);
-            print!("{}", text);
+            context.write_output(&text);
         }
         // 返回输出的完整字符串
         let result = Value::String(output);
@@ -439,7 +439,7 @@ pub fn execute_echo_statement(args: &Value, context: &mut Context) -> Result<Val
             };
             
             output_text.push_str(&text);
-            print!("{}", text);
+            context.write_output(&text);
         }
         
         // 创建输出值
@@ -4,7 +4,7 @@ use super::super::error::{InterpreterError, Result};
 use super::super::error::error_messages::statement::{self, control_flow, switch, try_catch};
 use super::basic::evaluate_condition;
 use super::get_number_value;
-use super::execute_statement;
+use super::execute_statement_at;
 use super::super::variable_reference::{VariableReference, ReferenceType};
 use super::store_result_with_compatibility;
 
@@ -13,18 +13,21 @@ pub fn execute_if_statement(args: &Value, context: &mut Context) -> Result<Value
     if let Some(obj) = args.as_object() {
         if let (Some(condition), Some(then_block)) = (obj.get("condition"), obj.get("then")) {
             let condition_result = evaluate_condition(condition, context);
-            let block = if condition_result {
-                then_block
+            let (branch, block) = if condition_result {
+                ("then", then_block)
             } else {
-                obj.get("else").unwrap_or(then_block)
+                match obj.get("else") {
+                    Some(else_block) => ("else", else_block),
+                    None => ("then", then_block),
+                }
             };
 
             let mut last_result = Value::Null;
             if let Some(statements) = block.as_array() {
-                for stmt in statements {
+                for (i, stmt) in statements.iter().enumerate() {
                     if let Some(obj) = stmt.as_object() {
                         if let Some((stmt_type, args)) = obj.iter().next() {
-                            last_result = execute_statement(stmt_type, args, context, None)?;
+                            last_result = execute_statement_at(&format!("if/{}/{}", branch, i), stmt_type, args, context, None)?;
                         }
                     }
                 }
@@ -50,10 +53,10 @@ pub fn execute_while_statement(args: &Value, context: &mut Context) -> Result<Va
             
             while evaluate_condition(condition, context) {
                 if let Some(statements) = body.as_array() {
-                    for stmt in statements {
+                    for (i, stmt) in statements.iter().enumerate() {
                         if let Some(obj) = stmt.as_object() {
                             if let Some((stmt_type, args)) = obj.iter().next() {
-                                last_result = execute_statement(stmt_type, args, context, None)?;
+                                last_result = execute_statement_at(&format!("while/body/{}", i), stmt_type, args, context, None)?;
                             }
                         }
                     }
@@ -128,10 +131,10 @@ pub fn execute_for_statement(args: &Value, context: &mut Context) -> Result<Valu
                     
                     // 执行循环体
                     if let Some(statements) = body.as_array() {
-                        for stmt in statements {
+                        for (i, stmt) in statements.iter().enumerate() {
                             if let Some(obj) = stmt.as_object() {
                                 if let Some((stmt_type, args)) = obj.iter().next() {
-                                    last_result = execute_statement(stmt_type, args, context, None)?;
+                                    last_result = execute_statement_at(&format!("for/body/{}", i), stmt_type, args, context, None)?;
                                 }
                             }
                         }
@@ -181,10 +184,10 @@ pub fn execute_for_statement(args: &Value, context: &mut Context) -> Result<Valu
                         context.set_variable(var_name.to_string(), Value::Number(serde_json::Number::from_f64(current).unwrap()))?;
                         
                         if let Some(statements) = body.as_array() {
-                            for stmt in statements {
+                            for (i, stmt) in statements.iter().enumerate() {
                                 if let Some(obj) = stmt.as_object() {
                                     if let Some((stmt_type, args)) = obj.iter().next() {
-                                        last_result = execute_statement(stmt_type, args, context, None)?;
+                                        last_result = execute_statement_at(&format!("for/body/{}", i), stmt_type, args, context, None)?;
                                     }
                                 }
                             }
@@ -234,10 +237,10 @@ pub fn execute_for_statement(args: &Value, context: &mut Context) -> Result<Valu
                     context.set_variable(var_name.to_string(), Value::Number(serde_json::Number::from_f64(current).unwrap()))?;
                     
                     if let Some(statements) = body.as_array() {
-                        for stmt in statements {
+                        for (i, stmt) in statements.iter().enumerate() {
                             if let Some(obj) = stmt.as_object() {
                                 if let Some((stmt_type, args)) = obj.iter().next() {
-                                    last_result = execute_statement(stmt_type, args, context, None)?;
+                                    last_result = execute_statement_at(&format!("for/body/{}", i), stmt_type, args, context, None)?;
                                 }
                             }
                         }
@@ -282,7 +285,7 @@ pub fn execute_switch_statement(args: &Value, context: &mut Context) -> Result<V
             
             if let Some(cases_array) = cases.as_array() {
                 // 遍历所有case
-                for case in cases_array {
+                for (case_index, case) in cases_array.iter().enumerate() {
                     if let Some(case_obj) = case.as_object() {
                         if let (Some(case_value), Some(statements)) = (case_obj.get("case"), case_obj.get("do")) {
                             let case_val = context.resolve_value(case_value);
@@ -298,10 +301,10 @@ pub fn execute_switch_statement(args: &Value, context: &mut Context) -> Result<V
                                 executed = true;
                                 // 执行匹配的case
                                 if let Some(statements_array) = statements.as_array() {
-                                    for stmt in statements_array {
+                                    for (i, stmt) in statements_array.iter().enumerate() {
                                         if let Some(obj) = stmt.as_object() {
                                             if let Some((stmt_type, args)) = obj.iter().next() {
-                                                last_result = execute_statement(stmt_type, args, context, None)?;
+                                                last_result = execute_statement_at(&format!("switch/cases/{}/do/{}", case_index, i), stmt_type, args, context, None)?;
                                             }
                                         }
                                     }
@@ -316,10 +319,10 @@ pub fn execute_switch_statement(args: &Value, context: &mut Context) -> Result<V
                 if !executed {
                     if let Some(default_block) = obj.get("default") {
                         if let Some(statements) = default_block.as_array() {
-                        for stmt in statements {
+                        for (i, stmt) in statements.iter().enumerate() {
                             if let Some(obj) = stmt.as_object() {
                                 if let Some((stmt_type, args)) = obj.iter().next() {
-                                        last_result = execute_statement(stmt_type, args, context, None)?;
+                                        last_result = execute_statement_at(&format!("switch/default/{}", i), stmt_type, args, context, None)?;
                                     }
                                 }
                             }
@@ -360,20 +363,14 @@ pub fn execute_try_statement(args: &Value, context: &mut Context) -> Result<Valu
             let mut had_error = false;
         
             if let Some(statements) = try_block.as_array() {
-                for stmt in statements {
+                for (i, stmt) in statements.iter().enumerate() {
             if let Some(obj) = stmt.as_object() {
                 if let Some((stmt_type, args)) = obj.iter().next() {
-                    match execute_statement(stmt_type, args, context, None) {
+                    match execute_statement_at(&format!("try/try/{}", i), stmt_type, args, context, None) {
                                 Ok(res) => result = res,
                         Err(e) => {
                                     // 捕获错误，存储错误信息
-                                    let error_msg = match &e {
-                                        InterpreterError::RuntimeError(msg) => msg.clone(),
-                                        InterpreterError::FunctionError(msg) => msg.clone(),
-                                        InterpreterError::ModuleError(msg) => msg.clone(),
-                                        InterpreterError::InvalidProgramStructure(msg) => msg.clone(),
-                                        InterpreterError::VariableError(msg) => msg.clone(),
-                                    };
+                                    let error_msg = e.message().to_string();
                                     
                                     // 设置错误变量
                                     context.set_variable(error_var.to_string(), Value::String(error_msg))?;
@@ -389,10 +386,10 @@ pub fn execute_try_statement(args: &Value, context: &mut Context) -> Result<Valu
             // 如果有错误，执行catch块
             if had_error {
                 if let Some(statements) = catch_block.as_array() {
-                    for stmt in statements {
+                    for (i, stmt) in statements.iter().enumerate() {
                 if let Some(obj) = stmt.as_object() {
                     if let Some((stmt_type, args)) = obj.iter().next() {
-                                result = execute_statement(stmt_type, args, context, None)?;
+                                result = execute_statement_at(&format!("try/catch/{}", i), stmt_type, args, context, None)?;
                             }
                         }
                     }
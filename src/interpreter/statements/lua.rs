@@ -0,0 +1,47 @@
+use serde_json::Value;
+use super::super::context::Context;
+use super::super::error::{InterpreterError, Result};
+use super::super::error::error_messages::statement::lua;
+use super::super::profiler;
+use super::store_result_with_compatibility;
+
+// execute_lua_statement - 在解释器共用的Lua状态中执行内联Lua代码块
+//
+// {"lua": {"code": "...", "args": {...}, "output": "x"}}，code也可以是按行分开的字符串数组。
+// args中的值可以是变量引用，代码块通过局部变量args读取，返回值保存到output变量。
+pub fn execute_lua_statement(args: &Value, context: &mut Context) -> Result<Value> {
+    let obj = args.as_object().ok_or_else(|| InterpreterError::RuntimeError(
+        lua::ARGS_NOT_OBJ.to_string()
+    ))?;
+    
+    let code = match obj.get("code") {
+        Some(Value::String(code)) => code.clone(),
+        Some(Value::Array(lines)) => lines.iter()
+            .map(|line| line.as_str().ok_or_else(|| InterpreterError::RuntimeError(lua::CODE_NOT_STRING.to_string())))
+            .collect::<Result<Vec<_>>>()?
+            .join("\n"),
+        Some(_) => return Err(InterpreterError::RuntimeError(lua::CODE_NOT_STRING.to_string())),
+        None => return Err(InterpreterError::RuntimeError(lua::MISSING_CODE.to_string())),
+    };
+    
+    // 解析参数中的变量引用
+    let mut lua_args = serde_json::Map::new();
+    match obj.get("args") {
+        Some(Value::Object(values)) => {
+            for (name, value) in values {
+                lua_args.insert(name.clone(), context.resolve_value_raw(value)?);
+            }
+        },
+        None | Some(Value::Null) => {},
+        Some(_) => return Err(InterpreterError::RuntimeError(lua::ARGS_MUST_BE_OBJ.to_string())),
+    }
+    
+    let runtime = context.inline_lua()?;
+    let result = {
+        let _span = profiler::span(profiler::SpanKind::Lua, "lua");
+        runtime.run(&code, &Value::Object(lua_args), context)?
+    };
+    
+    store_result_with_compatibility(args, &result, context)?;
+    Ok(result)
+}
@@ -5,13 +5,16 @@ mod function;
 mod array;
 mod object;
 mod exec;
+mod lua;
 mod regex;
+mod assert;
 
 use serde_json::Value;
 use super::context::Context;
 use super::error::{InterpreterError, Result};
 use super::error::error_messages::statement;
 use super::variable_reference::{VariableReference, ReferenceType};
+use super::source_map::escape_segment;
 use crate::modules::jl_module;
 use crate::modules::external_module;
 use crate::modules::external_module::ExternalModule;
@@ -26,6 +29,8 @@ pub use array::*;
 pub use object::*;
 pub use regex::*;
 pub use exec::*;
+pub use lua::*;
+pub use assert::*;
 
 // 兼容性辅助函数 - 存储结果到result和可选的output变量
 pub fn store_result_with_compatibility(args: &Value, result: &Value, context: &mut Context) -> Result<()> {
@@ -44,6 +49,25 @@ pub fn store_result_with_compatibility(args: &Value, result: &Value, context: &m
     Ok(())
 }
 
+// 在指定位置执行语句：记录执行位置并调用执行钩子
+// segment是相对当前位置的路径片段（如 "if/then/0"），以'/'开头时为绝对路径
+pub fn execute_statement_at(segment: &str, stmt_type: &str, args: &Value, context: &mut Context, full_stmt: Option<&Value>) -> Result<Value> {
+    // --watch-modules 时在语句之间重新加载修改过的模块
+    context.reload_changed_modules();
+    if !context.has_hooks() {
+        return execute_statement(stmt_type, args, context, full_stmt);
+    }
+
+    context.push_location(segment);
+    let result = match context.run_before_hooks(stmt_type) {
+        Ok(()) => execute_statement(stmt_type, args, context, full_stmt),
+        Err(e) => Err(e),
+    };
+    context.run_after_hooks(stmt_type, &result);
+    context.pop_location();
+    result
+}
+
 // 这是主要的语句执行函数，调度到各个具体的语句处理器
 // 现在直接返回结果值，同时保持向后兼容性
 pub fn execute_statement(stmt_type: &str, args: &Value, context: &mut Context, full_stmt: Option<&Value>) -> Result<Value> {
@@ -62,6 +86,7 @@ pub fn execute_statement(stmt_type: &str, args: &Value, context: &mut Context, f
         "while" => return execute_while_statement(args, context),
         "for" => return execute_for_statement(args, context),
         "exec" => return execute_exec_statement(args, context),
+        "lua" => return execute_lua_statement(args, context),
         "switch" => return execute_switch_statement(args, context),
         "try" => return execute_try_statement(args, context),
         "get_property" => return execute_get_property_statement(args, context),
@@ -83,6 +108,14 @@ pub fn execute_statement(stmt_type: &str, args: &Value, context: &mut Context, f
         "regex.test" => return execute_regex_test(args, context),
         "regex.replace" => return execute_regex_replace(args, context),
         "regex.split" => return execute_regex_split(args, context),
+        "assert.equal" => return execute_assert_equal(args, context),
+        "assert.deep_equal" => return execute_assert_deep_equal(args, context),
+        "assert.not_equal" => return execute_assert_not_equal(args, context),
+        "assert.true" => return execute_assert_true(args, context),
+        "assert.throws" => return execute_assert_throws(args, context),
+        "assert.matches" => return execute_assert_matches(args, context),
+        "assert.approx" => return execute_assert_approx(args, context),
+        "assert.output" => return execute_assert_output(args, context),
         _ => {}  // 不是内置语句，继续处理
     }
     
@@ -126,14 +159,17 @@ pub fn execute_statement(stmt_type: &str, args: &Value, context: &mut Context, f
             
             // 2. 检查是否是JLang模块类型，并且尝试获取函数定义
             let mut func_def_opt: Option<Value> = None;
+            let mut func_source: Option<String> = None;
             let mut is_jlang_module = false;
             
-            if let Some(module) = context.modules.get(module_name) {
+            if let Some(shared) = context.modules.get(module_name) {
+                let module = shared.borrow();
                 // 检查JlModule类型
                 if let Some(jl_module) = module.as_any().downcast_ref::<jl_module::JlModule>() {
                     if let Some(func_def) = jl_module.get_function(function_name) {
                         is_jlang_module = true;
                         func_def_opt = Some(func_def.clone());
+                        func_source = Some(jl_module.get_path().to_string());
                     }
                 }
                 
@@ -142,6 +178,7 @@ pub fn execute_statement(stmt_type: &str, args: &Value, context: &mut Context, f
                     if let Some(func_def) = external_module.get_jlang_function(function_name) {
                         is_jlang_module = true;
                         func_def_opt = Some(func_def);
+                        func_source = Some(external_module.get_path().to_string());
                     }
                 }
             }
@@ -164,7 +201,10 @@ pub fn execute_statement(stmt_type: &str, args: &Value, context: &mut Context, f
                 }
                 
                 let params = Value::Object(params_map);
-                return execute_function(&func_def, context, Some(&params));
+                context.enter_function(stmt_type, func_source, format!("/program/{}", escape_segment(function_name)));
+                let result = execute_function(&func_def, context, Some(&params));
+                context.leave_function();
+                return result;
             }
             
             // 4. 如果不是JLang模块或找不到函数，尝试标准模块处理
@@ -219,7 +259,7 @@ pub fn execute_statement(stmt_type: &str, args: &Value, context: &mut Context, f
                     let module_name = parts[0];
                     let function_name = parts[1];
                     if let Some(module) = context.modules.get(module_name) {
-                        for (fname, _) in module.get_functions() {
+                        for (fname, _) in module.borrow().get_functions() {
                             if fname == function_name {
                                 return Err(InterpreterError::FunctionError(
                                     super::error::error_messages::context::function_name_conflict_module(stmt_type)
@@ -265,7 +305,11 @@ pub fn execute_statement(stmt_type: &str, args: &Value, context: &mut Context, f
             }
 
             let params = Value::Object(params_map);
-            return execute_function(&func, context, Some(&params));
+            let source = context.current_path.clone();
+            context.enter_function(stmt_type, source, format!("/program/{}", escape_segment(stmt_type)));
+            let result = execute_function(&func, context, Some(&params));
+            context.leave_function();
+            return result;
         }
     }
 
@@ -274,12 +318,19 @@ pub fn execute_statement(stmt_type: &str, args: &Value, context: &mut Context, f
     ))
 }
 
+// 所有内置语句名称
+pub const BUILTIN_STATEMENTS: &[&str] = &[
+    "var", "echo", "concat", "if", "while", "for", "comment", "exec", "lua", "switch", "try", "return", "get_property",
+    "array.create", "array.push", "array.pop", "array.get", "array.set", "array.length", "array.slice",
+    "object.create", "object.get", "object.set", "object.has", "object.keys", "object.values", "object.delete",
+    "regex.match", "regex.test", "regex.replace", "regex.split",
+    "assert.equal", "assert.deep_equal", "assert.not_equal", "assert.true", "assert.throws",
+    "assert.matches", "assert.approx", "assert.output",
+];
+
 // 检查是否是内置语句
 pub fn is_builtin_statement(name: &str) -> bool {
-    matches!(name, "var" | "echo" | "concat" | "if" | "while" | "for" | "comment" | "exec" | "switch" | "return" | "get_property"
-             | "array.create" | "array.push" | "array.pop" | "array.get" | "array.set" | "array.length" | "array.slice"
-             | "object.create" | "object.get" | "object.set" | "object.has" | "object.keys" | "object.values" | "object.delete"
-             | "regex.match" | "regex.test" | "regex.replace" | "regex.split")
+    BUILTIN_STATEMENTS.contains(&name)
 }
 
 // 帮助函数：获取数值
@@ -447,7 +498,7 @@ pub fn execute_function(func: &Value, context: &mut Context, params: Option<&Val
 
     // 执行函数体
     let mut last_result = Value::Null;
-    for stmt in statements {
+    for (i, stmt) in statements.iter().enumerate() {
         if let Some(obj) = stmt.as_object() {
             if let Some((stmt_type, args)) = obj.iter().next() {
                 // 检查是否有嵌套的模块函数调用
@@ -473,7 +524,7 @@ pub fn execute_function(func: &Value, context: &mut Context, params: Option<&Val
                 }
                 
                 // 执行语句并获取结果
-                last_result = execute_statement(stmt_type, args, context, None)?;
+                last_result = execute_statement_at(&format!("body/{}", i), stmt_type, args, context, None)?;
                 
                 // 检查是否遇到return语句
                 if context.is_returning() {
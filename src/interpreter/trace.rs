@@ -0,0 +1,150 @@
+// 不确定输入的录制和回放
+//
+// 随机数、用户输入、环境变量、外部命令的输出和当前时间都通过 `capture` 获取。
+// 录制时按顺序记下每个值，回放时按同样的顺序返回记录的值，从而精确重现一次运行。
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use serde_json::{json, Value};
+
+const TRACE_VERSION: u64 = 1;
+
+/// 不确定输入的种类
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceKind {
+    Random,
+    Input,
+    Env,
+    Exec,
+    Time,
+}
+
+impl TraceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TraceKind::Random => "random",
+            TraceKind::Input => "input",
+            TraceKind::Env => "env",
+            TraceKind::Exec => "exec",
+            TraceKind::Time => "time",
+        }
+    }
+}
+
+// 一个记录的值，key用于检查回放是否与录制一致（例如环境变量名、命令）
+struct Event {
+    kind: String,
+    key: String,
+    value: Value,
+}
+
+enum Mode {
+    // depth为正在获取的值的嵌套层数，嵌套获取的值已包含在外层的值中，不单独记录
+    Record { events: Vec<Event>, depth: usize },
+    Replay { events: VecDeque<Event>, consumed: usize, diverged: usize },
+}
+
+static TRACE: Mutex<Option<Mode>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Mode>> {
+    TRACE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 开始录制
+pub fn start_recording() {
+    *lock() = Some(Mode::Record { events: Vec::new(), depth: 0 });
+}
+
+/// 从记录文件开始回放
+pub fn start_replay(path: &str) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("无法读取记录文件 '{}': {}", path, e))?;
+    let trace: Value = serde_json::from_str(&text).map_err(|e| format!("记录文件 '{}' 格式错误: {}", path, e))?;
+    if trace.get("version").and_then(|v| v.as_u64()) != Some(TRACE_VERSION) {
+        return Err(format!("记录文件 '{}' 的版本不受支持", path));
+    }
+    let events = trace.get("events").and_then(|e| e.as_array())
+        .ok_or_else(|| format!("记录文件 '{}' 缺少 events 数组", path))?
+        .iter()
+        .map(|event| Event {
+            kind: event.get("kind").and_then(|k| k.as_str()).unwrap_or("").to_string(),
+            key: event.get("key").and_then(|k| k.as_str()).unwrap_or("").to_string(),
+            value: event.get("value").cloned().unwrap_or(Value::Null),
+        })
+        .collect();
+    *lock() = Some(Mode::Replay { events, consumed: 0, diverged: 0 });
+    Ok(())
+}
+
+/// 是否正在回放
+pub fn is_replaying() -> bool {
+    matches!(*lock(), Some(Mode::Replay { .. }))
+}
+
+/// 获取一个不确定的值：回放时返回记录的值，否则调用produce获取，录制时同时记下
+pub fn capture<F: FnOnce() -> Value>(kind: TraceKind, key: &str, produce: F) -> Value {
+    let mut guard = lock();
+    match guard.as_mut() {
+        None => {
+            drop(guard);
+            produce()
+        },
+        Some(Mode::Record { depth, .. }) if *depth > 0 => {
+            drop(guard);
+            produce()
+        },
+        Some(Mode::Record { depth, .. }) => {
+            // produce可能再次获取不确定的值（例如参数中引用环境变量），不能持有锁
+            *depth += 1;
+            drop(guard);
+            let value = produce();
+            if let Some(Mode::Record { events, depth }) = lock().as_mut() {
+                *depth -= 1;
+                events.push(Event { kind: kind.name().to_string(), key: key.to_string(), value: value.clone() });
+            }
+            value
+        },
+        Some(Mode::Replay { events, consumed, diverged }) => {
+            let matches = events.front().is_some_and(|e| e.kind == kind.name() && e.key == key);
+            if matches {
+                *consumed += 1;
+                return events.pop_front().map(|e| e.value).unwrap_or(Value::Null);
+            }
+            // 与记录不一致时使用实际的值继续运行，结束时报告失败
+            *diverged += 1;
+            let expected = match events.front() {
+                Some(event) => format!("{}({})", event.kind, event.key),
+                None => "记录已用完".to_string(),
+            };
+            eprintln!("警告: 回放与记录不一致: 第 {} 个值应为 {}，实际请求 {}({})",
+                *consumed + 1, expected, kind.name(), key);
+            drop(guard);
+            produce()
+        },
+    }
+}
+
+/// 结束录制并把记录写入文件
+pub fn write_recording(path: &str, program: &str) -> Result<usize, String> {
+    let events = match lock().take() {
+        Some(Mode::Record { events, .. }) => events,
+        _ => return Ok(0),
+    };
+    let count = events.len();
+    let trace = json!({
+        "version": TRACE_VERSION,
+        "program": program,
+        "events": events.into_iter()
+            .map(|e| json!({ "kind": e.kind, "key": e.key, "value": e.value }))
+            .collect::<Vec<_>>(),
+    });
+    let text = serde_json::to_string_pretty(&trace).map_err(|e| e.to_string())?;
+    std::fs::write(path, text).map_err(|e| format!("无法写入记录文件 '{}': {}", path, e))?;
+    Ok(count)
+}
+
+/// 结束回放，返回与记录不一致的次数和未使用的记录数
+pub fn finish_replay() -> (usize, usize) {
+    match lock().take() {
+        Some(Mode::Replay { events, diverged, .. }) => (diverged, events.len()),
+        _ => (0, 0),
+    }
+}
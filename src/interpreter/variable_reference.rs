@@ -2,6 +2,7 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::env;
 use super::error::{InterpreterError, Result};
+use super::trace::{self, TraceKind};
 
 /// 变量引用类型，用于区分不同类型的标识符
 #[derive(Debug, PartialEq)]
@@ -132,21 +133,21 @@ impl VariableReference {
         }
     }
     
+    // 读取环境变量（录制和回放时经过trace），不存在时为null
+    fn env_value(&self) -> Value {
+        trace::capture(TraceKind::Env, &self.name, || {
+            env::var(&self.name).map(Value::String).unwrap_or(Value::Null)
+        })
+    }
+
     /// 获取完整的值，包括处理环境变量
     pub fn resolve_value(&self, 
                        variables: &HashMap<String, Value>, 
                        constants: &HashMap<String, Value>) -> Value {
         match self.ref_type {
             ReferenceType::Environment => {
-                // 获取环境变量值
-                match env::var(&self.name) {
-                    Ok(value) => {
-                        Value::String(value)
-                    },
-                    Err(_) => {
-                        Value::Null
-                    }
-                }
+                // 获取环境变量值，不存在时为null
+                self.env_value()
             },
             ReferenceType::Variable => {
                 // 处理嵌套属性访问
@@ -183,15 +184,13 @@ impl VariableReference {
         match self.ref_type {
             ReferenceType::Environment => {
                 // 获取环境变量值
-                match env::var(&self.name) {
-                    Ok(value) => {
-                        Ok(Value::String(value))
-                    },
-                    Err(_) => {
+                match self.env_value() {
+                    Value::Null => {
                         Err(InterpreterError::VariableError(
                             format!("环境变量 '{}' 不存在", self.name)
                         ))
-                    }
+                    },
+                    value => Ok(value),
                 }
             },
             ReferenceType::Variable => {
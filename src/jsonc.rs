@@ -0,0 +1,474 @@
+// JSONC/JSON5源码解析器
+//
+// 支持 `//` 和 `/* */` 注释、尾随逗号、未加引号的键、单引号字符串，
+// 以及JSON5的数字写法（十六进制、省略整数或小数部分、正号）。
+// 解析结果为 serde_json::Value，错误信息包含行号、列号和出错的源码行。
+use std::fmt;
+use serde_json::{Map, Number, Value};
+
+/// 源码解析错误
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub message: String,
+    /// 行号，从1开始
+    pub line: usize,
+    /// 列号，从1开始，按字符计算
+    pub column: usize,
+    // 出错的源码行
+    source_line: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "第 {} 行第 {} 列: {}", self.line, self.column, self.message)?;
+        let gutter = self.line.to_string();
+        writeln!(f, "{} | {}", gutter, self.source_line)?;
+        let padding: String = self.source_line.chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        write!(f, "{} | {}^", " ".repeat(gutter.len()), padding)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl ParseError {
+    /// 创建指向源码中指定位置的错误（供YAML/TOML等其他前端使用）
+    pub fn new(source: &str, line: usize, column: usize, message: &str) -> Self {
+        let source_line = source.lines().nth(line.saturating_sub(1)).unwrap_or("").to_string();
+        ParseError { message: message.to_string(), line, column, source_line }
+    }
+}
+
+/// 解析JSONC/JSON5源码
+pub fn parse(source: &str) -> Result<Value, ParseError> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+    // 跳过UTF-8 BOM
+    if parser.peek() == Some('\u{feff}') {
+        parser.pos += 1;
+    }
+    parser.skip_trivia()?;
+    let value = parser.parse_value()?;
+    parser.skip_trivia()?;
+    if parser.peek().is_some() {
+        return Err(parser.error("程序结尾有多余的内容"));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> ParseError {
+        self.error_at(self.line, self.column, message)
+    }
+
+    fn error_at(&self, line: usize, column: usize, message: &str) -> ParseError {
+        let source_line = self.chars.iter()
+            .collect::<String>()
+            .lines()
+            .nth(line - 1)
+            .unwrap_or("")
+            .to_string();
+        ParseError { message: message.to_string(), line, column, source_line }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    // 跳过空白和注释
+    fn skip_trivia(&mut self) -> Result<(), ParseError> {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == '\u{feff}' {
+                self.bump();
+            } else if c == '/' && self.peek_at(1) == Some('/') {
+                while let Some(c) = self.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    self.bump();
+                }
+            } else if c == '/' && self.peek_at(1) == Some('*') {
+                let (line, column) = (self.line, self.column);
+                self.bump();
+                self.bump();
+                loop {
+                    match self.bump() {
+                        Some('*') if self.peek() == Some('/') => {
+                            self.bump();
+                            break;
+                        },
+                        Some(_) => {},
+                        None => return Err(self.error_at(line, column, "块注释没有结束，缺少 '*/'")),
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ParseError> {
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') | Some('\'') => Ok(Value::String(self.parse_string()?)),
+            Some(c) if c == '-' || c == '+' || c == '.' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) if is_identifier_start(c) => {
+                let (line, column) = (self.line, self.column);
+                let word = self.parse_identifier();
+                match word.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "null" => Ok(Value::Null),
+                    "Infinity" | "NaN" => Err(self.error_at(line, column, &format!("不支持的数值 '{}'", word))),
+                    _ => Err(self.error_at(line, column, &format!("意外的标识符 '{}'，字符串需要加引号", word))),
+                }
+            },
+            Some(c) => Err(self.error(&format!("意外的字符 '{}'", c))),
+            None => Err(self.error("意外的文件结尾")),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value, ParseError> {
+        let (line, column) = (self.line, self.column);
+        self.bump();
+        let mut map = Map::new();
+        loop {
+            self.skip_trivia()?;
+            match self.peek() {
+                Some('}') => {
+                    self.bump();
+                    return Ok(Value::Object(map));
+                },
+                None => return Err(self.error_at(line, column, "对象没有结束，缺少 '}'")),
+                _ => {}
+            }
+
+            let key = match self.peek() {
+                Some('"') | Some('\'') => self.parse_string()?,
+                Some(c) if is_identifier_start(c) => self.parse_identifier(),
+                Some(c) => return Err(self.error(&format!("应为对象键，但遇到 '{}'", c))),
+                None => unreachable!(),
+            };
+
+            self.skip_trivia()?;
+            if self.peek() != Some(':') {
+                return Err(self.error(&format!("键 '{}' 后面缺少 ':'", key)));
+            }
+            self.bump();
+            self.skip_trivia()?;
+            let value = self.parse_value()?;
+            map.insert(key, value);
+
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                },
+                Some('}') => {},
+                Some(c) => return Err(self.error(&format!("对象成员之间缺少 ','，遇到 '{}'", c))),
+                None => return Err(self.error_at(line, column, "对象没有结束，缺少 '}'")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, ParseError> {
+        let (line, column) = (self.line, self.column);
+        self.bump();
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(']') => {
+                    self.bump();
+                    return Ok(Value::Array(items));
+                },
+                None => return Err(self.error_at(line, column, "数组没有结束，缺少 ']'")),
+                _ => {}
+            }
+
+            items.push(self.parse_value()?);
+
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                },
+                Some(']') => {},
+                Some(c) => return Err(self.error(&format!("数组元素之间缺少 ','，遇到 '{}'", c))),
+                None => return Err(self.error_at(line, column, "数组没有结束，缺少 ']'")),
+            }
+        }
+    }
+
+    fn parse_identifier(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if !is_identifier_part(c) {
+                break;
+            }
+            word.push(c);
+            self.bump();
+        }
+        word
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        let (line, column) = (self.line, self.column);
+        let quote = self.bump().unwrap_or('"');
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some(c) if c == quote => return Ok(value),
+                Some('\\') => {
+                    let (esc_line, esc_column) = (self.line, self.column - 1);
+                    match self.bump() {
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some('r') => value.push('\r'),
+                        Some('b') => value.push('\u{8}'),
+                        Some('f') => value.push('\u{c}'),
+                        Some('v') => value.push('\u{b}'),
+                        Some('0') if !self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) => value.push('\0'),
+                        Some('x') => {
+                            let code = self.parse_hex(2, esc_line, esc_column)?;
+                            value.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        },
+                        Some('u') => {
+                            let code = self.parse_hex(4, esc_line, esc_column)?;
+                            // 处理UTF-16代理对
+                            if (0xD800..0xDC00).contains(&code) && self.peek() == Some('\\') && self.peek_at(1) == Some('u') {
+                                self.bump();
+                                self.bump();
+                                let low = self.parse_hex(4, esc_line, esc_column)?;
+                                let combined = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                                value.push(char::from_u32(combined).unwrap_or('\u{fffd}'));
+                            } else {
+                                value.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                            }
+                        },
+                        // 行尾的反斜杠表示续行
+                        Some('\n') => {},
+                        Some('\r') => {
+                            if self.peek() == Some('\n') {
+                                self.bump();
+                            }
+                        },
+                        Some(c) if c.is_ascii_digit() => {
+                            return Err(self.error_at(esc_line, esc_column, "字符串中不允许八进制转义"));
+                        },
+                        Some(c) => value.push(c),
+                        None => return Err(self.error_at(line, column, "字符串没有结束")),
+                    }
+                },
+                Some('\n') => return Err(self.error_at(line, column, "字符串没有结束，字符串中不能直接换行")),
+                Some(c) => value.push(c),
+                None => return Err(self.error_at(line, column, "字符串没有结束")),
+            }
+        }
+    }
+
+    fn parse_hex(&mut self, digits: usize, line: usize, column: usize) -> Result<u32, ParseError> {
+        let mut code = 0;
+        for _ in 0..digits {
+            match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(digit) => {
+                    code = code * 16 + digit;
+                    self.bump();
+                },
+                None => return Err(self.error_at(line, column, "无效的转义序列")),
+            }
+        }
+        Ok(code)
+    }
+
+    fn parse_number(&mut self) -> Result<Value, ParseError> {
+        let (line, column) = (self.line, self.column);
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '+' || c == '-' {
+                text.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+
+        let invalid = || self.error_at(line, column, &format!("无效的数字 '{}'", text));
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(&text)),
+        };
+        if digits == "Infinity" || digits == "NaN" {
+            return Err(self.error_at(line, column, &format!("不支持的数值 '{}'", text)));
+        }
+
+        // 十六进制整数
+        if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+            let magnitude = u64::from_str_radix(hex, 16).map_err(|_| invalid())?;
+            return integer_value(negative, magnitude).ok_or_else(invalid);
+        }
+
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-')) {
+            return Err(invalid());
+        }
+        if digits.chars().all(|c| c.is_ascii_digit()) {
+            if let Ok(magnitude) = digits.parse::<u64>() {
+                if let Some(value) = integer_value(negative, magnitude) {
+                    return Ok(value);
+                }
+            }
+        }
+
+        // Rust的浮点解析接受 ".5" 和 "5."
+        let float: f64 = digits.parse().map_err(|_| invalid())?;
+        let float = if negative { -float } else { float };
+        Number::from_f64(float).map(Value::Number).ok_or_else(invalid)
+    }
+}
+
+fn integer_value(negative: bool, magnitude: u64) -> Option<Value> {
+    if !negative {
+        return Some(Value::Number(magnitude.into()));
+    }
+    match i64::try_from(-(magnitude as i128)) {
+        Ok(value) => Some(Value::Number(value.into())),
+        Err(_) => Number::from_f64(-(magnitude as f64)).map(Value::Number),
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || c == '$' || c.is_alphabetic()
+}
+
+fn is_identifier_part(c: char) -> bool {
+    is_identifier_start(c) || c.is_alphanumeric() || c == '\u{200c}' || c == '\u{200d}'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn error(source: &str) -> ParseError {
+        parse(source).expect_err("应该解析失败")
+    }
+
+    #[test]
+    fn plain_json() {
+        let value = parse(r#"{"a": [1, -2, 3.5, true, false, null], "b": {"c": "d"}}"#).unwrap();
+        assert_eq!(value, json!({"a": [1, -2, 3.5, true, false, null], "b": {"c": "d"}}));
+    }
+
+    #[test]
+    fn comments() {
+        let source = "// 行注释\n{\n  /* 块\n注释 */ \"a\": 1, // 行尾\n  \"b\": /* 值前 */ 2\n}\n// 结尾";
+        assert_eq!(parse(source).unwrap(), json!({"a": 1, "b": 2}));
+        // 字符串中的注释标记不是注释
+        assert_eq!(parse(r#"["// 不是注释", "/* 也不是 */"]"#).unwrap(), json!(["// 不是注释", "/* 也不是 */"]));
+    }
+
+    #[test]
+    fn trailing_commas() {
+        assert_eq!(parse("{\"a\": [1, 2,], \"b\": {\"c\": 3,},}").unwrap(), json!({"a": [1, 2], "b": {"c": 3}}));
+        assert!(parse("[1,,2]").is_err());
+        assert!(parse("[,]").is_err());
+    }
+
+    #[test]
+    fn unquoted_keys_and_single_quotes() {
+        let value = parse("{name: 'JiLang', $id: 1, _x2: 'it\\'s', 中文: \"值\"}").unwrap();
+        assert_eq!(value, json!({"name": "JiLang", "$id": 1, "_x2": "it's", "中文": "值"}));
+    }
+
+    #[test]
+    fn json5_numbers() {
+        let value = parse("[0x1F, -0xff, +1, .5, 5., 1e3, -2.5E-1]").unwrap();
+        assert_eq!(value, json!([31, -255, 1, 0.5, 5.0, 1000.0, -0.25]));
+        // 超出i64范围的负数转换为浮点数，u64范围内的正整数保持整数
+        assert_eq!(parse("18446744073709551615").unwrap(), json!(u64::MAX));
+        assert_eq!(parse("-9223372036854775808").unwrap(), json!(i64::MIN));
+        assert!(parse("-9223372036854775809").unwrap().is_f64());
+        assert!(parse("0xZZ").is_err());
+        assert!(parse("Infinity").is_err());
+        assert!(parse("-NaN").is_err());
+    }
+
+    #[test]
+    fn escapes() {
+        let value = parse(r#""a\nb\tc\\d\"e\x41中😀\0""#).unwrap();
+        assert_eq!(value, json!("a\nb\tc\\d\"eA中😀\0"));
+        // 行尾反斜杠续行
+        assert_eq!(parse("'a\\\nb'").unwrap(), json!("ab"));
+        assert!(parse(r#""\u12""#).is_err());
+        assert!(parse(r#""\1""#).is_err());
+    }
+
+    #[test]
+    fn bom_is_skipped() {
+        assert_eq!(parse("\u{feff}[1]").unwrap(), json!([1]));
+    }
+
+    #[test]
+    fn error_positions() {
+        let err = error("{\n  \"a\": 1\n  \"b\": 2\n}");
+        assert_eq!((err.line, err.column), (3, 3));
+        assert!(err.message.contains("缺少 ','"));
+
+        let err = error("[1, 2");
+        assert_eq!((err.line, err.column), (1, 1));
+
+        let err = error("{\"a\": tru}");
+        assert_eq!((err.line, err.column), (1, 7));
+        assert!(err.message.contains("tru"));
+
+        // 列号按字符计算
+        let err = error("[\"中文\", @]");
+        assert_eq!((err.line, err.column), (1, 8));
+
+        let err = error("{}\n/* 没有结束");
+        assert_eq!((err.line, err.column), (2, 1));
+
+        let err = error("\"第一行\n第二行\"");
+        assert_eq!((err.line, err.column), (1, 1));
+
+        let err = error("[1] 2");
+        assert_eq!((err.line, err.column), (1, 5));
+    }
+
+    #[test]
+    fn error_display_points_at_column() {
+        let err = error("{\n\ta: ?\n}");
+        assert_eq!(err.to_string(), "第 2 行第 5 列: 意外的字符 '?'\n2 | \ta: ?\n  | \t   ^");
+    }
+}
@@ -0,0 +1,712 @@
+// 语言服务器协议（Language Server Protocol）服务器
+//
+// 为.jl/.jil文件提供诊断、补全、悬停文档、跳转到定义和变量重命名。
+// 通过标准输入输出与编辑器通信。
+use std::collections::HashMap;
+use std::io::{self, BufReader};
+use std::path::Path;
+use serde_json::{json, Value};
+use crate::interpreter::source_map::{escape_segment, SourceMap, SourcePosition, StringToken};
+use crate::interpreter::statements::{is_builtin_statement, BUILTIN_STATEMENTS};
+use crate::modules::{get_registry, get_registry_mut};
+use crate::modules::external_module::{FunctionMetadata, JLangExternalModule};
+use crate::protocol::{read_message, write_message};
+
+// LSP诊断级别
+const SEVERITY_ERROR: i64 = 1;
+const SEVERITY_WARNING: i64 = 2;
+
+// LSP补全项类型
+const KIND_FUNCTION: i64 = 3;
+const KIND_VARIABLE: i64 = 6;
+const KIND_KEYWORD: i64 = 14;
+const KIND_CONSTANT: i64 = 21;
+
+// 变量引用前缀，重命名时需要全部处理
+const VARIABLE_PREFIXES: &[&str] = &["@var.", "$", "￥"];
+
+/// 已包含模块的函数信息
+struct ModuleInfo {
+    functions: Vec<FunctionMetadata>,
+    // JL模块的源文件，用于跳转到定义
+    source: Option<String>,
+}
+
+/// 单个文档的分析结果
+struct Analysis {
+    program: Option<Value>,
+    source_map: SourceMap,
+    diagnostics: Vec<Value>,
+}
+
+struct LanguageServer {
+    documents: HashMap<String, String>,
+    modules: HashMap<(String, String), Option<ModuleInfo>>,
+    // 命令行指定的额外模块搜索路径
+    module_paths: Vec<String>,
+}
+
+impl LanguageServer {
+    fn new(module_paths: Vec<String>) -> Self {
+        LanguageServer {
+            documents: HashMap::new(),
+            modules: HashMap::new(),
+            module_paths,
+        }
+    }
+
+    // 加载文档所在目录可见的模块，结果按目录缓存
+    fn module_info(&mut self, dir: &str, name: &str) -> Option<&ModuleInfo> {
+        let key = (dir.to_string(), name.to_string());
+        if !self.modules.contains_key(&key) {
+            get_registry_mut().set_base_path(dir);
+            let modules_dir = Path::new(dir).join("..").to_string_lossy().to_string();
+            get_registry_mut().add_search_path(modules_dir);
+            for path in &self.module_paths {
+                get_registry_mut().add_search_path(path.clone());
+            }
+
+            let info = match name {
+                "io" | "math" | "http" => crate::schema::module_functions(name)
+                    .map(|functions| ModuleInfo { functions, source: None }),
+                _ => get_registry().load_module(name, None).ok().map(|module| {
+                    let mut functions: Vec<FunctionMetadata> = module.get_all_function_metadata()
+                        .into_iter()
+                        .cloned()
+                        .collect();
+                    functions.sort_by(|a, b| a.name.cmp(&b.name));
+                    let source = module.as_any()
+                        .downcast_ref::<JLangExternalModule>()
+                        .map(|m| m.get_path().to_string());
+                    ModuleInfo { functions, source }
+                }),
+            };
+            self.modules.insert(key.clone(), info);
+        }
+        self.modules.get(&key).and_then(|info| info.as_ref())
+    }
+
+    fn analyze(&mut self, uri: &str, text: &str) -> Analysis {
+        let source_map = SourceMap::from_source(text);
+        let mut diagnostics = Vec::new();
+
+        let program: Value = match crate::jsonc::parse(text) {
+            Ok(program) => program,
+            Err(e) => {
+                // 解析错误的列号按字符计算，LSP需要UTF-16编码单元
+                let line_text = text.lines().nth(e.line - 1).unwrap_or("");
+                let column = line_text.chars().take(e.column - 1).map(char::len_utf16).sum::<usize>() + 1;
+                let position = SourcePosition { line: e.line, column };
+                diagnostics.push(diagnostic(point_range(position), SEVERITY_ERROR, &format!("JSON 解析错误: {}", e.message)));
+                return Analysis { program: None, source_map, diagnostics };
+            }
+        };
+
+        let dir = document_dir(uri);
+        let mut checker = Checker {
+            source_map: &source_map,
+            diagnostics: &mut diagnostics,
+        };
+
+        if !program.is_object() {
+            checker.report("", SEVERITY_ERROR, "程序必须是一个 JSON 对象");
+            return Analysis { program: Some(program), source_map, diagnostics };
+        }
+
+        // 检查include的模块是否存在
+        let mut included = Vec::new();
+        if let Some(include) = program.get("include") {
+            match include.as_array() {
+                Some(names) => {
+                    for (i, name) in names.iter().enumerate() {
+                        match crate::modules::include_name(name) {
+                            Some(name) => {
+                                if self.module_info(&dir, name).is_none() {
+                                    checker.report(&format!("/include/{}", i), SEVERITY_ERROR, &format!("未找到模块 '{}'", name));
+                                }
+                                included.push(name.to_string());
+                            },
+                            None => checker.report(&format!("/include/{}", i), SEVERITY_ERROR, "模块名必须是字符串或 {\"module\": 名称, \"options\": {...}} 对象"),
+                        }
+                    }
+                },
+                None => checker.report("/include", SEVERITY_ERROR, "'include' 必须是一个数组"),
+            }
+        }
+
+        let constants = program.get("const").and_then(|c| c.as_object()).cloned().unwrap_or_default();
+        if program.get("const").map(|c| !c.is_object()).unwrap_or(false) {
+            checker.report("/const", SEVERITY_ERROR, "'const' 必须是一个对象");
+        }
+
+        // 收集用户函数和模块函数，供语句类型检查使用
+        let program_obj = program.get("program").and_then(|p| p.as_object()).cloned().unwrap_or_default();
+        let user_functions: Vec<String> = program_obj.keys().filter(|k| *k != "main").cloned().collect();
+        let mut module_functions: HashMap<String, Vec<String>> = HashMap::new();
+        for name in &included {
+            if let Some(info) = self.module_info(&dir, name) {
+                module_functions.insert(name.clone(), info.functions.iter().map(|f| f.name.clone()).collect());
+            }
+        }
+        let known = KnownNames { user_functions: &user_functions, included: &included, module_functions: &module_functions };
+
+        match program.get("program") {
+            Some(Value::Object(obj)) => {
+                match obj.get("main").and_then(|m| m.get("body")) {
+                    Some(Value::Array(body)) => checker.check_block("/program/main/body", body, &known),
+                    Some(_) => checker.report("/program/main/body", SEVERITY_ERROR, "'program.main.body' 必须是一个数组"),
+                    None => checker.report("/program", SEVERITY_ERROR, "程序缺少 'program.main.body' 字段"),
+                }
+
+                for (func_name, func) in obj.iter().filter(|(k, _)| *k != "main") {
+                    let func_path = format!("/program/{}", escape_segment(func_name));
+                    if is_builtin_statement(func_name) {
+                        checker.report(&func_path, SEVERITY_ERROR, &format!("函数名 '{}' 与内置语句冲突", func_name));
+                    }
+                    if !func.get("params").map(|p| p.is_object()).unwrap_or(false) {
+                        checker.report(&func_path, SEVERITY_ERROR, "函数缺少参数定义或参数定义不是对象");
+                    }
+                    match func.get("body") {
+                        Some(Value::Array(body)) => checker.check_block(&format!("{}/body", func_path), body, &known),
+                        _ => checker.report(&func_path, SEVERITY_ERROR, "函数缺少 'body' 字段或 'body' 不是数组"),
+                    }
+                }
+            },
+            Some(_) => checker.report("/program", SEVERITY_ERROR, "'program' 必须是一个对象"),
+            None => checker.report("", SEVERITY_ERROR, "程序缺少 'program.main.body' 字段"),
+        }
+
+        // 检查常量引用
+        for token in source_map.strings().iter().filter(|t| !t.is_key) {
+            if let Some(rest) = token.value.strip_prefix("@const.") {
+                let name = reference_root(rest);
+                if !constants.contains_key(name) {
+                    diagnostics.push(diagnostic(token_range(token), SEVERITY_WARNING, &format!("未定义的常量 '{}'", name)));
+                }
+            }
+        }
+
+        Analysis { program: Some(program), source_map, diagnostics }
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) {
+        let text = self.documents.get(uri).cloned().unwrap_or_default();
+        let analysis = self.analyze(uri, &text);
+        send_notification("textDocument/publishDiagnostics", json!({
+            "uri": uri,
+            "diagnostics": analysis.diagnostics,
+        }));
+    }
+
+    fn completion(&mut self, uri: &str, line: usize, character: usize) -> Value {
+        let text = self.documents.get(uri).cloned().unwrap_or_default();
+        let analysis = self.analyze(uri, &text);
+        let prefix = string_prefix_before(&text, line, character);
+        let mut items = Vec::new();
+
+        if prefix.starts_with("@var.") || prefix.starts_with('$') || prefix.starts_with('￥') {
+            for name in declared_variables(&analysis.source_map) {
+                items.push(completion_item(&name, KIND_VARIABLE, "变量"));
+            }
+        } else if prefix.starts_with("@const.") {
+            if let Some(constants) = analysis.program.as_ref().and_then(|p| p.get("const")).and_then(|c| c.as_object()) {
+                for (name, value) in constants {
+                    items.push(completion_item(name, KIND_CONSTANT, &value.to_string()));
+                }
+            }
+        } else if prefix.starts_with("@param.") || prefix.starts_with("@params.") {
+            let position = SourcePosition { line: line + 1, column: character + 1 };
+            let enclosing = analysis.source_map.string_at(position)
+                .and_then(|token| function_of_path(&token.path));
+            if let Some(functions) = analysis.program.as_ref().and_then(|p| p.get("program")).and_then(|p| p.as_object()) {
+                for (func_name, func) in functions {
+                    if enclosing.as_ref().map(|f| f != func_name).unwrap_or(false) {
+                        continue;
+                    }
+                    if let Some(params) = func.get("params").and_then(|p| p.as_object()) {
+                        for param in params.keys() {
+                            items.push(completion_item(param, KIND_VARIABLE, &format!("{} 的参数", func_name)));
+                        }
+                    }
+                }
+            }
+        } else if prefix.starts_with("@env.") {
+            let mut names: Vec<String> = std::env::vars().map(|(k, _)| k).collect();
+            names.sort();
+            for name in names {
+                items.push(completion_item(&name, KIND_VARIABLE, "环境变量"));
+            }
+        } else {
+            for name in BUILTIN_STATEMENTS {
+                items.push(completion_item(name, KIND_KEYWORD, "内置语句"));
+            }
+            if let Some(functions) = analysis.program.as_ref().and_then(|p| p.get("program")).and_then(|p| p.as_object()) {
+                for func_name in functions.keys().filter(|k| *k != "main") {
+                    items.push(completion_item(func_name, KIND_FUNCTION, "用户函数"));
+                }
+            }
+            let dir = document_dir(uri);
+            for name in included_modules(analysis.program.as_ref()) {
+                if let Some(info) = self.module_info(&dir, &name) {
+                    for func in &info.functions {
+                        let mut item = completion_item(&format!("{}.{}", name, func.name), KIND_FUNCTION, &func.description);
+                        item["documentation"] = json!({ "kind": "markdown", "value": function_doc(&name, func) });
+                        items.push(item);
+                    }
+                }
+            }
+        }
+
+        json!({ "isIncomplete": false, "items": items })
+    }
+
+    fn hover(&mut self, uri: &str, line: usize, character: usize) -> Value {
+        let text = self.documents.get(uri).cloned().unwrap_or_default();
+        let analysis = self.analyze(uri, &text);
+        let position = SourcePosition { line: line + 1, column: character + 1 };
+        let token = match analysis.source_map.string_at(position) {
+            Some(token) => token.clone(),
+            None => return Value::Null,
+        };
+
+        let contents = if is_builtin_statement(&token.value) {
+            Some(format!("**{}**\n\n内置语句", token.value))
+        } else if let Some(rest) = token.value.strip_prefix("@const.") {
+            analysis.program.as_ref()
+                .and_then(|p| p.get("const"))
+                .and_then(|c| c.get(reference_root(rest)))
+                .map(|value| format!("**常量** `{}` = `{}`", reference_root(rest), value))
+        } else if let Some((module_name, func_name)) = token.value.split_once('.') {
+            let dir = document_dir(uri);
+            if included_modules(analysis.program.as_ref()).iter().any(|m| m == module_name) {
+                self.module_info(&dir, module_name)
+                    .and_then(|info| info.functions.iter().find(|f| f.name == func_name))
+                    .map(|func| function_doc(module_name, func))
+            } else {
+                None
+            }
+        } else {
+            analysis.program.as_ref()
+                .and_then(|p| p.get("program"))
+                .and_then(|p| p.get(&token.value))
+                .filter(|_| token.value != "main")
+                .map(|func| {
+                    let params: Vec<String> = func.get("params")
+                        .and_then(|p| p.as_object())
+                        .map(|p| p.keys().cloned().collect())
+                        .unwrap_or_default();
+                    format!("**{}**({})\n\n用户函数", token.value, params.join(", "))
+                })
+        };
+
+        match contents {
+            Some(value) => json!({
+                "contents": { "kind": "markdown", "value": value },
+                "range": token_range(&token),
+            }),
+            None => Value::Null,
+        }
+    }
+
+    fn definition(&mut self, uri: &str, line: usize, character: usize) -> Value {
+        let text = self.documents.get(uri).cloned().unwrap_or_default();
+        let analysis = self.analyze(uri, &text);
+        let position = SourcePosition { line: line + 1, column: character + 1 };
+        let token = match analysis.source_map.string_at(position) {
+            Some(token) => token.clone(),
+            None => return Value::Null,
+        };
+
+        // 用户函数定义在当前文件中
+        let is_user_function = token.value != "main" && analysis.program.as_ref()
+            .and_then(|p| p.get("program"))
+            .and_then(|p| p.get(&token.value))
+            .is_some();
+        if is_user_function {
+            return definition_location(uri, &analysis.source_map, &format!("/program/{}", escape_segment(&token.value)));
+        }
+
+        // JL模块函数定义在模块文件中
+        if let Some((module_name, func_name)) = token.value.split_once('.') {
+            if included_modules(analysis.program.as_ref()).iter().any(|m| m == module_name) {
+                let dir = document_dir(uri);
+                let source = self.module_info(&dir, module_name).and_then(|info| info.source.clone());
+                if let Some(source) = source {
+                    let module_text = std::fs::read_to_string(&source).unwrap_or_default();
+                    let module_map = SourceMap::from_source(&module_text);
+                    return definition_location(&path_to_uri(&source), &module_map, &format!("/program/{}", escape_segment(func_name)));
+                }
+            }
+        }
+
+        Value::Null
+    }
+
+    fn rename(&mut self, uri: &str, line: usize, character: usize, new_name: &str) -> std::result::Result<Value, String> {
+        let text = self.documents.get(uri).cloned().unwrap_or_default();
+        let source_map = SourceMap::from_source(&text);
+        let position = SourcePosition { line: line + 1, column: character + 1 };
+        let token = source_map.string_at(position).ok_or("这里没有可以重命名的变量")?;
+        let name = variable_name_of(token).ok_or("只能重命名变量")?;
+
+        let mut edits = Vec::new();
+        for token in source_map.strings() {
+            if is_variable_declaration(token) && token.value == name {
+                edits.push(text_edit(token, 0, utf16_len(&token.value), new_name));
+                continue;
+            }
+            if token.is_key {
+                continue;
+            }
+            for prefix in VARIABLE_PREFIXES {
+                if let Some(rest) = token.value.strip_prefix(prefix) {
+                    if reference_root(rest) == name {
+                        edits.push(text_edit(token, utf16_len(prefix), utf16_len(&name), new_name));
+                    }
+                }
+            }
+        }
+
+        Ok(json!({ "changes": { uri: edits } }))
+    }
+}
+
+// 已知的函数名，用于检查语句类型
+struct KnownNames<'a> {
+    user_functions: &'a [String],
+    included: &'a [String],
+    module_functions: &'a HashMap<String, Vec<String>>,
+}
+
+// 静态检查器，把发现的问题定位到源码
+struct Checker<'a> {
+    source_map: &'a SourceMap,
+    diagnostics: &'a mut Vec<Value>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, path: &str, severity: i64, message: &str) {
+        let range = path_range(self.source_map, path);
+        self.diagnostics.push(diagnostic(range, severity, message));
+    }
+
+    fn check_block(&mut self, path: &str, statements: &[Value], known: &KnownNames) {
+        for (i, stmt) in statements.iter().enumerate() {
+            let stmt_path = format!("{}/{}", path, i);
+            let (stmt_type, args) = match stmt.as_object().and_then(|obj| obj.iter().next()) {
+                Some(entry) => entry,
+                None => {
+                    self.report(&stmt_path, SEVERITY_ERROR, "语句必须是一个非空对象");
+                    continue;
+                }
+            };
+            let type_path = format!("{}/{}", stmt_path, escape_segment(stmt_type));
+            self.check_statement_type(&type_path, stmt_type, known);
+
+            // 递归检查嵌套的语句块
+            if let Some(args_obj) = args.as_object() {
+                for field in ["body", "then", "else", "default", "try", "catch", "finally"] {
+                    if let Some(Value::Array(block)) = args_obj.get(field) {
+                        self.check_block(&format!("{}/{}", type_path, field), block, known);
+                    }
+                }
+                if let Some(Value::Array(cases)) = args_obj.get("cases") {
+                    for (case_index, case) in cases.iter().enumerate() {
+                        if let Some(Value::Array(block)) = case.get("do") {
+                            self.check_block(&format!("{}/cases/{}/do", type_path, case_index), block, known);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_statement_type(&mut self, path: &str, stmt_type: &str, known: &KnownNames) {
+        if is_builtin_statement(stmt_type) || known.user_functions.iter().any(|f| f == stmt_type) {
+            return;
+        }
+        if let Some((module_name, func_name)) = stmt_type.split_once('.') {
+            if !known.included.iter().any(|m| m == module_name) {
+                self.report(path, SEVERITY_ERROR, &format!("未包含模块 '{}'，请在 include 中添加", module_name));
+            } else if let Some(functions) = known.module_functions.get(module_name) {
+                if !functions.iter().any(|f| f == func_name) {
+                    self.report(path, SEVERITY_WARNING, &format!("模块 '{}' 中未找到函数 '{}'", module_name, func_name));
+                }
+            }
+            return;
+        }
+        self.report(path, SEVERITY_ERROR, &format!("未知的语句类型: {}", stmt_type));
+    }
+}
+
+fn diagnostic(range: Value, severity: i64, message: &str) -> Value {
+    json!({
+        "range": range,
+        "severity": severity,
+        "source": "jlang",
+        "message": message,
+    })
+}
+
+fn completion_item(label: &str, kind: i64, detail: &str) -> Value {
+    json!({ "label": label, "kind": kind, "detail": detail })
+}
+
+// 生成模块函数的Markdown文档
+fn function_doc(module_name: &str, func: &FunctionMetadata) -> String {
+    let params: Vec<&str> = func.parameters.iter().map(|p| p.name.as_str()).collect();
+    let mut doc = format!("**{}.{}**({}) -> {}\n\n{}", module_name, func.name, params.join(", "), func.return_type, func.description);
+    if !func.parameters.is_empty() {
+        doc.push_str("\n\n参数:");
+        for param in &func.parameters {
+            doc.push_str(&format!("\n- `{}`: {} ({}{})",
+                param.name,
+                param.description,
+                param.type_description,
+                if param.optional { ", 可选" } else { "" }));
+        }
+    }
+    if !func.example.is_empty() {
+        doc.push_str(&format!("\n\n示例:\n```json\n{}\n```", func.example));
+    }
+    doc
+}
+
+fn lsp_position(position: SourcePosition) -> Value {
+    json!({ "line": position.line.saturating_sub(1), "character": position.column.saturating_sub(1) })
+}
+
+fn point_range(position: SourcePosition) -> Value {
+    json!({ "start": lsp_position(position), "end": lsp_position(position) })
+}
+
+fn token_range(token: &StringToken) -> Value {
+    json!({ "start": lsp_position(token.start), "end": lsp_position(token.end) })
+}
+
+// 路径对应的源码范围：优先使用值对应的键，其次是值本身的位置
+fn path_range(source_map: &SourceMap, path: &str) -> Value {
+    if let Some(token) = source_map.strings().iter().find(|t| t.is_key && t.path == path) {
+        return token_range(token);
+    }
+    let position = source_map.position(path).unwrap_or(SourcePosition { line: 1, column: 1 });
+    point_range(position)
+}
+
+fn definition_location(uri: &str, source_map: &SourceMap, path: &str) -> Value {
+    json!({ "uri": uri, "range": path_range(source_map, path) })
+}
+
+// 替换字符串字面量内从offset开始、长度为len的内容（均按UTF-16计算）
+fn text_edit(token: &StringToken, offset: usize, len: usize, new_text: &str) -> Value {
+    let start = SourcePosition { line: token.start.line, column: token.start.column + 1 + offset };
+    let end = SourcePosition { line: start.line, column: start.column + len };
+    json!({
+        "range": { "start": lsp_position(start), "end": lsp_position(end) },
+        "newText": new_text,
+    })
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+// 引用路径的根名称，例如 "user.name" 或 "list[0]" 中的 "user"/"list"
+fn reference_root(reference: &str) -> &str {
+    let end = reference.find(['.', '[']).unwrap_or(reference.len());
+    &reference[..end]
+}
+
+// 判断字符串是否声明了变量：var语句的键、for语句的var、output参数
+fn is_variable_declaration(token: &StringToken) -> bool {
+    let segments: Vec<&str> = token.path.rsplit('/').collect();
+    if token.is_key {
+        // .../<index>/var/<name>
+        segments.len() >= 3 && segments[1] == "var" && segments[2].parse::<usize>().is_ok()
+    } else {
+        // .../for/var 或 .../output
+        (segments.len() >= 2 && segments[0] == "var" && segments[1] == "for")
+            || segments.first() == Some(&"output")
+    }
+}
+
+// 获取字符串所指的变量名（变量引用或变量声明）
+fn variable_name_of(token: &StringToken) -> Option<String> {
+    if is_variable_declaration(token) {
+        return Some(token.value.clone());
+    }
+    if token.is_key {
+        return None;
+    }
+    VARIABLE_PREFIXES.iter()
+        .find_map(|prefix| token.value.strip_prefix(prefix))
+        .map(|rest| reference_root(rest).to_string())
+        .filter(|name| !name.is_empty())
+}
+
+// 收集文档中声明的所有变量名
+fn declared_variables(source_map: &SourceMap) -> Vec<String> {
+    let mut names: Vec<String> = source_map.strings().iter()
+        .filter(|t| is_variable_declaration(t))
+        .map(|t| t.value.clone())
+        .collect();
+    names.push("result".to_string());
+    names.sort();
+    names.dedup();
+    names
+}
+
+// 路径所在的用户函数名（main除外）
+fn function_of_path(path: &str) -> Option<String> {
+    let mut segments = path.trim_start_matches('/').split('/');
+    match (segments.next(), segments.next()) {
+        (Some("program"), Some(name)) if name != "main" => Some(name.replace("~1", "/").replace("~0", "~")),
+        _ => None,
+    }
+}
+
+fn included_modules(program: Option<&Value>) -> Vec<String> {
+    program.and_then(|p| p.get("include"))
+        .and_then(|i| i.as_array())
+        .map(|arr| arr.iter().filter_map(crate::modules::include_name).map(|s| s.to_string()).collect())
+        .unwrap_or_default()
+}
+
+// 光标前当前字符串内已输入的内容
+fn string_prefix_before(text: &str, line: usize, character: usize) -> String {
+    let line_text = text.lines().nth(line).unwrap_or("");
+    let units: Vec<u16> = line_text.encode_utf16().take(character).collect();
+    let before = String::from_utf16_lossy(&units);
+    match before.rfind('"') {
+        Some(pos) => before[pos + 1..].to_string(),
+        None => String::new(),
+    }
+}
+
+fn uri_to_path(uri: &str) -> String {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let decoded = urlencoding::decode(path).map(|p| p.into_owned()).unwrap_or_else(|_| path.to_string());
+    // Windows路径形如 /C:/dir/file.jl
+    let bytes = decoded.as_bytes();
+    if bytes.len() > 2 && bytes[0] == b'/' && bytes[2] == b':' {
+        decoded[1..].to_string()
+    } else {
+        decoded
+    }
+}
+
+fn path_to_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+fn document_dir(uri: &str) -> String {
+    let path = uri_to_path(uri);
+    Path::new(&path).parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|| ".".to_string())
+}
+
+fn send(message: Value) {
+    let _ = write_message(&mut io::stdout().lock(), &message);
+}
+
+fn send_notification(method: &str, params: Value) {
+    send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+}
+
+fn send_response(id: &Value, result: Value) {
+    send(json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+}
+
+fn send_error(id: &Value, message: &str) {
+    send(json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32603, "message": message } }));
+}
+
+// 从请求参数中取出文档URI和光标位置
+fn text_position(params: &Value) -> (String, usize, usize) {
+    let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+    let line = params["position"]["line"].as_u64().unwrap_or(0) as usize;
+    let character = params["position"]["character"].as_u64().unwrap_or(0) as usize;
+    (uri, line, character)
+}
+
+/// 启动LSP服务器，处理标准输入上的请求直到收到exit通知
+pub fn run_lsp_server(extra_module_paths: Vec<String>) {
+    let mut server = LanguageServer::new(extra_module_paths);
+    let mut reader = BufReader::new(io::stdin());
+    let mut shutdown = false;
+
+    while let Some(message) = read_message(&mut reader) {
+        let method = message.get("method").and_then(|m| m.as_str()).unwrap_or("").to_string();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let id = message.get("id").cloned();
+
+        match (method.as_str(), id) {
+            ("initialize", Some(id)) => send_response(&id, json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "completionProvider": { "triggerCharacters": [".", "\"", "@", "$"] },
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "renameProvider": true,
+                },
+                "serverInfo": { "name": "jlang-lsp", "version": crate::VERSION },
+            })),
+            ("textDocument/didOpen", None) => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+                let text = params["textDocument"]["text"].as_str().unwrap_or("").to_string();
+                server.documents.insert(uri.clone(), text);
+                server.publish_diagnostics(&uri);
+            },
+            ("textDocument/didChange", None) => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+                // 使用全量同步，最后一次变更即为完整文本
+                if let Some(text) = params["contentChanges"].as_array().and_then(|c| c.last()).and_then(|c| c["text"].as_str()) {
+                    server.documents.insert(uri.clone(), text.to_string());
+                }
+                server.publish_diagnostics(&uri);
+            },
+            ("textDocument/didClose", None) => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+                server.documents.remove(&uri);
+                send_notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": [] }));
+            },
+            ("textDocument/completion", Some(id)) => {
+                let (uri, line, character) = text_position(&params);
+                send_response(&id, server.completion(&uri, line, character));
+            },
+            ("textDocument/hover", Some(id)) => {
+                let (uri, line, character) = text_position(&params);
+                send_response(&id, server.hover(&uri, line, character));
+            },
+            ("textDocument/definition", Some(id)) => {
+                let (uri, line, character) = text_position(&params);
+                send_response(&id, server.definition(&uri, line, character));
+            },
+            ("textDocument/rename", Some(id)) => {
+                let (uri, line, character) = text_position(&params);
+                let new_name = params["newName"].as_str().unwrap_or("");
+                match server.rename(&uri, line, character, new_name) {
+                    Ok(edit) => send_response(&id, edit),
+                    Err(e) => send_error(&id, &e),
+                }
+            },
+            ("shutdown", Some(id)) => {
+                shutdown = true;
+                send_response(&id, Value::Null);
+            },
+            ("exit", None) => {
+                std::process::exit(if shutdown { 0 } else { 1 });
+            },
+            (_, Some(id)) => {
+                send(json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32601, "message": format!("不支持的方法: {}", method) } }));
+            },
+            _ => {},
+        }
+    }
+}
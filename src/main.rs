@@ -1,59 +1,27 @@
 mod interpreter;
+mod jsonc;
 mod modules;
+mod dap;
+mod formatter;
+mod lsp;
+mod protocol;
+mod schema;
+mod source_format;
+mod test_runner;
 
 use serde_json::Value;
 use std::env;
 use std::fs;
 use interpreter::Interpreter;
-use modules::{get_module, get_registry, get_registry_mut};
+use modules::{SharedModule, get_module_with_options, get_registry, get_registry_mut};
+use modules::external_module::ExternalModuleOptions;
 use std::path::Path;
+use std::rc::Rc;
+use std::cell::RefCell;
 use dotenv::dotenv;
 use crate::modules::lua_module;
 use crate::modules::external_module::ExternalModule;
 
-// 预处理函数：移除所有//注释
-fn preprocess_json(input: &str) -> String {
-    let mut result = String::new();
-    let mut in_string = false;
-    let mut escape_next = false;
-    
-    for line in input.lines() {
-        let mut processed_line = String::new();
-        let mut i = 0;
-        let chars: Vec<char> = line.chars().collect();
-        
-        while i < chars.len() {
-            let c = chars[i];
-            
-            // 检查是否在字符串内
-            if c == '"' && !escape_next {
-                in_string = !in_string;
-            }
-            
-            // 检查是否是转义字符
-            escape_next = c == '\\' && !escape_next;
-            
-            // 检查注释开始
-            if !in_string && c == '/' && i + 1 < chars.len() && chars[i + 1] == '/' {
-                // 找到注释，忽略后面的内容
-                break;
-            }
-            
-            // 正常字符，添加到结果中
-            processed_line.push(c);
-            i += 1;
-        }
-        
-        // 添加处理后的行（如果非空）
-        if !processed_line.trim().is_empty() {
-            result.push_str(&processed_line);
-            result.push('\n');
-        }
-    }
-    
-    result
-}
-
 // 程序信息常量
 const VERSION: &str = env!("CARGO_PKG_VERSION", "0.3.0");
 const CREATOR: &str = "HelloAIXIAOJI";
@@ -92,6 +60,126 @@ pub fn is_print_full_values() -> bool {
     unsafe { PRINT_FULL_VALUES }
 }
 
+// 已加载的程序：源文件、解析后的JSON和包含的模块
+pub struct LoadedProgram {
+    pub path: String,
+    pub source: String,
+    pub program: Value,
+    pub modules: Vec<SharedModule>,
+    pub module_errors: Vec<String>,
+}
+
+// 读取并解析程序文件，设置模块搜索路径并加载include中的模块
+pub fn load_program(filename: &str, extra_module_paths: Vec<String>) -> Result<LoadedProgram, String> {
+    // 获取程序文件的绝对路径
+    let absolute_path = std::fs::canonicalize(filename)
+        .map_err(|e| format!("错误: 无法获取文件 '{}' 的绝对路径: {}", filename, e))?;
+    
+    // 获取程序文件所在目录
+    let program_dir = match absolute_path.parent() {
+        Some(dir) => dir.to_string_lossy().to_string(),
+        None => return Err(format!("错误: 无法获取文件 '{}' 的父目录", filename)),
+    };
+    
+    // 设置模块注册表的基础路径为程序文件所在目录
+    get_registry_mut().set_base_path(&program_dir);
+    
+    // 输出调试信息
+    if is_debug_mode() {
+        println!("程序文件: {}", absolute_path.to_string_lossy());
+        println!("程序目录: {}", program_dir);
+        println!("模块搜索路径: {:?}", get_registry().get_search_paths());
+    }
+    
+    // 添加额外的模块搜索路径
+    for path in extra_module_paths {
+        get_registry_mut().add_search_path(path);
+    }
+    
+    // 显式添加examples/modules目录作为搜索路径
+    let modules_dir = std::path::Path::new(&program_dir).join("..").to_string_lossy().to_string();
+    get_registry_mut().add_search_path(modules_dir);
+    
+    if is_debug_mode() {
+        println!("添加额外模块路径后: {:?}", get_registry().get_search_paths());
+    }
+    
+    // 读取程序文件
+    let program_text = fs::read_to_string(filename)
+        .map_err(|e| format!("无法读取程序文件 '{}': {}", filename, e))?;
+    
+    // 按扩展名解析 JSONC/JSON5、YAML 或 TOML 源码
+    let format = source_format::SourceFormat::from_path(filename);
+    let program: Value = source_format::parse(&program_text, format)
+        .map_err(|e| format!("{} 解析错误 ({}): {}", format.name(), filename, e))?;
+    
+    // 从程序的include字段获取需要加载的模块
+    let mut modules = Vec::new();
+    let mut module_errors = Vec::new();
+    load_includes(&program, &mut modules, &mut module_errors);
+    
+    Ok(LoadedProgram {
+        path: absolute_path.to_string_lossy().to_string(),
+        source: program_text,
+        program,
+        modules,
+        module_errors,
+    })
+}
+
+// 加载程序include中的模块，JL模块自己的include也一起加载。
+// 同一个模块文件只加载一次，被多处include时共用同一个实例
+fn load_includes(program: &Value, modules: &mut Vec<SharedModule>, module_errors: &mut Vec<String>) {
+    let Some(include_array) = program.get("include").and_then(|v| v.as_array()) else {
+        return;
+    };
+    for entry in include_array {
+        let Some(name) = modules::include_name(entry) else {
+            continue;
+        };
+        let options = match entry.get("options").map(ExternalModuleOptions::from_json) {
+            Some(Ok(options)) => Some(options),
+            Some(Err(e)) => {
+                report_module_error(format!("模块 '{}' 的选项无效: {}", name, e.message()), module_errors);
+                continue;
+            },
+            None => None,
+        };
+        let module = match get_module_with_options(name, options) {
+            Ok(Some(module)) => module,
+            Ok(None) => {
+                report_module_error(format!("未找到模块 '{}'。您能凭空变出这个模块吗？", name), module_errors);
+                continue;
+            },
+            // 找到了模块文件但加载失败，显示加载器给出的原因
+            Err(e) => {
+                report_module_error(format!("加载模块 '{}' 失败: {}", name, e.message()), module_errors);
+                continue;
+            },
+        };
+        // 已经加载过（包括循环include）时不再重复处理
+        if modules.iter().any(|loaded| Rc::ptr_eq(loaded, &module)) {
+            continue;
+        }
+        modules.push(module.clone());
+        let nested = module.borrow().as_any()
+            .downcast_ref::<modules::external_module::JLangExternalModule>()
+            .map(|jl_module| jl_module.get_program().clone());
+        if let Some(nested) = nested {
+            load_includes(&nested, modules, module_errors);
+        }
+    }
+}
+
+// --check-all 时收集模块错误，否则作为警告输出
+fn report_module_error(error_msg: String, module_errors: &mut Vec<String>) {
+    if is_check_all() {
+        module_errors.push(error_msg);
+    } else {
+        eprintln!("警告: {}", error_msg);
+    }
+}
+
 fn main() {
     // 加载.env文件中的环境变量
     dotenv().ok();
@@ -99,6 +187,17 @@ fn main() {
     // 获取命令行参数
     let args: Vec<String> = env::args().collect();
     
+    // fmt子命令有自己的参数（其中--check的含义与运行时不同），单独解析
+    if args.get(1).map(|a| a.as_str()) == Some("fmt") {
+        std::process::exit(formatter::run_fmt(&args[2..]));
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("convert") {
+        std::process::exit(source_format::run_convert(&args[2..]));
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("test") {
+        std::process::exit(test_runner::run_tests(&args[2..]));
+    }
+    
     // 默认参数值
     let mut filename = String::new();
     
@@ -108,6 +207,35 @@ fn main() {
     // 模块元数据查询
     let mut modulemeta_path = None;
     
+    // 子命令（如 dap），只能作为第一个参数出现
+    let mut subcommand = None;
+    
+    // 运行前按Schema校验程序
+    let mut validate = false;
+    
+    // 随机数种子
+    let mut seed: Option<u64> = None;
+    
+    // 不确定输入的记录文件
+    let mut record_trace: Option<String> = None;
+    let mut replay_trace: Option<String> = None;
+    
+    // HTTP录制/回放目录及匹配规则
+    let mut http_record: Option<String> = None;
+    let mut http_replay: Option<String> = None;
+    let mut http_match: Option<String> = None;
+    
+    // 性能分析及报告文件前缀
+    let mut profile = false;
+    let mut profile_out: Option<String> = None;
+    
+    // 覆盖率统计及报告文件前缀
+    let mut coverage = false;
+    let mut coverage_out: Option<String> = None;
+    
+    // 模块文件修改后自动重新加载
+    let mut watch_modules = false;
+    
     // 解析命令行参数
     let mut i = 1;
     while i < args.len() {
@@ -156,7 +284,7 @@ fn main() {
                     i += 1;
                     modulemeta_path = Some(args[i].clone());
                 } else {
-                    eprintln!("错误: --modulemeta 需要提供模块文件路径");
+                    eprintln!("错误: --modulemeta 需要提供模块文件路径或内置模块名称");
                     std::process::exit(1);
                 }
             },
@@ -180,6 +308,92 @@ fn main() {
                 print_available_modules();
                 return;
             },
+            "dap" if i == 1 => {
+                // 以调试适配器协议服务器模式运行
+                subcommand = Some("dap");
+            },
+            "lsp" if i == 1 => {
+                // 以语言服务器协议服务器模式运行
+                subcommand = Some("lsp");
+            },
+            "schema" if i == 1 => {
+                // 输出程序的JSON Schema
+                subcommand = Some("schema");
+            },
+            "--validate" => {
+                validate = true;
+            },
+            "--coverage" => {
+                coverage = true;
+            },
+            "--coverage-out" => {
+                // 覆盖率报告文件前缀，同时启用覆盖率统计
+                if i + 1 < args.len() {
+                    i += 1;
+                    coverage = true;
+                    coverage_out = Some(args[i].clone());
+                } else {
+                    eprintln!("错误: --coverage-out 需要提供文件前缀");
+                    std::process::exit(1);
+                }
+            },
+            "--seed" => {
+                // 使用确定的随机数序列，使程序的运行结果可以重现
+                match args.get(i + 1).and_then(|value| value.parse::<u64>().ok()) {
+                    Some(value) => {
+                        i += 1;
+                        seed = Some(value);
+                    },
+                    None => {
+                        eprintln!("错误: --seed 需要提供一个非负整数");
+                        std::process::exit(1);
+                    }
+                }
+            },
+            "--record" | "--replay" => {
+                // 记录或回放随机数、输入、环境变量、命令输出和时间
+                if i + 1 < args.len() {
+                    i += 1;
+                    if args[i - 1] == "--record" {
+                        record_trace = Some(args[i].clone());
+                    } else {
+                        replay_trace = Some(args[i].clone());
+                    }
+                } else {
+                    eprintln!("错误: {} 需要提供记录文件路径", args[i]);
+                    std::process::exit(1);
+                }
+            },
+            "--http-record" | "--http-replay" | "--http-match" => {
+                if i + 1 < args.len() {
+                    i += 1;
+                    match args[i - 1].as_str() {
+                        "--http-record" => http_record = Some(args[i].clone()),
+                        "--http-replay" => http_replay = Some(args[i].clone()),
+                        _ => http_match = Some(args[i].clone()),
+                    }
+                } else {
+                    eprintln!("错误: {} 需要提供参数", args[i]);
+                    std::process::exit(1);
+                }
+            },
+            "--profile" => {
+                profile = true;
+            },
+            "--watch-modules" => {
+                watch_modules = true;
+            },
+            "--profile-out" => {
+                // 性能分析报告文件前缀，同时启用性能分析
+                if i + 1 < args.len() {
+                    i += 1;
+                    profile = true;
+                    profile_out = Some(args[i].clone());
+                } else {
+                    eprintln!("错误: --profile-out 需要提供文件前缀");
+                    std::process::exit(1);
+                }
+            },
             _ => {
                 // 假设这是文件名
                 filename = args[i].clone();
@@ -188,6 +402,23 @@ fn main() {
         i += 1;
     }
     
+    // 执行子命令
+    match subcommand {
+        Some("dap") => {
+            dap::run_dap_server(extra_module_paths);
+            return;
+        },
+        Some("lsp") => {
+            lsp::run_lsp_server(extra_module_paths);
+            return;
+        },
+        Some("schema") => {
+            let input = if filename.is_empty() { None } else { Some(filename.as_str()) };
+            std::process::exit(schema::run_schema(input, extra_module_paths));
+        },
+        _ => {}
+    }
+    
     // 如果指定了modulemeta参数，查询并显示模块元数据
     if let Some(module_path) = modulemeta_path {
         display_module_metadata(&module_path);
@@ -201,97 +432,93 @@ fn main() {
     }
     
     // 检查文件扩展名
-    if !filename.ends_with(".jl") && !filename.ends_with(".jil") {
-        eprintln!("警告: 文件 '{}' 没有 .jl 或 .jil 扩展名，但我们将尝试执行它", filename);
+    if !source_format::is_source_file(&filename) {
+        eprintln!("警告: 文件 '{}' 没有 .jl、.jil、.jl.yaml 或 .jl.toml 扩展名，但我们将尝试执行它", filename);
     }
     
-    // 获取程序文件的绝对路径
-    let absolute_path = match std::fs::canonicalize(&filename) {
-        Ok(path) => path,
+    // 加载程序文件及其包含的模块
+    let LoadedProgram { path: absolute_path, source, program, modules, module_errors } = match load_program(&filename, extra_module_paths) {
+        Ok(loaded) => loaded,
         Err(e) => {
-            eprintln!("错误: 无法获取文件 '{}' 的绝对路径: {}", filename, e);
+            eprintln!("{}", e);
             std::process::exit(1);
         }
     };
     
-    // 获取程序文件所在目录
-    let program_dir = match absolute_path.parent() {
-        Some(dir) => dir.to_string_lossy().to_string(),
-        None => {
-            eprintln!("错误: 无法获取文件 '{}' 的父目录", filename);
-            std::process::exit(1);
-        }
-    };
-    
-    // 设置模块注册表的基础路径为程序文件所在目录
-    get_registry_mut().set_base_path(&program_dir);
-    
-    // 输出调试信息
-    if is_debug_mode() {
-        println!("程序文件: {}", absolute_path.to_string_lossy());
-        println!("程序目录: {}", program_dir);
-        println!("模块搜索路径: {:?}", get_registry().get_search_paths());
-    }
-    
-    // 添加额外的模块搜索路径
-    for path in extra_module_paths {
-        get_registry_mut().add_search_path(path);
-    }
-    
-    // 显式添加examples/modules目录作为搜索路径
-    let modules_dir = std::path::Path::new(&program_dir).join("..").to_string_lossy().to_string();
-    get_registry_mut().add_search_path(modules_dir);
-    
-    if is_debug_mode() {
-        println!("添加额外模块路径后: {:?}", get_registry().get_search_paths());
+    if validate && !schema::validate_program(&absolute_path, &source, &program) {
+        modules::destroy_modules(&modules);
+        std::process::exit(1);
     }
     
-    // 读取程序文件
-    let program_text = match fs::read_to_string(&filename) {
-        Ok(text) => text,
-        Err(e) => {
-            eprintln!("无法读取程序文件 '{}': {}", filename, e);
+    // 启用不确定输入的记录或回放。原生模块和插件模块的结果无法记录，回放时会不一致
+    if record_trace.is_some() || replay_trace.is_some() {
+        let untraced = modules.iter().find(|module| {
+            let module = module.borrow();
+            module.as_any().is::<modules::native_module::NativeModule>() || module.as_any().is::<modules::plugin_module::PluginModule>()
+        });
+        if let Some(module) = untraced {
+            eprintln!("错误: --record/--replay 不支持原生模块和插件模块 '{}'，它们产生的值无法记录", module.borrow().get_name());
+            modules::destroy_modules(&modules);
             std::process::exit(1);
         }
-    };
-    
-    // 预处理JSON文件，移除//注释
-    let preprocessed_text = preprocess_json(&program_text);
-    
-    // 解析 JSON
-    let program: Value = match serde_json::from_str(&preprocessed_text) {
-        Ok(json) => json,
-        Err(e) => {
-            eprintln!("JSON 解析错误: {}。您连json都不会写吗？你是小学生吗？", e);
+    }
+    match (&record_trace, &replay_trace) {
+        (Some(_), Some(_)) => {
+            eprintln!("错误: --record 和 --replay 不能同时使用");
+            modules::destroy_modules(&modules);
             std::process::exit(1);
-        }
-    };
+        },
+        (Some(_), None) => interpreter::trace::start_recording(),
+        (None, Some(path)) => {
+            if let Err(e) = interpreter::trace::start_replay(path) {
+                eprintln!("错误: {}", e);
+                modules::destroy_modules(&modules);
+                std::process::exit(1);
+            }
+        },
+        (None, None) => {},
+    }
     
-    // 获取需要加载的模块列表
-    let mut modules = Vec::new();
-    let mut module_errors = Vec::new();
+    // 启用HTTP录制或回放
+    let http_cassette_result = match (&http_record, &http_replay) {
+        (Some(_), Some(_)) => Err("--http-record 和 --http-replay 不能同时使用".to_string()),
+        (Some(dir), None) => modules::http_cassette::start_recording(dir, http_match.as_deref()),
+        (None, Some(dir)) => modules::http_cassette::start_replay(dir, http_match.as_deref()),
+        (None, None) => Ok(()),
+    };
+    if let Err(e) = http_cassette_result {
+        eprintln!("错误: {}", e);
+        modules::destroy_modules(&modules);
+        std::process::exit(1);
+    }
     
-    // 从程序的include字段获取需要加载的模块
-    if let Some(include_array) = program.get("include").and_then(|v| v.as_array()) {
-        for module_name in include_array {
-            if let Some(name) = module_name.as_str() {
-                if let Some(module) = get_module(name) {
-                    modules.push(module);
-                } else {
-                    let error_msg = format!("未找到模块 '{}'。您能凭空变出这个模块吗？", name);
-                    if is_check_all() {
-                        module_errors.push(error_msg);
-                    } else {
-                        eprintln!("警告: {}", error_msg);
-                    }
+    // 覆盖率统计包括主程序和所有JL模块文件
+    let coverage_data = if coverage {
+        let mut data = interpreter::coverage::Coverage::new();
+        data.add_source(&absolute_path, &source);
+        for module in &modules {
+            if let Some(module) = module.borrow().as_any().downcast_ref::<modules::external_module::JLangExternalModule>() {
+                if let Ok(text) = fs::read_to_string(module.get_path()) {
+                    data.add_source(module.get_path(), &text);
                 }
             }
         }
-    }
+        Some(Rc::new(RefCell::new(data)))
+    } else {
+        None
+    };
     
     // 创建解释器
     match Interpreter::new(program, modules) {
         Ok(mut interpreter) => {
+            interpreter.set_source_path(&absolute_path);
+            if let Some(seed) = seed {
+                interpreter.seed_random(seed);
+            }
+            if watch_modules {
+                interpreter.watch_modules();
+            }
+            
             // 收集错误信息（对于check-all模式）
             let mut all_errors = Vec::new();
             
@@ -322,13 +549,63 @@ fn main() {
                 return;
             }
             
+            if let Some(data) = &coverage_data {
+                interpreter.add_hook(Box::new(interpreter::coverage::CoverageHook::new(data.clone())));
+            }
+            
+            if profile {
+                interpreter::profiler::enable();
+                interpreter.add_hook(Box::new(interpreter::profiler::ProfilerHook));
+            }
+            
             // 运行程序
-            if let Err(e) = interpreter.run() {
+            let run_result = interpreter.run();
+            
+            // 无论程序是否出错都输出性能分析报告
+            if profile {
+                let prefix = profile_out.unwrap_or_else(|| format!("{}.profile", program_stem(&filename)));
+                write_profile_report(&prefix);
+            }
+            
+            if let Some(data) = &coverage_data {
+                let prefix = coverage_out.unwrap_or_else(|| format!("{}.coverage", program_stem(&filename)));
+                write_coverage_report(&mut data.borrow_mut(), &prefix);
+            }
+            
+            // 程序出错时也保存记录，以便重现
+            if let Some(path) = &record_trace {
+                match interpreter::trace::write_recording(path, &absolute_path) {
+                    Ok(count) => eprintln!("已记录 {} 个不确定值: {}", count, path),
+                    Err(e) => eprintln!("错误: {}", e),
+                }
+            }
+            if replay_trace.is_some() {
+                let (diverged, remaining) = interpreter::trace::finish_replay();
+                if remaining > 0 {
+                    eprintln!("警告: 记录中还有 {} 个值没有使用", remaining);
+                }
+                if diverged > 0 && run_result.is_ok() {
+                    eprintln!("错误: 回放时有 {} 个值与记录不一致", diverged);
+                    interpreter.destroy_modules();
+                    std::process::exit(1);
+                }
+            }
+            
+            // 回放时有请求没有匹配的录制，视为失败
+            let unmatched = modules::http_cassette::unmatched_count();
+            if unmatched > 0 && run_result.is_ok() {
+                eprintln!("错误: HTTP回放中有 {} 个请求没有匹配的录制", unmatched);
+                interpreter.destroy_modules();
+                std::process::exit(1);
+            }
+            
+            if let Err(e) = run_result {
                 // 根据错误类型和当前模式决定行为
                 match e {
                     interpreter::error::InterpreterError::InvalidProgramStructure(_) => {
                         // 程序结构错误总是致命的
                         eprintln!("错误: {}", e);
+                        interpreter.destroy_modules();
                         std::process::exit(1);
                     },
                     _ => {
@@ -337,6 +614,7 @@ fn main() {
                             eprintln!("警告: {}", e);
                         } else {
                             eprintln!("错误: {}", e);
+                            interpreter.destroy_modules();
                             std::process::exit(1);
                         }
                     }
@@ -350,19 +628,86 @@ fn main() {
     }
 }
 
+// 程序文件名去掉扩展名，用作报告文件的默认前缀
+fn program_stem(filename: &str) -> String {
+    let file_name = Path::new(filename).file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    source_format::strip_source_extension(&file_name).unwrap_or(&file_name).to_string()
+}
+
+// 打印覆盖率摘要，并写出LCOV和JSON报告
+fn write_coverage_report(data: &mut interpreter::coverage::Coverage, prefix: &str) {
+    let files = data.report();
+    eprint!("{}", interpreter::coverage::summary_table(&files));
+    
+    let lcov_path = format!("{}.lcov", prefix);
+    match fs::write(&lcov_path, interpreter::coverage::lcov(&files)) {
+        Ok(()) => eprintln!("LCOV报告已写入: {}", lcov_path),
+        Err(e) => eprintln!("错误: 无法写入文件 '{}': {}", lcov_path, e),
+    }
+    
+    let json_path = format!("{}.json", prefix);
+    let json = serde_json::to_string_pretty(&interpreter::coverage::json_report(&files)).unwrap_or_default();
+    match fs::write(&json_path, json) {
+        Ok(()) => eprintln!("覆盖率报告已写入: {}", json_path),
+        Err(e) => eprintln!("错误: 无法写入文件 '{}': {}", json_path, e),
+    }
+}
+
+// 打印性能分析表格，并写出折叠栈文件和JSON报告
+fn write_profile_report(prefix: &str) {
+    let report = match interpreter::profiler::finish() {
+        Some(report) => report,
+        None => return,
+    };
+    eprint!("{}", report.table());
+    
+    let folded_path = format!("{}.folded", prefix);
+    match fs::write(&folded_path, report.folded_stacks()) {
+        Ok(()) => eprintln!("折叠栈已写入: {}", folded_path),
+        Err(e) => eprintln!("错误: 无法写入文件 '{}': {}", folded_path, e),
+    }
+    
+    let json_path = format!("{}.json", prefix);
+    let json = serde_json::to_string_pretty(&report.json_report()).unwrap_or_default();
+    match fs::write(&json_path, json) {
+        Ok(()) => eprintln!("性能报告已写入: {}", json_path),
+        Err(e) => eprintln!("错误: 无法写入文件 '{}': {}", json_path, e),
+    }
+}
+
 // 打印帮助信息
 fn print_help() {
     println!("JiLang 解释器 v{}", VERSION);
     println!("用法: jlang [选项] 文件名");
-    println!("文件扩展名: .jl 或 .jil");
+    println!("      jlang dap [选项]             以调试适配器协议(DAP)服务器模式运行，通过标准输入输出通信");
+    println!("      jlang fmt [--check] 文件名...   格式化程序并保留注释，--check只检查是否需要格式化（需要时返回非零退出码）");
+    println!("      jlang test [文件或目录...] [--filter 文本]  运行tests部分和*_test.jl文件中的测试（有失败时返回非零退出码）");
+    println!("      jlang convert 文件名 [--to json|yaml|toml] [-o 输出文件]  在JSON、YAML和TOML源码格式之间转换");
+    println!("      jlang schema [文件名]           输出程序的JSON Schema，指定文件时包含其模块函数和用户函数");
+    println!("      jlang lsp [选项]             以语言服务器协议(LSP)服务器模式运行，提供诊断、补全、悬停、跳转和重命名");
+    println!("文件扩展名: .jl 或 .jil（JSONC/JSON5），.jl.yaml/.jl.yml（YAML），.jl.toml（TOML）");
     println!("选项:");
     println!("  --debug                      启用调试模式");
     println!("  --ignore-non-critical-errors 忽略非关键错误");
     println!("  --check                      只检查错误，不执行代码");
     println!("  --check-all                  检查所有类型错误并统一报告");
     println!("  --print-full                 打印完整值");
+    println!("  --validate                   运行前按JSON Schema校验程序结构");
+    println!("  --seed <整数>                设置随机数种子，math中的随机函数每次运行得到相同结果");
+    println!("  --record <文件>              记录随机数、输入、环境变量、命令输出和时间等不确定值（不支持原生模块和插件模块）");
+    println!("  --replay <文件>              按顺序回放记录的不确定值，重现一次运行");
+    println!("  --http-record <目录>         把HTTP请求和响应录制到目录（覆盖以前的录制）");
+    println!("  --http-replay <目录>         从目录回放HTTP响应，不访问网络，未匹配的请求视为失败");
+    println!("  --http-match <规则>          录制/回放的匹配规则，逗号分隔: method,url,path,body,header:名称（默认 method,url）");
+    println!("  --coverage                   统计语句覆盖率并输出LCOV和JSON报告");
+    println!("  --coverage-out <前缀>        覆盖率报告文件前缀（默认为 程序名.coverage）");
+    println!("  --profile                    输出性能分析报告（表格、折叠栈和JSON）");
+    println!("  --profile-out <前缀>         性能分析报告文件前缀（默认为 程序名.profile）");
     println!("  --module-path <路径>         添加模块搜索路径");
-    println!("  --modulemeta <文件路径>      显示指定模块文件的元数据");
+    println!("  --modulemeta <文件路径|名称> 显示指定模块文件或内置模块的元数据");
+    println!("  --watch-modules              模块文件修改后自动重新加载（用于长时间运行的程序）");
     println!("  --help                       显示帮助信息");
     println!("  --about                      显示关于信息");
     println!("  --creator                    显示创建者信息");
@@ -396,8 +741,11 @@ fn print_creator() {
 fn print_available_modules() {
     println!("JiLang可用模块:");
     println!("内置模块:");
-    println!("  io     - 输入/输出操作模块");
-    println!("  math   - 数学运算模块");
+    for name in modules::BUILTIN_MODULES {
+        if let Some(metadata) = modules::builtin_metadata(name) {
+            println!("  {:<6} - {}", name, metadata.description);
+        }
+    }
     
     // 获取已注册的加载器
     println!("\n已注册的模块加载器:");
@@ -420,15 +768,10 @@ fn print_available_modules() {
                 if let Ok(entry) = entry {
                     let path = entry.path();
                     if path.is_file() {
-                        if let Some(ext) = path.extension() {
-                            if ext == "jl" || ext == "jil" {
-                                if let Some(name) = path.file_stem() {
-                                    if let Some(name_str) = name.to_str() {
-                                        println!("  {} (JiLang模块)", name_str);
-                                        found_modules = true;
-                                    }
-                                }
-                            }
+                        let file_name = entry.file_name().to_string_lossy().to_string();
+                        if let Some(name) = source_format::strip_source_extension(&file_name) {
+                            println!("  {} (JiLang模块)", name);
+                            found_modules = true;
                         }
                     }
                 }
@@ -445,6 +788,15 @@ fn print_available_modules() {
 fn display_module_metadata(path: &str) {
     use modules::{get_registry, get_registry_mut};
     
+    // 内置模块没有文件，直接显示生成的元数据
+    if !std::path::Path::new(path).exists() {
+        if let Some(metadata) = modules::builtin_metadata(path) {
+            println!("内置模块: {}", path);
+            print_module_metadata(&metadata, None);
+            return;
+        }
+    }
+    
     // 获取程序文件的绝对路径
     let absolute_path = match std::fs::canonicalize(path) {
         Ok(path) => path,
@@ -476,9 +828,13 @@ fn display_module_metadata(path: &str) {
     };
     
     // 尝试提取模块名（移除扩展名）
-    let module_name = match file_name.rfind('.') {
-        Some(pos) => file_name[..pos].to_string(),
-        None => file_name,
+    let module_name = match source_format::strip_source_extension(&file_name)
+        .or_else(|| get_registry().strip_module_extension(&file_name)) {
+        Some(name) => name.to_string(),
+        None => match file_name.rfind('.') {
+            Some(pos) => file_name[..pos].to_string(),
+            None => file_name,
+        },
     };
     
     println!("正在加载模块: {} (路径: {})", module_name, absolute_path.to_string_lossy());
@@ -488,48 +844,7 @@ fn display_module_metadata(path: &str) {
     
     match module_result {
         Ok(module) => {
-            // 获取模块元数据
-            let metadata = module.get_metadata();
-            
-            println!("\n=== 模块元数据 ===");
-            println!("名称: {}", metadata.name);
-            println!("版本: {}", metadata.version);
-            println!("描述: {}", metadata.description);
-            println!("作者: {}", metadata.author);
-            
-            println!("\n=== 支持的函数 ===");
-            for (func_name, func_meta) in &metadata.functions {
-                println!("* {}", func_name);
-                println!("  描述: {}", func_meta.description);
-                if !func_meta.parameters.is_empty() {
-                    println!("  参数:");
-                    for param in &func_meta.parameters {
-                        println!("    - {}: {} ({}{})",
-                            param.name,
-                            param.description,
-                            param.type_description,
-                            if param.optional { ", 可选" } else { "" }
-                        );
-                    }
-                }
-                println!("  返回值: {}", func_meta.return_type);
-                if !func_meta.example.is_empty() {
-                    println!("  示例: {}", func_meta.example);
-                }
-            }
-            
-            // 检查是否有module_meta
-            if let Some(module) = module.as_any().downcast_ref::<lua_module::LuaModule>() {
-                if let Some(meta_value) = module.get_module_meta_value() {
-                    println!("\n=== 模块自定义元数据 ===");
-                    println!("{}", serde_json::to_string_pretty(&meta_value).unwrap_or_else(|_| "无法格式化元数据".to_string()));
-                }
-            } else if let Some(module) = module.as_any().downcast_ref::<modules::external_module::JLangExternalModule>() {
-                if let Some(meta_value) = module.get_module_meta_value() {
-                    println!("\n=== 模块自定义元数据 ===");
-                    println!("{}", serde_json::to_string_pretty(&meta_value).unwrap_or_else(|_| "无法格式化元数据".to_string()));
-                }
-            }
+            print_module_metadata(module.get_metadata(), module.get_module_meta_value());
         },
         Err(e) => {
             eprintln!("无法加载模块 {}: {}", module_name, e);
@@ -537,3 +852,42 @@ fn display_module_metadata(path: &str) {
         }
     }
 }
+
+// 打印模块元数据，函数按名称排序
+fn print_module_metadata(metadata: &modules::external_module::ModuleMetadata, meta_value: Option<&serde_json::Value>) {
+    println!("\n=== 模块元数据 ===");
+    println!("名称: {}", metadata.name);
+    println!("版本: {}", metadata.version);
+    println!("描述: {}", metadata.description.replace('\n', "\n      "));
+    println!("作者: {}", metadata.author);
+    
+    println!("\n=== 支持的函数 ===");
+    let mut functions: Vec<_> = metadata.functions.iter().collect();
+    functions.sort_by(|a, b| a.0.cmp(b.0));
+    for (func_name, func_meta) in functions {
+        println!("* {}", func_name);
+        // 多行的描述和示例缩进到冒号后对齐
+        println!("  描述: {}", func_meta.description.replace('\n', "\n        "));
+        if !func_meta.parameters.is_empty() {
+            println!("  参数:");
+            for param in &func_meta.parameters {
+                println!("    - {}: {} ({}{})",
+                    param.name,
+                    param.description,
+                    param.type_description,
+                    if param.optional { ", 可选" } else { "" }
+                );
+            }
+        }
+        println!("  返回值: {}", func_meta.return_type);
+        if !func_meta.example.is_empty() {
+            println!("  示例: {}", func_meta.example.replace('\n', "\n        "));
+        }
+    }
+    
+    // 检查是否有module_meta
+    if let Some(meta_value) = meta_value {
+        println!("\n=== 模块自定义元数据 ===");
+        println!("{}", serde_json::to_string_pretty(meta_value).unwrap_or_else(|_| "无法格式化元数据".to_string()));
+    }
+}
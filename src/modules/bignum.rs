@@ -0,0 +1,775 @@
+// 数学模块的精确数值：任意精度整数和十进制小数
+//
+// 整数运算优先使用i64，溢出时提升为BigInt；十进制小数（math.decimal）用
+// “整数 × 10^-scale” 表示，加减乘都是精确的，除法和取整需要指定舍入方式。
+// 超出i64范围的整数以十进制字符串表示，十进制小数以 {"$decimal": "0.10"} 表示。
+use std::cmp::Ordering;
+use std::fmt;
+use serde_json::{json, Value};
+
+// 每个limb保存9位十进制数字
+const BASE: u64 = 1_000_000_000;
+const BASE_DIGITS: usize = 9;
+
+/// 十进制小数在JSON中的键
+pub const DECIMAL_KEY: &str = "$decimal";
+
+/// 任意精度整数
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    // 低位在前，没有多余的高位0；零为空数组且不为负
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        BigInt { negative: false, limbs: Vec::new() }
+    }
+
+    pub fn from_i64(value: i64) -> Self {
+        Self::from_magnitude(value < 0, value.unsigned_abs() as u128)
+    }
+
+    pub fn from_u64(value: u64) -> Self {
+        Self::from_magnitude(false, value as u128)
+    }
+
+    fn from_magnitude(negative: bool, mut magnitude: u128) -> Self {
+        let mut limbs = Vec::new();
+        while magnitude > 0 {
+            limbs.push((magnitude % BASE as u128) as u32);
+            magnitude /= BASE as u128;
+        }
+        Self::normalized(negative, limbs)
+    }
+
+    fn normalized(negative: bool, mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        let negative = negative && !limbs.is_empty();
+        BigInt { negative, limbs }
+    }
+
+    /// 解析整数字面量，例如 `-12345678901234567890`
+    pub fn parse(text: &str) -> Option<Self> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let bytes = digits.as_bytes();
+        let mut limbs = Vec::with_capacity(bytes.len() / BASE_DIGITS + 1);
+        let mut end = bytes.len();
+        while end > 0 {
+            let start = end.saturating_sub(BASE_DIGITS);
+            limbs.push(digits[start..end].parse::<u32>().ok()?);
+            end = start;
+        }
+        Some(Self::normalized(negative, limbs))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.limbs.first().is_some_and(|limb| limb % 2 == 1)
+    }
+
+    /// 在i64范围内时返回i64
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.limbs.len() > 3 {
+            return None;
+        }
+        let magnitude = self.limbs.iter().rev().fold(0i128, |acc, limb| acc * BASE as i128 + *limb as i128);
+        let value = if self.negative { -magnitude } else { magnitude };
+        i64::try_from(value).ok()
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::normalized(!self.negative, self.limbs.clone())
+    }
+
+    pub fn abs(&self) -> Self {
+        BigInt { negative: false, limbs: self.limbs.clone() }
+    }
+
+    fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+        a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+    }
+
+    fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+        let mut carry = 0u64;
+        for i in 0..a.len().max(b.len()) {
+            let sum = carry + *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64;
+            result.push((sum % BASE) as u32);
+            carry = sum / BASE;
+        }
+        if carry > 0 {
+            result.push(carry as u32);
+        }
+        result
+    }
+
+    // 要求 a >= b
+    fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let mut result = Vec::with_capacity(a.len());
+        let mut borrow = 0i64;
+        for (i, limb) in a.iter().enumerate() {
+            let mut diff = *limb as i64 - borrow - *b.get(i).unwrap_or(&0) as i64;
+            borrow = if diff < 0 { diff += BASE as i64; 1 } else { 0 };
+            result.push(diff as u32);
+        }
+        result
+    }
+
+    fn mul_small(a: &[u32], factor: u32) -> Vec<u32> {
+        let mut result = Vec::with_capacity(a.len() + 1);
+        let mut carry = 0u64;
+        for limb in a {
+            let product = *limb as u64 * factor as u64 + carry;
+            result.push((product % BASE) as u32);
+            carry = product / BASE;
+        }
+        if carry > 0 {
+            result.push(carry as u32);
+        }
+        result
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if self.negative == other.negative {
+            return Self::normalized(self.negative, Self::add_magnitude(&self.limbs, &other.limbs));
+        }
+        match Self::cmp_magnitude(&self.limbs, &other.limbs) {
+            Ordering::Less => Self::normalized(other.negative, Self::sub_magnitude(&other.limbs, &self.limbs)),
+            _ => Self::normalized(self.negative, Self::sub_magnitude(&self.limbs, &other.limbs)),
+        }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut result = vec![0u64; self.limbs.len() + other.limbs.len() + 1];
+        for (i, a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.limbs.iter().enumerate() {
+                let current = result[i + j] + *a as u64 * *b as u64 + carry;
+                result[i + j] = current % BASE;
+                carry = current / BASE;
+            }
+            let mut k = i + other.limbs.len();
+            while carry > 0 {
+                let current = result[k] + carry;
+                result[k] = current % BASE;
+                carry = current / BASE;
+                k += 1;
+            }
+        }
+        Self::normalized(self.negative != other.negative, result.into_iter().map(|limb| limb as u32).collect())
+    }
+
+    /// 向零取整的除法，返回商和余数（余数与被除数同号），除数为零时返回None
+    pub fn div_rem(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+        let mut quotient = vec![0u32; self.limbs.len()];
+        let mut remainder: Vec<u32> = Vec::new();
+        for i in (0..self.limbs.len()).rev() {
+            remainder.insert(0, self.limbs[i]);
+            while remainder.last() == Some(&0) {
+                remainder.pop();
+            }
+            // 二分查找本位的商
+            let (mut low, mut high) = (0u32, (BASE - 1) as u32);
+            while low < high {
+                let mid = low + (high - low).div_ceil(2);
+                if Self::cmp_magnitude(&Self::trimmed(Self::mul_small(&other.limbs, mid)), &remainder) == Ordering::Greater {
+                    high = mid - 1;
+                } else {
+                    low = mid;
+                }
+            }
+            if low > 0 {
+                remainder = Self::trimmed(Self::sub_magnitude(&remainder, &Self::trimmed(Self::mul_small(&other.limbs, low))));
+            }
+            quotient[i] = low;
+        }
+        Some((
+            Self::normalized(self.negative != other.negative, quotient),
+            Self::normalized(self.negative, remainder),
+        ))
+    }
+
+    fn trimmed(mut limbs: Vec<u32>) -> Vec<u32> {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        limbs
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> Self {
+        let mut result = BigInt::from_i64(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul(&base);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.mul(&base);
+            }
+        }
+        result
+    }
+
+    pub fn pow10(exponent: u32) -> Self {
+        BigInt::from_i64(10).pow(exponent)
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = String::new();
+        if self.negative {
+            text.push('-');
+        }
+        match self.limbs.split_last() {
+            None => text.push('0'),
+            Some((high, rest)) => {
+                text.push_str(&high.to_string());
+                for limb in rest.iter().rev() {
+                    text.push_str(&format!("{:09}", limb));
+                }
+            }
+        }
+        f.write_str(&text)
+    }
+}
+
+/// 舍入方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// 四舍五入（0.5远离零）
+    HalfUp,
+    /// 0.5向零舍去
+    HalfDown,
+    /// 银行家舍入（0.5取偶数）
+    HalfEven,
+    /// 远离零
+    Up,
+    /// 向零截断
+    Down,
+    /// 向正无穷
+    Ceiling,
+    /// 向负无穷
+    Floor,
+}
+
+impl RoundingMode {
+    pub const NAMES: &'static [&'static str] = &["half_up", "half_down", "half_even", "up", "down", "ceiling", "floor"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "half_up" => Some(RoundingMode::HalfUp),
+            "half_down" => Some(RoundingMode::HalfDown),
+            "half_even" => Some(RoundingMode::HalfEven),
+            "up" => Some(RoundingMode::Up),
+            "down" => Some(RoundingMode::Down),
+            "ceiling" => Some(RoundingMode::Ceiling),
+            "floor" => Some(RoundingMode::Floor),
+            _ => None,
+        }
+    }
+}
+
+// 按舍入方式对 numerator / divisor 取整
+fn divide_rounded(numerator: &BigInt, divisor: &BigInt, mode: RoundingMode) -> Option<BigInt> {
+    let (quotient, remainder) = numerator.div_rem(divisor)?;
+    if remainder.is_zero() {
+        return Some(quotient);
+    }
+    let negative = numerator.is_negative() != divisor.is_negative();
+    let away_from_zero = match mode {
+        RoundingMode::Up => true,
+        RoundingMode::Down => false,
+        RoundingMode::Ceiling => !negative,
+        RoundingMode::Floor => negative,
+        _ => match remainder.abs().mul(&BigInt::from_i64(2)).add(&divisor.abs().neg()) {
+            half if half.is_zero() => match mode {
+                RoundingMode::HalfUp => true,
+                RoundingMode::HalfDown => false,
+                _ => quotient.is_odd(),
+            },
+            half => !half.is_negative(),
+        },
+    };
+    Some(if away_from_zero {
+        quotient.add(&BigInt::from_i64(if negative { -1 } else { 1 }))
+    } else {
+        quotient
+    })
+}
+
+/// 十进制小数：value × 10^-scale
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decimal {
+    value: BigInt,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn from_int(value: BigInt) -> Self {
+        Decimal { value, scale: 0 }
+    }
+
+    /// 解析十进制数字，例如 `19.99`、`-0.005`、`1.5e3`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (number, exponent) = match text.find(['e', 'E']) {
+            Some(pos) => (&text[..pos], text[pos + 1..].parse::<i32>().ok()?),
+            None => (text, 0),
+        };
+        let (int_part, frac_part) = number.split_once('.').unwrap_or((number, ""));
+        if !number.bytes().any(|b| b.is_ascii_digit()) || !frac_part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let digits = format!("{}{}", int_part, frac_part);
+        // 允许 ".5" 和 "-.5" 这样的写法
+        let digits = if int_part.is_empty() || int_part == "-" || int_part == "+" {
+            format!("{}0{}", int_part, frac_part)
+        } else {
+            digits
+        };
+        let value = BigInt::parse(&digits)?;
+        let scale = frac_part.len() as i64 - exponent as i64;
+        if scale >= 0 {
+            Some(Decimal { value, scale: u32::try_from(scale).ok()? })
+        } else {
+            Some(Decimal { value: value.mul(&BigInt::pow10(u32::try_from(-scale).ok()?)), scale: 0 })
+        }
+    }
+
+    /// 从浮点数转换，使用浮点数的最短十进制表示
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if value.is_finite() {
+            Self::parse(&value.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// 从JSON值解析：{"$decimal": "..."}
+    pub fn from_value(value: &Value) -> Option<Self> {
+        decimal_text(value).and_then(Self::parse)
+    }
+
+    pub fn to_value(&self) -> Value {
+        json!({ DECIMAL_KEY: self.to_string() })
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    // 转换为更大的小数位数（精确）
+    fn with_scale(&self, scale: u32) -> Self {
+        if scale <= self.scale {
+            return self.clone();
+        }
+        Decimal { value: self.value.mul(&BigInt::pow10(scale - self.scale)), scale }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let scale = self.scale.max(other.scale);
+        Decimal { value: self.with_scale(scale).value.add(&other.with_scale(scale).value), scale }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        Decimal { value: self.value.mul(&other.value), scale: self.scale + other.scale }
+    }
+
+    pub fn neg(&self) -> Self {
+        Decimal { value: self.value.neg(), scale: self.scale }
+    }
+
+    pub fn abs(&self) -> Self {
+        Decimal { value: self.value.abs(), scale: self.scale }
+    }
+
+    pub fn pow(&self, exponent: u32) -> Self {
+        Decimal { value: self.value.pow(exponent), scale: self.scale * exponent }
+    }
+
+    /// 保留scale位小数，除数为零时返回None
+    pub fn div(&self, other: &Self, scale: u32, mode: RoundingMode) -> Option<Self> {
+        // self / other × 10^scale = self.value × 10^(scale + other.scale - self.scale) / other.value
+        let shift = scale as i64 + other.scale as i64 - self.scale as i64;
+        let (numerator, divisor) = if shift >= 0 {
+            (self.value.mul(&BigInt::pow10(shift as u32)), other.value.clone())
+        } else {
+            (self.value.clone(), other.value.mul(&BigInt::pow10((-shift) as u32)))
+        };
+        Some(Decimal { value: divide_rounded(&numerator, &divisor, mode)?, scale })
+    }
+
+    /// 舍入到scale位小数，位数更多时补0
+    pub fn round(&self, scale: u32, mode: RoundingMode) -> Self {
+        if scale >= self.scale {
+            return self.with_scale(scale);
+        }
+        let divisor = BigInt::pow10(self.scale - scale);
+        let value = divide_rounded(&self.value, &divisor, mode).unwrap_or_else(BigInt::zero);
+        Decimal { value, scale }
+    }
+
+    /// 去掉小数部分末尾的0
+    pub fn normalize(&self) -> Self {
+        let mut result = self.clone();
+        let ten = BigInt::from_i64(10);
+        while result.scale > 0 {
+            match result.value.div_rem(&ten) {
+                Some((quotient, remainder)) if remainder.is_zero() => {
+                    result = Decimal { value: quotient, scale: result.scale - 1 };
+                }
+                _ => break,
+            }
+        }
+        result
+    }
+
+    /// 整数部分（小数位数为0时）
+    pub fn to_integer(&self) -> Option<BigInt> {
+        if self.scale == 0 { Some(self.value.clone()) } else { None }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.value.abs().to_string();
+        let scale = self.scale as usize;
+        let sign = if self.value.is_negative() { "-" } else { "" };
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int_part, frac_part)
+    }
+}
+
+/// 如果是十进制小数值，返回其文本
+pub fn decimal_text(value: &Value) -> Option<&str> {
+    match value {
+        Value::Object(obj) if obj.len() == 1 => obj.get(DECIMAL_KEY).and_then(|v| v.as_str()),
+        _ => None,
+    }
+}
+
+/// 精确数值：i64整数、大整数或十进制小数
+#[derive(Clone, Debug)]
+pub enum Exact {
+    Int(i64),
+    Big(BigInt),
+    Decimal(Decimal),
+}
+
+impl Exact {
+    /// 从JSON值解析：整数、整数字符串或十进制小数，浮点数等其他值返回None
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => Some(Exact::Int(i)),
+                (None, Some(u)) => Some(Exact::Big(BigInt::from_u64(u))),
+                _ => None,
+            },
+            Value::String(s) => BigInt::parse(s.trim()).map(Self::from_big),
+            _ => Decimal::from_value(value).map(Exact::Decimal),
+        }
+    }
+
+    /// 大整数在i64范围内时转为Int
+    pub fn from_big(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(i) => Exact::Int(i),
+            None => Exact::Big(value),
+        }
+    }
+
+    pub fn is_decimal(&self) -> bool {
+        matches!(self, Exact::Decimal(_))
+    }
+
+    pub fn to_big(&self) -> BigInt {
+        match self {
+            Exact::Int(i) => BigInt::from_i64(*i),
+            Exact::Big(b) => b.clone(),
+            Exact::Decimal(d) => d.round(0, RoundingMode::Down).to_integer().unwrap_or_else(BigInt::zero),
+        }
+    }
+
+    pub fn to_decimal(&self) -> Decimal {
+        match self {
+            Exact::Decimal(d) => d.clone(),
+            other => Decimal::from_int(other.to_big()),
+        }
+    }
+
+    /// 转换为JSON值：i64为数字，更大的整数为字符串，小数为 {"$decimal": ...}
+    pub fn to_value(&self) -> Value {
+        match self {
+            Exact::Int(i) => Value::Number((*i).into()),
+            Exact::Big(b) => Value::String(b.to_string()),
+            Exact::Decimal(d) => d.to_value(),
+        }
+    }
+
+    // 二元运算：有小数时按小数计算，否则先用i64计算，溢出时提升为大整数
+    fn binary(
+        &self,
+        other: &Self,
+        int_op: fn(i64, i64) -> Option<i64>,
+        big_op: fn(&BigInt, &BigInt) -> BigInt,
+        decimal_op: fn(&Decimal, &Decimal) -> Decimal,
+    ) -> Self {
+        match (self, other) {
+            (Exact::Decimal(_), _) | (_, Exact::Decimal(_)) => Exact::Decimal(decimal_op(&self.to_decimal(), &other.to_decimal())),
+            (Exact::Int(a), Exact::Int(b)) => match int_op(*a, *b) {
+                Some(result) => Exact::Int(result),
+                None => Self::from_big(big_op(&BigInt::from_i64(*a), &BigInt::from_i64(*b))),
+            },
+            _ => Self::from_big(big_op(&self.to_big(), &other.to_big())),
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        self.binary(other, i64::checked_add, BigInt::add, Decimal::add)
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.binary(other, i64::checked_sub, BigInt::sub, Decimal::sub)
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        self.binary(other, i64::checked_mul, BigInt::mul, Decimal::mul)
+    }
+
+    /// 比较大小，按差的符号判断，整数和小数可以互相比较
+    pub fn compare(&self, other: &Self) -> Ordering {
+        match self.sub(other) {
+            Exact::Int(difference) => difference.cmp(&0),
+            Exact::Big(difference) if difference.is_negative() => Ordering::Less,
+            Exact::Big(_) => Ordering::Greater,
+            Exact::Decimal(difference) if difference.value.is_zero() => Ordering::Equal,
+            Exact::Decimal(difference) if difference.value.is_negative() => Ordering::Less,
+            Exact::Decimal(_) => Ordering::Greater,
+        }
+    }
+
+    pub fn abs(&self) -> Self {
+        match self {
+            Exact::Int(i) => match i.checked_abs() {
+                Some(abs) => Exact::Int(abs),
+                None => Exact::Big(BigInt::from_i64(*i).abs()),
+            },
+            Exact::Big(b) => Exact::Big(b.abs()),
+            Exact::Decimal(d) => Exact::Decimal(d.abs()),
+        }
+    }
+
+    pub fn pow(&self, exponent: u32) -> Self {
+        match self {
+            Exact::Int(i) => match i.checked_pow(exponent) {
+                Some(result) => Exact::Int(result),
+                None => Self::from_big(BigInt::from_i64(*i).pow(exponent)),
+            },
+            Exact::Big(b) => Self::from_big(b.pow(exponent)),
+            Exact::Decimal(d) => Exact::Decimal(d.pow(exponent)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        BigInt::parse(text).expect("无效的整数")
+    }
+
+    fn dec(text: &str) -> Decimal {
+        Decimal::parse(text).expect("无效的小数")
+    }
+
+    // 覆盖limb边界附近的值，用i128的结果对照
+    const SAMPLES: &[i128] = &[
+        0, 1, -1, 7, -13, 999_999_999, 1_000_000_000, -1_000_000_000, 1_000_000_001,
+        999_999_999_999_999_999, 1_000_000_000_000_000_000, -123_456_789_012_345_678,
+        i64::MAX as i128, i64::MIN as i128, 4_611_686_018_427_387_904_000_000,
+    ];
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!(big("0").to_string(), "0");
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("+42").to_string(), "42");
+        assert_eq!(big("000123").to_string(), "123");
+        assert_eq!(big("-1000000000000000000000").to_string(), "-1000000000000000000000");
+        assert!(BigInt::parse("").is_none());
+        assert!(BigInt::parse("-").is_none());
+        assert!(BigInt::parse("12a").is_none());
+        assert_eq!(BigInt::from_u64(u64::MAX).to_string(), u64::MAX.to_string());
+        assert_eq!(BigInt::from_i64(i64::MIN).to_string(), i64::MIN.to_string());
+    }
+
+    #[test]
+    fn add_sub_carry_and_borrow() {
+        assert_eq!(big("999999999").add(&big("1")).to_string(), "1000000000");
+        assert_eq!(big("999999999999999999").add(&big("1")).to_string(), "1000000000000000000");
+        assert_eq!(big("1000000000000000000").sub(&big("1")).to_string(), "999999999999999999");
+        assert_eq!(big("1").sub(&big("1000000000")).to_string(), "-999999999");
+        assert_eq!(big("5").sub(&big("5")), BigInt::zero());
+        for &a in SAMPLES {
+            for &b in SAMPLES {
+                let (x, y) = (big(&a.to_string()), big(&b.to_string()));
+                assert_eq!(x.add(&y).to_string(), (a + b).to_string(), "{} + {}", a, b);
+                assert_eq!(x.sub(&y).to_string(), (a - b).to_string(), "{} - {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn mul_and_pow() {
+        for &a in SAMPLES.iter().filter(|a| a.abs() <= i64::MAX as i128 + 1) {
+            for &b in SAMPLES.iter().filter(|b| b.abs() <= i64::MAX as i128 + 1) {
+                let expected = a.checked_mul(b).unwrap();
+                assert_eq!(big(&a.to_string()).mul(&big(&b.to_string())).to_string(), expected.to_string(), "{} * {}", a, b);
+            }
+        }
+        assert_eq!(BigInt::from_i64(2).pow(100).to_string(), "1267650600228229401496703205376");
+        assert_eq!(BigInt::from_i64(-3).pow(3).to_string(), "-27");
+        assert_eq!(BigInt::from_i64(7).pow(0).to_string(), "1");
+        assert_eq!(BigInt::pow10(20).to_string(), "100000000000000000000");
+    }
+
+    #[test]
+    fn division_truncates_toward_zero() {
+        for &a in SAMPLES {
+            for &b in SAMPLES.iter().filter(|b| **b != 0) {
+                let (q, r) = big(&a.to_string()).div_rem(&big(&b.to_string())).unwrap();
+                assert_eq!(q.to_string(), (a / b).to_string(), "{} / {}", a, b);
+                assert_eq!(r.to_string(), (a % b).to_string(), "{} % {}", a, b);
+            }
+        }
+        assert!(big("1").div_rem(&BigInt::zero()).is_none());
+        let (q, r) = big("100000000000000000000000000001").div_rem(&big("100000000000000000000")).unwrap();
+        assert_eq!((q.to_string(), r.to_string()), ("1000000000".to_string(), "1".to_string()));
+    }
+
+    #[test]
+    fn i64_conversion() {
+        assert_eq!(big("9223372036854775807").to_i64(), Some(i64::MAX));
+        assert_eq!(big("-9223372036854775808").to_i64(), Some(i64::MIN));
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+        assert!(big("-3").is_odd());
+        assert!(!big("1000000000").is_odd());
+    }
+
+    #[test]
+    fn decimal_parse_and_display() {
+        assert_eq!(dec("19.99").to_string(), "19.99");
+        assert_eq!(dec("-0.005").to_string(), "-0.005");
+        assert_eq!(dec(".5").to_string(), "0.5");
+        assert_eq!(dec("-.5").to_string(), "-0.5");
+        assert_eq!(dec("1.5e3").to_string(), "1500");
+        assert_eq!(dec("1.5e-3").to_string(), "0.0015");
+        assert_eq!(dec("0.10").scale(), 2);
+        assert!(Decimal::parse("abc").is_none());
+        assert!(Decimal::parse("1.2.3").is_none());
+        assert_eq!(Decimal::from_f64(0.1).unwrap().to_string(), "0.1");
+        assert!(Decimal::from_f64(f64::NAN).is_none());
+    }
+
+    #[test]
+    fn decimal_arithmetic_is_exact() {
+        assert_eq!(dec("0.1").add(&dec("0.2")).to_string(), "0.3");
+        assert_eq!(dec("1.00").sub(&dec("0.999")).to_string(), "0.001");
+        assert_eq!(dec("1.5").mul(&dec("-0.25")).to_string(), "-0.375");
+        assert_eq!(dec("1.1").pow(2).to_string(), "1.21");
+        assert_eq!(dec("1.2300").normalize().to_string(), "1.23");
+        assert_eq!(dec("100").normalize().to_string(), "100");
+        assert_eq!(dec("1").div(&dec("3"), 5, RoundingMode::HalfUp).unwrap().to_string(), "0.33333");
+        assert_eq!(dec("2").div(&dec("3"), 2, RoundingMode::HalfUp).unwrap().to_string(), "0.67");
+        assert_eq!(dec("1.000").div(&dec("0.5"), 0, RoundingMode::Down).unwrap().to_string(), "2");
+        assert!(dec("1").div(&dec("0.00"), 2, RoundingMode::HalfUp).is_none());
+    }
+
+    #[test]
+    fn rounding_modes() {
+        let cases: &[(&str, [&str; 7])] = &[
+            // 值      half_up half_down half_even up    down  ceiling floor
+            ("2.5",  ["3",  "2",  "2",  "3",  "2",  "3",  "2"]),
+            ("3.5",  ["4",  "3",  "4",  "4",  "3",  "4",  "3"]),
+            ("-2.5", ["-3", "-2", "-2", "-3", "-2", "-2", "-3"]),
+            ("2.4",  ["2",  "2",  "2",  "3",  "2",  "3",  "2"]),
+            ("2.6",  ["3",  "3",  "3",  "3",  "2",  "3",  "2"]),
+            ("-2.6", ["-3", "-3", "-3", "-3", "-2", "-2", "-3"]),
+            ("2",    ["2",  "2",  "2",  "2",  "2",  "2",  "2"]),
+        ];
+        for (value, expected) in cases {
+            for (name, expected) in RoundingMode::NAMES.iter().zip(expected.iter()) {
+                let mode = RoundingMode::parse(name).unwrap();
+                assert_eq!(dec(value).round(0, mode).to_string(), *expected, "{} {}", value, name);
+            }
+        }
+        assert_eq!(dec("1.005").round(2, RoundingMode::HalfUp).to_string(), "1.01");
+        assert_eq!(dec("1.005").round(2, RoundingMode::HalfEven).to_string(), "1.00");
+        assert_eq!(dec("1.5").round(3, RoundingMode::Down).to_string(), "1.500");
+        assert!(RoundingMode::parse("nearest").is_none());
+    }
+
+    #[test]
+    fn exact_promotes_on_overflow() {
+        let max = Exact::Int(i64::MAX);
+        assert_eq!(max.add(&Exact::Int(1)).to_value(), Value::String("9223372036854775808".to_string()));
+        assert_eq!(max.add(&Exact::Int(1)).sub(&Exact::Int(1)).to_value(), Value::from(i64::MAX));
+        assert_eq!(Exact::Int(i64::MIN).abs().to_value(), Value::String("9223372036854775808".to_string()));
+        assert_eq!(Exact::Int(10).pow(20).to_value(), Value::String("100000000000000000000".to_string()));
+        let sum = Exact::Int(1).add(&Exact::from_value(&json!({"$decimal": "0.50"})).unwrap());
+        assert!(sum.is_decimal());
+        assert_eq!(sum.to_value(), json!({"$decimal": "1.50"}));
+        assert!(Exact::from_value(&json!(1.5)).is_none());
+        assert!(matches!(Exact::from_value(&json!(u64::MAX)), Some(Exact::Big(_))));
+    }
+
+    #[test]
+    fn exact_comparison() {
+        let decimal = |text: &str| Exact::Decimal(dec(text));
+        assert_eq!(Exact::Int(3).compare(&Exact::Int(i64::MAX)), Ordering::Less);
+        assert_eq!(Exact::Int(i64::MAX).compare(&Exact::Int(i64::MIN)), Ordering::Greater);
+        assert_eq!(Exact::Big(big("18446744073709551615")).compare(&Exact::Int(i64::MAX)), Ordering::Greater);
+        assert_eq!(Exact::Int(2).compare(&decimal("2.00")), Ordering::Equal);
+        assert_eq!(decimal("-0.01").compare(&Exact::Int(0)), Ordering::Less);
+        assert_eq!(decimal("0.10").compare(&decimal("0.1")), Ordering::Equal);
+    }
+}
@@ -1,4 +1,4 @@
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::HashMap;
 use crate::interpreter::context::Context;
 use crate::interpreter::error::{InterpreterError, Result};
@@ -49,6 +49,84 @@ pub struct ParameterMetadata {
     pub default_value: Option<Value>,
 }
 
+impl ModuleMetadata {
+    /// 从JSON解析模块元数据，字段名与结构体相同，缺少的字段使用默认值。
+    /// functions可以是以函数名为键的对象，也可以是函数元数据数组
+    pub fn from_json(value: &Value, name: &str) -> Result<Self> {
+        let invalid = |message: &str| InterpreterError::ModuleError(format!("模块 '{}' 的元数据无效: {}", name, message));
+        if !value.is_object() {
+            return Err(invalid("必须是一个对象"));
+        }
+        let text = |value: &Value, key: &str| value.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+
+        let entries: Vec<(Option<&String>, &Value)> = match value.get("functions") {
+            Some(Value::Object(functions)) => functions.iter().map(|(key, info)| (Some(key), info)).collect(),
+            Some(Value::Array(functions)) => functions.iter().map(|info| (None, info)).collect(),
+            None | Some(Value::Null) => Vec::new(),
+            Some(_) => return Err(invalid("functions 必须是对象或数组")),
+        };
+        let mut functions = HashMap::new();
+        for (key, info) in entries {
+            let func_name = key.cloned().or_else(|| text(info, "name"))
+                .ok_or_else(|| invalid("函数元数据缺少 name"))?;
+            let parameters = info.get("parameters").and_then(|p| p.as_array()).map(|parameters| {
+                parameters.iter().map(|param| ParameterMetadata {
+                    name: text(param, "name").unwrap_or_default(),
+                    description: text(param, "description").unwrap_or_default(),
+                    type_description: text(param, "type_description").unwrap_or_else(|| "any".to_string()),
+                    optional: param.get("optional").and_then(|o| o.as_bool()).unwrap_or(false),
+                    default_value: param.get("default_value").filter(|v| !v.is_null()).cloned(),
+                }).collect()
+            }).unwrap_or_default();
+            functions.insert(func_name.clone(), FunctionMetadata {
+                description: text(info, "description").unwrap_or_default(),
+                name: func_name,
+                parameters,
+                return_type: text(info, "return_type").unwrap_or_else(|| "Any".to_string()),
+                example: text(info, "example").unwrap_or_default(),
+            });
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            version: text(value, "version").unwrap_or_else(|| "1.0.0".to_string()),
+            description: text(value, "description").unwrap_or_default(),
+            author: text(value, "author").unwrap_or_else(|| "未知".to_string()),
+            functions,
+        })
+    }
+
+    /// 转换为JSON，格式与from_json接受的相同，functions按函数名排序
+    pub fn to_json(&self) -> Value {
+        let mut functions: Vec<&FunctionMetadata> = self.functions.values().collect();
+        functions.sort_by(|a, b| a.name.cmp(&b.name));
+        let functions: serde_json::Map<String, Value> = functions.into_iter().map(|function| {
+            let parameters: Vec<Value> = function.parameters.iter().map(|param| json!({
+                "name": param.name,
+                "description": param.description,
+                "type_description": param.type_description,
+                "optional": param.optional,
+                "default_value": param.default_value,
+            })).collect();
+            (function.name.clone(), json!({
+                "name": function.name,
+                "description": function.description,
+                "parameters": parameters,
+                "return_type": function.return_type,
+                "example": function.example,
+            }))
+        }).collect();
+
+        json!({
+            "name": self.name,
+            "version": self.version,
+            "description": self.description,
+            "author": self.author,
+            "functions": functions,
+        })
+    }
+}
+
 /// 外部模块类型枚举
 #[derive(Clone, Debug, PartialEq)]
 pub enum ExternalModuleType {
@@ -60,8 +138,12 @@ pub enum ExternalModuleType {
     External(String), // 具体语言名称
 }
 
+// 沙箱中未指定限制时使用的默认值
+const DEFAULT_SANDBOX_MEMORY_MB: usize = 64;
+const DEFAULT_SANDBOX_TIMEOUT_MS: u64 = 5000;
+
 /// 外部模块配置选项
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ExternalModuleOptions {
     /// 是否允许文件系统访问
     pub allow_filesystem: bool,
@@ -71,6 +153,10 @@ pub struct ExternalModuleOptions {
     pub memory_limit_mb: Option<usize>,
     /// 执行超时 (ms)
     pub execution_timeout_ms: Option<u64>,
+    /// 执行指令数限制（每次调用）
+    pub instruction_limit: Option<u64>,
+    /// 是否在沙箱中运行，None时由模块位置决定：项目目录之外的模块默认启用
+    pub sandbox: Option<bool>,
     /// 自定义环境变量
     pub env_vars: HashMap<String, String>,
 }
@@ -82,11 +168,75 @@ impl Default for ExternalModuleOptions {
             allow_network: false,
             memory_limit_mb: None,
             execution_timeout_ms: None,
+            instruction_limit: None,
+            sandbox: None,
             env_vars: HashMap::new(),
         }
     }
 }
 
+impl ExternalModuleOptions {
+    /// 位于path的模块是否在沙箱中运行：未明确指定时，项目目录之外的模块在沙箱中运行
+    pub fn is_sandboxed(&self, path: &str) -> bool {
+        self.sandbox.unwrap_or_else(|| !is_inside_project(path))
+    }
+    
+    /// 内存限制，沙箱中未指定时使用默认值
+    pub fn memory_limit_mb(&self, sandboxed: bool) -> Option<usize> {
+        self.memory_limit_mb.or(sandboxed.then_some(DEFAULT_SANDBOX_MEMORY_MB))
+    }
+    
+    /// 执行超时，沙箱中未指定时使用默认值
+    pub fn execution_timeout_ms(&self, sandboxed: bool) -> Option<u64> {
+        self.execution_timeout_ms.or(sandboxed.then_some(DEFAULT_SANDBOX_TIMEOUT_MS))
+    }
+    
+    /// 从include项的options对象解析，例如 `{"sandbox": true, "memory_limit_mb": 16}`
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let obj = value.as_object().ok_or_else(|| InterpreterError::ModuleError(
+            "模块选项必须是一个对象".to_string()
+        ))?;
+        let invalid = |key: &str, expected: &str| InterpreterError::ModuleError(
+            format!("模块选项 '{}' 必须是{}", key, expected)
+        );
+        let mut options = Self::default();
+        for (key, value) in obj {
+            match key.as_str() {
+                "allow_filesystem" => options.allow_filesystem = value.as_bool().ok_or_else(|| invalid(key, "布尔值"))?,
+                "allow_network" => options.allow_network = value.as_bool().ok_or_else(|| invalid(key, "布尔值"))?,
+                "sandbox" => options.sandbox = Some(value.as_bool().ok_or_else(|| invalid(key, "布尔值"))?),
+                "memory_limit_mb" => options.memory_limit_mb = Some(value.as_u64().ok_or_else(|| invalid(key, "非负整数"))? as usize),
+                "execution_timeout_ms" => options.execution_timeout_ms = Some(value.as_u64().ok_or_else(|| invalid(key, "非负整数"))?),
+                "instruction_limit" => options.instruction_limit = Some(value.as_u64().ok_or_else(|| invalid(key, "非负整数"))?),
+                "env_vars" => {
+                    let vars = value.as_object().ok_or_else(|| invalid(key, "对象"))?;
+                    for (name, value) in vars {
+                        let value = value.as_str().ok_or_else(|| invalid(&format!("env_vars.{}", name), "字符串"))?;
+                        options.env_vars.insert(name.clone(), value.to_string());
+                    }
+                },
+                _ => return Err(InterpreterError::ModuleError(format!(
+                    "未知的模块选项 '{}'，可用选项: allow_filesystem, allow_network, sandbox, memory_limit_mb, execution_timeout_ms, instruction_limit, env_vars",
+                    key
+                ))),
+            }
+        }
+        Ok(options)
+    }
+}
+
+// 模块文件是否位于项目目录（程序文件所在目录）中
+fn is_inside_project(path: &str) -> bool {
+    let base = match super::get_registry().get_base_path() {
+        Some(base) => base,
+        None => return false,
+    };
+    match (std::fs::canonicalize(path), std::fs::canonicalize(base)) {
+        (Ok(path), Ok(base)) => path.starts_with(base),
+        _ => false,
+    }
+}
+
 /// 外部模块接口特征
 /// 
 /// 这个特征定义了外部模块的标准接口，所有外部模块都应该实现这个特征。
@@ -107,6 +257,9 @@ pub trait ExternalModule: Module {
     /// 重新加载模块（如果支持）
     fn reload(&mut self) -> Result<()>;
     
+    /// 获取模块的源文件路径，文件修改后可以重新加载
+    fn source_path(&self) -> Option<&str>;
+    
     /// 调用模块中的函数，并返回结果
     fn call_function(&self, name: &str, args: &[Value], context: &mut Context) -> Result<Value>;
     
@@ -309,6 +462,20 @@ impl ModuleRegistry {
             .collect()
     }
     
+    /// 去掉文件名中加载器支持的扩展名，多个扩展名匹配时使用最长的（如 plugin.json）
+    pub fn strip_module_extension<'a>(&self, file_name: &'a str) -> Option<&'a str> {
+        self.loaders.iter()
+            .flat_map(|loader| loader.get_supported_extensions())
+            .filter(|ext| file_name.len() > ext.len() + 1 && file_name.ends_with(ext) && file_name[..file_name.len() - ext.len()].ends_with('.'))
+            .max_by_key(|ext| ext.len())
+            .map(|ext| &file_name[..file_name.len() - ext.len() - 1])
+    }
+    
+    /// 获取基础路径（程序文件所在目录）
+    pub fn get_base_path(&self) -> Option<&str> {
+        self.base_path.as_deref()
+    }
+    
     /// 获取所有搜索路径
     pub fn get_search_paths(&self) -> &[String] {
         &self.search_paths
@@ -412,7 +579,7 @@ pub struct JLangModuleLoader;
 impl ModuleLoader for JLangModuleLoader {
     fn can_load(&self, path: &str) -> bool {
         let file_exists = std::path::Path::new(path).exists();
-        let has_jl_ext = path.ends_with(".jl") || path.ends_with(".jil");
+        let has_jl_ext = crate::source_format::is_source_file(path);
         
         if crate::is_debug_mode() {
             println!("检查模块文件: {} (存在: {}, 扩展名正确: {})", 
@@ -434,46 +601,27 @@ impl ModuleLoader for JLangModuleLoader {
             ));
         }
         
-        // 这里调用现有的JlModule创建逻辑，但将其包装为ExternalModule
-        let jl_module = super::jl_module::JlModule::new(name, path)?;
-        
-        // 读取文件内容以提取module_meta
-        let content = std::fs::read_to_string(path)
-            .map_err(|e| InterpreterError::ModuleError(format!("无法读取文件 '{}': {}", path, e)))?;
-        
-        // 解析JSON
-        let program: Value = serde_json::from_str(&content)
-            .map_err(|e| InterpreterError::ModuleError(format!("无效的JSON格式: {}", e)))?;
-        
-        // 提取module_meta
-        let module_meta = program.get("module_meta").cloned();
+        let module = JLangExternalModule::open(name, path, options.unwrap_or_default())?;
         
         if crate::is_debug_mode() {
             println!("成功加载模块: {}", name);
             // 输出模块中的函数
             println!("模块 {} 中的函数:", name);
-            for (fname, _) in jl_module.get_functions() {
+            for fname in module.metadata.functions.keys() {
                 println!("  - {}", fname);
             }
             
-            if let Some(meta) = &module_meta {
+            if let Some(meta) = &module.module_meta {
                 println!("模块定义了自定义元数据:");
                 println!("{}", serde_json::to_string_pretty(&meta).unwrap_or_else(|_| "无法格式化元数据".to_string()));
             }
         }
         
-        let metadata = create_metadata_for_jl_module(name, path)?;
-        
-        Ok(Box::new(JLangExternalModule {
-            internal_module: jl_module,
-            metadata,
-            module_meta,
-            options: options.unwrap_or_default(),
-        }))
+        Ok(Box::new(module))
     }
     
     fn get_supported_extensions(&self) -> Vec<&'static str> {
-        vec!["jl", "jil"]
+        vec!["jl", "jil", "jl.yaml", "jl.yml", "jl.toml"]
     }
     
     fn get_loader_name(&self) -> &'static str {
@@ -481,16 +629,8 @@ impl ModuleLoader for JLangModuleLoader {
     }
 }
 
-/// 从JL模块创建元数据
-fn create_metadata_for_jl_module(name: &str, path: &str) -> Result<ModuleMetadata> {
-    // 读取文件内容
-    let content = std::fs::read_to_string(path)
-        .map_err(|e| InterpreterError::ModuleError(format!("无法读取文件 '{}': {}", path, e)))?;
-    
-    // 解析JSON
-    let program: Value = serde_json::from_str(&content)
-        .map_err(|e| InterpreterError::ModuleError(format!("无效的JSON格式: {}", e)))?;
-    
+/// 从JL模块程序创建元数据
+fn create_metadata_for_jl_module(name: &str, program: &Value) -> ModuleMetadata {
     // 提取元数据
     let mut metadata = ModuleMetadata {
         name: name.to_string(),
@@ -585,7 +725,7 @@ fn create_metadata_for_jl_module(name: &str, path: &str) -> Result<ModuleMetadat
         }
     }
     
-    Ok(metadata)
+    metadata
 }
 
 /// JiLang模块的ExternalModule实现
@@ -610,6 +750,31 @@ impl Module for JLangExternalModule {
     }
 }
 
+impl JLangExternalModule {
+    // 加载模块文件，文件只解析一次，解析结果在同一文件的模块之间共用
+    fn open(name: &str, path: &str, options: ExternalModuleOptions) -> Result<Self> {
+        let program = super::module_cache::load(path, || super::jl_module::JlModule::parse_file(path))?;
+        let metadata = create_metadata_for_jl_module(name, &program);
+        let module_meta = program.get("module_meta").cloned();
+        Ok(Self {
+            internal_module: super::jl_module::JlModule::new(name, path, program),
+            metadata,
+            module_meta,
+            options,
+        })
+    }
+    
+    // 获取模块文件路径
+    pub fn get_path(&self) -> &str {
+        self.internal_module.get_path()
+    }
+    
+    // 获取解析后的模块程序，加载程序时用来处理模块自己的include
+    pub fn get_program(&self) -> &Value {
+        self.internal_module.get_program()
+    }
+}
+
 impl ExternalModule for JLangExternalModule {
     fn get_module_type(&self) -> ExternalModuleType {
         ExternalModuleType::JLang
@@ -629,10 +794,13 @@ impl ExternalModule for JLangExternalModule {
     }
     
     fn reload(&mut self) -> Result<()> {
-        // JiLang模块目前不支持重新加载
-        Err(InterpreterError::ModuleError(
-            "JiLang模块不支持重新加载".to_string()
-        ))
+        // 文件修改后缓存失效，open会重新解析
+        *self = Self::open(&self.metadata.name, self.get_path(), self.options.clone())?;
+        Ok(())
+    }
+    
+    fn source_path(&self) -> Option<&str> {
+        Some(self.get_path())
     }
     
     // 获取模块元数据值
@@ -667,8 +835,12 @@ impl ExternalModule for JLangExternalModule {
             
             let params = Value::Object(params_map);
             // 调用函数
-            let result = crate::interpreter::statements::execute_function(func_def, context, Some(&params))?;
-            Ok(result)
+            let frame_name = format!("{}.{}", self.internal_module.get_name(), name);
+            let frame_path = format!("/program/{}", crate::interpreter::source_map::escape_segment(name));
+            context.enter_function(&frame_name, Some(self.get_path().to_string()), frame_path);
+            let result = crate::interpreter::statements::execute_function(func_def, context, Some(&params));
+            context.leave_function();
+            result
         } else {
             Err(InterpreterError::FunctionError(
                 format!("函数 '{}' 不存在", name)
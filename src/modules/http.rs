@@ -3,16 +3,32 @@ use reqwest::{blocking, header};
 use std::collections::HashMap;
 use std::time::Duration;
 use crate::interpreter::context::Context;
-use super::Module;
+use crate::interpreter::profiler;
+use super::http_cassette;
+use jilang_macros::{jilang_module, jilang_fn};
 
 pub struct HttpModule;
 
+/// HTTP请求模块
+#[jilang_module]
 impl HttpModule {
     pub fn new() -> Self {
         HttpModule
     }
 
-    // GET 请求
+    /// 发送GET请求
+    ///
+    /// # 参数
+    /// - url (String): 请求地址
+    /// - headers (Object, 可选): 请求头
+    /// - timeout (Number, 可选): 超时时间，单位为秒
+    ///
+    /// # 返回
+    /// {"status", "headers", "body", "raw"} 形式的响应对象，请求失败时为 {"error": 消息}
+    ///
+    /// # 示例
+    /// {"http.get": ["https://api.example.com/users", {"Accept": "application/json"}], "output": "response"}
+    #[jilang_fn]
     fn get(args: &[Value], context: &mut Context) -> Value {
         if args.is_empty() {
             return json!({
@@ -56,7 +72,20 @@ impl HttpModule {
         Self::execute_request("GET", &url, None, headers_map, timeout)
     }
 
-    // POST 请求
+    /// 发送POST请求，请求体是对象或数组时按JSON发送
+    ///
+    /// # 参数
+    /// - url (String): 请求地址
+    /// - body (Any): 请求体
+    /// - headers (Object, 可选): 请求头
+    /// - timeout (Number, 可选): 超时时间，单位为秒
+    ///
+    /// # 返回
+    /// {"status", "headers", "body", "raw"} 形式的响应对象，请求失败时为 {"error": 消息}
+    ///
+    /// # 示例
+    /// {"http.post": ["https://api.example.com/users", {"name": "张三"}], "output": "response"}
+    #[jilang_fn]
     fn post(args: &[Value], context: &mut Context) -> Value {
         if args.len() < 2 {
             return json!({
@@ -103,7 +132,20 @@ impl HttpModule {
         Self::execute_request("POST", &url, Some(body), headers_map, timeout)
     }
 
-    // PUT 请求
+    /// 发送PUT请求，请求体是对象或数组时按JSON发送
+    ///
+    /// # 参数
+    /// - url (String): 请求地址
+    /// - body (Any): 请求体
+    /// - headers (Object, 可选): 请求头
+    /// - timeout (Number, 可选): 超时时间，单位为秒
+    ///
+    /// # 返回
+    /// {"status", "headers", "body", "raw"} 形式的响应对象，请求失败时为 {"error": 消息}
+    ///
+    /// # 示例
+    /// {"http.put": ["https://api.example.com/users/1", {"name": "李四"}], "output": "response"}
+    #[jilang_fn]
     fn put(args: &[Value], context: &mut Context) -> Value {
         if args.len() < 2 {
             return json!({
@@ -150,7 +192,19 @@ impl HttpModule {
         Self::execute_request("PUT", &url, Some(body), headers_map, timeout)
     }
 
-    // DELETE 请求
+    /// 发送DELETE请求
+    ///
+    /// # 参数
+    /// - url (String): 请求地址
+    /// - headers (Object, 可选): 请求头
+    /// - timeout (Number, 可选): 超时时间，单位为秒
+    ///
+    /// # 返回
+    /// {"status", "headers", "body", "raw"} 形式的响应对象，请求失败时为 {"error": 消息}
+    ///
+    /// # 示例
+    /// {"http.delete": ["https://api.example.com/users/1"], "output": "response"}
+    #[jilang_fn]
     fn delete(args: &[Value], context: &mut Context) -> Value {
         if args.is_empty() {
             return json!({
@@ -263,6 +317,23 @@ impl HttpModule {
     
     // 通用请求执行函数
     fn execute_request(method: &str, url: &str, body: Option<Value>, headers: HashMap<String, String>, timeout: Option<f64>) -> Value {
+        // 性能分析按方法和不含查询参数的URL统计
+        let _span = profiler::span(profiler::SpanKind::Http, &format!("{} {}", method, url.split('?').next().unwrap_or(url)));
+        
+        // 回放模式下直接返回录制的响应
+        if let Some(response) = http_cassette::replay(method, url, &headers, body.as_ref()) {
+            return response;
+        }
+        
+        let recorded_body = body.clone();
+        let recorded_headers = headers.clone();
+        let response = Self::send_request(method, url, body, headers, timeout);
+        http_cassette::record(method, url, &recorded_headers, recorded_body.as_ref(), &response);
+        response
+    }
+    
+    // 发送请求并把响应转换为JSON结果
+    fn send_request(method: &str, url: &str, body: Option<Value>, headers: HashMap<String, String>, timeout: Option<f64>) -> Value {
         // 创建客户端
         let client_builder = blocking::Client::builder();
         
@@ -367,7 +438,17 @@ impl HttpModule {
         }
     }
     
-    // URL解码
+    /// URL解码
+    ///
+    /// # 参数
+    /// - text (String): 要解码的字符串
+    ///
+    /// # 返回
+    /// 解码后的字符串，失败时为 {"error": 消息}
+    ///
+    /// # 示例
+    /// {"http.url_decode": ["%E4%BD%A0%E5%A5%BD"], "output": "text"}
+    #[jilang_fn]
     fn url_decode(args: &[Value], context: &mut Context) -> Value {
         if args.is_empty() {
             return Value::String("".to_string());
@@ -402,7 +483,17 @@ impl HttpModule {
         }
     }
     
-    // URL编码
+    /// URL编码
+    ///
+    /// # 参数
+    /// - text (String): 要编码的字符串
+    ///
+    /// # 返回
+    /// 编码后的字符串
+    ///
+    /// # 示例
+    /// {"http.url_encode": ["a b&c"], "output": "encoded"}
+    #[jilang_fn]
     fn url_encode(args: &[Value], context: &mut Context) -> Value {
         if args.is_empty() {
             return Value::String("".to_string());
@@ -433,24 +524,3 @@ impl HttpModule {
         Value::String(encoded.to_string())
     }
 }
-
-impl Module for HttpModule {
-    fn get_name(&self) -> &'static str {
-        "http"
-    }
-    
-    fn get_functions(&self) -> Vec<(&'static str, Box<dyn Fn(&[Value], &mut Context) -> Value + Send + Sync + 'static>)> {
-        vec![
-            ("get", Box::new(Self::get)),
-            ("post", Box::new(Self::post)),
-            ("put", Box::new(Self::put)),
-            ("delete", Box::new(Self::delete)),
-            ("url_encode", Box::new(Self::url_encode)),
-            ("url_decode", Box::new(Self::url_decode)),
-        ]
-    }
-    
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-} 
\ No newline at end of file
@@ -0,0 +1,214 @@
+// HTTP请求的录制和回放
+//
+// 录制模式下，每次请求和响应保存为目录中的一个JSON文件（按请求顺序编号）；
+// 回放模式下从这些文件中查找匹配的请求直接返回响应，不访问网络。
+// 匹配规则可以配置：method、url、path（不含查询参数的URL）、body 和 header:名称。
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde_json::{json, Value};
+
+// 默认按方法和完整URL匹配
+const DEFAULT_MATCH_RULES: &str = "method,url";
+
+// 录制时隐藏这些请求头的值，除非它们参与匹配
+const SENSITIVE_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie", "x-api-key"];
+
+#[derive(Clone, Debug, PartialEq)]
+enum MatchRule {
+    Method,
+    Url,
+    Path,
+    Body,
+    Header(String),
+}
+
+/// 解析匹配规则，例如 `method,path,header:Authorization`
+fn parse_rules(text: &str) -> Result<Vec<MatchRule>, String> {
+    text.split(',')
+        .map(|rule| rule.trim())
+        .filter(|rule| !rule.is_empty())
+        .map(|rule| match rule.to_lowercase().as_str() {
+            "method" => Ok(MatchRule::Method),
+            "url" => Ok(MatchRule::Url),
+            "path" => Ok(MatchRule::Path),
+            "body" => Ok(MatchRule::Body),
+            lower => match lower.strip_prefix("header:") {
+                Some(name) if !name.is_empty() => Ok(MatchRule::Header(name.to_string())),
+                _ => Err(format!("未知的匹配规则 '{}'，可用规则: method, url, path, body, header:名称", rule)),
+            },
+        })
+        .collect()
+}
+
+// 一次录制的请求
+struct Interaction {
+    file: PathBuf,
+    request: Value,
+    response: Value,
+    used: bool,
+}
+
+enum Mode {
+    Record { dir: PathBuf, next: usize },
+    Replay { interactions: Vec<Interaction> },
+}
+
+struct Cassette {
+    mode: Mode,
+    rules: Vec<MatchRule>,
+    unmatched: usize,
+}
+
+static CASSETTE: Mutex<Option<Cassette>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Cassette>> {
+    CASSETTE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// 录制文件名形如 0001-GET-api.example.com_users.json
+fn is_cassette_file(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    name.ends_with(".json") && name.len() > 5 && name[..4].chars().all(|c| c.is_ascii_digit()) && name[4..].starts_with('-')
+}
+
+fn cassette_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("无法读取录制目录 '{}': {}", dir.display(), e))?;
+    let mut files: Vec<PathBuf> = entries.filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| is_cassette_file(path))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// 启用录制：清除目录中以前的录制文件，之后的每次请求都保存到该目录
+pub fn start_recording(dir: &str, rules: Option<&str>) -> Result<(), String> {
+    let rules = parse_rules(rules.unwrap_or(DEFAULT_MATCH_RULES))?;
+    let dir = PathBuf::from(dir);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("无法创建录制目录 '{}': {}", dir.display(), e))?;
+    for file in cassette_files(&dir)? {
+        std::fs::remove_file(&file).map_err(|e| format!("无法删除旧的录制文件 '{}': {}", file.display(), e))?;
+    }
+    *lock() = Some(Cassette { mode: Mode::Record { dir, next: 1 }, rules, unmatched: 0 });
+    Ok(())
+}
+
+/// 启用回放：加载目录中的所有录制文件，之后的请求都不访问网络
+pub fn start_replay(dir: &str, rules: Option<&str>) -> Result<(), String> {
+    let rules = parse_rules(rules.unwrap_or(DEFAULT_MATCH_RULES))?;
+    let mut interactions = Vec::new();
+    for file in cassette_files(Path::new(dir))? {
+        let text = std::fs::read_to_string(&file).map_err(|e| format!("无法读取录制文件 '{}': {}", file.display(), e))?;
+        let value: Value = serde_json::from_str(&text).map_err(|e| format!("录制文件 '{}' 格式错误: {}", file.display(), e))?;
+        match (value.get("request"), value.get("response")) {
+            (Some(request), Some(response)) => interactions.push(Interaction {
+                file,
+                request: request.clone(),
+                response: response.clone(),
+                used: false,
+            }),
+            _ => return Err(format!("录制文件 '{}' 缺少 request 或 response", file.display())),
+        }
+    }
+    *lock() = Some(Cassette { mode: Mode::Replay { interactions }, rules, unmatched: 0 });
+    Ok(())
+}
+
+/// 回放模式下未能匹配的请求数
+pub fn unmatched_count() -> usize {
+    lock().as_ref().map(|c| c.unmatched).unwrap_or(0)
+}
+
+fn request_value(method: &str, url: &str, headers: &HashMap<String, String>, body: Option<&Value>, rules: &[MatchRule]) -> Value {
+    let mut header_map = serde_json::Map::new();
+    for (name, value) in headers {
+        let lower = name.to_lowercase();
+        let matched = rules.iter().any(|rule| matches!(rule, MatchRule::Header(h) if *h == lower));
+        let value = if SENSITIVE_HEADERS.contains(&lower.as_str()) && !matched {
+            "<已隐藏>".to_string()
+        } else {
+            value.clone()
+        };
+        header_map.insert(lower, Value::String(value));
+    }
+    json!({
+        "method": method,
+        "url": url,
+        "headers": header_map,
+        "body": body.cloned().unwrap_or(Value::Null),
+    })
+}
+
+fn without_query(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+fn rule_matches(rule: &MatchRule, recorded: &Value, request: &Value) -> bool {
+    match rule {
+        MatchRule::Method => recorded["method"].as_str().map(|m| m.to_uppercase()) == request["method"].as_str().map(|m| m.to_uppercase()),
+        MatchRule::Url => recorded["url"] == request["url"],
+        MatchRule::Path => recorded["url"].as_str().map(without_query) == request["url"].as_str().map(without_query),
+        MatchRule::Body => recorded["body"] == request["body"],
+        MatchRule::Header(name) => recorded["headers"].get(name) == request["headers"].get(name),
+    }
+}
+
+/// 回放模式下返回录制的响应；未启用回放时返回None
+pub fn replay(method: &str, url: &str, headers: &HashMap<String, String>, body: Option<&Value>) -> Option<Value> {
+    let mut guard = lock();
+    let cassette = guard.as_mut()?;
+    let interactions = match &mut cassette.mode {
+        Mode::Replay { interactions } => interactions,
+        Mode::Record { .. } => return None,
+    };
+    let request = request_value(method, url, headers, body, &cassette.rules);
+    let matches = |interaction: &Interaction| cassette.rules.iter().all(|rule| rule_matches(rule, &interaction.request, &request));
+
+    // 按录制顺序使用未用过的匹配项；都用过时重复使用最后一个（例如轮询同一地址）
+    let index = interactions.iter().position(|i| !i.used && matches(i))
+        .or_else(|| interactions.iter().rposition(matches));
+    match index {
+        Some(index) => {
+            let interaction = &mut interactions[index];
+            interaction.used = true;
+            if crate::is_debug_mode() {
+                println!("HTTP回放: {} {} <- {}", method, url, interaction.file.display());
+            }
+            Some(interaction.response.clone())
+        },
+        None => {
+            cassette.unmatched += 1;
+            let message = format!("HTTP回放中没有匹配的请求: {} {}", method, url);
+            eprintln!("错误: {}", message);
+            Some(json!({ "error": message }))
+        },
+    }
+}
+
+/// 录制模式下保存请求和响应
+pub fn record(method: &str, url: &str, headers: &HashMap<String, String>, body: Option<&Value>, response: &Value) {
+    let mut guard = lock();
+    let cassette = match guard.as_mut() {
+        Some(cassette) => cassette,
+        None => return,
+    };
+    let request = request_value(method, url, headers, body, &cassette.rules);
+    let (dir, next) = match &mut cassette.mode {
+        Mode::Record { dir, next } => (dir, next),
+        Mode::Replay { .. } => return,
+    };
+
+    let target: String = without_query(url.split("://").nth(1).unwrap_or(url)).chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .take(60)
+        .collect();
+    let file = dir.join(format!("{:04}-{}-{}.json", next, method, target.trim_end_matches('_')));
+    *next += 1;
+
+    let content = json!({ "request": request, "response": response });
+    let text = serde_json::to_string_pretty(&content).unwrap_or_default();
+    if let Err(e) = std::fs::write(&file, text) {
+        eprintln!("错误: 无法写入录制文件 '{}': {}", file.display(), e);
+    }
+}
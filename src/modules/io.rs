@@ -3,25 +3,51 @@ use std::fs::OpenOptions;
 use std::path::Path;
 use std::io::Write;
 use serde_json::{Value, json};
+use std::time::{SystemTime, UNIX_EPOCH};
 use crate::interpreter::context::Context;
-use super::Module;
+use crate::interpreter::trace::{self, TraceKind};
+use jilang_macros::{jilang_module, jilang_fn};
 
 pub struct IoModule;
 
+/// 输入/输出操作模块：控制台输入输出、文件和JSON读写
+#[jilang_module]
 impl IoModule {
     pub fn new() -> Self {
         IoModule
     }
 
-    fn echo(args: &[Value], _context: &mut Context) -> Value {
+    /// 输出参数拼接成的文本，不自动换行
+    ///
+    /// # 参数
+    /// - values (Any, 可选): 要输出的值，可以传入多个
+    ///
+    /// # 返回
+    /// 输出的文本
+    ///
+    /// # 示例
+    /// {"io.echo": ["你好，", "@var.name", "\n"]}
+    #[jilang_fn]
+    fn echo(args: &[Value], context: &mut Context) -> Value {
         let mut result = String::new();
         for arg in args {
             result.push_str(&arg.to_string());
         }
-        print!("{}", result);
+        context.write_output(&result);
         Value::String(result)
     }
 
+    /// 读取文本文件的全部内容
+    ///
+    /// # 参数
+    /// - path (String): 文件路径
+    ///
+    /// # 返回
+    /// 文件内容，失败时为以 "Error:" 开头的字符串
+    ///
+    /// # 示例
+    /// {"io.read_file": ["data.txt"], "output": "content"}
+    #[jilang_fn]
     fn read_file(args: &[Value], context: &mut Context) -> Value {
         if let Some(path) = args.get(0) {
             let resolved_path = context.resolve_value(path);
@@ -34,6 +60,18 @@ impl IoModule {
         }
     }
 
+    /// 把内容写入文件，文件已存在时覆盖
+    ///
+    /// # 参数
+    /// - path (String): 文件路径
+    /// - content (Any): 要写入的内容
+    ///
+    /// # 返回
+    /// 结果信息，失败时以 "Error:" 开头
+    ///
+    /// # 示例
+    /// {"io.write_file": ["out.txt", "@var.report"]}
+    #[jilang_fn]
     fn write_file(args: &[Value], context: &mut Context) -> Value {
         if let (Some(path), Some(content)) = (
             args.get(0),
@@ -50,7 +88,7 @@ impl IoModule {
         }
     }
 
-    fn input(args: &[Value], _context: &mut Context) -> Value {
+    fn read_input(args: &[Value], _context: &mut Context) -> Value {
         let prompt = args.get(0)
             .and_then(|v| v.as_str())
             .unwrap_or("");
@@ -73,7 +111,18 @@ impl IoModule {
         }
     }
     
-    // 新增: 向文件追加内容
+    /// 向文件末尾追加内容，文件不存在时创建
+    ///
+    /// # 参数
+    /// - path (String): 文件路径
+    /// - content (Any): 要追加的内容
+    ///
+    /// # 返回
+    /// 结果信息，失败时以 "Error:" 开头
+    ///
+    /// # 示例
+    /// {"io.append_file": ["log.txt", "完成\n"]}
+    #[jilang_fn]
     fn append_file(args: &[Value], context: &mut Context) -> Value {
         if let (Some(path), Some(content)) = (
             args.get(0),
@@ -101,19 +150,32 @@ impl IoModule {
         }
     }
     
-    // 新增: 检查文件是否存在
-    fn file_exists(args: &[Value], context: &mut Context) -> Value {
-        if let Some(path) = args.get(0) {
-            let resolved_path = context.resolve_value(path);
-            let path_str = resolved_path.to_string();
-            let exists = Path::new(&path_str).exists();
-            Value::Bool(exists)
-        } else {
-            Value::String("Error: No file path provided for file_exists".to_string())
-        }
+    /// 检查文件或目录是否存在
+    ///
+    /// # 参数
+    /// - path: 要检查的路径
+    ///
+    /// # 返回
+    /// 存在时为true
+    ///
+    /// # 示例
+    /// {"io.file_exists": ["config.json"], "output": "exists"}
+    #[jilang_fn]
+    fn file_exists(path: String) -> bool {
+        Path::new(&path).exists()
     }
     
-    // 新增: 删除文件
+    /// 删除文件
+    ///
+    /// # 参数
+    /// - path (String): 文件路径
+    ///
+    /// # 返回
+    /// 结果信息，文件不存在或删除失败时以 "Error:" 开头
+    ///
+    /// # 示例
+    /// {"io.delete_file": ["temp.txt"]}
+    #[jilang_fn]
     fn delete_file(args: &[Value], context: &mut Context) -> Value {
         if let Some(path) = args.get(0) {
             let resolved_path = context.resolve_value(path);
@@ -132,7 +194,17 @@ impl IoModule {
         }
     }
     
-    // 新增: 列出目录内容
+    /// 列出目录内容，路径为空字符串时列出当前目录
+    ///
+    /// # 参数
+    /// - path (String): 目录路径
+    ///
+    /// # 返回
+    /// {"files": [...], "directories": [...]}，失败时为以 "Error:" 开头的字符串
+    ///
+    /// # 示例
+    /// {"io.list_dir": ["."], "output": "entries"}
+    #[jilang_fn]
     fn list_dir(args: &[Value], context: &mut Context) -> Value {
         if let Some(path) = args.get(0) {
             let resolved_path = context.resolve_value(path);
@@ -173,8 +245,103 @@ impl IoModule {
         }
     }
     
+    // 读取用户输入，录制和回放时经过trace，回放时显示提示和记录的输入
+    fn traced_input(args: &[Value], context: &mut Context, read: fn(&[Value], &mut Context) -> Value) -> Value {
+        let prompt = args.first().and_then(|v| v.as_str()).unwrap_or("").to_string();
+        let value = trace::capture(TraceKind::Input, &prompt, || read(args, context));
+        if trace::is_replaying() {
+            let shown = match &value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            context.write_output(&format!("{}{}\n", prompt, shown));
+        }
+        value
+    }
+    
+    /// 显示提示并读取一行输入，输入是数字时转换为数字
+    ///
+    /// # 参数
+    /// - prompt (String, 可选): 提示文本
+    ///
+    /// # 返回
+    /// 输入的文本或数字
+    ///
+    /// # 示例
+    /// {"io.input": ["请输入姓名: "], "output": "name"}
+    #[jilang_fn]
+    fn input(args: &[Value], context: &mut Context) -> Value {
+        Self::traced_input(args, context, Self::read_input)
+    }
+    
+    /// 读取数字输入，输入无效或超出范围时要求重新输入
+    ///
+    /// # 参数
+    /// - prompt (String, 可选): 提示文本，默认为 "请输入一个数字: "
+    /// - min (Number, 可选): 最小值
+    /// - max (Number, 可选): 最大值
+    ///
+    /// # 返回
+    /// 输入的数字
+    ///
+    /// # 示例
+    /// {"io.input_number": ["请输入年龄: ", 0, 150], "output": "age"}
+    #[jilang_fn]
+    fn input_number(args: &[Value], context: &mut Context) -> Value {
+        Self::traced_input(args, context, Self::read_input_number)
+    }
+    
+    /// 读取输入，直接回车时使用默认值
+    ///
+    /// # 参数
+    /// - prompt (String, 可选): 提示文本，默认值显示在提示后的方括号中
+    /// - default (Any, 可选): 默认值，默认为空字符串
+    ///
+    /// # 返回
+    /// 输入的文本或数字，没有输入时为默认值
+    ///
+    /// # 示例
+    /// {"io.input_with_default": ["端口", 8080], "output": "port"}
+    #[jilang_fn]
+    fn input_with_default(args: &[Value], context: &mut Context) -> Value {
+        Self::traced_input(args, context, Self::read_input_with_default)
+    }
+    
+    /// 请求用户确认，接受 y/yes/是 和 n/no/否
+    ///
+    /// # 参数
+    /// - prompt (String, 可选): 提示文本，默认为 "确认? (y/n): "
+    /// - default (Bool, 可选): 直接回车时的结果，默认为false
+    ///
+    /// # 返回
+    /// 确认时为true
+    ///
+    /// # 示例
+    /// {"io.confirm": ["确定要删除吗? (y/n): "], "output": "ok"}
+    #[jilang_fn]
+    fn confirm(args: &[Value], context: &mut Context) -> Value {
+        Self::traced_input(args, context, Self::read_confirm)
+    }
+    
+    /// 当前时间，录制和回放时经过trace
+    ///
+    /// # 返回
+    /// Unix时间戳，单位为毫秒
+    ///
+    /// # 示例
+    /// {"io.now": [], "output": "start"}
+    #[jilang_fn]
+    fn now() -> Value {
+        trace::capture(TraceKind::Time, "", || {
+            let millis = SystemTime::now().duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            json!(millis)
+        })
+    }
+    
     // 新增: 专门用于获取数字输入，带验证
-    fn input_number(args: &[Value], _context: &mut Context) -> Value {
+    fn read_input_number(args: &[Value], _context: &mut Context) -> Value {
         let prompt = args.get(0)
             .and_then(|v| v.as_str())
             .unwrap_or("请输入一个数字: ");
@@ -213,7 +380,7 @@ impl IoModule {
     }
     
     // 新增: 带默认值的输入
-    fn input_with_default(args: &[Value], _context: &mut Context) -> Value {
+    fn read_input_with_default(args: &[Value], _context: &mut Context) -> Value {
         let prompt = args.get(0)
             .and_then(|v| v.as_str())
             .unwrap_or("");
@@ -251,7 +418,7 @@ impl IoModule {
     }
     
     // 新增: 获取用户确认(y/n)
-    fn confirm(args: &[Value], _context: &mut Context) -> Value {
+    fn read_confirm(args: &[Value], _context: &mut Context) -> Value {
         let prompt = args.get(0)
             .and_then(|v| v.as_str())
             .unwrap_or("确认? (y/n): ");
@@ -285,7 +452,17 @@ impl IoModule {
         }
     }
     
-    // 新增: 读取JSON文件并解析
+    /// 读取并解析JSON文件
+    ///
+    /// # 参数
+    /// - path (String): 文件路径
+    ///
+    /// # 返回
+    /// 解析出的JSON值，失败时为以 "Error" 开头的字符串
+    ///
+    /// # 示例
+    /// {"io.read_json": ["config.json"], "output": "config"}
+    #[jilang_fn]
     fn read_json(args: &[Value], context: &mut Context) -> Value {
         if let Some(path) = args.get(0) {
             let resolved_path = context.resolve_value(path);
@@ -303,7 +480,19 @@ impl IoModule {
         }
     }
     
-    // 新增: 将对象写入为JSON文件
+    /// 把值写入JSON文件
+    ///
+    /// # 参数
+    /// - path (String): 文件路径
+    /// - data (Any): 要写入的值
+    /// - pretty (Bool, 可选): 是否格式化输出，默认为false
+    ///
+    /// # 返回
+    /// 结果信息，失败时以 "Error" 开头
+    ///
+    /// # 示例
+    /// {"io.write_json": ["config.json", "@var.config", true]}
+    #[jilang_fn]
     fn write_json(args: &[Value], context: &mut Context) -> Value {
         if let (Some(path), Some(data)) = (
             args.get(0),
@@ -338,6 +527,18 @@ impl IoModule {
         }
     }
 
+    /// 按点分隔的路径读取变量中的嵌套属性，数组用数字下标
+    ///
+    /// # 参数
+    /// - object (String): 引用对象或数组的变量，例如 "@var.user"
+    /// - path (String): 属性路径，例如 "profile.name" 或 "items.0"
+    ///
+    /// # 返回
+    /// 属性值，路径不存在时为null
+    ///
+    /// # 示例
+    /// {"io.json_get": ["@var.user", "profile.name"], "output": "name"}
+    #[jilang_fn]
     fn json_get(args: &[Value], context: &mut Context) -> Value {
         if args.len() < 2 {
             return Value::String("Error: json_get需要至少两个参数: JSON对象和属性路径".to_string());
@@ -402,6 +603,19 @@ impl IoModule {
         current
     }
     
+    /// 按点分隔的路径设置变量中的嵌套属性，中间不存在的属性会被创建
+    ///
+    /// # 参数
+    /// - object (String): 引用对象或数组的变量，例如 "@var.user"
+    /// - path (String): 属性路径
+    /// - value (Any): 新值
+    ///
+    /// # 返回
+    /// 修改后的对象，变量也会被更新
+    ///
+    /// # 示例
+    /// {"io.json_set": ["@var.user", "profile.age", 30]}
+    #[jilang_fn]
     fn json_set(args: &[Value], context: &mut Context) -> Value {
         if args.len() < 3 {
             return Value::String("Error: json_set需要三个参数: JSON对象, 属性路径和新值".to_string());
@@ -545,33 +759,3 @@ impl IoModule {
         Ok(())
     }
 }
-
-impl Module for IoModule {
-    fn get_name(&self) -> &'static str {
-        "io"
-    }
-    
-    fn get_functions(&self) -> Vec<(&'static str, Box<dyn Fn(&[Value], &mut Context) -> Value + Send + Sync + 'static>)> {
-        vec![
-            ("echo", Box::new(Self::echo)),
-            ("input", Box::new(Self::input)),
-            ("input_with_default", Box::new(Self::input_with_default)),
-            ("input_number", Box::new(Self::input_number)),
-            ("confirm", Box::new(Self::confirm)),
-            ("read_file", Box::new(Self::read_file)),
-            ("write_file", Box::new(Self::write_file)),
-            ("append_file", Box::new(Self::append_file)),
-            ("file_exists", Box::new(Self::file_exists)),
-            ("delete_file", Box::new(Self::delete_file)),
-            ("list_dir", Box::new(Self::list_dir)),
-            ("read_json", Box::new(Self::read_json)),
-            ("write_json", Box::new(Self::write_json)),
-            ("json_get", Box::new(Self::json_get)),
-            ("json_set", Box::new(Self::json_set)),
-        ]
-    }
-    
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-} 
\ No newline at end of file
@@ -1,4 +1,4 @@
-use std::fs;
+use std::sync::Arc;
 use serde_json::Value;
 use crate::interpreter::context::Context;
 use crate::interpreter::error::{InterpreterError, Result};
@@ -6,41 +6,38 @@ use super::Module;
 
 pub struct JlModule {
     name: String,
-    functions: Vec<(String, Value)>,
+    path: String,
+    // 解析后的模块程序，同一文件的模块共用（见module_cache）
+    program: Arc<Value>,
 }
 
 impl JlModule {
-    pub fn new(name: &str, file_path: &str) -> Result<Self> {
-        let content = fs::read_to_string(file_path)
-            .map_err(|e| InterpreterError::ModuleError(format!("无法读取文件 '{}': {}", file_path, e)))?;
-        
-        let program: Value = serde_json::from_str(&content)
-            .map_err(|e| InterpreterError::ModuleError(format!("无效的 JSON 格式: {}", e)))?;
-
-        let mut functions = Vec::new();
-        if let Some(program_obj) = program.get("program") {
-            if let Some(obj) = program_obj.as_object() {
-                for (func_name, func_def) in obj {
-                    if crate::is_debug_mode() {
-                        println!("在模块 '{}' 中找到函数: {}", name, func_name);
-                    }
-                    functions.push((func_name.clone(), func_def.clone()));
+    pub fn new(name: &str, file_path: &str, program: Arc<Value>) -> Self {
+        match program.get("program").map(|p| p.as_object()) {
+            Some(Some(obj)) if crate::is_debug_mode() => {
+                for func_name in obj.keys() {
+                    println!("在模块 '{}' 中找到函数: {}", name, func_name);
                 }
-            } else if crate::is_debug_mode() {
-                println!("警告: 模块 '{}' 中的 'program' 不是对象", name);
-            }
-        } else if crate::is_debug_mode() {
-            println!("警告: 模块 '{}' 中没有 'program' 字段", name);
+                println!("模块 '{}' 中共加载了 {} 个函数", name, obj.len());
+            },
+            Some(None) if crate::is_debug_mode() => println!("警告: 模块 '{}' 中的 'program' 不是对象", name),
+            None if crate::is_debug_mode() => println!("警告: 模块 '{}' 中没有 'program' 字段", name),
+            _ => {}
         }
 
-        if crate::is_debug_mode() {
-            println!("模块 '{}' 中共加载了 {} 个函数", name, functions.len());
+        JlModule {
+            name: name.to_string(),
+            path: file_path.to_string(),
+            program,
         }
+    }
 
-        Ok(JlModule {
-            name: name.to_string(),
-            functions,
-        })
+    /// 读取并解析模块文件
+    pub fn parse_file(file_path: &str) -> Result<Value> {
+        let content = std::fs::read_to_string(file_path)
+            .map_err(|e| InterpreterError::ModuleError(format!("无法读取文件 '{}': {}", file_path, e)))?;
+        crate::source_format::parse_file(file_path, &content)
+            .map_err(|e| InterpreterError::ModuleError(format!("无效的模块源码 ({}): {}", file_path, e)))
     }
 }
 
@@ -59,18 +56,25 @@ impl Module for JlModule {
 }
 
 impl JlModule {
+    // 获取模块文件路径
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    // 获取解析后的模块程序
+    pub fn get_program(&self) -> &Value {
+        &self.program
+    }
+
     pub fn get_function(&self, name: &str) -> Option<&Value> {
         if crate::is_debug_mode() {
             println!("尝试在模块 '{}' 中查找函数: '{}'", self.name, name);
-            println!("可用函数: {}", self.functions.iter()
-                .map(|(fname, _)| fname.clone())
-                .collect::<Vec<_>>()
-                .join(", "));
+            println!("可用函数: {}", self.program.get("program").and_then(|p| p.as_object())
+                .map(|obj| obj.keys().cloned().collect::<Vec<_>>().join(", "))
+                .unwrap_or_default());
         }
         
-        let result = self.functions.iter()
-            .find(|(func_name, _)| func_name == name)
-            .map(|(_, func_def)| func_def);
+        let result = self.program.get("program").and_then(|p| p.as_object()).and_then(|obj| obj.get(name));
             
         if crate::is_debug_mode() {
             if result.is_some() {
@@ -0,0 +1,280 @@
+// Lua模块的LuaDoc注释解析
+//
+// 只扫描源码，不执行模块：定义在模块返回的表上的函数（`function M.f(...)`、`function M:f(...)`、
+// `M.f = function(...)`）使用紧挨在定义上方的注释作为文档，支持 `---@param 名称 类型 描述`、
+// `---@return 类型 描述` 和 `---@example`（或 `---@usage`），其余注释行作为函数描述。
+// 文件开头第一段不属于任何函数的注释描述模块本身，可以包含 `@author` 和 `@version`。
+use serde_json::Value;
+use mlua::{Lua, LuaOptions, StdLib, HookTriggers, ChunkMode, Error as LuaError};
+use super::external_module::{FunctionMetadata, ParameterMetadata};
+use super::lua_module::lua_to_json;
+
+// 求值module_meta表字面量时的限制，正常的字面量远用不到这么多
+const LITERAL_MEMORY_LIMIT: usize = 1024 * 1024;
+const LITERAL_INSTRUCTION_LIMIT: u32 = 10000;
+
+/// 从Lua源码中解析出的模块文档
+#[derive(Clone, Debug, Default)]
+pub struct LuaDoc {
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub version: Option<String>,
+    /// 按源码顺序排列的函数元数据，重复定义的函数以最后一次为准
+    pub functions: Vec<FunctionMetadata>,
+    /// `module_meta` 字段的表字面量
+    pub module_meta: Option<Value>,
+}
+
+impl LuaDoc {
+    pub fn function(&self, name: &str) -> Option<&FunctionMetadata> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+}
+
+/// 解析Lua模块源码中的文档注释
+pub fn parse(content: &str) -> LuaDoc {
+    let table = returned_table(content);
+    let mut doc = LuaDoc::default();
+    let mut comments: Vec<String> = Vec::new();
+    let mut header_done = false;
+    let mut block_end: Option<String> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+
+        // 跳过 --[[ ... ]] 块注释
+        if let Some(end) = &block_end {
+            if trimmed.contains(end.as_str()) {
+                block_end = None;
+            }
+            comments.clear();
+            continue;
+        }
+        if let Some(end) = trimmed.strip_prefix("--").and_then(long_bracket_end) {
+            if !trimmed[2..].contains(end.as_str()) {
+                block_end = Some(end);
+            }
+            comments.clear();
+            continue;
+        }
+
+        if let Some(comment) = trimmed.strip_prefix("--") {
+            let comment = comment.trim_start_matches('-');
+            comments.push(comment.strip_prefix(' ').unwrap_or(comment).to_string());
+            continue;
+        }
+
+        if trimmed.is_empty() || !header_done {
+            // 文件开头第一段独立的注释描述模块
+            if !comments.is_empty() && !header_done {
+                header_done = true;
+                if trimmed.is_empty() || definition(trimmed, table.as_deref()).is_none() {
+                    apply_module_header(&mut doc, &comments);
+                    comments.clear();
+                }
+            }
+            if trimmed.is_empty() {
+                comments.clear();
+                continue;
+            }
+        }
+
+        header_done = true;
+        if let Some(name) = definition(trimmed, table.as_deref()) {
+            let function = function_metadata(name, &comments);
+            doc.functions.retain(|f| f.name != function.name);
+            doc.functions.push(function);
+        }
+        comments.clear();
+    }
+
+    doc.module_meta = module_meta_literal(content);
+    doc
+}
+
+// 模块最后 `return M` 返回的表名；返回的不是变量时为None，此时接受任何表上的函数定义
+fn returned_table(content: &str) -> Option<String> {
+    let last = content.lines().map(str::trim).rev()
+        .find(|line| !line.is_empty() && !line.starts_with("--"))?;
+    let name = last.strip_prefix("return")?.trim().trim_end_matches(';').trim();
+    is_identifier(name).then(|| name.to_string())
+}
+
+// 如果这一行定义了模块函数，返回函数名
+fn definition<'a>(line: &'a str, table: Option<&str>) -> Option<&'a str> {
+    let qualified = if let Some(rest) = line.strip_prefix("function ") {
+        rest.split('(').next()?.trim()
+    } else {
+        let (target, value) = line.split_once('=')?;
+        let value = value.trim_start().strip_prefix("function")?;
+        if !value.trim_start().starts_with('(') {
+            return None;
+        }
+        target.trim()
+    };
+    let (owner, name) = qualified.split_once(['.', ':'])?;
+    if !is_identifier(owner) || !is_identifier(name) || table.map(|t| t != owner).unwrap_or(false) {
+        return None;
+    }
+    Some(name)
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// `[[` 或 `[==[` 开头时返回对应的结束括号
+fn long_bracket_end(text: &str) -> Option<String> {
+    let rest = text.strip_prefix('[')?;
+    let level = rest.chars().take_while(|c| *c == '=').count();
+    rest[level..].starts_with('[').then(|| format!("]{}]", "=".repeat(level)))
+}
+
+// 把注释行拆成标签和内容，不是标签时返回None
+fn tag(line: &str) -> Option<(&str, &str)> {
+    Some(split_word(line.trim_start().strip_prefix('@')?))
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (text, ""),
+    }
+}
+
+fn apply_module_header(doc: &mut LuaDoc, comments: &[String]) {
+    let mut description = Vec::new();
+    for line in comments {
+        match tag(line) {
+            Some(("author", author)) => doc.author = Some(author.to_string()),
+            Some(("version", version)) | Some(("release", version)) => doc.version = Some(version.to_string()),
+            Some(("description", text)) => description.push(text),
+            Some(_) => {}
+            None => description.push(line.as_str()),
+        }
+    }
+    let description = description.join("\n").trim().to_string();
+    if !description.is_empty() {
+        doc.description = Some(description);
+    }
+}
+
+fn function_metadata(name: &str, comments: &[String]) -> FunctionMetadata {
+    let mut description = Vec::new();
+    let mut example = Vec::new();
+    let mut parameters = Vec::new();
+    let mut returns = Vec::new();
+    let mut in_example = false;
+
+    for line in comments {
+        let Some((tag, rest)) = tag(line) else {
+            if in_example {
+                example.push(line.as_str());
+            } else {
+                description.push(line.as_str());
+            }
+            continue;
+        };
+        in_example = false;
+        match tag {
+            "param" => parameters.extend(parameter(rest)),
+            "return" | "returns" => {
+                let (type_name, text) = split_word(rest);
+                returns.push(if text.is_empty() { type_name.to_string() } else { format!("{}（{}）", type_name, text) });
+            }
+            "example" | "usage" => {
+                in_example = true;
+                if !rest.is_empty() {
+                    example.push(rest);
+                }
+            }
+            "description" => description.push(rest),
+            _ => {}
+        }
+    }
+
+    let description = description.join("\n").trim().to_string();
+    FunctionMetadata {
+        name: name.to_string(),
+        description: if description.is_empty() { format!("Lua函数 {}", name) } else { description },
+        parameters,
+        return_type: if returns.is_empty() { "Any".to_string() } else { returns.join(", ") },
+        example: example.join("\n").trim_end().to_string(),
+    }
+}
+
+// `名称 类型 描述`，名称或类型以?结尾、或类型中包含nil时参数可选
+fn parameter(text: &str) -> Option<ParameterMetadata> {
+    let (name, rest) = split_word(text);
+    if name.is_empty() {
+        return None;
+    }
+    let (type_name, description) = split_word(rest);
+    let optional = name.ends_with('?') || type_name.ends_with('?') || type_name.split('|').any(|t| t == "nil");
+    Some(ParameterMetadata {
+        name: name.trim_end_matches('?').to_string(),
+        description: description.to_string(),
+        type_description: match type_name.trim_end_matches('?') {
+            "" => "any".to_string(),
+            type_name => type_name.to_string(),
+        },
+        optional,
+        default_value: None,
+    })
+}
+
+// 找到 `module_meta = { ... }` 并求值其中的表字面量。求值在没有任何标准库和全局变量的
+// 独立Lua状态中进行，并限制内存和指令数；不是纯字面量时返回None，由运行时的值补充
+fn module_meta_literal(content: &str) -> Option<Value> {
+    let start = content.match_indices("module_meta").find_map(|(index, _)| {
+        let rest = content[index + "module_meta".len()..].trim_start();
+        let rest = rest.strip_prefix('=').filter(|rest| !rest.starts_with('='))?.trim_start();
+        rest.starts_with('{').then(|| content.len() - rest.len())
+    })?;
+    let literal = balanced_braces(&content[start..])?;
+
+    let lua = Lua::new_with(StdLib::NONE, LuaOptions::default()).ok()?;
+    lua.set_memory_limit(LITERAL_MEMORY_LIMIT).ok()?;
+    let triggers = HookTriggers { every_nth_instruction: Some(LITERAL_INSTRUCTION_LIMIT), ..Default::default() };
+    lua.set_hook(triggers, |_, _| Err(LuaError::RuntimeError("module_meta 不是表字面量".to_string()))).ok()?;
+    let value = lua.load(&format!("return {}", literal))
+        .set_mode(ChunkMode::Text)
+        .set_environment(lua.create_table().ok()?).ok()?
+        .eval::<mlua::Value>().ok()?;
+    lua_to_json(value).ok()
+}
+
+// 从 `{` 开始截取到与之匹配的 `}`，跳过字符串和注释中的括号
+fn balanced_braces(text: &str) -> Option<&str> {
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[..=i]);
+                }
+            }
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
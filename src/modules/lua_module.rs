@@ -1,12 +1,25 @@
 use std::fs;
+use std::cell::{Cell, RefCell, RefMut};
 use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use serde_json::Value;
-use mlua::{Lua, prelude::LuaFunction, prelude::LuaTable, Error as LuaError};
+use rand::{Rng, RngCore};
+use mlua::{Lua, LuaOptions, StdLib, HookTriggers, RegistryKey, Scope, Variadic, prelude::LuaTable, Error as LuaError};
 use crate::interpreter::context::Context;
-use crate::interpreter::error::{InterpreterError, Result};
+use crate::interpreter::error::{InterpreterError, Result, error_messages};
+use crate::interpreter::trace::{self, TraceKind};
+use crate::interpreter::variable_reference::VariableReference;
+use crate::interpreter::profiler;
 use super::Module;
+use super::lua_doc::{self, LuaDoc};
+use super::module_cache;
 use super::external_module::{ExternalModule, ModuleLoader, ExternalModuleType, ModuleMetadata, FunctionMetadata, ExternalModuleOptions};
 
+// 每执行这么多条指令检查一次时间和指令数预算，指令数限制按这个粒度计算
+const BUDGET_CHECK_INTERVAL: u32 = 1000;
+
 /// Lua模块加载器 - 用于加载.lua文件模块
 pub struct LuaModuleLoader;
 
@@ -35,15 +48,10 @@ impl ModuleLoader for LuaModuleLoader {
             ));
         }
         
-        // 读取Lua文件内容
-        let content = fs::read_to_string(path)
-            .map_err(|e| InterpreterError::ModuleError(format!("无法读取Lua文件 '{}': {}", path, e)))?;
-        
-        // 提取函数名称列表和module_meta，不实际执行代码
-        let (functions, module_meta) = extract_module_info(&content)?;
-        
-        // 创建元数据
-        let metadata = create_metadata(name, &functions, module_meta.as_ref())?;
+        // 从LuaDoc注释中静态提取函数和模块信息，模块代码在initialize时才执行
+        let source = load_source(path)?;
+        let module_meta = source.doc.module_meta.clone();
+        let metadata = create_metadata(name, &source.doc, None, module_meta.as_ref());
         
         if crate::is_debug_mode() {
             println!("成功加载Lua模块: {}", name);
@@ -61,10 +69,11 @@ impl ModuleLoader for LuaModuleLoader {
         Ok(Box::new(LuaModule {
             name: name.to_string(),
             path: path.to_string(),
-            content,
+            source,
             metadata,
             module_meta,
             options: options.unwrap_or_default(),
+            state: RefCell::new(None),
         }))
     }
     
@@ -77,14 +86,34 @@ impl ModuleLoader for LuaModuleLoader {
     }
 }
 
-/// 创建模块元数据
-fn create_metadata(name: &str, functions: &[String], module_meta: Option<&Value>) -> Result<ModuleMetadata> {
+/// 编译检查过的Lua源码和从注释中解析的文档，同一文件的模块共用
+struct LuaSource {
+    content: String,
+    doc: LuaDoc,
+}
+
+// 读取Lua文件，只编译不执行，尽早报告语法错误；文件没有修改时使用缓存
+fn load_source(path: &str) -> Result<Arc<LuaSource>> {
+    module_cache::load(path, || {
+        let content = fs::read_to_string(path)
+            .map_err(|e| InterpreterError::ModuleError(format!("无法读取Lua文件 '{}': {}", path, e)))?;
+        Lua::new_with(StdLib::NONE, LuaOptions::default())
+            .and_then(|lua| lua.load(&content).set_name(format!("@{}", path))?.into_function().map(|_| ()))
+            .map_err(|e| InterpreterError::ModuleError(format!("Lua模块加载错误: {}", e)))?;
+        let doc = lua_doc::parse(&content);
+        Ok(LuaSource { content, doc })
+    })
+}
+
+/// 创建模块元数据：函数文档来自LuaDoc注释，`functions` 为模块表中实际存在的函数
+/// （模块尚未执行时为None，只使用注释中找到的函数），module_meta覆盖注释中的模块信息
+fn create_metadata(name: &str, doc: &LuaDoc, functions: Option<&[String]>, module_meta: Option<&Value>) -> ModuleMetadata {
     // 默认元数据
     let mut metadata = ModuleMetadata {
         name: name.to_string(),
-        version: "1.0.0".to_string(),
-        description: "Lua模块".to_string(),
-        author: "未知".to_string(),
+        version: doc.version.clone().unwrap_or_else(|| "1.0.0".to_string()),
+        description: doc.description.clone().unwrap_or_else(|| "Lua模块".to_string()),
+        author: doc.author.clone().unwrap_or_else(|| "未知".to_string()),
         functions: HashMap::new(),
     };
     
@@ -103,63 +132,48 @@ fn create_metadata(name: &str, functions: &[String], module_meta: Option<&Value>
         }
     }
     
-    // 为每个函数创建元数据
-    for func_name in functions {
-        let function_meta = FunctionMetadata {
+    // 为每个函数创建元数据，没有文档注释的函数使用默认描述
+    let documented: Vec<String> = doc.functions.iter().map(|f| f.name.clone()).collect();
+    for func_name in functions.unwrap_or(&documented) {
+        let function_meta = doc.function(func_name).cloned().unwrap_or_else(|| FunctionMetadata {
             name: func_name.clone(),
             description: format!("Lua函数 {}", func_name),
-            parameters: Vec::new(), // 在Lua中难以静态获取参数信息
+            parameters: Vec::new(),
             return_type: "Any".to_string(),
             example: "".to_string(),
-        };
+        });
         
         metadata.functions.insert(func_name.clone(), function_meta);
     }
     
-    Ok(metadata)
+    metadata
 }
 
-/// 从Lua文件内容中提取函数名称和module_meta
-fn extract_module_info(content: &str) -> Result<(Vec<String>, Option<Value>)> {
-    // 创建一个临时Lua环境来解析模块
-    let lua = Lua::new();
-    
-    // 加载Lua代码
-    let chunk_result = match lua.load(content).eval::<LuaTable>() {
-        Ok(table) => table,
-        Err(e) => {
-            return Err(InterpreterError::ModuleError(
-                format!("Lua模块加载错误: {}", e)
-            ));
-        }
-    };
-    
+/// 从模块表中读取函数名称和module_meta
+fn read_module_table(table: &LuaTable) -> (Vec<String>, Option<Value>) {
     // 调试：检查Lua返回的表结构
-    debug_print_lua_table(&chunk_result, "根模块表");
+    debug_print_lua_table(table, "根模块表");
     
-    // 提取函数名称
     let mut function_names = Vec::new();
     let mut module_meta = None;
     
-    for pair in chunk_result.clone().pairs::<String, mlua::Value>() {
-        if let Ok((key, value)) = pair {
-            match key.as_str() {
-                "module_meta" => {
-                    // 提取模块元数据
-                    if let Ok(json_value) = lua_to_json(value.clone()) {
-                        module_meta = Some(json_value);
-                    }
-                },
-                _ => {
-                    if let mlua::Value::Function(_) = value {
-                        function_names.push(key);
-                    }
+    for (key, value) in table.clone().pairs::<String, mlua::Value>().flatten() {
+        match key.as_str() {
+            "module_meta" => {
+                // 提取模块元数据
+                if let Ok(json_value) = lua_to_json(value.clone()) {
+                    module_meta = Some(json_value);
+                }
+            },
+            _ => {
+                if let mlua::Value::Function(_) = value {
+                    function_names.push(key);
                 }
             }
         }
     }
     
-    Ok((function_names, module_meta))
+    (function_names, module_meta)
 }
 
 // 调试函数：递归打印Lua表结构
@@ -192,14 +206,676 @@ fn debug_print_lua_table(table: &LuaTable, prefix: &str) {
     }
 }
 
+/// 由模块选项和模块位置决定的Lua执行限制
+///
+/// 沙箱模式只开放不能访问系统的标准库，未指定的内存和时间限制使用默认值；
+/// 非沙箱模式使用完整的标准库，只应用明确指定的限制。
+#[derive(Clone)]
+struct LuaLimits {
+    sandboxed: bool,
+    allow_filesystem: bool,
+    env_vars: HashMap<String, String>,
+    memory_limit_mb: Option<usize>,
+    timeout_ms: Option<u64>,
+    instruction_limit: Option<u64>,
+}
+
+/// 一次顶层调用剩余的执行预算，由指令钩子检查
+///
+/// 预算用完后exceeded一直保持到下次重置，pcall等捕获了钩子的错误也不能继续执行。
+#[derive(Clone, Copy, Default)]
+struct Budget {
+    deadline: Option<Instant>,
+    remaining_instructions: Option<u64>,
+    exceeded: Option<BudgetExceeded>,
+}
+
+#[derive(Clone, Copy)]
+enum BudgetExceeded {
+    Timeout,
+    Instructions,
+}
+
+impl LuaLimits {
+    fn new(options: &ExternalModuleOptions, path: &str) -> Self {
+        let sandboxed = options.is_sandboxed(path);
+        Self {
+            sandboxed,
+            allow_filesystem: options.allow_filesystem,
+            env_vars: options.env_vars.clone(),
+            memory_limit_mb: options.memory_limit_mb(sandboxed),
+            timeout_ms: options.execution_timeout_ms(sandboxed),
+            instruction_limit: options.instruction_limit,
+        }
+    }
+    
+    // 创建应用了这些限制的Lua状态，返回的预算在每次顶层调用前用reset重置
+    fn create_lua(&self) -> mlua::Result<(Lua, Rc<Cell<Budget>>)> {
+        let lua = if self.sandboxed {
+            let mut libs = StdLib::COROUTINE | StdLib::TABLE | StdLib::STRING | StdLib::UTF8 | StdLib::MATH | StdLib::OS;
+            if self.allow_filesystem {
+                libs |= StdLib::IO;
+            }
+            let lua = Lua::new_with(libs, LuaOptions::default())?;
+            self.restrict_globals(&lua)?;
+            lua
+        } else {
+            Lua::new()
+        };
+        
+        if let Some(mb) = self.memory_limit_mb {
+            lua.set_memory_limit(mb.saturating_mul(1024 * 1024))?;
+        }
+        
+        let budget = Rc::new(Cell::new(Budget::default()));
+        if self.timeout_ms.is_some() || self.instruction_limit.is_some() {
+            let (limits, hook_budget) = (self.clone(), budget.clone());
+            let triggers = HookTriggers { every_nth_instruction: Some(BUDGET_CHECK_INTERVAL), ..Default::default() };
+            lua.set_hook(triggers, move |_, _| {
+                let mut current = hook_budget.get();
+                if current.exceeded.is_none() {
+                    if current.deadline.map(|deadline| Instant::now() >= deadline).unwrap_or(false) {
+                        current.exceeded = Some(BudgetExceeded::Timeout);
+                    } else if let Some(remaining) = current.remaining_instructions {
+                        match remaining.checked_sub(BUDGET_CHECK_INTERVAL as u64) {
+                            Some(remaining) => current.remaining_instructions = Some(remaining),
+                            None => current.exceeded = Some(BudgetExceeded::Instructions),
+                        }
+                    }
+                    hook_budget.set(current);
+                }
+                match limits.exceeded_message(&hook_budget) {
+                    Some(message) => Err(LuaError::RuntimeError(message)),
+                    None => Ok(()),
+                }
+            })?;
+            self.guard_protected_calls(&lua, budget.clone())?;
+        }
+        Ok((lua, budget))
+    }
+    
+    // 预算用完时的错误信息
+    fn exceeded_message(&self, budget: &Cell<Budget>) -> Option<String> {
+        budget.get().exceeded.map(|exceeded| match exceeded {
+            BudgetExceeded::Timeout => format!("Lua代码执行超时（限制为 {} 毫秒）", self.timeout_ms.unwrap_or(0)),
+            BudgetExceeded::Instructions => format!("Lua代码超过指令数限制（{} 条）", self.instruction_limit.unwrap_or(0)),
+        })
+    }
+    
+    // 包装pcall、xpcall、coroutine.resume和coroutine.wrap，预算用完后重新抛出被捕获的错误。
+    // 包装函数用Lua编写，协程仍然可以在pcall中让出
+    fn guard_protected_calls(&self, lua: &Lua, budget: Rc<Cell<Budget>>) -> mlua::Result<()> {
+        let limits = self.clone();
+        let check = lua.create_function(move |_, results: mlua::MultiValue| {
+            match limits.exceeded_message(&budget) {
+                Some(message) => Err(LuaError::RuntimeError(message)),
+                None => Ok(results),
+            }
+        })?;
+        lua.load(r#"
+            local check = ...
+            local pcall, xpcall = pcall, xpcall
+            _G.pcall = function(...) return check(pcall(...)) end
+            _G.xpcall = function(...) return check(xpcall(...)) end
+            local coroutine = _G.coroutine
+            if coroutine then
+                local resume, wrap = coroutine.resume, coroutine.wrap
+                coroutine.resume = function(...) return check(resume(...)) end
+                coroutine.wrap = function(...)
+                    local f = wrap(...)
+                    return function(...) return check(f(...)) end
+                end
+            end
+        "#).set_name("=[jilang]")?.call(check)
+    }
+    
+    fn reset(&self, budget: &Cell<Budget>) {
+        budget.set(Budget {
+            deadline: self.timeout_ms.map(|ms| Instant::now() + Duration::from_millis(ms)),
+            remaining_instructions: self.instruction_limit,
+            exceeded: None,
+        });
+    }
+    
+    // 去掉沙箱中可以执行命令、加载字节码或任意文件的函数
+    fn restrict_globals(&self, lua: &Lua) -> mlua::Result<()> {
+        let globals = lua.globals();
+        
+        // 只允许加载文本代码，构造的字节码可以破坏Lua虚拟机
+        globals.set("load", text_only_loader(lua, globals.get("load")?, 2)?)?;
+        if self.allow_filesystem {
+            globals.set("loadfile", text_only_loader(lua, globals.get("loadfile")?, 1)?)?;
+            let io: LuaTable = globals.get("io")?;
+            io.set("popen", mlua::Value::Nil)?;
+        } else {
+            globals.set("loadfile", mlua::Value::Nil)?;
+        }
+        globals.set("dofile", mlua::Value::Nil)?;
+        globals.set("require", mlua::Value::Nil)?;
+        
+        // os只保留时间函数，getenv只能读取选项中提供的环境变量
+        let os: LuaTable = globals.get("os")?;
+        let safe_os = lua.create_table()?;
+        let mut allowed = vec!["time", "clock", "date", "difftime"];
+        if self.allow_filesystem {
+            allowed.extend(["remove", "rename", "tmpname"]);
+        }
+        for name in allowed {
+            safe_os.set(name, os.get::<_, mlua::Value>(name)?)?;
+        }
+        let env_vars = self.env_vars.clone();
+        safe_os.set("getenv", lua.create_function(move |_, name: String| Ok(env_vars.get(&name).cloned()))?)?;
+        globals.set("os", safe_os)?;
+        Ok(())
+    }
+}
+
+// 包装load/loadfile，强制mode参数（位于mode_index）为"t"
+fn text_only_loader<'lua>(lua: &'lua Lua, loader: mlua::Function<'lua>, mode_index: usize) -> mlua::Result<mlua::Function<'lua>> {
+    let key = lua.create_registry_value(loader)?;
+    lua.create_function(move |lua, args: Variadic<mlua::Value>| {
+        let mut args: Vec<mlua::Value> = args.into_iter().collect();
+        // 保留之后的env参数是否存在，env为nil与未提供的含义不同
+        if args.len() <= mode_index {
+            args.resize(mode_index + 1, mlua::Value::Nil);
+        }
+        args[mode_index] = mlua::Value::String(lua.create_string("t")?);
+        lua.registry_value::<mlua::Function>(&key)?.call::<_, mlua::MultiValue>(Variadic::from_iter(args))
+    })
+}
+
+/// 设置了jilang表和执行限制的Lua状态，Lua模块和内联Lua代码块共用
+struct LuaRuntime {
+    lua: Lua,
+    host: HostApi,
+    limits: LuaLimits,
+    budget: Rc<Cell<Budget>>,
+    // 正在进行的调用层数，嵌套调用共用最外层调用的执行预算
+    depth: Cell<usize>,
+}
+
+impl LuaRuntime {
+    fn new(limits: LuaLimits) -> Result<Self> {
+        let (lua, budget) = limits.create_lua()
+            .map_err(|e| InterpreterError::ModuleError(format!("创建Lua状态失败: {}", e)))?;
+        limits.reset(&budget);
+        let host = HostApi::install(&lua, &limits)
+            .map_err(|e| InterpreterError::ModuleError(format!("设置jilang表失败: {}", e)))?;
+        Ok(Self { lua, host, limits, budget, depth: Cell::new(0) })
+    }
+    
+    // 在context上调用Lua函数，args作为唯一的参数传入
+    fn call<'lua>(&'lua self, function: mlua::Function<'lua>, args: mlua::Value<'lua>, context: &mut Context) -> std::result::Result<Value, String> {
+        // 预算按最外层调用计算
+        if self.depth.get() == 0 {
+            self.limits.reset(&self.budget);
+        }
+        self.depth.set(self.depth.get() + 1);
+        let result = self.host.call(&self.lua, function, args, context);
+        self.depth.set(self.depth.get() - 1);
+        // Lua代码可能捕获了预算用完的错误后正常返回
+        self.check_budget()?;
+        result
+    }
+    
+    fn check_budget(&self) -> std::result::Result<(), String> {
+        match self.limits.exceeded_message(&self.budget) {
+            Some(message) => Err(message),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 模块的Lua状态，在多次调用之间保留全局变量和upvalue
+struct LuaState {
+    runtime: LuaRuntime,
+    // 模块代码返回的表
+    module_table: RegistryKey,
+}
+
+/// 程序中内联Lua代码块（lua语句）共用的Lua状态
+///
+/// 代码块是程序的一部分，不在沙箱中运行；代码块之间保留全局变量。
+pub struct InlineLua {
+    runtime: LuaRuntime,
+    // 编译后的代码块，按代码文本缓存
+    chunks: RefCell<HashMap<String, RegistryKey>>,
+}
+
+impl InlineLua {
+    pub fn new() -> Result<Self> {
+        let _span = profiler::span(profiler::SpanKind::LuaState, "lua");
+        let options = ExternalModuleOptions { sandbox: Some(false), ..Default::default() };
+        Ok(Self {
+            runtime: LuaRuntime::new(LuaLimits::new(&options, ""))?,
+            chunks: RefCell::new(HashMap::new()),
+        })
+    }
+    
+    /// 执行代码块并把返回值转换为JSON，args对象在代码块中是局部变量args（也是...）
+    pub fn run(&self, code: &str, args: &Value, context: &mut Context) -> Result<Value> {
+        let lua = &self.runtime.lua;
+        let function = self.chunk(code)?;
+        let args = json_to_lua(lua, args)
+            .map_err(|e| InterpreterError::RuntimeError(format!("转换参数为Lua值失败: {}", e)))?;
+        self.runtime.call(function, args, context)
+            .map_err(|message| InterpreterError::RuntimeError(format!("Lua代码块执行失败: {}", message)))
+    }
+    
+    fn chunk(&self, code: &str) -> Result<mlua::Function<'_>> {
+        let lua = &self.runtime.lua;
+        if let Some(key) = self.chunks.borrow().get(code) {
+            return lua.registry_value(key)
+                .map_err(|e| InterpreterError::RuntimeError(format!("获取Lua代码块失败: {}", e)));
+        }
+        // args的声明与代码放在同一行，错误信息中的行号与代码一致
+        let function = lua.load(&format!("local args = ...; {}", code)).set_name("=[lua]")
+            .and_then(|chunk| chunk.into_function())
+            .map_err(|e| InterpreterError::RuntimeError(format!("Lua代码块编译错误: {}", e)))?;
+        let key = lua.create_registry_value(function.clone())
+            .map_err(|e| InterpreterError::RuntimeError(format!("保存Lua代码块失败: {}", e)))?;
+        self.chunks.borrow_mut().insert(code.to_string(), key);
+        Ok(function)
+    }
+}
+
+// 需要访问Context的jilang函数，只在调用Lua函数期间绑定
+const CONTEXT_FUNCTIONS: &[&str] = &["get_var", "set_var", "get_const", "call", "call_function", "module", "print", "random"];
+
+// jilang表中需要Context的函数。它们调用bindings中当前绑定的Rust函数，失败时在调用者的
+// 位置抛出字符串错误，因此可以被pcall捕获，也可以在模块加载时保存到局部变量中。
+// math.random改为使用解释器的随机数生成器（--seed），并且可以被--record记录
+const HOST_PRELUDE: &str = r#"
+local jilang, bindings, error, setmetatable, rawset = ...
+local function unwrap(ok, result)
+    if not ok then
+        error(result, 3)
+    end
+    return result
+end
+for _, name in ipairs({"get_var", "set_var", "get_const", "call", "call_function", "print"}) do
+    jilang[name] = function(...)
+        -- 不使用尾调用，error的层级才能指向调用者
+        local result = unwrap(bindings[name](...))
+        return result
+    end
+end
+jilang.module = function(name)
+    unwrap(bindings.module(name))
+    return setmetatable({}, {
+        __index = function(proxy, function_name)
+            local f = function(args)
+                local result = unwrap(bindings.call_function(name .. "." .. function_name, args))
+                return result
+            end
+            rawset(proxy, function_name, f)
+            return f
+        end,
+    })
+end
+if math then
+    math.random = function(...)
+        local result = unwrap(bindings.random(...))
+        return result
+    end
+end
+"#;
+
+/// Lua状态中的jilang表，以及带调用栈地调用Lua函数所需的函数
+///
+/// 在执行模块代码之前创建，xpcall等函数在模块能修改全局变量之前保存。
+struct HostApi {
+    bindings: RegistryKey,
+    xpcall: RegistryKey,
+    traceback: RegistryKey,
+}
+
+impl HostApi {
+    // 创建jilang全局表。print和env一直可用，需要Context的函数在调用之外使用时报错
+    fn install(lua: &Lua, limits: &LuaLimits) -> mlua::Result<Self> {
+        let globals = lua.globals();
+        let jilang = lua.create_table()?;
+        
+        // 与@env.引用相同，沙箱中只能读取选项提供的环境变量
+        let (env_vars, sandboxed) = (limits.env_vars.clone(), limits.sandboxed);
+        jilang.set("env", lua.create_function(move |_, name: String| {
+            let value = match env_vars.get(&name) {
+                Some(value) => Value::String(value.clone()),
+                None if sandboxed => Value::Null,
+                None => trace::capture(TraceKind::Env, &name, || {
+                    std::env::var(&name).map(Value::String).unwrap_or(Value::Null)
+                }),
+            };
+            Ok(value.as_str().map(|v| v.to_string()))
+        })?)?;
+        
+        let bindings = lua.create_table()?;
+        for name in CONTEXT_FUNCTIONS {
+            bindings.set(*name, lua.create_function(move |_, _: mlua::MultiValue| {
+                Ok((false, format!("jilang.{} 只能在模块函数被调用期间使用", name)))
+            })?)?;
+        }
+        // 调用期间print写入context的输出（可以被测试捕获），之外直接写到标准输出
+        bindings.set("print", lua.create_function(|_, text: String| {
+            println!("{}", text);
+            Ok((true, mlua::Value::Nil))
+        })?)?;
+        // 调用之外（例如模块加载时）没有Context的随机数生成器
+        bindings.set("random", lua.create_function(|lua, (m, n): (Option<i64>, Option<i64>)| {
+            let result = random_range(m, n).map(|range| {
+                trace::capture(TraceKind::Random, "lua.math.random", || lua_random(&mut rand::thread_rng(), range))
+            });
+            host_result(lua, result)
+        })?)?;
+        trace_os_time(lua)?;
+        lua.load(HOST_PRELUDE).set_name("=[jilang]")?
+            .call::<_, ()>((jilang.clone(), bindings.clone(), globals.get::<_, mlua::Value>("error")?, globals.get::<_, mlua::Value>("setmetatable")?, globals.get::<_, mlua::Value>("rawset")?))?;
+        
+        let traceback = lua.create_function(|lua, error: mlua::Value| {
+            Ok(format!("{}\n{}", error_text(error), traceback(lua)))
+        })?;
+        let xpcall: mlua::Function = globals.get("xpcall")?;
+        globals.set("jilang", jilang)?;
+        
+        Ok(Self {
+            bindings: lua.create_registry_value(bindings)?,
+            xpcall: lua.create_registry_value(xpcall)?,
+            traceback: lua.create_registry_value(traceback)?,
+        })
+    }
+    
+    // 在context上调用Lua函数并把结果转换为JSON。调用期间jilang表中的函数借用context，
+    // Lua中的错误连同调用栈一起返回，JiLang的错误在Lua中可以被pcall捕获
+    fn call<'lua>(&self, lua: &'lua Lua, function: mlua::Function<'lua>, args: mlua::Value<'lua>, context: &mut Context) -> std::result::Result<Value, String> {
+        let context = RefCell::new(context);
+        let result = lua.scope(|scope| {
+            let bindings: LuaTable = lua.registry_value(&self.bindings)?;
+            // 嵌套调用会替换绑定，结束后恢复外层调用绑定的函数
+            let previous = CONTEXT_FUNCTIONS.iter()
+                .map(|name| bindings.get::<_, mlua::Value>(*name))
+                .collect::<mlua::Result<Vec<_>>>()?;
+            let result = bind(scope, &bindings, &context)
+                .and_then(|_| self.protected_call(lua, function, args));
+            for (name, value) in CONTEXT_FUNCTIONS.iter().zip(previous) {
+                bindings.set(*name, value)?;
+            }
+            Ok(match result? {
+                Ok(value) => Ok(lua_to_json(value)?),
+                Err(message) => Err(message),
+            })
+        });
+        result.unwrap_or_else(|e| Err(e.to_string()))
+    }
+    
+    // 通过xpcall调用，失败时返回带调用栈的错误信息
+    fn protected_call<'lua>(&self, lua: &'lua Lua, function: mlua::Function<'lua>, args: mlua::Value<'lua>) -> mlua::Result<std::result::Result<mlua::Value<'lua>, String>> {
+        let xpcall: mlua::Function = lua.registry_value(&self.xpcall)?;
+        let handler: mlua::Function = lua.registry_value(&self.traceback)?;
+        let (ok, value): (bool, mlua::Value) = xpcall.call((function, handler, args))?;
+        Ok(if ok { Ok(value) } else { Err(error_text(value)) })
+    }
+}
+
+// 为本次调用创建借用context的绑定函数，返回 (true, 结果) 或 (false, 错误信息)
+fn bind<'lua, 'scope>(scope: &Scope<'lua, 'scope>, bindings: &LuaTable<'lua>, context: &'scope RefCell<&mut Context>) -> mlua::Result<()> {
+    bindings.set("get_var", scope.create_function(move |lua, name: String| {
+        // 除了变量名，也接受@var.、@const.、@env.等引用
+        let reference = if VariableReference::is_reference(&name) { name } else { format!("@var.{}", name) };
+        let value = borrow(context).map(|context| context.get_value(&reference).unwrap_or(Value::Null));
+        host_result(lua, value)
+    })?)?;
+    
+    bindings.set("set_var", scope.create_function(move |lua, (name, value): (String, mlua::Value)| {
+        let result = lua_value(value).and_then(|value| {
+            borrow(context)?.set_variable(name.clone(), value)
+                .map(|_| Value::Bool(true))
+                .map_err(|e| InterpreterError::VariableError(format!("设置变量 {} 失败: {}", name, e.message())))
+        });
+        host_result(lua, result)
+    })?)?;
+    
+    bindings.set("get_const", scope.create_function(move |lua, name: String| {
+        let value = borrow(context).map(|context| context.constants.get(&name).cloned().unwrap_or(Value::Null));
+        host_result(lua, value)
+    })?)?;
+    
+    bindings.set("call", scope.create_function(move |lua, (statement, args): (String, mlua::Value)| {
+        let result = lua_value(args).and_then(|args| {
+            // 将参数转换为JiLang数组
+            let args = match args {
+                Value::Array(arr) => arr,
+                value => vec![value],
+            };
+            let mut context = borrow(context)?;
+            crate::interpreter::statements::execute_statement(&statement, &Value::Array(args), &mut context, None)
+                .map_err(|e| InterpreterError::RuntimeError(format!("执行语句 {} 失败: {}", statement, e.message())))
+        });
+        host_result(lua, result)
+    })?)?;
+    
+    bindings.set("call_function", scope.create_function(move |lua, (name, args): (String, mlua::Value)| {
+        let result = lua_value(args).and_then(|args| {
+            let mut context = borrow(context)?;
+            call_jilang_function(&mut context, &name, args)
+                .map_err(|e| InterpreterError::FunctionError(format!("调用函数 {} 失败: {}", name, e.message())))
+        });
+        host_result(lua, result)
+    })?)?;
+    
+    bindings.set("print", scope.create_function(move |lua, text: String| {
+        let result = borrow(context).map(|mut context| {
+            context.write_output(&format!("{}\n", text));
+            Value::Null
+        });
+        host_result(lua, result)
+    })?)?;
+    
+    bindings.set("module", scope.create_function(move |lua, name: String| {
+        let result = borrow(context).and_then(|context| match context.modules.contains_key(&name) {
+            true => Ok(Value::Null),
+            false => Err(InterpreterError::ModuleError(error_messages::context::module_not_found(&name))),
+        });
+        host_result(lua, result)
+    })?)?;
+    
+    bindings.set("random", scope.create_function(move |lua, (m, n): (Option<i64>, Option<i64>)| {
+        let result = random_range(m, n).and_then(|range| borrow(context).map(|mut context| {
+            trace::capture(TraceKind::Random, "lua.math.random", || context.with_rng(|rng| lua_random(rng, range)))
+        }));
+        host_result(lua, result)
+    })?)?;
+    
+    Ok(())
+}
+
+// math.random的参数：没有参数时返回None（[0,1)的浮点数），m表示[1,m]，m和n表示[m,n]，
+// 与Lua相同，random(0)返回任意整数
+fn random_range(m: Option<i64>, n: Option<i64>) -> Result<Option<(i64, i64)>> {
+    let range = match (m, n) {
+        (None, _) => return Ok(None),
+        (Some(0), None) => (i64::MIN, i64::MAX),
+        (Some(m), None) => (1, m),
+        (Some(m), Some(n)) => (m, n),
+    };
+    if range.0 > range.1 {
+        return Err(InterpreterError::RuntimeError(format!("math.random 的区间 [{}, {}] 为空", range.0, range.1)));
+    }
+    Ok(Some(range))
+}
+
+fn lua_random(rng: &mut dyn RngCore, range: Option<(i64, i64)>) -> Value {
+    match range {
+        None => Value::from(rng.gen::<f64>()),
+        Some((low, high)) => Value::from(rng.gen_range(low..=high)),
+    }
+}
+
+// os.time()、os.clock()和不指定时间的os.date读取当前时间，经过trace记录或回放；
+// os.time(表)和os.date(格式, 时间)只做转换，直接调用原来的函数
+fn trace_os_time(lua: &Lua) -> mlua::Result<()> {
+    let os = match lua.globals().get::<_, mlua::Value>("os")? {
+        mlua::Value::Table(os) => os,
+        _ => return Ok(()),
+    };
+    for (name, explicit_arg) in [("time", 0), ("clock", usize::MAX), ("date", 1)] {
+        let original = match os.get::<_, mlua::Value>(name)? {
+            mlua::Value::Function(original) => lua.create_registry_value(original)?,
+            _ => continue,
+        };
+        let key = format!("lua.os.{}", name);
+        os.set(name, lua.create_function(move |lua, args: mlua::MultiValue| {
+            let original: mlua::Function = lua.registry_value(&original)?;
+            if !matches!(args.iter().nth(explicit_arg), None | Some(mlua::Value::Nil)) {
+                return original.call::<_, mlua::Value>(args);
+            }
+            let mut error = None;
+            let value = trace::capture(TraceKind::Time, &key, || {
+                original.call::<_, mlua::Value>(args).and_then(lua_to_json).unwrap_or_else(|e| {
+                    error = Some(e);
+                    Value::Null
+                })
+            });
+            match error {
+                Some(e) => Err(e),
+                None => json_to_lua(lua, &value),
+            }
+        })?)?;
+    }
+    Ok(())
+}
+
+fn host_result(lua: &Lua, result: Result<Value>) -> mlua::Result<(bool, mlua::Value<'_>)> {
+    match result {
+        Ok(value) => Ok((true, json_to_lua(lua, &value)?)),
+        Err(e) => Ok((false, mlua::Value::String(lua.create_string(e.message())?))),
+    }
+}
+
+fn lua_value(value: mlua::Value) -> Result<Value> {
+    lua_to_json(value).map_err(|e| InterpreterError::RuntimeError(format!("转换Lua值为JSON值失败: {}", e)))
+}
+
+// 在绑定函数中借用当前调用的Context
+fn borrow<'a, 'c>(context: &'a RefCell<&'c mut Context>) -> Result<RefMut<'a, &'c mut Context>> {
+    context.try_borrow_mut()
+        .map_err(|_| InterpreterError::RuntimeError("jilang 接口正在被另一个jilang调用使用".to_string()))
+}
+
+// Lua错误值的文本，Rust函数产生的错误是userdata
+fn error_text(error: mlua::Value) -> String {
+    match error {
+        mlua::Value::String(s) => s.to_string_lossy().into_owned(),
+        mlua::Value::Error(e) => {
+            // 钩子和Rust函数的错误被包装为CallbackError，只保留原因
+            let mut cause = &e;
+            while let LuaError::CallbackError { cause: inner, .. } = cause {
+                cause = inner;
+            }
+            match cause {
+                LuaError::RuntimeError(message) => message.clone(),
+                other => other.to_string(),
+            }
+        },
+        other => format!("（{} 类型的错误值）", other.type_name()),
+    }
+}
+
+// 调用用户函数或模块函数。args是数组时按位置传参，是对象时按参数名传参，
+// 按参数名传参只支持用户函数和JL模块函数（它们声明了参数列表）
+pub(super) fn call_jilang_function(context: &mut Context, name: &str, args: Value) -> Result<Value> {
+    if crate::interpreter::statements::is_builtin_statement(name) {
+        return Err(InterpreterError::FunctionError(format!("'{}' 是内置语句，请使用 jilang.call", name)));
+    }
+    let params = declared_params(context, name);
+    let args = match args {
+        Value::Array(args) => args,
+        Value::Null => Vec::new(),
+        Value::Object(named) if named.is_empty() => Vec::new(),
+        Value::Object(named) => {
+            let params = params.as_ref().ok_or_else(|| InterpreterError::FunctionError(
+                format!("函数 '{}' 没有声明参数列表，只能按位置传参", name)
+            ))?;
+            if let Some(unknown) = named.keys().find(|key| !params.contains(key)) {
+                return Err(InterpreterError::FunctionError(
+                    format!("函数 '{}' 没有名为 '{}' 的参数，参数为: {}", name, unknown, params.join(", "))
+                ));
+            }
+            params.iter()
+                .map(|param| named.get(param).cloned().ok_or_else(|| InterpreterError::FunctionError(
+                    error_messages::statement::missing_parameter(param)
+                )))
+                .collect::<Result<Vec<_>>>()?
+        },
+        value => vec![value],
+    };
+    match name.split_once('.') {
+        // 没有声明参数的模块函数直接调用，不经过语句（语句会把结果存入result变量）
+        Some((module, function)) if params.is_none() => context.call_module_function(module, function, &args),
+        Some(_) => crate::interpreter::statements::execute_statement(name, &Value::Array(args), context, None),
+        None if params.is_some() => crate::interpreter::statements::execute_statement(name, &Value::Array(args), context, None),
+        None => Err(InterpreterError::FunctionError(format!("未找到用户函数 '{}'", name))),
+    }
+}
+
+// 用户函数或JL模块函数声明的参数名（按声明顺序）
+fn declared_params(context: &Context, name: &str) -> Option<Vec<String>> {
+    let func = match name.split_once('.') {
+        Some((module, function)) => {
+            let module = context.modules.get(module)?.borrow();
+            let module = module.as_any();
+            if let Some(jl_module) = module.downcast_ref::<super::jl_module::JlModule>() {
+                jl_module.get_function(function).cloned()
+            } else if let Some(external) = module.downcast_ref::<super::external_module::JLangExternalModule>() {
+                external.get_jlang_function(function)
+            } else {
+                None
+            }
+        },
+        None => context.program.get("program").and_then(|p| p.get(name)).cloned(),
+    }?;
+    Some(func.get("params").and_then(|p| p.as_object()).map(|p| p.keys().cloned().collect()).unwrap_or_default())
+}
+
+// 出错位置的Lua调用栈，格式与Lua的debug.traceback相同，省略没有名字的C函数和jilang表的包装函数
+fn traceback(lua: &Lua) -> String {
+    let mut lines = vec!["stack traceback:".to_string()];
+    // 第0层是错误处理函数本身
+    let mut level = 1;
+    while let Some(debug) = lua.inspect_stack(level) {
+        level += 1;
+        let source = debug.source();
+        let names = debug.names();
+        let name = names.name.map(String::from_utf8_lossy);
+        let short_src = source.short_src.map(String::from_utf8_lossy).unwrap_or_default();
+        if short_src == "[jilang]" {
+            continue;
+        }
+        let line = match (source.what, debug.curr_line()) {
+            (Some(b"C"), _) => match name {
+                Some(name) => format!("[C]: in function '{}'", name),
+                None => continue,
+            },
+            (Some(b"main"), line) => format!("{}:{}: in main chunk", short_src, line),
+            (_, line) => match name {
+                Some(name) => format!("{}:{}: in function '{}'", short_src, line, name),
+                None => format!("{}:{}: in function <{}:{}>", short_src, line, short_src, source.line_defined),
+            },
+        };
+        lines.push(format!("\t{}", line));
+    }
+    lines.join("\n")
+}
+
 /// Lua模块实现
 pub struct LuaModule {
     name: String,
     path: String,
-    content: String,       // 存储Lua代码内容，每次调用时创建新环境
+    source: Arc<LuaSource>, // Lua代码（创建Lua状态时执行）和从注释中解析的文档
     metadata: ModuleMetadata,
     module_meta: Option<Value>, // 存储模块自定义元数据
     options: ExternalModuleOptions,
+    // 在initialize中创建，destroy时释放；reload后在下次调用时重新创建
+    state: RefCell<Option<Rc<LuaState>>>,
 }
 
 impl Module for LuaModule {
@@ -231,115 +907,45 @@ impl ExternalModule for LuaModule {
     
     fn set_options(&mut self, options: ExternalModuleOptions) -> Result<()> {
         self.options = options;
+        // 限制在创建Lua状态时应用，下次调用时使用新选项重新创建
+        self.state.replace(None);
         Ok(())
     }
     
     fn reload(&mut self) -> Result<()> {
-        // 重新加载Lua模块内容
-        self.content = fs::read_to_string(&self.path)
-            .map_err(|e| InterpreterError::ModuleError(format!("无法读取Lua文件 '{}': {}", self.path, e)))?;
-        
-        // 重新提取函数名和模块元数据
-        let (functions, module_meta) = extract_module_info(&self.content)?;
+        // 重新读取Lua模块内容并解析文档注释
+        self.source = load_source(&self.path)?;
+        self.module_meta = self.source.doc.module_meta.clone();
+        self.metadata = create_metadata(&self.name, &self.source.doc, None, self.module_meta.as_ref());
         
-        // 更新元数据
-        self.metadata = create_metadata(&self.name, &functions, module_meta.as_ref())?;
-        
-        // 更新模块元数据
-        self.module_meta = module_meta;
-        
-        Ok(())
+        // 丢弃旧的Lua状态，使用新代码重新创建
+        self.state.replace(None);
+        self.initialize()
+    }
+    
+    fn source_path(&self) -> Option<&str> {
+        Some(&self.path)
     }
     
     fn call_function(&self, name: &str, args: &[Value], context: &mut Context) -> Result<Value> {
-        // 为每次调用创建新的Lua环境
-        let lua = Lua::new();
-        
-        // 设置JiLang环境
-        self.setup_jilang_environment(&lua, context)?;
-        
-        // 加载模块代码
-        let module_table = lua.load(&self.content).eval::<mlua::Value>()
-            .map_err(|e| InterpreterError::ModuleError(
-                format!("Lua模块加载错误: {}", e)
-            ))?;
+        // 不持有RefCell的借用，Lua函数可能通过jilang.call再次调用本模块
+        let state = self.state()?;
+        let table: LuaTable = state.runtime.lua.registry_value(&state.module_table)
+            .map_err(|e| InterpreterError::ModuleError(format!("获取Lua模块 '{}' 的表失败: {}", self.name, e)))?;
+        let lua = &state.runtime.lua;
         
-        // 调试：详细分析模块结构
         if crate::is_debug_mode() {
             println!("调试: 调用函数 '{}' 的详细分析", name);
-            println!("模块返回值类型: {:?}", module_table.type_name());
-            
-            match module_table {
-                mlua::Value::Table(ref table) => {
-                    debug_print_lua_table(table, "模块");
-                    println!("尝试获取函数路径: 模块.{}", name);
-                },
-                _ => {
-                    println!("错误: 模块不是表结构，而是 {:?}", module_table.type_name());
-                }
-            }
+            debug_print_lua_table(&table, "模块");
         }
         
-        // 获取函数 - 尝试从table中获取
-        if let mlua::Value::Table(ref table) = module_table {
-            // 首先直接尝试获取函数（可能是第一级导出）
-            let lua_fn_result = table.get::<_, mlua::Value>(name);
-            
-            // 如果直接获取失败，检查是否函数在模块表中的各个字段
-            let lua_fn = match lua_fn_result {
-                Ok(mlua::Value::Function(f)) => {
-                    if crate::is_debug_mode() {
-                        println!("直接在模块表中找到函数: '{}'", name);
-                    }
-                    f
-                },
+        {
+            let lua_fn = match table.get::<_, mlua::Value>(name) {
+                Ok(mlua::Value::Function(f)) => f,
                 _ => {
-                    // 在直接获取失败的情况下，尝试更详细的搜索
-                    if crate::is_debug_mode() {
-                        println!("直接获取函数失败，尝试遍历模块表寻找...");
-                    }
-                    
-                    // 列出所有键并寻找匹配的函数
-                    let mut found_fn = None;
-                    for pair in table.clone().pairs::<String, mlua::Value>() {
-                        if let Ok((key, val)) = pair {
-                            if crate::is_debug_mode() {
-                                println!("检查键: '{}' (类型: {:?})", key, val.type_name());
-                            }
-                            
-                            if key == name {
-                                if let mlua::Value::Function(f) = val {
-                                    found_fn = Some(f);
-                                    if crate::is_debug_mode() {
-                                        println!("在模块表中找到函数: '{}'", name);
-                                    }
-                                    break;
-                                } else if crate::is_debug_mode() {
-                                    println!("键名匹配但不是函数: '{}'", name);
-                                }
-                            }
-                        }
-                    }
-                    
-                    // 如果还是找不到，返回错误
-                    match found_fn {
-                        Some(f) => f,
-                        None => {
-                            if crate::is_debug_mode() {
-                                println!("未能在模块表中找到函数: '{}'", name);
-                                println!("模块中的所有键:");
-                                for pair in table.clone().pairs::<String, mlua::Value>() {
-                                    if let Ok((key, val)) = pair {
-                                        println!("  - {}: {:?}", key, val.type_name());
-                                    }
-                                }
-                            }
-                            
-                            return Err(InterpreterError::FunctionError(
-                                format!("Lua模块 '{}' 中未找到函数 '{}'", self.name, name)
-                            ));
-                        }
-                    }
+                    return Err(InterpreterError::FunctionError(
+                        format!("Lua模块 '{}' 中未找到函数 '{}'", self.name, name)
+                    ));
                 }
             };
             
@@ -350,7 +956,7 @@ impl ExternalModule for LuaModule {
                     println!("转换第{}个参数: {:?}", i, arg);
                 }
                 
-                let lua_value = match json_to_lua(&lua, arg) {
+                let lua_value = match json_to_lua(lua, arg) {
                     Ok(val) => {
                         if crate::is_debug_mode() {
                             println!("  转换结果: {} (类型: {:?})", 
@@ -383,15 +989,12 @@ impl ExternalModule for LuaModule {
                 println!("调用Lua函数 '{}' 传入 {} 个参数", name, lua_args.len());
             }
             
-            let lua_result = lua_fn.call::<_, mlua::Value>(lua_args)
-                .map_err(|e| InterpreterError::RuntimeError(
-                    format!("Lua函数 '{}' 调用失败: {}", name, e)
-                ))?;
-            
-            // 转换Lua结果为JSON值
-            let result = lua_to_json(lua_result)
-                .map_err(|e| InterpreterError::RuntimeError(
-                    format!("转换Lua结果为JSON值失败: {}", e)
+            // 参数以一个数组表的形式传给Lua函数
+            let lua_args = lua.create_sequence_from(lua_args)
+                .map_err(|e| InterpreterError::RuntimeError(format!("转换参数为Lua值失败: {}", e)))?;
+            let result = state.runtime.call(lua_fn, mlua::Value::Table(lua_args), context)
+                .map_err(|message| InterpreterError::RuntimeError(
+                    format!("Lua函数 '{}' 调用失败: {}", name, message)
                 ))?;
             
             if crate::is_debug_mode() {
@@ -399,10 +1002,6 @@ impl ExternalModule for LuaModule {
             }
             
             Ok(result)
-        } else {
-            Err(InterpreterError::ModuleError(
-                format!("Lua模块 '{}' 未返回一个表", self.name)
-            ))
         }
     }
     
@@ -419,12 +1018,28 @@ impl ExternalModule for LuaModule {
     }
     
     fn initialize(&mut self) -> Result<()> {
-        // Lua模块初始化时不需要特殊操作
+        // 创建Lua状态并执行模块代码，然后用模块表中实际的函数和module_meta更新元数据
+        let state = self.state()?;
+        let table: LuaTable = state.runtime.lua.registry_value(&state.module_table)
+            .map_err(|e| InterpreterError::ModuleError(format!("获取Lua模块 '{}' 的表失败: {}", self.name, e)))?;
+        let (functions, module_meta) = read_module_table(&table);
+        if module_meta.is_some() {
+            self.module_meta = module_meta;
+        }
+        self.metadata = create_metadata(&self.name, &self.source.doc, Some(&functions), self.module_meta.as_ref());
+        
+        if crate::is_debug_mode() {
+            println!("Lua模块 '{}' 中的函数:", self.name);
+            for fname in self.metadata.functions.keys() {
+                println!("  - {}", fname);
+            }
+        }
         Ok(())
     }
     
     fn destroy(&mut self) -> Result<()> {
-        // Lua模块销毁时不需要特殊操作
+        // 关闭Lua状态
+        self.state.replace(None);
         Ok(())
     }
     
@@ -438,121 +1053,50 @@ impl ExternalModule for LuaModule {
 }
 
 impl LuaModule {
-    // 设置JiLang环境到Lua状态机
-    fn setup_jilang_environment(&self, lua: &Lua, context: &mut Context) -> Result<()> {
-        // 创建jilang全局表
-        let globals = lua.globals();
-        let jilang_table = lua.create_table()
-            .map_err(|e| InterpreterError::RuntimeError(format!("创建Lua表失败: {}", e)))?;
-        
-        // 添加变量访问函数
-        self.add_get_var_function(lua, &jilang_table, context)?;
-        
-        // 添加设置变量函数
-        self.add_set_var_function(lua, &jilang_table, context)?;
-        
-        // 添加打印函数
-        self.add_print_function(lua, &jilang_table)?;
-        
-        // 添加调用JiLang函数
-        self.add_call_function(lua, &jilang_table, context)?;
-        
-        // 设置jilang表到全局环境
-        globals.set("jilang", jilang_table)
-            .map_err(|e| InterpreterError::RuntimeError(format!("设置jilang表失败: {}", e)))?;
-        
-        Ok(())
+    // 获取模块的Lua状态，还没有创建（或已被reload丢弃）时创建
+    fn state(&self) -> Result<Rc<LuaState>> {
+        if let Some(state) = self.state.borrow().as_ref() {
+            return Ok(state.clone());
+        }
+        let state = Rc::new(self.create_state()?);
+        self.state.replace(Some(state.clone()));
+        Ok(state)
     }
     
-    fn add_get_var_function(&self, lua: &Lua, table: &LuaTable, context: &Context) -> Result<()> {
-        // 捕获context的引用到Lua闭包
-        let context_ref = context as *const Context;
-        
-        let get_var = lua.create_function(move |lua_ctx, var_name: String| {
-            // 安全地从指针恢复context引用
-            let context = unsafe { &*context_ref };
-            
-            // 获取变量值
-            if let Some(value) = context.get_value(&var_name) {
-                // 将JiLang变量值转换为Lua值
-                json_to_lua(lua_ctx, &value)
-            } else {
-                // 如果变量不存在，返回nil
-                Ok(mlua::Value::Nil)
-            }
-        }).map_err(|e| InterpreterError::RuntimeError(format!("创建get_var函数失败: {}", e)))?;
-        
-        table.set("get_var", get_var)
-            .map_err(|e| InterpreterError::RuntimeError(format!("设置get_var函数失败: {}", e)))?;
+    // 创建Lua状态，设置jilang表并执行模块代码，性能分析时单独记录这部分开销
+    fn create_state(&self) -> Result<LuaState> {
+        let _span = profiler::span(profiler::SpanKind::LuaState, &self.name);
+        if crate::is_debug_mode() {
+            println!("创建Lua模块 '{}' 的Lua状态", self.name);
+        }
         
-        Ok(())
-    }
-    
-    fn add_set_var_function(&self, lua: &Lua, table: &LuaTable, context: &mut Context) -> Result<()> {
-        // 捕获context的可变引用到Lua闭包
-        let context_ptr = context as *mut Context;
+        let limits = LuaLimits::new(&self.options, &self.path);
+        if crate::is_debug_mode() && limits.sandboxed {
+            println!("Lua模块 '{}' 在沙箱中运行", self.name);
+        }
+        let runtime = LuaRuntime::new(limits)?;
+        let lua = &runtime.lua;
         
-        let set_var = lua.create_function(move |_, (var_name, value): (String, mlua::Value)| {
-            // 安全地从指针恢复context可变引用
-            let context = unsafe { &mut *context_ptr };
-            
-            // 将Lua值转换为JiLang值
-            match lua_to_json(value) {
-                Ok(json_value) => {
-                    // 设置变量
-                    match context.set_variable(var_name.clone(), json_value) {
-                        Ok(_) => Ok(true),
-                        Err(e) => Err(LuaError::RuntimeError(format!("设置变量 {} 失败: {}", var_name, e)))
-                    }
-                },
-                Err(e) => Err(e)
+        // 加载模块代码
+        let module_table = match lua.load(&self.source.content).set_name(format!("@{}", self.path))
+            .and_then(|chunk| chunk.eval::<mlua::Value>())
+            .and_then(|value| runtime.check_budget().map(|_| value).map_err(LuaError::RuntimeError))
+        {
+            Ok(mlua::Value::Table(table)) => lua.create_registry_value(table)
+                .map_err(|e| InterpreterError::ModuleError(format!("保存Lua模块表失败: {}", e)))?,
+            Ok(_) => {
+                return Err(InterpreterError::ModuleError(
+                    format!("Lua模块 '{}' 未返回一个表", self.name)
+                ));
             }
-        }).map_err(|e| InterpreterError::RuntimeError(format!("创建set_var函数失败: {}", e)))?;
-        
-        table.set("set_var", set_var)
-            .map_err(|e| InterpreterError::RuntimeError(format!("设置set_var函数失败: {}", e)))?;
-        
-        Ok(())
-    }
-    
-    fn add_print_function(&self, lua: &Lua, table: &LuaTable) -> Result<()> {
-        let print_fn = lua.create_function(|_, text: String| {
-            println!("{}", text);
-            Ok(())
-        }).map_err(|e| InterpreterError::RuntimeError(format!("创建print函数失败: {}", e)))?;
-        
-        table.set("print", print_fn)
-            .map_err(|e| InterpreterError::RuntimeError(format!("设置print函数失败: {}", e)))?;
-        
-        Ok(())
-    }
-    
-    fn add_call_function(&self, lua: &Lua, table: &LuaTable, context: &mut Context) -> Result<()> {
-        // 捕获context的可变引用到Lua闭包
-        let context_ptr = context as *mut Context;
-        
-        let call_fn = lua.create_function(move |lua_ctx, (func_name, args): (String, mlua::Value)| {
-            // 安全地从指针恢复context可变引用
-            let context = unsafe { &mut *context_ptr };
-            
-            // 将参数转换为JiLang数组
-            let jilang_args = match lua_to_json(args) {
-                Ok(Value::Array(arr)) => arr,
-                Ok(value) => vec![value],
-                Err(e) => return Err(e)
-            };
-            
-            // 执行JiLang语句
-            match crate::interpreter::statements::execute_statement(&func_name, &Value::Array(jilang_args), context, None) {
-                Ok(result) => json_to_lua(lua_ctx, &result),
-                Err(e) => Err(LuaError::RuntimeError(format!("执行语句 {} 失败: {}", func_name, e)))
+            Err(e) => {
+                return Err(InterpreterError::ModuleError(
+                    format!("Lua模块加载错误: {}", e)
+                ));
             }
-        }).map_err(|e| InterpreterError::RuntimeError(format!("创建call函数失败: {}", e)))?;
-        
-        table.set("call", call_fn)
-            .map_err(|e| InterpreterError::RuntimeError(format!("设置call函数失败: {}", e)))?;
+        };
         
-        Ok(())
+        Ok(LuaState { runtime, module_table })
     }
     
     // 获取模块自定义元数据
@@ -561,6 +1105,12 @@ impl LuaModule {
     }
 }
 
+impl Drop for LuaModule {
+    fn drop(&mut self) {
+        let _ = self.destroy();
+    }
+}
+
 /// 将JSON值转换为Lua值
 fn json_to_lua<'lua>(lua: &'lua Lua, value: &Value) -> mlua::Result<mlua::Value<'lua>> {
     if crate::is_debug_mode() {
@@ -665,7 +1215,7 @@ fn json_to_lua<'lua>(lua: &'lua Lua, value: &Value) -> mlua::Result<mlua::Value<
 }
 
 /// 将Lua值转换为JSON值
-fn lua_to_json(value: mlua::Value) -> mlua::Result<Value> {
+pub(super) fn lua_to_json(value: mlua::Value) -> mlua::Result<Value> {
     if crate::is_debug_mode() {
         println!("将Lua值转换为JSON: 类型={:?}", value.type_name());
     }
@@ -785,4 +1335,87 @@ fn lua_to_json(value: mlua::Value) -> mlua::Result<Value> {
             Ok(Value::String(format!("<不支持的Lua值类型: {}>", value.type_name())))
         },
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // 在按options创建的Lua状态中执行代码块
+    fn run(options: ExternalModuleOptions, code: &str) -> std::result::Result<Value, String> {
+        let runtime = LuaRuntime::new(LuaLimits::new(&options, "test.lua")).unwrap();
+        let function = runtime.lua.load(code).into_function().unwrap();
+        let mut context = Context::new(json!({"program": {}}), Vec::new()).unwrap();
+        runtime.call(function, mlua::Value::Nil, &mut context)
+    }
+
+    fn sandbox() -> ExternalModuleOptions {
+        ExternalModuleOptions { sandbox: Some(true), ..Default::default() }
+    }
+
+    #[test]
+    fn pcall_cannot_catch_timeout() {
+        let options = ExternalModuleOptions { execution_timeout_ms: Some(50), ..sandbox() };
+        let code = "while true do pcall(function() while true do end end) end";
+        assert_eq!(run(options.clone(), code), Err("Lua代码执行超时（限制为 50 毫秒）".to_string()));
+        // 捕获错误后正常返回也算失败
+        let code = "pcall(function() while true do end end) return 1";
+        assert_eq!(run(options.clone(), code), Err("Lua代码执行超时（限制为 50 毫秒）".to_string()));
+        let code = "local co = coroutine.wrap(function() while true do end end) pcall(co) return 1";
+        assert_eq!(run(options, code), Err("Lua代码执行超时（限制为 50 毫秒）".to_string()));
+    }
+
+    #[test]
+    fn pcall_cannot_catch_instruction_limit() {
+        let options = ExternalModuleOptions { instruction_limit: Some(100_000), ..Default::default() };
+        let code = "while true do xpcall(function() while true do end end, function(e) return e end) end";
+        assert_eq!(run(options.clone(), code), Err("Lua代码超过指令数限制（100000 条）".to_string()));
+        let code = "local co = coroutine.create(function() while true do end end) coroutine.resume(co) return 1";
+        assert_eq!(run(options.clone(), code), Err("Lua代码超过指令数限制（100000 条）".to_string()));
+        // 每次调用重新计算预算，协程仍然可以在pcall中让出
+        let code = "local co = coroutine.wrap(function() pcall(coroutine.yield, 1) return 2 end) return {co(), co()}";
+        assert_eq!(run(options, code), Ok(json!([1, 2])));
+    }
+
+    #[test]
+    fn memory_limit_stops_allocation() {
+        let options = ExternalModuleOptions { memory_limit_mb: Some(8), ..sandbox() };
+        let code = "local t = {} for i = 1, 1e7 do t[i] = string.rep('x', 100) .. i end return #t";
+        let error = run(options, code).unwrap_err();
+        assert!(error.contains("memory"), "{}", error);
+    }
+
+    #[test]
+    fn math_random_uses_context_seed() {
+        let code = "return {math.random(), math.random(10), math.random(-3, 3), pcall(math.random, 2, 1)}";
+        let run_seeded = |options: ExternalModuleOptions| {
+            let runtime = LuaRuntime::new(LuaLimits::new(&options, "test.lua")).unwrap();
+            let function = runtime.lua.load(code).into_function().unwrap();
+            let mut context = Context::new(json!({"program": {}}), Vec::new()).unwrap();
+            context.seed_random(42);
+            runtime.call(function, mlua::Value::Nil, &mut context).unwrap()
+        };
+        let result = run_seeded(sandbox());
+        assert_eq!(result, run_seeded(ExternalModuleOptions::default()));
+        assert!((0.0..1.0).contains(&result[0].as_f64().unwrap()), "{}", result);
+        assert!((1..=10).contains(&result[1].as_i64().unwrap()), "{}", result);
+        assert!((-3..=3).contains(&result[2].as_i64().unwrap()), "{}", result);
+        assert_eq!(result[3], json!(false));
+    }
+
+    #[test]
+    fn sandbox_removes_system_access() {
+        let code = "return {type(os.execute), type(io), type(require), type(dofile), type(loadfile), type(os.exit)}";
+        assert_eq!(run(sandbox(), code), Ok(json!(["nil", "nil", "nil", "nil", "nil", "nil"])));
+        assert_eq!(run(sandbox(), "return os.getenv('PATH')"), Ok(Value::Null));
+    }
+
+    #[test]
+    fn sandbox_rejects_binary_chunks() {
+        let code = "local f, err = load(string.dump(function() return 1 end), 'x', 'b') return {f == nil, err}";
+        let result = run(sandbox(), code).unwrap();
+        assert_eq!(result[0], json!(true));
+        assert!(result[1].as_str().unwrap().contains("binary"), "{}", result);
+        assert_eq!(run(sandbox(), "return load('return 1 + 1')()"), Ok(json!(2)));
+    }
+}
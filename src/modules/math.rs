@@ -1,22 +1,32 @@
 use serde_json::Value;
 use crate::interpreter::context::Context;
-use super::Module;
+use super::bignum::{BigInt, Decimal, Exact, RoundingMode};
+use super::{math_linalg, math_stats};
 use crate::interpreter::error::{InterpreterError, Result};
 use crate::interpreter::error::error_messages::math;
 use crate::interpreter::variable_reference::VariableReference;
+use crate::interpreter::trace::{self, TraceKind};
 use std::panic;
 use regex;
 use std::f64::consts::PI;
+use std::cmp::Ordering;
 use rand::Rng;
+use rand::seq::SliceRandom;
+use jilang_macros::{jilang_module, jilang_fn};
+
+// 十进制小数除法默认保留的小数位数
+const DEFAULT_DIVISION_SCALE: u32 = 20;
 
 pub struct MathModule;
 
+/// 数学运算模块：算术、精确小数、三角函数、随机数、统计和线性代数
+#[jilang_module]
 impl MathModule {
     pub fn new() -> Self {
         MathModule
     }
 
-    fn get_number(value: &Value, context: &Context) -> Result<f64> {
+    pub(super) fn get_number(value: &Value, context: &Context) -> Result<f64> {
         match value {
             Value::Number(n) => n.as_f64().ok_or_else(|| 
                 InterpreterError::RuntimeError(math::INVALID_NUMBER_CONVERSION.to_string())
@@ -53,7 +63,9 @@ impl MathModule {
                                 }
                             },
                             Value::Object(obj) => {
-                                if obj.is_empty() {
+                                if let Some(decimal) = Decimal::from_value(&resolved) {
+                                    Ok(decimal.to_f64())
+                                } else if obj.is_empty() {
                                     Ok(0.0)
                                 } else {
                                     Err(InterpreterError::RuntimeError(
@@ -98,7 +110,9 @@ impl MathModule {
                 }
             },
             Value::Object(obj) => {
-                if obj.is_empty() {
+                if let Some(decimal) = Decimal::from_value(value) {
+                    Ok(decimal.to_f64())
+                } else if obj.is_empty() {
                     Ok(0.0)
                 } else {
                     Err(InterpreterError::RuntimeError(
@@ -154,7 +168,92 @@ impl MathModule {
         Ok(0.0)
     }
 
+    // 解析变量引用，得到参数的实际值
+    pub(super) fn resolve_arg(value: &Value, context: &Context) -> Value {
+        match value {
+            Value::String(s) if VariableReference::is_reference(s) => context.get_value(s).unwrap_or_else(|| value.clone()),
+            _ => value.clone(),
+        }
+    }
+    
+    // 获取精确运算的操作数：全部是整数时按整数计算；有十进制小数时其他操作数都转为小数；
+    // 有浮点数（且没有小数）或无法转换的参数时返回None，按浮点数计算
+    pub(super) fn exact_operands(args: &[Value], context: &Context) -> Option<Vec<Exact>> {
+        let values: Vec<Value> = args.iter().map(|arg| Self::resolve_arg(arg, context)).collect();
+        let has_decimal = values.iter().any(|v| Decimal::from_value(v).is_some());
+        values.iter()
+            .map(|value| match Exact::from_value(value) {
+                Some(exact) => Some(exact),
+                None if has_decimal => Self::get_number(value, context).ok()
+                    .and_then(Decimal::from_f64)
+                    .map(Exact::Decimal),
+                None => None,
+            })
+            .collect()
+    }
+    
+    // 精确计算的最大值（ordering为Greater）或最小值（Less）：第一个参数是数组（或引用数组的变量）时在数组中查找。
+    // 有浮点数或无法转换的参数时返回None，按浮点数计算
+    fn exact_extreme(args: &[Value], context: &Context, ordering: Ordering) -> Option<Value> {
+        let items = match args.first().map(|arg| Self::resolve_arg(arg, context)) {
+            Some(Value::Array(items)) => items,
+            _ => args.to_vec(),
+        };
+        let operands = Self::exact_operands(&items, context)?;
+        operands.into_iter()
+            .reduce(|best, x| if x.compare(&best) == ordering { x } else { best })
+            .map(|best| best.to_value())
+    }
+    
+    // 浮点数结果：整数值在i64范围内时返回整数
+    pub(super) fn integral_value(value: f64) -> Value {
+        if value.fract() == 0.0 && value.abs() < 9.0e15 {
+            Value::Number((value as i64).into())
+        } else {
+            Value::Number(serde_json::Number::from_f64(value).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()))
+        }
+    }
+    
+    // 解析舍入方式参数，默认为四舍五入
+    fn rounding_mode(value: Option<&Value>, context: &Context) -> std::result::Result<RoundingMode, String> {
+        match value.map(|v| Self::resolve_arg(v, context)) {
+            None | Some(Value::Null) => Ok(RoundingMode::HalfUp),
+            Some(Value::String(name)) => RoundingMode::parse(&name).ok_or_else(|| format!(
+                "未知的舍入方式 '{}'，可用的舍入方式: {}", name, RoundingMode::NAMES.join(", ")
+            )),
+            Some(other) => Err(format!("舍入方式必须是字符串，实际为 {}", other)),
+        }
+    }
+    
+    // 解析小数位数参数
+    fn scale_arg(value: Option<&Value>, context: &Context, default: u32) -> std::result::Result<u32, String> {
+        match value.map(|v| Self::resolve_arg(v, context)) {
+            None | Some(Value::Null) => Ok(default),
+            Some(value) => match Exact::from_value(&value) {
+                Some(Exact::Int(scale)) if (0..=1000).contains(&scale) => Ok(scale as u32),
+                _ => Err(format!("小数位数必须是0到1000之间的整数，实际为 {}", value)),
+            },
+        }
+    }
+
+    /// 加法，对所有参数求和。参数都是整数或十进制小数时结果是精确的
+    ///
+    /// # 参数
+    /// - numbers (Number, 可选): 要相加的数字，可以传入任意多个
+    ///
+    /// # 返回
+    /// 所有参数的和，没有参数时为0
+    ///
+    /// # 示例
+    /// {"math.add": [1, 2, 3], "output": "sum"}
+    #[jilang_fn]
     fn add(args: &[Value], context: &mut Context) -> Value {
+        if let Some(operands) = Self::exact_operands(args, context) {
+            if let Some((first, rest)) = operands.split_first() {
+                return rest.iter().fold(first.clone(), |acc, x| acc.add(x)).to_value();
+            }
+        }
+        
         if args.is_empty() {
             return Value::Number(serde_json::Number::from_f64(0.0).unwrap());
         }
@@ -195,7 +294,25 @@ impl MathModule {
         Value::Number(serde_json::Number::from_f64(result).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()))
     }
 
+    /// 减法，用第一个参数依次减去其余参数
+    ///
+    /// # 参数
+    /// - minuend (Number): 被减数
+    /// - subtrahends (Number, 可选): 减数，可以传入多个
+    ///
+    /// # 返回
+    /// 差
+    ///
+    /// # 示例
+    /// {"math.subtract": [10, 3, 2], "output": "diff"}
+    #[jilang_fn]
     fn subtract(args: &[Value], context: &mut Context) -> Value {
+        if let Some(operands) = Self::exact_operands(args, context) {
+            if let Some((first, rest)) = operands.split_first() {
+                return rest.iter().fold(first.clone(), |acc, x| acc.sub(x)).to_value();
+            }
+        }
+        
         match args.first().map(|v| Self::get_number(v, context)) {
             Some(Ok(first)) => {
                 let result = args[1..].iter()
@@ -211,7 +328,24 @@ impl MathModule {
         }
     }
 
+    /// 乘法，对所有参数求积。参数都是整数或十进制小数时结果是精确的
+    ///
+    /// # 参数
+    /// - numbers (Number, 可选): 要相乘的数字，可以传入任意多个
+    ///
+    /// # 返回
+    /// 所有参数的积，没有参数时为0
+    ///
+    /// # 示例
+    /// {"math.multiply": [2, 3, 4], "output": "product"}
+    #[jilang_fn]
     fn multiply(args: &[Value], context: &mut Context) -> Value {
+        if let Some(operands) = Self::exact_operands(args, context) {
+            if let Some((first, rest)) = operands.split_first() {
+                return rest.iter().fold(first.clone(), |acc, x| acc.mul(x)).to_value();
+            }
+        }
+        
         if args.is_empty() {
             return Value::Number(serde_json::Number::from_f64(0.0).unwrap());
         }
@@ -254,10 +388,26 @@ impl MathModule {
         Value::Number(serde_json::Number::from_f64(result).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()))
     }
 
+    /// 除法，用第一个参数依次除以其余参数。整数能整除时结果为整数，
+    /// 十进制小数的结果保留20位小数，除数为0时报错
+    ///
+    /// # 参数
+    /// - dividend (Number): 被除数
+    /// - divisors (Number, 可选): 除数，可以传入多个
+    ///
+    /// # 返回
+    /// 商
+    ///
+    /// # 示例
+    /// {"math.divide": [10, 4], "output": "quotient"}
+    #[jilang_fn]
     fn divide(args: &[Value], context: &mut Context) -> Value {
         if args.is_empty() {
             return Value::Number(serde_json::Number::from_f64(0.0).unwrap());
         }
+        if let Some(result) = Self::exact_divide(args, context) {
+            return result;
+        }
         
         match Self::get_number(&args[0], context) {
             Ok(first) => {
@@ -289,7 +439,147 @@ impl MathModule {
         }
     }
 
+    // 精确除法：整数能整除时结果为整数，有小数时保留 DEFAULT_DIVISION_SCALE 位并去掉末尾的0；
+    // 整数不能整除时返回None，按浮点数计算
+    fn exact_divide(args: &[Value], context: &Context) -> Option<Value> {
+        let operands = Self::exact_operands(args, context)?;
+        let (first, rest) = operands.split_first()?;
+        if rest.iter().any(|x| x.to_big().is_zero() && !x.is_decimal()) {
+            // 与浮点数除法一致
+            panic!("{}", math::DIVISION_BY_ZERO);
+        }
+        let mut result = first.clone();
+        for divisor in rest {
+            result = match (&result, divisor) {
+                (Exact::Decimal(_), _) | (_, Exact::Decimal(_)) => {
+                    let quotient = result.to_decimal().div(&divisor.to_decimal(), DEFAULT_DIVISION_SCALE, RoundingMode::HalfEven);
+                    match quotient {
+                        Some(quotient) => Exact::Decimal(quotient.normalize()),
+                        None => panic!("{}", math::DIVISION_BY_ZERO),
+                    }
+                },
+                _ => match result.to_big().div_rem(&divisor.to_big()) {
+                    Some((quotient, remainder)) if remainder.is_zero() => Exact::from_big(quotient),
+                    _ => return None,
+                },
+            };
+        }
+        Some(result.to_value())
+    }
+    
+    /// 十进制小数的除法，可以指定小数位数和舍入方式
+    ///
+    /// # 参数
+    /// - dividend (Number): 被除数，可以是数字或数字字符串
+    /// - divisor (Number): 除数
+    /// - scale (Integer, 可选): 结果的小数位数，默认为20
+    /// - mode (String, 可选): 舍入方式：half_up、half_down、half_even、up、down、ceiling 或 floor，默认为 half_up
+    ///
+    /// # 返回
+    /// 十进制小数
+    ///
+    /// # 示例
+    /// {"math.decimal_div": ["10", "3", 2], "output": "result"}
+    #[jilang_fn]
+    fn decimal_div(args: &[Value], context: &mut Context) -> Value {
+        let operands = match args.get(..2).and_then(|pair| {
+            let pair: Vec<Value> = pair.iter().map(|v| Self::resolve_arg(v, context)).collect();
+            let to_decimal = |value: &Value| Exact::from_value(value).map(|e| e.to_decimal())
+                .or_else(|| Self::get_number(value, context).ok().and_then(Decimal::from_f64));
+            Some((to_decimal(&pair[0])?, to_decimal(&pair[1])?))
+        }) {
+            Some(operands) => operands,
+            None => {
+                eprintln!("错误: math.decimal_div 需要被除数和除数两个数字");
+                return Value::Null;
+            }
+        };
+        let scale = match Self::scale_arg(args.get(2), context, DEFAULT_DIVISION_SCALE) {
+            Ok(scale) => scale,
+            Err(err) => {
+                eprintln!("错误: {}", err);
+                return Value::Null;
+            }
+        };
+        let mode = match Self::rounding_mode(args.get(3), context) {
+            Ok(mode) => mode,
+            Err(err) => {
+                eprintln!("错误: {}", err);
+                return Value::Null;
+            }
+        };
+        match operands.0.div(&operands.1, scale, mode) {
+            Some(result) => result.to_value(),
+            None => panic!("{}", math::DIVISION_BY_ZERO),
+        }
+    }
+    
+    /// 创建十进制小数，指定小数位数时按舍入方式取整
+    ///
+    /// # 参数
+    /// - value (Number): 数字或数字字符串，例如 "0.1"
+    /// - scale (Integer, 可选): 小数位数
+    /// - mode (String, 可选): 舍入方式，默认为 half_up
+    ///
+    /// # 返回
+    /// 十进制小数
+    ///
+    /// # 示例
+    /// {"math.decimal": ["1.005", 2], "output": "price"}
+    #[jilang_fn]
+    fn decimal(args: &[Value], context: &mut Context) -> Value {
+        let value = match args.first().map(|v| Self::resolve_arg(v, context)) {
+            Some(value) => value,
+            None => {
+                eprintln!("错误: math.decimal 需要一个数字或数字字符串");
+                return Value::Null;
+            }
+        };
+        let decimal = match &value {
+            Value::String(s) => Decimal::parse(s),
+            Value::Number(n) if n.is_f64() => n.as_f64().and_then(Decimal::from_f64),
+            _ => Exact::from_value(&value).map(|e| e.to_decimal()),
+        };
+        let decimal = match decimal {
+            Some(decimal) => decimal,
+            None => {
+                eprintln!("错误: 无法将 {} 转换为十进制小数", value);
+                return Value::Null;
+            }
+        };
+        if args.len() < 2 {
+            return decimal.to_value();
+        }
+        match (Self::scale_arg(args.get(1), context, decimal.scale()), Self::rounding_mode(args.get(2), context)) {
+            (Ok(scale), Ok(mode)) => decimal.round(scale, mode).to_value(),
+            (Err(err), _) | (_, Err(err)) => {
+                eprintln!("错误: {}", err);
+                Value::Null
+            }
+        }
+    }
+
+    /// 幂运算，整数或十进制小数的非负整数次幂是精确的
+    ///
+    /// # 参数
+    /// - base (Number): 底数
+    /// - exponent (Number): 指数
+    ///
+    /// # 返回
+    /// base的exponent次幂
+    ///
+    /// # 示例
+    /// {"math.pow": [2, 10], "output": "result"}
+    #[jilang_fn]
     fn pow(args: &[Value], context: &mut Context) -> Value {
+        // 整数或小数的非负整数次幂是精确的
+        if let Some(operands) = Self::exact_operands(args, context) {
+            if let [base, Exact::Int(exponent)] = operands.as_slice() {
+                if (0..=u16::MAX as i64).contains(exponent) {
+                    return base.pow(*exponent as u32).to_value();
+                }
+            }
+        }
         match (args.get(0).map(|v| Self::get_number(v, context)), args.get(1).map(|v| Self::get_number(v, context))) {
             (Some(Ok(base)), Some(Ok(exp))) => {
                 let result = base.powf(exp);
@@ -303,6 +593,17 @@ impl MathModule {
         }
     }
 
+    /// 平方根
+    ///
+    /// # 参数
+    /// - number (Number): 被开方数
+    ///
+    /// # 返回
+    /// 平方根
+    ///
+    /// # 示例
+    /// {"math.sqrt": [16], "output": "root"}
+    #[jilang_fn]
     fn sqrt(args: &[Value], context: &mut Context) -> Value {
         match args.first().map(|v| Self::get_number(v, context)) {
             Some(Ok(num)) => {
@@ -317,12 +618,38 @@ impl MathModule {
         }
     }
 
+    /// 四舍五入到整数；十进制小数可以指定小数位数和舍入方式
+    ///
+    /// # 参数
+    /// - number (Number): 要取整的数
+    /// - scale (Integer, 可选): 十进制小数保留的小数位数，默认为0
+    /// - mode (String, 可选): 十进制小数的舍入方式，默认为 half_up
+    ///
+    /// # 返回
+    /// 取整后的数
+    ///
+    /// # 示例
+    /// {"math.round": [3.6], "output": "rounded"}
+    #[jilang_fn]
     fn round(args: &[Value], context: &mut Context) -> Value {
-        match args.first().map(|v| Self::get_number(v, context)) {
-            Some(Ok(num)) => {
-                let result = num.round();
-                Value::Number(serde_json::Number::from_f64(result).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()))
+        match Self::exact_operands(&args[..args.len().min(1)], context).and_then(|v| v.into_iter().next()) {
+            Some(Exact::Decimal(decimal)) => {
+                return match (Self::scale_arg(args.get(1), context, 0), Self::rounding_mode(args.get(2), context)) {
+                    (Ok(0), Ok(mode)) => Exact::from_big(
+                        decimal.round(0, mode).to_integer().unwrap_or_else(BigInt::zero)
+                    ).to_value(),
+                    (Ok(scale), Ok(mode)) => decimal.round(scale, mode).to_value(),
+                    (Err(err), _) | (_, Err(err)) => {
+                        eprintln!("错误: {}", err);
+                        Value::Null
+                    }
+                };
             }
+            Some(integer) => return integer.to_value(),
+            None => {}
+        }
+        match args.first().map(|v| Self::get_number(v, context)) {
+            Some(Ok(num)) => Self::integral_value(num.round()),
             Some(Err(err)) => {
                 eprintln!("错误: {}", err);
                 Value::Number(serde_json::Number::from_f64(0.0).unwrap())
@@ -331,10 +658,21 @@ impl MathModule {
         }
     }
     
-    // 新增函数
-    
-    // 绝对值
+    /// 绝对值
+    ///
+    /// # 参数
+    /// - number (Number): 数字
+    ///
+    /// # 返回
+    /// 绝对值
+    ///
+    /// # 示例
+    /// {"math.abs": [-5], "output": "result"}
+    #[jilang_fn]
     fn abs(args: &[Value], context: &mut Context) -> Value {
+        if let Some(operand) = Self::exact_operands(&args[..args.len().min(1)], context).and_then(|v| v.into_iter().next()) {
+            return operand.abs().to_value();
+        }
         match args.first().map(|v| Self::get_number(v, context)) {
             Some(Ok(num)) => {
                 let result = num.abs();
@@ -348,7 +686,17 @@ impl MathModule {
         }
     }
     
-    // 正弦函数 (角度值，非弧度)
+    /// 正弦函数，参数为角度而不是弧度
+    ///
+    /// # 参数
+    /// - degrees (Number): 角度
+    ///
+    /// # 返回
+    /// 正弦值
+    ///
+    /// # 示例
+    /// {"math.sin": [30], "output": "result"}
+    #[jilang_fn]
     fn sin(args: &[Value], context: &mut Context) -> Value {
         match args.first().map(|v| Self::get_number(v, context)) {
             Some(Ok(degrees)) => {
@@ -364,7 +712,17 @@ impl MathModule {
         }
     }
     
-    // 余弦函数 (角度值，非弧度)
+    /// 余弦函数，参数为角度而不是弧度
+    ///
+    /// # 参数
+    /// - degrees (Number): 角度
+    ///
+    /// # 返回
+    /// 余弦值
+    ///
+    /// # 示例
+    /// {"math.cos": [60], "output": "result"}
+    #[jilang_fn]
     fn cos(args: &[Value], context: &mut Context) -> Value {
         match args.first().map(|v| Self::get_number(v, context)) {
             Some(Ok(degrees)) => {
@@ -380,7 +738,17 @@ impl MathModule {
         }
     }
     
-    // 正切函数 (角度值，非弧度)
+    /// 正切函数，参数为角度而不是弧度
+    ///
+    /// # 参数
+    /// - degrees (Number): 角度
+    ///
+    /// # 返回
+    /// 正切值
+    ///
+    /// # 示例
+    /// {"math.tan": [45], "output": "result"}
+    #[jilang_fn]
     fn tan(args: &[Value], context: &mut Context) -> Value {
         match args.first().map(|v| Self::get_number(v, context)) {
             Some(Ok(degrees)) => {
@@ -396,7 +764,17 @@ impl MathModule {
         }
     }
     
-    // 对数函数 (以10为底)
+    /// 以10为底的对数，参数必须为正数
+    ///
+    /// # 参数
+    /// - number (Number): 真数
+    ///
+    /// # 返回
+    /// 对数值
+    ///
+    /// # 示例
+    /// {"math.log": [1000], "output": "result"}
+    #[jilang_fn]
     fn log(args: &[Value], context: &mut Context) -> Value {
         match args.first().map(|v| Self::get_number(v, context)) {
             Some(Ok(num)) => {
@@ -416,7 +794,17 @@ impl MathModule {
         }
     }
     
-    // 自然对数函数 (以e为底)
+    /// 自然对数（以e为底），参数必须为正数
+    ///
+    /// # 参数
+    /// - number (Number): 真数
+    ///
+    /// # 返回
+    /// 对数值
+    ///
+    /// # 示例
+    /// {"math.ln": [10], "output": "result"}
+    #[jilang_fn]
     fn ln(args: &[Value], context: &mut Context) -> Value {
         match args.first().map(|v| Self::get_number(v, context)) {
             Some(Ok(num)) => {
@@ -436,12 +824,27 @@ impl MathModule {
         }
     }
     
-    // 最大值
+    /// 最大值，第一个参数是数组（或引用数组的变量）时在数组中查找。参数都是整数或十进制小数时结果是精确的
+    ///
+    /// # 参数
+    /// - numbers (Number): 数组，或直接传入多个数字
+    ///
+    /// # 返回
+    /// 最大值，没有有效数字时为0
+    ///
+    /// # 示例
+    /// {"math.max": [3, 7, 5], "output": "largest"}
+    #[jilang_fn]
     fn max(args: &[Value], context: &mut Context) -> Value {
         if args.is_empty() {
             return Value::Number(serde_json::Number::from_f64(0.0).unwrap());
         }
         
+        // 参数都是整数或十进制小数时精确比较，结果保持原来的类型
+        if let Some(value) = Self::exact_extreme(args, context, Ordering::Greater) {
+            return value;
+        }
+        
         // 检查第一个参数是否为数组
         if let Some(first_arg) = args.first() {
             // 如果是数组，提取数组中的元素作为数字
@@ -508,12 +911,27 @@ impl MathModule {
         Value::Number(serde_json::Number::from_f64(max_value).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()))
     }
     
-    // 最小值
+    /// 最小值，第一个参数是数组（或引用数组的变量）时在数组中查找。参数都是整数或十进制小数时结果是精确的
+    ///
+    /// # 参数
+    /// - numbers (Number): 数组，或直接传入多个数字
+    ///
+    /// # 返回
+    /// 最小值，没有有效数字时为0
+    ///
+    /// # 示例
+    /// {"math.min": ["@var.scores"], "output": "lowest"}
+    #[jilang_fn]
     fn min(args: &[Value], context: &mut Context) -> Value {
         if args.is_empty() {
             return Value::Number(serde_json::Number::from_f64(0.0).unwrap());
         }
         
+        // 参数都是整数或十进制小数时精确比较，结果保持原来的类型
+        if let Some(value) = Self::exact_extreme(args, context, Ordering::Less) {
+            return value;
+        }
+        
         // 检查第一个参数是否为数组
         if let Some(first_arg) = args.first() {
             // 如果是数组，提取数组中的元素作为数字
@@ -580,13 +998,28 @@ impl MathModule {
         Value::Number(serde_json::Number::from_f64(min_value).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()))
     }
     
-    // 向下取整
+    /// 向下取整，整数保持不变
+    ///
+    /// # 参数
+    /// - number (Number): 数字
+    ///
+    /// # 返回
+    /// 不大于参数的最大整数
+    ///
+    /// # 示例
+    /// {"math.floor": [3.7], "output": "result"}
+    #[jilang_fn]
     fn floor(args: &[Value], context: &mut Context) -> Value {
+        // 整数保持不变，小数和浮点数的结果为整数
+        match Self::exact_operands(&args[..args.len().min(1)], context).and_then(|v| v.into_iter().next()) {
+            Some(Exact::Decimal(decimal)) => return Exact::from_big(
+                decimal.round(0, RoundingMode::Floor).to_integer().unwrap_or_else(BigInt::zero)
+            ).to_value(),
+            Some(integer) => return integer.to_value(),
+            None => {}
+        }
         match args.first().map(|v| Self::get_number(v, context)) {
-            Some(Ok(num)) => {
-                let result = num.floor();
-                Value::Number(serde_json::Number::from_f64(result).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()))
-            }
+            Some(Ok(num)) => Self::integral_value(num.floor()),
             Some(Err(err)) => {
                 eprintln!("错误: {}", err);
                 Value::Number(serde_json::Number::from_f64(0.0).unwrap())
@@ -595,13 +1028,28 @@ impl MathModule {
         }
     }
     
-    // 向上取整
+    /// 向上取整，整数保持不变
+    ///
+    /// # 参数
+    /// - number (Number): 数字
+    ///
+    /// # 返回
+    /// 不小于参数的最小整数
+    ///
+    /// # 示例
+    /// {"math.ceil": [3.2], "output": "result"}
+    #[jilang_fn]
     fn ceil(args: &[Value], context: &mut Context) -> Value {
+        // 整数保持不变，小数和浮点数的结果为整数
+        match Self::exact_operands(&args[..args.len().min(1)], context).and_then(|v| v.into_iter().next()) {
+            Some(Exact::Decimal(decimal)) => return Exact::from_big(
+                decimal.round(0, RoundingMode::Ceiling).to_integer().unwrap_or_else(BigInt::zero)
+            ).to_value(),
+            Some(integer) => return integer.to_value(),
+            None => {}
+        }
         match args.first().map(|v| Self::get_number(v, context)) {
-            Some(Ok(num)) => {
-                let result = num.ceil();
-                Value::Number(serde_json::Number::from_f64(result).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()))
-            }
+            Some(Ok(num)) => Self::integral_value(num.ceil()),
             Some(Err(err)) => {
                 eprintln!("错误: {}", err);
                 Value::Number(serde_json::Number::from_f64(0.0).unwrap())
@@ -610,12 +1058,27 @@ impl MathModule {
         }
     }
     
-    // 随机数生成
+    /// 随机数。没有参数时返回 [0, 1) 中的数，一个参数时返回 [0, max) 中的数，
+    /// 两个参数时返回 [min, max) 中的数
+    ///
+    /// # 参数
+    /// - min (Number, 可选): 最小值，只有一个参数时为最大值
+    /// - max (Number, 可选): 最大值
+    ///
+    /// # 返回
+    /// 随机数
+    ///
+    /// # 示例
+    /// {"math.random": [1, 100], "output": "value"}
+    #[jilang_fn]
     fn random(args: &[Value], context: &mut Context) -> Value {
+        trace::capture(TraceKind::Random, "random", || Self::generate_random(args, context))
+    }
+    
+    fn generate_random(args: &[Value], context: &mut Context) -> Value {
         // 没有参数 - 返回0到1之间的随机数
         if args.is_empty() {
-            let mut rng = rand::thread_rng();
-            let random_value = rng.gen::<f64>(); // 生成0到1之间的随机数
+            let random_value = context.with_rng(|rng| rng.gen::<f64>()); // 生成0到1之间的随机数
             return Value::Number(serde_json::Number::from_f64(random_value).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()));
         }
         
@@ -623,8 +1086,11 @@ impl MathModule {
         if args.len() == 1 {
             match Self::get_number(&args[0], context) {
                 Ok(max) => {
-                    let mut rng = rand::thread_rng();
-                    let random_value = rng.gen_range(0.0..max);
+                    if max <= 0.0 {
+                        eprintln!("错误: 随机数范围中最小值必须小于最大值");
+                        return Value::Number(serde_json::Number::from_f64(0.0).unwrap());
+                    }
+                    let random_value = context.with_rng(|rng| rng.gen_range(0.0..max));
                     return Value::Number(serde_json::Number::from_f64(random_value).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()));
                 },
                 Err(err) => {
@@ -642,8 +1108,7 @@ impl MathModule {
                         eprintln!("错误: 随机数范围中最小值必须小于最大值");
                         return Value::Number(serde_json::Number::from_f64(0.0).unwrap());
                     }
-                    let mut rng = rand::thread_rng();
-                    let random_value = rng.gen_range(min..max);
+                    let random_value = context.with_rng(|rng| rng.gen_range(min..max));
                     return Value::Number(serde_json::Number::from_f64(random_value).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()));
                 },
                 (Err(err), _) | (_, Err(err)) => {
@@ -655,38 +1120,467 @@ impl MathModule {
         
         Value::Number(serde_json::Number::from_f64(0.0).unwrap())
     }
-}
-
-impl Module for MathModule {
-    fn get_name(&self) -> &'static str {
-        "math"
+    
+    /// 设置随机数种子，之后所有随机函数的结果都可以重现
+    ///
+    /// # 参数
+    /// - seed (Integer): 种子
+    ///
+    /// # 返回
+    /// 使用的种子
+    ///
+    /// # 示例
+    /// {"math.seed": [42]}
+    #[jilang_fn]
+    fn seed(args: &[Value], context: &mut Context) -> Value {
+        match args.first().map(|v| Self::get_number(v, context)) {
+            Some(Ok(seed)) => {
+                context.seed_random(seed as i64 as u64);
+                Value::Number(serde_json::Number::from(seed as i64))
+            }
+            Some(Err(err)) => {
+                eprintln!("错误: {}", err);
+                Value::Null
+            }
+            None => {
+                eprintln!("错误: math.seed 需要一个整数种子");
+                Value::Null
+            }
+        }
     }
-
-    fn get_functions(&self) -> Vec<(&'static str, Box<dyn Fn(&[Value], &mut Context) -> Value + Send + Sync + 'static>)> {
-        vec![
-            ("add", Box::new(Self::add)),
-            ("subtract", Box::new(Self::subtract)),
-            ("multiply", Box::new(Self::multiply)),
-            ("divide", Box::new(Self::divide)),
-            ("pow", Box::new(Self::pow)),
-            ("sqrt", Box::new(Self::sqrt)),
-            ("round", Box::new(Self::round)),
-            // 新函数
-            ("abs", Box::new(Self::abs)),
-            ("sin", Box::new(Self::sin)),
-            ("cos", Box::new(Self::cos)),
-            ("tan", Box::new(Self::tan)),
-            ("log", Box::new(Self::log)),
-            ("ln", Box::new(Self::ln)),
-            ("max", Box::new(Self::max)),
-            ("min", Box::new(Self::min)),
-            ("floor", Box::new(Self::floor)),
-            ("ceil", Box::new(Self::ceil)),
-            ("random", Box::new(Self::random))
-        ]
-    }
-    
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-} 
\ No newline at end of file
+    
+    // 获取数组参数，参数可以是数组或引用数组的变量
+    fn get_array(value: Option<&Value>, context: &Context) -> Option<Vec<Value>> {
+        match value {
+            Some(Value::Array(arr)) => Some(arr.clone()),
+            Some(Value::String(s)) if VariableReference::is_reference(s) => match context.get_value(s) {
+                Some(Value::Array(arr)) => Some(arr),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    
+    // 随机整数范围的边界：整数直接使用，其他数字按round取整，必须是i64范围内的有限数字
+    fn random_bound(value: &Value, context: &Context, round: fn(f64) -> f64) -> std::result::Result<i64, String> {
+        let resolved = Self::resolve_arg(value, context);
+        if let Some(Exact::Int(bound)) = Exact::from_value(&resolved) {
+            return Ok(bound);
+        }
+        let bound = round(Self::get_number(value, context).map_err(|e| e.to_string())?);
+        // i64::MAX转换为浮点数后是2^63，已经超出范围
+        if bound.is_finite() && bound >= i64::MIN as f64 && bound < i64::MAX as f64 {
+            Ok(bound as i64)
+        } else {
+            Err(format!("随机数范围必须是整数范围内的有限数字，实际为 {}", resolved))
+        }
+    }
+    
+    /// 随机整数。一个参数时返回 [0, n) 中的整数，两个参数时返回 [min, max] 中的整数（包含两端）
+    ///
+    /// # 参数
+    /// - min (Integer): 最小值，只有一个参数时为上界n
+    /// - max (Integer, 可选): 最大值
+    ///
+    /// # 返回
+    /// 随机整数
+    ///
+    /// # 示例
+    /// {"math.random_int": [1, 6], "output": "dice"}
+    #[jilang_fn]
+    fn random_int(args: &[Value], context: &mut Context) -> Value {
+        trace::capture(TraceKind::Random, "random_int", || {
+            let range = match args.len() {
+                0 => Err("math.random_int 需要提供范围".to_string()),
+                1 => Self::random_bound(&args[0], context, f64::floor)
+                    .and_then(|max| max.checked_sub(1).ok_or_else(|| format!("随机数范围的上界 {} 太小", max)))
+                    .map(|max| (0, max)),
+                _ => Self::random_bound(&args[0], context, f64::ceil)
+                    .and_then(|min| Ok((min, Self::random_bound(&args[1], context, f64::floor)?))),
+            };
+            match range {
+                Ok((min, max)) if min <= max => Value::Number(context.with_rng(|rng| rng.gen_range(min..=max)).into()),
+                Ok(_) => {
+                    eprintln!("错误: 随机数范围中最小值必须小于最大值");
+                    Value::Null
+                }
+                Err(err) => {
+                    eprintln!("错误: {}", err);
+                    Value::Null
+                }
+            }
+        })
+    }
+    
+    /// 从数组中随机选择一个元素
+    ///
+    /// # 参数
+    /// - items (Array): 数组或引用数组的变量，不能为空
+    ///
+    /// # 返回
+    /// 选中的元素
+    ///
+    /// # 示例
+    /// {"math.choice": [["红", "绿", "蓝"]], "output": "color"}
+    #[jilang_fn]
+    fn choice(args: &[Value], context: &mut Context) -> Value {
+        trace::capture(TraceKind::Random, "choice", || {
+            match Self::get_array(args.first(), context) {
+                Some(items) if !items.is_empty() => {
+                    let index = context.with_rng(|rng| rng.gen_range(0..items.len()));
+                    items[index].clone()
+                }
+                Some(_) => {
+                    eprintln!("错误: math.choice 不能从空数组中选择");
+                    Value::Null
+                }
+                None => {
+                    eprintln!("错误: math.choice 的参数必须是数组");
+                    Value::Null
+                }
+            }
+        })
+    }
+    
+    /// 返回随机打乱顺序的新数组，原数组不变
+    ///
+    /// # 参数
+    /// - items (Array): 数组或引用数组的变量
+    ///
+    /// # 返回
+    /// 打乱顺序后的数组
+    ///
+    /// # 示例
+    /// {"math.shuffle": ["@var.cards"], "output": "shuffled"}
+    #[jilang_fn]
+    fn shuffle(args: &[Value], context: &mut Context) -> Value {
+        trace::capture(TraceKind::Random, "shuffle", || {
+            match Self::get_array(args.first(), context) {
+                Some(mut items) => {
+                    context.with_rng(|rng| items.shuffle(rng));
+                    Value::Array(items)
+                }
+                None => {
+                    eprintln!("错误: math.shuffle 的参数必须是数组");
+                    Value::Array(Vec::new())
+                }
+            }
+        })
+    }
+    
+    /// 从数组中不重复地随机选择若干个元素
+    ///
+    /// # 参数
+    /// - items (Array): 数组或引用数组的变量
+    /// - count (Integer, 可选): 选择的个数，默认为1，不能超过数组长度
+    ///
+    /// # 返回
+    /// 选中元素组成的数组
+    ///
+    /// # 示例
+    /// {"math.sample": ["@var.names", 3], "output": "winners"}
+    #[jilang_fn]
+    fn sample(args: &[Value], context: &mut Context) -> Value {
+        trace::capture(TraceKind::Random, "sample", || {
+            let items = match Self::get_array(args.first(), context) {
+                Some(items) => items,
+                None => {
+                    eprintln!("错误: math.sample 的第一个参数必须是数组");
+                    return Value::Array(Vec::new());
+                }
+            };
+            let count = match args.get(1).map(|v| Self::get_number(v, context)) {
+                Some(Ok(count)) if count >= 0.0 => count as usize,
+                Some(Ok(_)) => {
+                    eprintln!("错误: math.sample 的数量不能为负数");
+                    return Value::Array(Vec::new());
+                }
+                Some(Err(err)) => {
+                    eprintln!("错误: {}", err);
+                    return Value::Array(Vec::new());
+                }
+                None => 1,
+            };
+            if count > items.len() {
+                eprintln!("错误: math.sample 的数量 {} 超过了数组长度 {}", count, items.len());
+                return Value::Array(Vec::new());
+            }
+            let indices = context.with_rng(|rng| rand::seq::index::sample(rng, items.len(), count).into_vec());
+            Value::Array(indices.into_iter().map(|i| items[i].clone()).collect())
+        })
+    }
+    
+    /// 正态分布随机数，使用Box-Muller变换
+    ///
+    /// # 参数
+    /// - mean (Number, 可选): 均值，默认为0
+    /// - stddev (Number, 可选): 标准差，默认为1
+    ///
+    /// # 返回
+    /// 随机数
+    ///
+    /// # 示例
+    /// {"math.gaussian": [170, 8], "output": "height"}
+    #[jilang_fn]
+    fn gaussian(args: &[Value], context: &mut Context) -> Value {
+        trace::capture(TraceKind::Random, "gaussian", || {
+            let mean = match args.first().map(|v| Self::get_number(v, context)) {
+                Some(Ok(mean)) => mean,
+                Some(Err(err)) => {
+                    eprintln!("错误: {}", err);
+                    return Value::Null;
+                }
+                None => 0.0,
+            };
+            let stddev = match args.get(1).map(|v| Self::get_number(v, context)) {
+                Some(Ok(stddev)) if stddev >= 0.0 => stddev,
+                Some(Ok(_)) => {
+                    eprintln!("错误: 标准差不能为负数");
+                    return Value::Null;
+                }
+                Some(Err(err)) => {
+                    eprintln!("错误: {}", err);
+                    return Value::Null;
+                }
+                None => 1.0,
+            };
+            // u1取(0, 1]，避免ln(0)
+            let (u1, u2) = context.with_rng(|rng| (1.0 - rng.gen::<f64>(), rng.gen::<f64>()));
+            let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+            Value::Number(serde_json::Number::from_f64(mean + z * stddev).unwrap_or(serde_json::Number::from_f64(0.0).unwrap()))
+        })
+    }
+    
+    /// 正态分布随机数，与 gaussian 相同
+    ///
+    /// # 参数
+    /// - mean (Number, 可选): 均值，默认为0
+    /// - stddev (Number, 可选): 标准差，默认为1
+    ///
+    /// # 返回
+    /// 随机数
+    ///
+    /// # 示例
+    /// {"math.normal": [0, 1], "output": "z"}
+    #[jilang_fn]
+    fn normal(args: &[Value], context: &mut Context) -> Value {
+        Self::gaussian(args, context)
+    }
+    
+    /// 求和，元素都是整数或十进制小数时结果是精确的
+    ///
+    /// # 参数
+    /// - numbers (Array): 数字数组，或直接传入多个数字
+    ///
+    /// # 返回
+    /// 和
+    ///
+    /// # 示例
+    /// {"math.sum": [[1, 2, 3]], "output": "total"}
+    #[jilang_fn]
+    fn sum(args: &[Value], context: &mut Context) -> Value {
+        math_stats::sum(args, context)
+    }
+    
+    /// 平均值
+    ///
+    /// # 参数
+    /// - numbers (Array): 数字数组，或直接传入多个数字
+    ///
+    /// # 返回
+    /// 平均值
+    ///
+    /// # 示例
+    /// {"math.mean": ["@var.scores"], "output": "average"}
+    #[jilang_fn]
+    fn mean(args: &[Value], context: &mut Context) -> Value {
+        math_stats::mean(args, context)
+    }
+    
+    /// 中位数，元素个数为偶数时取中间两个数的平均值
+    ///
+    /// # 参数
+    /// - numbers (Array): 数字数组，或直接传入多个数字
+    ///
+    /// # 返回
+    /// 中位数
+    ///
+    /// # 示例
+    /// {"math.median": [[3, 1, 4, 1, 5]], "output": "middle"}
+    #[jilang_fn]
+    fn median(args: &[Value], context: &mut Context) -> Value {
+        math_stats::median(args, context)
+    }
+    
+    /// 众数，出现次数相同时取最小的数
+    ///
+    /// # 参数
+    /// - numbers (Array): 数字数组，或直接传入多个数字
+    ///
+    /// # 返回
+    /// 众数
+    ///
+    /// # 示例
+    /// {"math.mode": [[1, 2, 2, 3]], "output": "most"}
+    #[jilang_fn]
+    fn mode(args: &[Value], context: &mut Context) -> Value {
+        math_stats::mode(args, context)
+    }
+    
+    /// 方差，默认为总体方差
+    ///
+    /// # 参数
+    /// - numbers (Array): 数字数组，或直接传入多个数字
+    /// - kind (String, 可选): 第一个参数是数组时，"sample" 或 true 计算样本方差，"population" 或 false 计算总体方差
+    ///
+    /// # 返回
+    /// 方差
+    ///
+    /// # 示例
+    /// {"math.variance": ["@var.data", "sample"], "output": "var"}
+    #[jilang_fn]
+    fn variance(args: &[Value], context: &mut Context) -> Value {
+        math_stats::variance(args, context)
+    }
+    
+    /// 标准差，默认为总体标准差
+    ///
+    /// # 参数
+    /// - numbers (Array): 数字数组，或直接传入多个数字
+    /// - kind (String, 可选): 第一个参数是数组时，"sample" 或 true 计算样本标准差，"population" 或 false 计算总体标准差
+    ///
+    /// # 返回
+    /// 标准差
+    ///
+    /// # 示例
+    /// {"math.stddev": ["@var.data"], "output": "sd"}
+    #[jilang_fn]
+    fn stddev(args: &[Value], context: &mut Context) -> Value {
+        math_stats::stddev(args, context)
+    }
+    
+    /// 百分位数，在相邻的两个数之间线性插值
+    ///
+    /// # 参数
+    /// - numbers (Array): 数字数组
+    /// - percent (Number): 百分比，0到100之间
+    ///
+    /// # 返回
+    /// 百分位数
+    ///
+    /// # 示例
+    /// {"math.percentile": ["@var.latencies", 95], "output": "p95"}
+    #[jilang_fn]
+    fn percentile(args: &[Value], context: &mut Context) -> Value {
+        math_stats::percentile(args, context)
+    }
+    
+    /// 直方图。每组包含起点不包含终点，最后一组包含终点
+    ///
+    /// # 参数
+    /// - numbers (Array): 数字数组
+    /// - bins (Integer, 可选): 分组数，默认为10；也可以是严格递增的分组边界数组
+    ///
+    /// # 返回
+    /// [{"start", "end", "count"}] 形式的数组
+    ///
+    /// # 示例
+    /// {"math.histogram": ["@var.ages", [0, 18, 60, 120]], "output": "groups"}
+    #[jilang_fn]
+    fn histogram(args: &[Value], context: &mut Context) -> Value {
+        math_stats::histogram(args, context)
+    }
+    
+    /// 向量点积
+    ///
+    /// # 参数
+    /// - a (Array): 向量
+    /// - b (Array): 长度相同的向量
+    ///
+    /// # 返回
+    /// 点积
+    ///
+    /// # 示例
+    /// {"math.dot": [[1, 2, 3], [4, 5, 6]], "output": "result"}
+    #[jilang_fn]
+    fn dot(args: &[Value], context: &mut Context) -> Value {
+        math_linalg::dot(args, context)
+    }
+    
+    /// 矩阵转置，元素保持原样
+    ///
+    /// # 参数
+    /// - matrix (Array): 矩阵（行数组）
+    ///
+    /// # 返回
+    /// 转置后的矩阵
+    ///
+    /// # 示例
+    /// {"math.transpose": [[[1, 2], [3, 4]]], "output": "t"}
+    #[jilang_fn]
+    fn transpose(args: &[Value], context: &mut Context) -> Value {
+        math_linalg::transpose(args, context)
+    }
+    
+    /// 矩阵乘法，第二个参数是向量时结果也是向量
+    ///
+    /// # 参数
+    /// - a (Array): 矩阵
+    /// - b (Array): 矩阵或向量
+    ///
+    /// # 返回
+    /// 乘积矩阵或向量
+    ///
+    /// # 示例
+    /// {"math.matrix_multiply": [[[1, 2], [3, 4]], [5, 6]], "output": "result"}
+    #[jilang_fn]
+    fn matrix_multiply(args: &[Value], context: &mut Context) -> Value {
+        math_linalg::matrix_multiply(args, context)
+    }
+    
+    /// 方阵的行列式
+    ///
+    /// # 参数
+    /// - matrix (Array): 方阵
+    ///
+    /// # 返回
+    /// 行列式
+    ///
+    /// # 示例
+    /// {"math.determinant": [[[1, 2], [3, 4]]], "output": "det"}
+    #[jilang_fn]
+    fn determinant(args: &[Value], context: &mut Context) -> Value {
+        math_linalg::determinant(args, context)
+    }
+    
+    /// 方阵的逆矩阵，矩阵奇异时报错
+    ///
+    /// # 参数
+    /// - matrix (Array): 方阵
+    ///
+    /// # 返回
+    /// 逆矩阵
+    ///
+    /// # 示例
+    /// {"math.inverse": [[[4, 7], [2, 6]]], "output": "inv"}
+    #[jilang_fn]
+    fn inverse(args: &[Value], context: &mut Context) -> Value {
+        math_linalg::inverse(args, context)
+    }
+    
+    /// 解线性方程组 A·x = b
+    ///
+    /// # 参数
+    /// - a (Array): 系数方阵A
+    /// - b (Array): 常数向量b
+    ///
+    /// # 返回
+    /// 解向量x
+    ///
+    /// # 示例
+    /// {"math.solve": [[[2, 1], [1, 3]], [3, 5]], "output": "x"}
+    #[jilang_fn]
+    fn solve(args: &[Value], context: &mut Context) -> Value {
+        math_linalg::solve(args, context)
+    }
+}
@@ -0,0 +1,195 @@
+// 数学模块的向量和矩阵运算
+//
+// 向量是数字数组，矩阵是行数组（每行元素个数相同）。元素按 MathModule::get_number
+// 的规则转换为数字。行列式、求逆和解方程使用部分主元的高斯消元法。
+use serde_json::Value;
+use crate::interpreter::context::Context;
+use super::math::MathModule;
+use super::math_stats::numbers;
+
+// 主元绝对值小于该值时认为矩阵是奇异的
+const SINGULAR_EPSILON: f64 = 1e-12;
+
+type Matrix = Vec<Vec<f64>>;
+
+fn report(result: Result<Value, String>) -> Value {
+    result.unwrap_or_else(|err| {
+        eprintln!("错误: {}", err);
+        Value::Null
+    })
+}
+
+fn number_value(value: f64) -> Value {
+    MathModule::integral_value(value)
+}
+
+fn vector_value(values: &[f64]) -> Value {
+    Value::Array(values.iter().map(|v| number_value(*v)).collect())
+}
+
+fn matrix_value(matrix: &Matrix) -> Value {
+    Value::Array(matrix.iter().map(|row| vector_value(row)).collect())
+}
+
+fn array_arg(function: &str, args: &[Value], index: usize, context: &Context) -> Result<Vec<Value>, String> {
+    match args.get(index).map(|arg| MathModule::resolve_arg(arg, context)) {
+        Some(Value::Array(items)) => Ok(items),
+        Some(other) => Err(format!("math.{} 的第 {} 个参数必须是数组，实际为 {}", function, index + 1, other)),
+        None => Err(format!("math.{} 缺少第 {} 个参数", function, index + 1)),
+    }
+}
+
+fn vector(function: &str, args: &[Value], index: usize, context: &Context) -> Result<Vec<f64>, String> {
+    numbers(function, &array_arg(function, args, index, context)?, context)
+}
+
+// 检查矩阵是否为非空的矩形
+fn rows(function: &str, items: Vec<Value>, context: &Context) -> Result<Vec<Vec<Value>>, String> {
+    let rows: Vec<Vec<Value>> = items.into_iter().enumerate()
+        .map(|(i, row)| match MathModule::resolve_arg(&row, context) {
+            Value::Array(row) => Ok(row),
+            other => Err(format!("math.{} 的矩阵第 {} 行不是数组: {}", function, i + 1, other)),
+        })
+        .collect::<Result<_, _>>()?;
+    let columns = rows.first().map(|row| row.len()).unwrap_or(0);
+    if columns == 0 {
+        return Err(format!("math.{} 的矩阵不能为空", function));
+    }
+    if let Some(i) = rows.iter().position(|row| row.len() != columns) {
+        return Err(format!("math.{} 的矩阵第 {} 行有 {} 个元素，第 1 行有 {} 个", function, i + 1, rows[i].len(), columns));
+    }
+    Ok(rows)
+}
+
+fn matrix(function: &str, args: &[Value], index: usize, context: &Context) -> Result<Matrix, String> {
+    rows(function, array_arg(function, args, index, context)?, context)?
+        .iter().enumerate()
+        .map(|(i, row)| numbers(function, row, context).map_err(|e| format!("{}（第 {} 行）", e, i + 1)))
+        .collect()
+}
+
+fn square_matrix(function: &str, args: &[Value], context: &Context) -> Result<Matrix, String> {
+    let matrix = matrix(function, args, 0, context)?;
+    if matrix.len() != matrix[0].len() {
+        return Err(format!("math.{} 需要方阵，实际为 {}×{} 矩阵", function, matrix.len(), matrix[0].len()));
+    }
+    Ok(matrix)
+}
+
+// 对增广矩阵 [A | B] 做高斯-约当消元，把A化为单位矩阵；返回A的行列式，A奇异时返回None
+fn gauss_jordan(a: &mut Matrix, b: &mut Matrix) -> Option<f64> {
+    let n = a.len();
+    let mut determinant = 1.0;
+    for col in 0..n {
+        let pivot = (col..n).max_by(|i, j| a[*i][col].abs().total_cmp(&a[*j][col].abs()))?;
+        if a[pivot][col].abs() < SINGULAR_EPSILON {
+            return None;
+        }
+        if pivot != col {
+            a.swap(pivot, col);
+            b.swap(pivot, col);
+            determinant = -determinant;
+        }
+        let factor = a[col][col];
+        determinant *= factor;
+        a[col].iter_mut().for_each(|v| *v /= factor);
+        b[col].iter_mut().for_each(|v| *v /= factor);
+        let (pivot_a, pivot_b) = (a[col].clone(), b[col].clone());
+        for row in 0..n {
+            let scale = a[row][col];
+            if row == col || scale == 0.0 {
+                continue;
+            }
+            a[row].iter_mut().zip(&pivot_a).for_each(|(v, p)| *v -= scale * p);
+            b[row].iter_mut().zip(&pivot_b).for_each(|(v, p)| *v -= scale * p);
+        }
+    }
+    Some(determinant)
+}
+
+/// 向量点积：[向量, 向量]
+pub fn dot(args: &[Value], context: &mut Context) -> Value {
+    let result = (|| {
+        let (a, b) = (vector("dot", args, 0, context)?, vector("dot", args, 1, context)?);
+        if a.len() != b.len() {
+            return Err(format!("math.dot 的两个向量长度不同: {} 和 {}", a.len(), b.len()));
+        }
+        Ok(number_value(a.iter().zip(&b).map(|(x, y)| x * y).sum()))
+    })();
+    report(result)
+}
+
+/// 矩阵转置，元素保持原样
+pub fn transpose(args: &[Value], context: &mut Context) -> Value {
+    let result = (|| {
+        let rows = rows("transpose", array_arg("transpose", args, 0, context)?, context)?;
+        Ok(Value::Array((0..rows[0].len())
+            .map(|col| Value::Array(rows.iter().map(|row| row[col].clone()).collect()))
+            .collect()))
+    })();
+    report(result)
+}
+
+/// 矩阵乘法：[矩阵, 矩阵或向量]，第二个参数是向量时结果也是向量
+pub fn matrix_multiply(args: &[Value], context: &mut Context) -> Value {
+    let result = (|| {
+        let a = matrix("matrix_multiply", args, 0, context)?;
+        let second = array_arg("matrix_multiply", args, 1, context)?;
+        let is_vector = second.iter().all(|item| !MathModule::resolve_arg(item, context).is_array());
+        let b: Matrix = if is_vector {
+            numbers("matrix_multiply", &second, context)?.into_iter().map(|v| vec![v]).collect()
+        } else {
+            matrix("matrix_multiply", args, 1, context)?
+        };
+        if a[0].len() != b.len() {
+            return Err(format!("math.matrix_multiply 的矩阵尺寸不匹配: {}×{} 和 {}×{}",
+                a.len(), a[0].len(), b.len(), b.first().map(|row| row.len()).unwrap_or(0)));
+        }
+        let product: Matrix = a.iter()
+            .map(|row| (0..b[0].len()).map(|col| row.iter().zip(&b).map(|(x, b_row)| x * b_row[col]).sum()).collect())
+            .collect();
+        Ok(if is_vector {
+            vector_value(&product.iter().map(|row| row[0]).collect::<Vec<_>>())
+        } else {
+            matrix_value(&product)
+        })
+    })();
+    report(result)
+}
+
+/// 方阵的行列式
+pub fn determinant(args: &[Value], context: &mut Context) -> Value {
+    let result = (|| {
+        let mut a = square_matrix("determinant", args, context)?;
+        let mut b = vec![Vec::new(); a.len()];
+        Ok(number_value(gauss_jordan(&mut a, &mut b).unwrap_or(0.0)))
+    })();
+    report(result)
+}
+
+/// 方阵的逆矩阵，矩阵奇异时报错
+pub fn inverse(args: &[Value], context: &mut Context) -> Value {
+    let result = (|| {
+        let mut a = square_matrix("inverse", args, context)?;
+        let n = a.len();
+        let mut b: Matrix = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+        gauss_jordan(&mut a, &mut b).ok_or_else(|| "math.inverse 的矩阵是奇异的，没有逆矩阵".to_string())?;
+        Ok(matrix_value(&b))
+    })();
+    report(result)
+}
+
+/// 解线性方程组 A·x = b：[A, b]，返回向量x
+pub fn solve(args: &[Value], context: &mut Context) -> Value {
+    let result = (|| {
+        let mut a = square_matrix("solve", args, context)?;
+        let b = vector("solve", args, 1, context)?;
+        if b.len() != a.len() {
+            return Err(format!("math.solve 的向量长度 {} 与矩阵的行数 {} 不同", b.len(), a.len()));
+        }
+        let mut b: Matrix = b.into_iter().map(|v| vec![v]).collect();
+        gauss_jordan(&mut a, &mut b).ok_or_else(|| "math.solve 的系数矩阵是奇异的，方程组没有唯一解".to_string())?;
+        Ok(vector_value(&b.iter().map(|row| row[0]).collect::<Vec<_>>()))
+    })();
+    report(result)
+}
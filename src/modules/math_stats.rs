@@ -0,0 +1,204 @@
+// 数学模块的统计函数
+//
+// sum、mean、median、mode、variance和stddev的第一个参数可以是数组（或引用数组的变量），
+// 也可以直接传入多个数字；variance和stddev只在第一个参数是数组时才读取第二个参数的方差类型。
+// percentile和histogram还需要百分比或分组参数，第一个参数必须是数组。
+// 元素按 MathModule::get_number 的规则转换为数字，无法转换时报告元素的位置。
+use serde_json::{json, Value};
+use crate::interpreter::context::Context;
+use super::bignum::Exact;
+use super::math::MathModule;
+
+// 取得参与统计的元素：第一个参数是数组时使用该数组，否则使用全部参数
+fn elements(args: &[Value], context: &Context) -> Vec<Value> {
+    match args.first().map(|arg| MathModule::resolve_arg(arg, context)) {
+        Some(Value::Array(items)) => items,
+        _ => args.to_vec(),
+    }
+}
+
+// 将元素转换为数字，错误信息包含函数名和元素位置
+pub(super) fn numbers(function: &str, items: &[Value], context: &Context) -> Result<Vec<f64>, String> {
+    items.iter().enumerate()
+        .map(|(i, item)| MathModule::get_number(item, context)
+            .map_err(|e| format!("math.{} 的第 {} 个元素 {} 不是数字: {}", function, i + 1, item, e.message())))
+        .collect()
+}
+
+// 取得非空的数字列表
+fn non_empty_numbers(function: &str, args: &[Value], context: &Context) -> Result<Vec<f64>, String> {
+    let values = numbers(function, &elements(args, context), context)?;
+    if values.is_empty() {
+        return Err(format!("math.{} 需要至少一个数字", function));
+    }
+    Ok(values)
+}
+
+// 统一处理错误：打印错误信息并返回null
+fn report(result: Result<Value, String>) -> Value {
+    result.unwrap_or_else(|err| {
+        eprintln!("错误: {}", err);
+        Value::Null
+    })
+}
+
+fn sorted(mut values: Vec<f64>) -> Vec<f64> {
+    values.sort_by(|a, b| a.total_cmp(b));
+    values
+}
+
+fn mean_of(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// 第一个参数是数组且第二个参数为 true 或 "sample" 时计算样本方差（除以n-1），否则计算总体方差；
+// 直接传入多个数字时所有参数都是元素
+fn variance_of(function: &str, args: &[Value], context: &Context) -> Result<f64, String> {
+    let values = non_empty_numbers(function, args, context)?;
+    let is_array = matches!(args.first().map(|arg| MathModule::resolve_arg(arg, context)), Some(Value::Array(_)));
+    let kind = if is_array { args.get(1) } else { None };
+    let sample = match kind.map(|arg| MathModule::resolve_arg(arg, context)) {
+        Some(Value::Bool(sample)) => sample,
+        Some(Value::String(kind)) if kind == "sample" => true,
+        Some(Value::String(kind)) if kind == "population" => false,
+        None | Some(Value::Null) => false,
+        Some(other) => return Err(format!("math.{} 的第二个参数必须是 \"sample\"、\"population\" 或布尔值，实际为 {}", function, other)),
+    };
+    if sample && values.len() < 2 {
+        return Err(format!("math.{} 计算样本方差需要至少两个数字", function));
+    }
+    let mean = mean_of(&values);
+    let squares: f64 = values.iter().map(|v| (v - mean).powi(2)).sum();
+    Ok(squares / (values.len() - usize::from(sample)) as f64)
+}
+
+/// 求和，元素都是整数或十进制小数时结果是精确的
+pub fn sum(args: &[Value], context: &mut Context) -> Value {
+    let items = elements(args, context);
+    if let Some(operands) = MathModule::exact_operands(&items, context) {
+        return operands.iter().fold(Exact::Int(0), |acc, x| acc.add(x)).to_value();
+    }
+    report(numbers("sum", &items, context).map(|values| MathModule::integral_value(values.iter().sum())))
+}
+
+/// 平均值
+pub fn mean(args: &[Value], context: &mut Context) -> Value {
+    report(non_empty_numbers("mean", args, context).map(|values| MathModule::integral_value(mean_of(&values))))
+}
+
+/// 中位数，元素个数为偶数时取中间两个数的平均值
+pub fn median(args: &[Value], context: &mut Context) -> Value {
+    report(non_empty_numbers("median", args, context).map(|values| {
+        let values = sorted(values);
+        let middle = values.len() / 2;
+        let median = if values.len().is_multiple_of(2) { (values[middle - 1] + values[middle]) / 2.0 } else { values[middle] };
+        MathModule::integral_value(median)
+    }))
+}
+
+/// 众数，出现次数相同时取最小的数
+pub fn mode(args: &[Value], context: &mut Context) -> Value {
+    report(non_empty_numbers("mode", args, context).map(|values| {
+        let values = sorted(values);
+        let (mut best, mut best_count) = (values[0], 0);
+        let mut start = 0;
+        while start < values.len() {
+            let end = start + values[start..].iter().take_while(|v| **v == values[start]).count();
+            if end - start > best_count {
+                best = values[start];
+                best_count = end - start;
+            }
+            start = end;
+        }
+        MathModule::integral_value(best)
+    }))
+}
+
+/// 方差：[数组, "sample"|"population"]，默认为总体方差
+pub fn variance(args: &[Value], context: &mut Context) -> Value {
+    report(variance_of("variance", args, context).map(MathModule::integral_value))
+}
+
+/// 标准差：[数组, "sample"|"population"]，默认为总体标准差
+pub fn stddev(args: &[Value], context: &mut Context) -> Value {
+    report(variance_of("stddev", args, context).map(|variance| MathModule::integral_value(variance.sqrt())))
+}
+
+/// 百分位数：[数组, 百分比(0-100)]，在相邻的两个数之间线性插值
+pub fn percentile(args: &[Value], context: &mut Context) -> Value {
+    let result = (|| {
+        let array = match args.first().map(|arg| MathModule::resolve_arg(arg, context)) {
+            Some(Value::Array(items)) => items,
+            _ => return Err("math.percentile 的第一个参数必须是数组".to_string()),
+        };
+        let values = sorted(numbers("percentile", &array, context)?);
+        if values.is_empty() {
+            return Err("math.percentile 需要至少一个数字".to_string());
+        }
+        let p = match args.get(1).map(|arg| MathModule::get_number(arg, context)) {
+            Some(Ok(p)) if (0.0..=100.0).contains(&p) => p,
+            Some(Ok(p)) => return Err(format!("math.percentile 的百分比必须在0到100之间，实际为 {}", p)),
+            Some(Err(e)) => return Err(format!("math.percentile 的百分比不是数字: {}", e.message())),
+            None => return Err("math.percentile 需要提供百分比".to_string()),
+        };
+        let rank = p / 100.0 * (values.len() - 1) as f64;
+        let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+        Ok(MathModule::integral_value(values[lower] + (values[upper] - values[lower]) * (rank - lower as f64)))
+    })();
+    report(result)
+}
+
+/// 直方图：[数组, 分组数或分组边界数组]，默认分为10组。
+/// 返回 [{"start", "end", "count"}]，每组包含起点不包含终点，最后一组包含终点
+pub fn histogram(args: &[Value], context: &mut Context) -> Value {
+    let result = (|| {
+        let array = match args.first().map(|arg| MathModule::resolve_arg(arg, context)) {
+            Some(Value::Array(items)) => items,
+            _ => return Err("math.histogram 的第一个参数必须是数组".to_string()),
+        };
+        let values = numbers("histogram", &array, context)?;
+        let edges = match args.get(1).map(|arg| MathModule::resolve_arg(arg, context)) {
+            Some(Value::Array(edges)) => {
+                let edges = numbers("histogram", &edges, context)?;
+                if edges.len() < 2 || edges.windows(2).any(|w| w[0] >= w[1]) {
+                    return Err("math.histogram 的分组边界必须至少有两个且严格递增".to_string());
+                }
+                edges
+            },
+            bins => {
+                let bins = match bins {
+                    None | Some(Value::Null) => 10,
+                    Some(bins) => match MathModule::get_number(&bins, context) {
+                        Ok(n) if n >= 1.0 && n.fract() == 0.0 => n as usize,
+                        _ => return Err(format!("math.histogram 的分组数必须是正整数，实际为 {}", bins)),
+                    },
+                };
+                if values.is_empty() {
+                    return Err("math.histogram 需要至少一个数字".to_string());
+                }
+                let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                // 所有数都相同时使用宽度为1的分组
+                let width = if max > min { (max - min) / bins as f64 } else { 1.0 };
+                (0..=bins).map(|i| if i == bins && max > min { max } else { min + width * i as f64 }).collect()
+            },
+        };
+        let last = edges.len() - 2;
+        let mut counts = vec![0u64; edges.len() - 1];
+        for value in values {
+            let bin = edges.windows(2).position(|w| value >= w[0] && value < w[1])
+                .or_else(|| (value == edges[last + 1]).then_some(last));
+            if let Some(bin) = bin {
+                counts[bin] += 1;
+            }
+        }
+        Ok(Value::Array(counts.iter().enumerate()
+            .map(|(i, count)| json!({
+                "start": MathModule::integral_value(edges[i]),
+                "end": MathModule::integral_value(edges[i + 1]),
+                "count": count,
+            }))
+            .collect()))
+    })();
+    report(result)
+}
@@ -1,15 +1,35 @@
 pub mod io;
 pub mod math;
+pub mod bignum;
+pub mod math_stats;
+pub mod math_linalg;
 pub mod jl_module;
 pub mod external_module;
+pub mod sdk;
+pub mod module_cache;
 pub mod lua_module;
+pub mod lua_doc;
+pub mod wasm_wasi;
+pub mod wasm_module;
+#[cfg(unix)]
+pub mod native_module;
+pub mod plugin_module;
 pub mod http;
+pub mod http_cassette;
 
 use serde_json::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::sync::Once;
 use crate::interpreter::context::Context;
-use external_module::{ModuleRegistry, JLangModuleLoader, ExternalModuleOptions};
+use crate::interpreter::error::Result;
+use external_module::{ModuleRegistry, JLangModuleLoader, ExternalModule, ExternalModuleOptions, ModuleMetadata};
+use sdk::ModuleInfo;
 use lua_module::LuaModuleLoader;
+use wasm_module::WasmModuleLoader;
+#[cfg(unix)]
+use native_module::NativeModuleLoader;
+use plugin_module::PluginModuleLoader;
 
 pub trait Module: std::any::Any {
     fn get_name(&self) -> &'static str;
@@ -17,6 +37,9 @@ pub trait Module: std::any::Any {
     fn as_any(&self) -> &dyn std::any::Any;
 }
 
+/// 加载后的模块。同一个模块文件的所有include（包括JL模块中的嵌套include）共用一个实例，见module_cache
+pub type SharedModule = Rc<RefCell<Box<dyn Module>>>;
+
 // 全局模块注册表实例
 static mut MODULE_REGISTRY: Option<ModuleRegistry> = None;
 static REGISTRY_INIT: Once = Once::new();
@@ -28,6 +51,10 @@ fn init_registry() {
             let mut registry = external_module::create_default_registry();
             registry.register_loader(Box::new(JLangModuleLoader));
             registry.register_loader(Box::new(LuaModuleLoader));
+            registry.register_loader(Box::new(WasmModuleLoader));
+            #[cfg(unix)]
+            registry.register_loader(Box::new(NativeModuleLoader));
+            registry.register_loader(Box::new(PluginModuleLoader));
             
             if crate::is_debug_mode() {
                 // 在移动registry前获取名称
@@ -57,18 +84,43 @@ pub fn get_registry_mut() -> &'static mut ModuleRegistry {
     }
 }
 
-pub fn get_module(name: &str) -> Option<Box<dyn Module>> {
+/// include中的一项：模块名字符串，或带选项的 `{"module": 名称, "options": {...}}`
+pub fn include_name(entry: &Value) -> Option<&str> {
+    match entry {
+        Value::String(name) => Some(name),
+        Value::Object(obj) => obj.get("module").and_then(|m| m.as_str()),
+        _ => None,
+    }
+}
+
+/// 内置模块的名称
+pub const BUILTIN_MODULES: &[&str] = &["io", "math", "http"];
+
+/// 内置模块的元数据，由 #[jilang_module] 根据文档注释生成；不是内置模块时返回None
+pub fn builtin_metadata(name: &str) -> Option<ModuleMetadata> {
+    match name {
+        "io" => Some(io::IoModule::module_metadata()),
+        "math" => Some(math::MathModule::module_metadata()),
+        "http" => Some(http::HttpModule::module_metadata()),
+        _ => None,
+    }
+}
+
+/// 加载模块，外部模块使用指定的选项（内置模块忽略选项）
+///
+/// 找不到模块时返回Ok(None)；找到了模块文件但加载失败（例如ABI版本不匹配、沙箱拒绝加载）时返回加载器的错误。
+/// 模块文件已经加载过时返回已加载的实例，这次指定的选项与实例的选项不同时给出警告
+pub fn get_module_with_options(name: &str, options: Option<ExternalModuleOptions>) -> Result<Option<SharedModule>> {
     if crate::is_debug_mode() {
         println!("尝试加载模块: {}", name);
     }
     
     // 检查是否存在同名外部模块冲突
-    let is_builtin = name == "io" || name == "math" || name == "http";
+    let is_builtin = BUILTIN_MODULES.contains(&name);
     let external_module_result = get_registry().check_module_exists(name);
     
     // 如果是内置模块且存在同名外部模块，发出警告
-    if is_builtin && external_module_result.is_some() {
-        let (path, module_type) = external_module_result.unwrap();
+    if let (true, Some((path, _))) = (is_builtin, &external_module_result) {
         eprintln!("警告: 发现同名模块冲突！");
         eprintln!("内置模块 '{}' 将被优先加载，忽略外部模块文件: {}", name, path);
         eprintln!("如需使用外部模块，请将其重命名为不同的名称。");
@@ -76,34 +128,77 @@ pub fn get_module(name: &str) -> Option<Box<dyn Module>> {
     
     // 首先尝试获取内置模块
     match name {
-        "io" => Some(Box::new(io::IoModule::new())),
-        "math" => Some(Box::new(math::MathModule::new())),
-        "http" => Some(Box::new(http::HttpModule::new())),
+        "io" => Ok(Some(Rc::new(RefCell::new(Box::new(io::IoModule::new()))))),
+        "math" => Ok(Some(Rc::new(RefCell::new(Box::new(math::MathModule::new()))))),
+        "http" => Ok(Some(Rc::new(RefCell::new(Box::new(http::HttpModule::new()))))),
         _ => {
-            // 检查是否存在多种类型的同名外部模块
-            if let Some(conflict) = get_registry().check_module_conflicts(name) {
-                eprintln!("警告: 发现同名外部模块冲突！");
-                for (path, module_type) in conflict {
-                    eprintln!("- {} ({})", path, module_type);
+            let Some((path, _)) = external_module_result else {
+                return Ok(None);
+            };
+            let requested = options.clone();
+            let (module, loaded) = module_cache::instance(&path, || load_external_module(name, options))?;
+            if let (false, Some(requested)) = (loaded, requested) {
+                let mut shared = module.borrow_mut();
+                if as_external_mut(shared.as_mut()).is_some_and(|m| *m.get_options() != requested) {
+                    eprintln!("警告: 模块 '{}' 已经加载，忽略这次include指定的选项", name);
                 }
-                eprintln!("将加载第一个找到的模块。如需明确指定模块，请重命名模块文件。");
             }
-            
-            // 尝试使用统一的外部模块系统加载
-            let result = get_registry().load_module(name, None);
-            match result {
-                Ok(module) => {
-                    if crate::is_debug_mode() {
-                        println!("成功加载外部模块: {}", name);
-                    }
-                    Some(module as Box<dyn Module>)
-                },
-                Err(e) => {
-                    if crate::is_debug_mode() {
-                        println!("加载模块 '{}' 失败: {}", name, e);
-                    }
-                    None
-                }
+            Ok(Some(module))
+        }
+    }
+}
+
+// 从模块文件加载外部模块并初始化
+fn load_external_module(name: &str, options: Option<ExternalModuleOptions>) -> Result<Box<dyn Module>> {
+    // 检查是否存在多种类型的同名外部模块
+    if let Some(conflict) = get_registry().check_module_conflicts(name) {
+        eprintln!("警告: 发现同名外部模块冲突！");
+        for (path, module_type) in conflict {
+            eprintln!("- {} ({})", path, module_type);
+        }
+        eprintln!("将加载第一个找到的模块。如需明确指定模块，请重命名模块文件。");
+    }
+    
+    // 尝试使用统一的外部模块系统加载
+    // 加载后初始化模块（例如创建Lua状态）
+    let result = get_registry().load_module(name, options)
+        .and_then(|mut module| module.initialize().map(|_| module));
+    let module = result?;
+    if crate::is_debug_mode() {
+        println!("成功加载外部模块: {}", name);
+    }
+    Ok(module as Box<dyn Module>)
+}
+
+/// 取得外部模块的 ExternalModule 接口，内置模块返回None
+pub fn as_external_mut(module: &mut dyn Module) -> Option<&mut dyn ExternalModule> {
+    let any: &mut dyn std::any::Any = module;
+    if any.is::<lua_module::LuaModule>() {
+        any.downcast_mut::<lua_module::LuaModule>().map(|m| m as &mut dyn ExternalModule)
+    } else if any.is::<external_module::JLangExternalModule>() {
+        any.downcast_mut::<external_module::JLangExternalModule>().map(|m| m as &mut dyn ExternalModule)
+    } else if any.is::<wasm_module::WasmModule>() {
+        any.downcast_mut::<wasm_module::WasmModule>().map(|m| m as &mut dyn ExternalModule)
+    } else if any.is::<plugin_module::PluginModule>() {
+        any.downcast_mut::<plugin_module::PluginModule>().map(|m| m as &mut dyn ExternalModule)
+    } else {
+        #[cfg(unix)]
+        if any.is::<native_module::NativeModule>() {
+            return any.downcast_mut::<native_module::NativeModule>().map(|m| m as &mut dyn ExternalModule);
+        }
+        None
+    }
+}
+
+/// 关闭外部模块（例如通知插件进程退出）。std::process::exit不会运行析构函数，退出前需要显式调用
+pub fn destroy_modules<'a>(modules: impl IntoIterator<Item = &'a SharedModule>) {
+    for module in modules {
+        let Ok(mut module) = module.try_borrow_mut() else {
+            continue;
+        };
+        if let Some(module) = as_external_mut(module.as_mut()) {
+            if let Err(e) = module.destroy() {
+                eprintln!("警告: 关闭模块 '{}' 失败: {}", module.get_name(), e);
             }
         }
     }
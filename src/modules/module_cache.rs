@@ -0,0 +1,218 @@
+// 模块缓存和文件监视
+//
+// 模块文件解析后的结果按规范化路径缓存，并记录文件的修改时间：同一个文件被多次include、
+// 被测试运行器或语言服务器反复加载时直接共用缓存的结果，文件修改后下次加载时重新解析。
+// 加载后的模块实例同样按规范化路径和修改时间登记：程序和JL模块的嵌套include引用同一个文件时
+// 共用一个实例（同一个Lua状态、WASM实例或插件进程），实例的选项由第一次加载时决定。
+//
+// --watch-modules 时 ModuleWatcher 记录已加载模块文件的修改时间，
+// 上下文在语句之间检查（模块函数执行期间不检查），文件变化时就地调用 ExternalModule::reload，
+// 所有引用这个实例的地方都使用重新加载后的模块。
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use crate::interpreter::error::{InterpreterError, Result};
+use super::{Module, SharedModule};
+
+// 两次检查模块文件之间的最短间隔，避免每次调用模块函数都访问文件系统
+const WATCH_INTERVAL: Duration = Duration::from_millis(300);
+
+struct Entry {
+    modified: SystemTime,
+    value: Arc<dyn Any + Send + Sync>,
+}
+
+static CACHE: Mutex<Option<HashMap<PathBuf, Entry>>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<HashMap<PathBuf, Entry>>> {
+    CACHE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// 取得模块文件的解析结果：缓存中没有或文件修改过时调用parse解析并缓存
+pub fn load<T: Any + Send + Sync>(path: &str, parse: impl FnOnce() -> Result<T>) -> Result<Arc<T>> {
+    let key = std::fs::canonicalize(path)
+        .map_err(|e| InterpreterError::ModuleError(format!("无法访问模块文件 '{}': {}", path, e)))?;
+    // 解析前取得修改时间，解析期间文件又被修改时下次加载会重新解析
+    let modified = modified_time(path)
+        .ok_or_else(|| InterpreterError::ModuleError(format!("无法读取模块文件 '{}' 的修改时间", path)))?;
+
+    let cached = lock().as_ref()
+        .and_then(|cache| cache.get(&key))
+        .filter(|entry| entry.modified == modified)
+        .and_then(|entry| entry.value.clone().downcast::<T>().ok());
+    if let Some(value) = cached {
+        if crate::is_debug_mode() {
+            println!("使用缓存的模块文件: {}", key.display());
+        }
+        return Ok(value);
+    }
+
+    // 解析时不持有锁
+    let value = Arc::new(parse()?);
+    lock().get_or_insert_with(HashMap::new)
+        .insert(key, Entry { modified, value: value.clone() });
+    Ok(value)
+}
+
+// 已登记的模块实例，不持有实例：所有引用都释放后实例随之关闭
+struct Instance {
+    modified: SystemTime,
+    module: Weak<RefCell<Box<dyn Module>>>,
+}
+
+thread_local! {
+    static INSTANCES: RefCell<HashMap<PathBuf, Instance>> = RefCell::new(HashMap::new());
+}
+
+/// 取得模块文件的实例：已经加载过并且文件没有修改时返回同一个实例，否则调用load加载并登记。
+/// 第二个返回值表示实例是否是这次新加载的
+pub fn instance(path: &str, load: impl FnOnce() -> Result<Box<dyn Module>>) -> Result<(SharedModule, bool)> {
+    let key = std::fs::canonicalize(path)
+        .map_err(|e| InterpreterError::ModuleError(format!("无法访问模块文件 '{}': {}", path, e)))?;
+    let modified = modified_time(path);
+    let existing = INSTANCES.with(|instances| {
+        instances.borrow().get(&key)
+            .filter(|instance| Some(instance.modified) == modified)
+            .and_then(|instance| instance.module.upgrade())
+    });
+    if let Some(module) = existing {
+        if crate::is_debug_mode() {
+            println!("使用已加载的模块实例: {}", key.display());
+        }
+        return Ok((module, false));
+    }
+
+    // 加载期间不借用登记表
+    let module: SharedModule = Rc::new(RefCell::new(load()?));
+    share(path, &module);
+    Ok((module, true))
+}
+
+/// 按文件当前的修改时间登记实例，重新加载模块后调用
+pub fn share(path: &str, module: &SharedModule) {
+    let (Ok(key), Some(modified)) = (std::fs::canonicalize(path), modified_time(path)) else {
+        return;
+    };
+    INSTANCES.with(|instances| {
+        let mut instances = instances.borrow_mut();
+        instances.retain(|_, instance| instance.module.strong_count() > 0);
+        instances.insert(key, Instance { modified, module: Rc::downgrade(module) });
+    });
+}
+
+/// 监视已加载模块的文件，--watch-modules 时使用
+pub struct ModuleWatcher {
+    // 模块名 -> (文件路径, 上次看到的修改时间)
+    files: HashMap<String, (String, Option<SystemTime>)>,
+    last_check: Instant,
+}
+
+impl Default for ModuleWatcher {
+    fn default() -> Self {
+        Self {
+            files: HashMap::new(),
+            last_check: Instant::now(),
+        }
+    }
+}
+
+impl ModuleWatcher {
+    pub fn watch(&mut self, name: &str, path: &str) {
+        self.files.insert(name.to_string(), (path.to_string(), modified_time(path)));
+    }
+
+    /// 返回文件修改过的模块，距离上次检查不足 WATCH_INTERVAL 时不检查
+    pub fn changed(&mut self) -> Vec<String> {
+        if self.last_check.elapsed() < WATCH_INTERVAL {
+            return Vec::new();
+        }
+        self.last_check = Instant::now();
+
+        let mut changed = Vec::new();
+        for (name, (path, modified)) in self.files.iter_mut() {
+            let current = modified_time(path);
+            // 文件暂时不存在时（编辑器先删除再写入）等到它重新出现
+            if current.is_some() && current != *modified {
+                *modified = current;
+                changed.push(name.clone());
+            }
+        }
+        changed.sort();
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // 在临时目录中写入模块文件，返回目录路径
+    fn write_files(dir_name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", dir_name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn nested_import_reuses_the_loaded_instance() {
+        let dir = write_files("jilang-nested-import", &[
+            ("counter.lua", "local M = {} function M.inc() return 1 end return M"),
+            // lib 包含 counter，也包含自己
+            ("lib.jl", r#"{"include": ["counter", "lib"], "program": {"bump": {"params": {}, "body": [{"counter.inc": []}]}}}"#),
+            ("main.jl", r#"{"include": ["counter", "lib"], "program": {"main": {"body": []}}}"#),
+        ]);
+        let main = dir.join("main.jl");
+        let loaded = crate::load_program(main.to_str().unwrap(), Vec::new()).unwrap();
+        let names: Vec<String> = loaded.modules.iter().map(|m| m.borrow().get_name().to_string()).collect();
+        assert_eq!(names, vec!["counter", "lib"]);
+
+        // 只通过lib间接包含counter时使用同一个实例
+        let only_lib = dir.join("only_lib.jl");
+        fs::write(&only_lib, r#"{"include": ["lib"], "program": {"main": {"body": []}}}"#).unwrap();
+        let nested = crate::load_program(only_lib.to_str().unwrap(), Vec::new()).unwrap();
+        assert_eq!(nested.modules.len(), 2);
+        assert!(Rc::ptr_eq(&loaded.modules[0], &nested.modules[1]));
+        assert!(Rc::ptr_eq(&loaded.modules[1], &nested.modules[0]));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn instances_are_shared_until_the_file_changes() {
+        let dir = write_files("jilang-module-instance", &[("m.lua", "return {}")]);
+        let path = dir.join("m.lua");
+        let path = path.to_str().unwrap();
+        let load = || -> Result<Box<dyn Module>> { Ok(Box::new(crate::modules::math::MathModule::new())) };
+
+        let (first, loaded) = instance(path, load).unwrap();
+        assert!(loaded);
+        let (second, loaded) = instance(path, load).unwrap();
+        assert!(!loaded);
+        assert!(Rc::ptr_eq(&first, &second));
+
+        // 文件修改后重新加载；就地重新加载的实例用share重新登记
+        let later = SystemTime::now() + Duration::from_secs(10);
+        fs::File::options().write(true).open(path).unwrap().set_modified(later).unwrap();
+        let (third, loaded) = instance(path, load).unwrap();
+        assert!(loaded);
+        assert!(!Rc::ptr_eq(&first, &third));
+        share(path, &first);
+        assert!(Rc::ptr_eq(&first, &instance(path, load).unwrap().0));
+
+        // 没有引用时实例被释放，不会被再次使用
+        drop((first, second, third));
+        assert!(instance(path, load).unwrap().1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+// 原生共享库模块
+//
+// 原生模块是导出以下C函数的共享库（.so），所有字符串都是以NUL结尾的UTF-8：
+//
+//     uint32_t jilang_abi_version(void);
+//     char *jilang_module_metadata(void);
+//     char *jilang_call(const char *function, const char *args);
+//     void jilang_free(char *ptr);
+//
+// - jilang_abi_version 返回模块编译时使用的ABI版本，必须等于 NATIVE_ABI_VERSION；
+// - jilang_module_metadata 返回ModuleMetadata的JSON（字段名与结构体相同）；
+// - jilang_call 的参数是函数名和参数数组的JSON，返回结果的JSON，返回NULL时结果为null，
+//   返回 {"$error": 消息} 时调用失败；
+// - jilang_free 释放上面两个函数返回的字符串，由模块自己的分配器分配的内存必须由模块释放。
+//
+// 原生代码不能被沙箱限制，选项中明确要求沙箱（sandbox: true）时拒绝加载。
+// 原生模块与解释器运行在同一进程中，选项中的 env_vars 在打开共享库之前设置为进程的环境变量。
+use serde_json::Value;
+use std::ffi::{c_char, c_void, CStr, CString};
+use crate::interpreter::context::Context;
+use crate::interpreter::error::{InterpreterError, Result};
+use super::external_module::{ExternalModule, ExternalModuleOptions, ExternalModuleType, FunctionMetadata, ModuleLoader, ModuleMetadata};
+use super::Module;
+
+/// 当前解释器支持的原生模块ABI版本
+pub const NATIVE_ABI_VERSION: u32 = 1;
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type MetadataFn = unsafe extern "C" fn() -> *mut c_char;
+type CallFn = unsafe extern "C" fn(*const c_char, *const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+/// 原生模块加载器
+pub struct NativeModuleLoader;
+
+impl ModuleLoader for NativeModuleLoader {
+    fn can_load(&self, path: &str) -> bool {
+        std::path::Path::new(path).exists() && path.ends_with(".so")
+    }
+
+    fn load(&self, name: &str, path: &str, options: Option<ExternalModuleOptions>) -> Result<Box<dyn ExternalModule>> {
+        if crate::is_debug_mode() {
+            println!("加载原生模块: {} 从文件: {}", name, path);
+        }
+
+        let options = options.unwrap_or_default();
+        check_options(name, &options)?;
+        let (library, metadata) = NativeLibrary::open(name, path, &options)?;
+
+        if crate::is_debug_mode() {
+            println!("成功加载原生模块: {}", name);
+            for (fname, meta) in metadata.functions.iter() {
+                println!("  - {} -> {}", fname, meta.return_type);
+            }
+        }
+
+        Ok(Box::new(NativeModule {
+            name: name.to_string(),
+            path: path.to_string(),
+            metadata,
+            options,
+            library: Some(library),
+        }))
+    }
+
+    fn get_supported_extensions(&self) -> Vec<&'static str> {
+        vec!["so"]
+    }
+
+    fn get_loader_name(&self) -> &'static str {
+        "原生模块加载器"
+    }
+}
+
+fn check_options(name: &str, options: &ExternalModuleOptions) -> Result<()> {
+    if options.sandbox == Some(true) {
+        return Err(InterpreterError::ModuleError(
+            format!("原生模块 '{}' 无法在沙箱中运行，请去掉 sandbox 选项或改用Lua/WASM模块", name)
+        ));
+    }
+    Ok(())
+}
+
+/// dlopen打开的共享库，释放时关闭
+struct NativeLibrary {
+    handle: *mut c_void,
+    call: CallFn,
+    free: FreeFn,
+}
+
+impl NativeLibrary {
+    // 打开共享库，检查ABI版本并读取元数据
+    fn open(name: &str, path: &str, options: &ExternalModuleOptions) -> Result<(Self, ModuleMetadata)> {
+        let error = |message: String| InterpreterError::ModuleError(format!("原生模块 '{}' 加载失败: {}", name, message));
+        // 库的初始化代码可能已经读取环境变量，必须在dlopen之前设置
+        for (key, value) in &options.env_vars {
+            std::env::set_var(key, value);
+        }
+        let c_path = CString::new(path).map_err(|_| error("路径中包含NUL字符".to_string()))?;
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            return Err(error(dl_error()));
+        }
+
+        let symbol = |symbol: &CStr| {
+            let address = unsafe { libc::dlsym(handle, symbol.as_ptr()) };
+            if address.is_null() {
+                Err(format!("缺少导出函数 '{}'，这不是JiLang原生模块或是用不兼容的SDK编译的", symbol.to_string_lossy()))
+            } else {
+                Ok(address)
+            }
+        };
+        let (call, free) = match (symbol(c"jilang_call"), symbol(c"jilang_free")) {
+            (Ok(call), Ok(free)) => unsafe { (std::mem::transmute::<*mut c_void, CallFn>(call), std::mem::transmute::<*mut c_void, FreeFn>(free)) },
+            (Err(message), _) | (_, Err(message)) => {
+                unsafe { libc::dlclose(handle) };
+                return Err(error(message));
+            }
+        };
+        // 之后的错误返回时由Drop关闭共享库
+        let library = Self { handle, call, free };
+
+        let abi_version = unsafe { std::mem::transmute::<*mut c_void, AbiVersionFn>(symbol(c"jilang_abi_version").map_err(error)?) };
+        let version = unsafe { abi_version() };
+        if version != NATIVE_ABI_VERSION {
+            return Err(error(format!(
+                "模块的ABI版本为 {}，当前解释器支持的版本为 {}，请用匹配的SDK重新编译模块",
+                version, NATIVE_ABI_VERSION
+            )));
+        }
+
+        let module_metadata = unsafe { std::mem::transmute::<*mut c_void, MetadataFn>(symbol(c"jilang_module_metadata").map_err(error)?) };
+        let text = library.take_string(unsafe { module_metadata() })
+            .ok_or_else(|| error("jilang_module_metadata 返回了NULL".to_string()))?;
+        let value: Value = serde_json::from_str(&text)
+            .map_err(|e| error(format!("jilang_module_metadata 返回的不是有效的JSON: {}", e)))?;
+        let metadata = ModuleMetadata::from_json(&value, name)?;
+
+        Ok((library, metadata))
+    }
+
+    // 复制模块返回的字符串并交还给模块释放
+    fn take_string(&self, ptr: *mut c_char) -> Option<String> {
+        if ptr.is_null() {
+            return None;
+        }
+        let text = unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned();
+        unsafe { (self.free)(ptr) };
+        Some(text)
+    }
+
+    fn call(&self, function: &str, args: &[Value]) -> std::result::Result<Value, String> {
+        let function = CString::new(function).map_err(|_| "函数名中包含NUL字符".to_string())?;
+        let args = CString::new(Value::Array(args.to_vec()).to_string()).map_err(|_| "参数中包含NUL字符".to_string())?;
+        let Some(output) = self.take_string(unsafe { (self.call)(function.as_ptr(), args.as_ptr()) }) else {
+            return Ok(Value::Null);
+        };
+
+        let result: Value = serde_json::from_str(&output).map_err(|e| format!("函数返回的不是有效的JSON: {}", e))?;
+        if let Some(message) = result.as_object().filter(|obj| obj.len() == 1).and_then(|obj| obj.get("$error")) {
+            return Err(message.as_str().map(|s| s.to_string()).unwrap_or_else(|| message.to_string()));
+        }
+        Ok(result)
+    }
+}
+
+impl Drop for NativeLibrary {
+    fn drop(&mut self) {
+        unsafe { libc::dlclose(self.handle) };
+    }
+}
+
+fn dl_error() -> String {
+    let message = unsafe { libc::dlerror() };
+    if message.is_null() {
+        "未知错误".to_string()
+    } else {
+        unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+    }
+}
+
+/// 原生模块实现
+pub struct NativeModule {
+    name: String,
+    path: String,
+    metadata: ModuleMetadata,
+    options: ExternalModuleOptions,
+    // 只在重新加载失败后为None
+    library: Option<NativeLibrary>,
+}
+
+impl Module for NativeModule {
+    fn get_name(&self) -> &'static str {
+        Box::leak(self.name.clone().into_boxed_str())
+    }
+
+    fn get_functions(&self) -> Vec<(&'static str, Box<dyn Fn(&[Value], &mut Context) -> Value + Send + Sync + 'static>)> {
+        Vec::new() // 使用自定义调用机制
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl ExternalModule for NativeModule {
+    fn get_module_type(&self) -> ExternalModuleType {
+        ExternalModuleType::External("Native".to_string())
+    }
+
+    fn get_metadata(&self) -> &ModuleMetadata {
+        &self.metadata
+    }
+
+    fn get_options(&self) -> &ExternalModuleOptions {
+        &self.options
+    }
+
+    fn set_options(&mut self, options: ExternalModuleOptions) -> Result<()> {
+        check_options(&self.name, &options)?;
+        self.options = options;
+        Ok(())
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        // 先关闭旧的共享库，否则dlopen会直接返回已经加载的那一份
+        self.library = None;
+        let (library, metadata) = NativeLibrary::open(&self.name, &self.path, &self.options)?;
+        self.library = Some(library);
+        self.metadata = metadata;
+        Ok(())
+    }
+
+    fn source_path(&self) -> Option<&str> {
+        Some(&self.path)
+    }
+
+    fn call_function(&self, name: &str, args: &[Value], _context: &mut Context) -> Result<Value> {
+        if !self.has_function(name) {
+            return Err(InterpreterError::FunctionError(
+                format!("原生模块 '{}' 中未找到函数 '{}'", self.name, name)
+            ));
+        }
+        let library = self.library.as_ref().ok_or_else(|| InterpreterError::ModuleError(
+            format!("原生模块 '{}' 重新加载失败，无法调用", self.name)
+        ))?;
+        library.call(name, args)
+            .map_err(|message| InterpreterError::RuntimeError(format!("原生函数 '{}' 调用失败: {}", name, message)))
+    }
+
+    fn get_function_metadata(&self, name: &str) -> Option<&FunctionMetadata> {
+        self.metadata.functions.get(name)
+    }
+
+    fn has_function(&self, name: &str) -> bool {
+        self.metadata.functions.contains_key(name)
+    }
+
+    fn get_all_function_metadata(&self) -> Vec<&FunctionMetadata> {
+        self.metadata.functions.values().collect()
+    }
+
+    fn initialize(&mut self) -> Result<()> {
+        Ok(()) // 共享库在加载时已经打开
+    }
+
+    fn destroy(&mut self) -> Result<()> {
+        Ok(()) // 共享库在模块释放时关闭
+    }
+
+    fn get_jlang_function(&self, _name: &str) -> Option<Value> {
+        None // 原生模块不支持该功能
+    }
+
+    fn get_module_meta_value(&self) -> Option<&Value> {
+        None
+    }
+}
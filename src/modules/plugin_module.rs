@@ -0,0 +1,467 @@
+// 进程外插件模块
+//
+// 插件由清单文件（<模块名>.plugin.json）声明，例如：
+//
+//     {"command": "python3", "args": ["./calc.py"], "env": {"CALC_PRECISION": "6"}}
+//
+// command和args中以 ./ 或 ../ 开头的路径相对于清单文件所在的目录，插件也在该目录中运行。
+// 解释器启动插件进程后一直保持运行，在 ExternalModule::destroy 中关闭。双方在插件的
+// 标准输入输出上以每行一条JSON-RPC 2.0消息通信，插件的标准错误输出直接显示在终端上：
+//
+// - metadata：返回ModuleMetadata的JSON（字段名与结构体相同）；
+// - call {"function": 名称, "args": [参数...]}：返回函数结果；
+// - shutdown：关闭前发送的通知，之后插件的标准输入被关闭，插件应该退出。
+//
+// 失败时返回JSON-RPC的error对象，其中的message作为错误信息。函数调用期间插件可以向解释器
+// 发送请求，方法与Lua模块的jilang表相同：get_var {name}、set_var {name, value}、
+// get_const {name}、call {statement, args}、call_function {name, args}。
+use serde_json::{json, Value};
+use std::cell::{RefCell, RefMut};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use crate::interpreter::context::Context;
+use crate::interpreter::error::{InterpreterError, Result};
+use crate::interpreter::variable_reference::VariableReference;
+use super::external_module::{ExternalModule, ExternalModuleOptions, ExternalModuleType, FunctionMetadata, ModuleLoader, ModuleMetadata};
+use super::lua_module::call_jilang_function;
+use super::Module;
+
+// 插件没有设置execution_timeout_ms时，读取元数据最多等待的时间
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+// 发送shutdown后等待插件退出的时间，超时后强制结束
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+// JSON-RPC错误码
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const CALLBACK_FAILED: i64 = -32000;
+
+/// 插件模块加载器
+pub struct PluginModuleLoader;
+
+impl ModuleLoader for PluginModuleLoader {
+    fn can_load(&self, path: &str) -> bool {
+        Path::new(path).exists() && path.ends_with(".plugin.json")
+    }
+
+    fn load(&self, name: &str, path: &str, options: Option<ExternalModuleOptions>) -> Result<Box<dyn ExternalModule>> {
+        if crate::is_debug_mode() {
+            println!("加载插件模块: {} 从清单: {}", name, path);
+        }
+
+        let options = options.unwrap_or_default();
+        check_options(name, &options)?;
+        let manifest = Manifest::read(name, path)?;
+        let mut process = PluginProcess::spawn(name, &manifest, &options)?;
+        let metadata = match process.metadata(name) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                process.shutdown();
+                return Err(e);
+            }
+        };
+
+        if crate::is_debug_mode() {
+            println!("成功加载插件模块: {}", name);
+            for (fname, meta) in metadata.functions.iter() {
+                println!("  - {} -> {}", fname, meta.return_type);
+            }
+        }
+
+        Ok(Box::new(PluginModule {
+            name: name.to_string(),
+            path: path.to_string(),
+            manifest,
+            metadata,
+            options,
+            process: RefCell::new(Some(process)),
+        }))
+    }
+
+    fn get_supported_extensions(&self) -> Vec<&'static str> {
+        vec!["plugin.json"]
+    }
+
+    fn get_loader_name(&self) -> &'static str {
+        "插件模块加载器"
+    }
+}
+
+fn check_options(name: &str, options: &ExternalModuleOptions) -> Result<()> {
+    if options.sandbox == Some(true) {
+        return Err(InterpreterError::ModuleError(
+            format!("插件模块 '{}' 运行在独立进程中，无法在沙箱中运行，请去掉 sandbox 选项或改用Lua/WASM模块", name)
+        ));
+    }
+    Ok(())
+}
+
+/// 插件清单
+#[derive(Clone, Debug)]
+struct Manifest {
+    command: String,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    dir: PathBuf,
+}
+
+impl Manifest {
+    fn read(name: &str, path: &str) -> Result<Self> {
+        let invalid = |message: String| InterpreterError::ModuleError(format!("插件模块 '{}' 的清单 '{}' 无效: {}", name, path, message));
+        let content = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let value: Value = serde_json::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+        let dir = Path::new(path).parent().map(Path::to_path_buf).unwrap_or_default();
+        let relative = |text: &str| match text.starts_with("./") || text.starts_with("../") {
+            true => dir.join(text).to_string_lossy().into_owned(),
+            false => text.to_string(),
+        };
+
+        let command = value.get("command").and_then(|c| c.as_str())
+            .ok_or_else(|| invalid("缺少字符串字段 command".to_string()))?;
+        let args = match value.get("args") {
+            None => Vec::new(),
+            Some(Value::Array(args)) => args.iter()
+                .map(|arg| arg.as_str().map(relative).ok_or_else(|| invalid("args 必须是字符串数组".to_string())))
+                .collect::<Result<Vec<_>>>()?,
+            Some(_) => return Err(invalid("args 必须是字符串数组".to_string())),
+        };
+        let mut env = HashMap::new();
+        match value.get("env") {
+            None => {}
+            Some(Value::Object(vars)) => for (key, value) in vars {
+                let value = value.as_str().ok_or_else(|| invalid(format!("env.{} 必须是字符串", key)))?;
+                env.insert(key.clone(), value.to_string());
+            },
+            Some(_) => return Err(invalid("env 必须是对象".to_string())),
+        }
+
+        Ok(Self { command: relative(command), args, env, dir })
+    }
+}
+
+/// 运行中的插件进程
+struct PluginProcess {
+    name: String,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    // 读取线程逐行转发插件的标准输出，插件退出后断开
+    lines: Receiver<String>,
+    next_id: u64,
+    timeout: Option<Duration>,
+}
+
+impl PluginProcess {
+    fn spawn(name: &str, manifest: &Manifest, options: &ExternalModuleOptions) -> Result<Self> {
+        let mut command = Command::new(&manifest.command);
+        command.args(&manifest.args)
+            .envs(&manifest.env)
+            .envs(&options.env_vars)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+        if !manifest.dir.as_os_str().is_empty() {
+            command.current_dir(&manifest.dir);
+        }
+        let mut child = command.spawn().map_err(|e| InterpreterError::ModuleError(
+            format!("无法启动插件模块 '{}' ({}): {}", name, manifest.command, e)
+        ))?;
+
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            name: name.to_string(),
+            stdin: child.stdin.take(),
+            child,
+            lines,
+            next_id: 1,
+            timeout: options.execution_timeout_ms.map(Duration::from_millis),
+        })
+    }
+
+    fn metadata(&mut self, name: &str) -> Result<ModuleMetadata> {
+        let timeout = self.timeout.unwrap_or(METADATA_TIMEOUT);
+        let value = self.request("metadata", Value::Null, Some(timeout), None)
+            .map_err(|e| InterpreterError::ModuleError(format!("读取插件模块 '{}' 的元数据失败: {}", name, e)))?;
+        ModuleMetadata::from_json(&value, name)
+    }
+
+    fn call(&mut self, function: &str, args: &[Value], context: &mut Context) -> std::result::Result<Value, String> {
+        self.request("call", json!({"function": function, "args": args}), self.timeout, Some(context))
+    }
+
+    fn send(&mut self, message: &Value) -> std::result::Result<(), String> {
+        let stdin = self.stdin.as_mut().ok_or("插件的标准输入已关闭")?;
+        writeln!(stdin, "{}", message)
+            .and_then(|_| stdin.flush())
+            .map_err(|e| format!("无法向插件发送消息: {}", e))
+    }
+
+    // 发送请求并等待对应的响应，等待期间处理插件发来的回调
+    fn request(&mut self, method: &str, params: Value, timeout: Option<Duration>, mut context: Option<&mut Context>) -> std::result::Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut request = json!({"jsonrpc": "2.0", "id": id, "method": method});
+        if !params.is_null() {
+            request["params"] = params;
+        }
+        self.send(&request)?;
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let line = match deadline {
+                Some(deadline) => self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => self.lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let line = match line {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    // 插件可能停在任意状态，不能再继续使用
+                    self.kill();
+                    return Err(format!("插件在 {} 毫秒内没有响应，已被终止", timeout.unwrap_or_default().as_millis()));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    let status = self.child.wait().map(|status| status.to_string()).unwrap_or_default();
+                    return Err(format!("插件进程已退出（{}）", status));
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message: Value = serde_json::from_str(&line)
+                .map_err(|e| format!("插件输出了无效的JSON-RPC消息: {} ({})", line, e))?;
+
+            if let Some(callback) = message.get("method").and_then(|m| m.as_str()) {
+                let result = callback_result(context.as_deref_mut(), callback, message.get("params"));
+                // 没有id的是通知，不需要回复
+                if let Some(callback_id) = message.get("id") {
+                    let mut response = json!({"jsonrpc": "2.0", "id": callback_id});
+                    match result {
+                        Ok(value) => response["result"] = value,
+                        Err((code, message)) => response["error"] = json!({"code": code, "message": message}),
+                    }
+                    self.send(&response)?;
+                }
+                continue;
+            }
+
+            if message.get("id").and_then(|i| i.as_u64()) != Some(id) {
+                continue; // 不是这次请求的响应
+            }
+            if let Some(error) = message.get("error").filter(|e| !e.is_null()) {
+                return Err(error.get("message").and_then(|m| m.as_str()).map(|m| m.to_string())
+                    .unwrap_or_else(|| error.to_string()));
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    // 通知插件关闭并等待退出，超时后强制结束
+    fn shutdown(&mut self) {
+        let _ = self.send(&json!({"jsonrpc": "2.0", "method": "shutdown"}));
+        self.stdin = None;
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        while Instant::now() < deadline {
+            match self.child.try_wait() {
+                Ok(None) => std::thread::sleep(Duration::from_millis(10)),
+                _ => return,
+            }
+        }
+        if crate::is_debug_mode() {
+            println!("插件模块 '{}' 没有及时退出，强制结束", self.name);
+        }
+        self.kill();
+    }
+
+    fn kill(&mut self) {
+        self.stdin = None;
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// 处理插件发来的回调请求，失败时返回JSON-RPC错误码和信息
+fn callback_result(context: Option<&mut Context>, method: &str, params: Option<&Value>) -> std::result::Result<Value, (i64, String)> {
+    let Some(context) = context else {
+        return Err((CALLBACK_FAILED, format!("{} 只能在模块函数被调用期间使用", method)));
+    };
+    let params = params.cloned().unwrap_or(Value::Null);
+    let text = |key: &str| params.get(key).and_then(|v| v.as_str()).map(|s| s.to_string())
+        .ok_or_else(|| (INVALID_PARAMS, format!("{} 缺少字符串参数 {}", method, key)));
+    let failed = |e: InterpreterError| (CALLBACK_FAILED, e.message().to_string());
+
+    match method {
+        "get_var" => {
+            // 除了变量名，也接受@var.、@const.、@env.等引用
+            let name = text("name")?;
+            let reference = if VariableReference::is_reference(&name) { name } else { format!("@var.{}", name) };
+            Ok(context.get_value(&reference).unwrap_or(Value::Null))
+        }
+        "set_var" => {
+            let name = text("name")?;
+            let value = params.get("value").cloned().unwrap_or(Value::Null);
+            context.set_variable(name.clone(), value)
+                .map(|_| Value::Bool(true))
+                .map_err(|e| (CALLBACK_FAILED, format!("设置变量 {} 失败: {}", name, e.message())))
+        }
+        "get_const" => Ok(context.constants.get(&text("name")?).cloned().unwrap_or(Value::Null)),
+        "call" => {
+            let statement = text("statement")?;
+            let args = match params.get("args").cloned().unwrap_or(Value::Null) {
+                Value::Array(args) => args,
+                Value::Null => Vec::new(),
+                value => vec![value],
+            };
+            crate::interpreter::statements::execute_statement(&statement, &Value::Array(args), context, None)
+                .map_err(|e| (CALLBACK_FAILED, format!("执行语句 {} 失败: {}", statement, e.message())))
+        }
+        "call_function" => {
+            let name = text("name")?;
+            call_jilang_function(context, &name, params.get("args").cloned().unwrap_or(Value::Null))
+                .map_err(failed)
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("未知的方法 '{}'", method))),
+    }
+}
+
+/// 插件模块实现
+pub struct PluginModule {
+    name: String,
+    path: String,
+    manifest: Manifest,
+    metadata: ModuleMetadata,
+    options: ExternalModuleOptions,
+    // destroy或修改选项后为None，下次调用时重新启动
+    process: RefCell<Option<PluginProcess>>,
+}
+
+impl PluginModule {
+    fn process(&self) -> Result<RefMut<'_, PluginProcess>> {
+        let mut slot = self.process.try_borrow_mut()
+            .map_err(|_| InterpreterError::RuntimeError(format!("插件模块 '{}' 正在被调用", self.name)))?;
+        if slot.is_none() {
+            *slot = Some(PluginProcess::spawn(&self.name, &self.manifest, &self.options)?);
+        }
+        Ok(RefMut::map(slot, |slot| slot.as_mut().unwrap()))
+    }
+
+    fn stop(&self) {
+        if let Some(mut process) = self.process.borrow_mut().take() {
+            process.shutdown();
+        }
+    }
+}
+
+impl Module for PluginModule {
+    fn get_name(&self) -> &'static str {
+        Box::leak(self.name.clone().into_boxed_str())
+    }
+
+    fn get_functions(&self) -> Vec<(&'static str, Box<dyn Fn(&[Value], &mut Context) -> Value + Send + Sync + 'static>)> {
+        Vec::new() // 使用自定义调用机制
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl ExternalModule for PluginModule {
+    fn get_module_type(&self) -> ExternalModuleType {
+        ExternalModuleType::External("Plugin".to_string())
+    }
+
+    fn get_metadata(&self) -> &ModuleMetadata {
+        &self.metadata
+    }
+
+    fn get_options(&self) -> &ExternalModuleOptions {
+        &self.options
+    }
+
+    fn set_options(&mut self, options: ExternalModuleOptions) -> Result<()> {
+        check_options(&self.name, &options)?;
+        self.options = options;
+        // 环境变量在启动时确定，使用新选项重新启动
+        self.stop();
+        Ok(())
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        self.stop();
+        self.manifest = Manifest::read(&self.name, &self.path)?;
+        let metadata = self.process()?.metadata(&self.name)?;
+        self.metadata = metadata;
+        Ok(())
+    }
+
+    // 清单修改后重新读取并重启插件进程
+    fn source_path(&self) -> Option<&str> {
+        Some(&self.path)
+    }
+
+    fn call_function(&self, name: &str, args: &[Value], context: &mut Context) -> Result<Value> {
+        if !self.has_function(name) {
+            return Err(InterpreterError::FunctionError(
+                format!("插件模块 '{}' 中未找到函数 '{}'", self.name, name)
+            ));
+        }
+        let mut process = self.process()?;
+        let result = process.call(name, args, context);
+        // 超时被终止或自己退出的插件在下次调用时重新启动
+        if result.is_err() && !matches!(process.child.try_wait(), Ok(None)) {
+            drop(process);
+            self.process.replace(None);
+        }
+        result.map_err(|message| InterpreterError::RuntimeError(format!("插件函数 '{}' 调用失败: {}", name, message)))
+    }
+
+    fn get_function_metadata(&self, name: &str) -> Option<&FunctionMetadata> {
+        self.metadata.functions.get(name)
+    }
+
+    fn has_function(&self, name: &str) -> bool {
+        self.metadata.functions.contains_key(name)
+    }
+
+    fn get_all_function_metadata(&self) -> Vec<&FunctionMetadata> {
+        self.metadata.functions.values().collect()
+    }
+
+    fn initialize(&mut self) -> Result<()> {
+        self.process()?;
+        Ok(())
+    }
+
+    fn destroy(&mut self) -> Result<()> {
+        self.stop();
+        Ok(())
+    }
+
+    fn get_jlang_function(&self, _name: &str) -> Option<Value> {
+        None // 插件模块不支持该功能
+    }
+
+    fn get_module_meta_value(&self) -> Option<&Value> {
+        None
+    }
+}
+
+impl Drop for PluginModule {
+    fn drop(&mut self) {
+        let _ = self.destroy();
+    }
+}
@@ -0,0 +1,476 @@
+// #[jilang_module] / #[jilang_fn] 生成的代码使用的参数和返回值转换
+//
+// 有类型的模块函数的参数通过 FromArg 从JSON值转换，参数是变量引用（@var.x 等）时先解析引用；
+// 返回值通过 IntoValue 转换回JSON值。类型名用于生成函数的元数据。
+use serde_json::{Map, Number, Value};
+use crate::interpreter::context::Context;
+use crate::interpreter::variable_reference::VariableReference;
+use super::external_module::ModuleMetadata;
+
+/// 由 #[jilang_module] 实现：根据文档注释和函数签名生成的模块元数据
+pub trait ModuleInfo {
+    fn module_metadata() -> ModuleMetadata;
+}
+
+/// 可以作为模块函数参数的类型
+pub trait FromArg: Sized {
+    /// 元数据中显示的参数类型
+    const TYPE_NAME: &'static str;
+    /// 参数缺省时是否可以转换（Option<T>）
+    const OPTIONAL: bool = false;
+
+    fn from_arg(value: Option<&Value>) -> Result<Self, String>;
+}
+
+/// 可以作为模块函数返回值的类型
+pub trait IntoValue {
+    /// 元数据中显示的返回值类型
+    const TYPE_NAME: &'static str;
+
+    fn into_value(self) -> Value;
+}
+
+/// 取出第index个参数并转换为T，失败时的信息包含参数名
+pub fn arg<T: FromArg>(args: &[Value], index: usize, name: &str, context: &Context) -> Result<T, String> {
+    let resolved = match args.get(index) {
+        Some(Value::String(text)) if VariableReference::is_reference(text) => Some(
+            context.resolve_value_raw(&Value::String(text.clone())).map_err(|e| format!("参数 '{}': {}", name, e.message()))?
+        ),
+        other => other.cloned(),
+    };
+    T::from_arg(resolved.as_ref()).map_err(|e| format!("参数 '{}' {}", name, e))
+}
+
+/// 函数出错时按内置模块的惯例输出错误并返回null
+pub fn report(module: &str, function: &str, result: Result<Value, String>) -> Value {
+    result.unwrap_or_else(|message| {
+        eprintln!("错误: {}.{}: {}", module, function, message);
+        Value::Null
+    })
+}
+
+fn describe(value: Option<&Value>) -> String {
+    match value {
+        None => "但没有提供".to_string(),
+        Some(value) => format!("实际为 {}", value),
+    }
+}
+
+fn expected<T>(type_name: &str, value: Option<&Value>) -> Result<T, String> {
+    Err(format!("应为{}，{}", type_name, describe(value)))
+}
+
+impl FromArg for Value {
+    const TYPE_NAME: &'static str = "Any";
+
+    fn from_arg(value: Option<&Value>) -> Result<Self, String> {
+        value.cloned().ok_or_else(|| "没有提供".to_string())
+    }
+}
+
+impl FromArg for String {
+    const TYPE_NAME: &'static str = "String";
+
+    fn from_arg(value: Option<&Value>) -> Result<Self, String> {
+        match value {
+            Some(Value::String(s)) => Ok(s.clone()),
+            // 和echo等语句一样，数字和布尔值按文本使用
+            Some(Value::Number(n)) => Ok(n.to_string()),
+            Some(Value::Bool(b)) => Ok(b.to_string()),
+            other => expected(<Self as FromArg>::TYPE_NAME, other),
+        }
+    }
+}
+
+impl FromArg for f64 {
+    const TYPE_NAME: &'static str = "Number";
+
+    fn from_arg(value: Option<&Value>) -> Result<Self, String> {
+        match value {
+            Some(Value::Number(n)) => n.as_f64().ok_or_else(|| format!("无法转换为数字: {}", n)),
+            Some(Value::String(s)) => s.trim().parse().map_err(|_| format!("应为Number，实际为 \"{}\"", s)),
+            other => expected(<Self as FromArg>::TYPE_NAME, other),
+        }
+    }
+}
+
+impl FromArg for i64 {
+    const TYPE_NAME: &'static str = "Integer";
+
+    fn from_arg(value: Option<&Value>) -> Result<Self, String> {
+        match value {
+            Some(Value::Number(n)) => match n.as_i64() {
+                Some(i) => Ok(i),
+                None => n.as_f64().filter(|f| f.fract() == 0.0 && f.abs() < i64::MAX as f64)
+                    .map(|f| f as i64)
+                    .ok_or_else(|| format!("应为整数，实际为 {}", n)),
+            },
+            Some(Value::String(s)) => s.trim().parse().map_err(|_| format!("应为整数，实际为 \"{}\"", s)),
+            other => expected(<Self as FromArg>::TYPE_NAME, other),
+        }
+    }
+}
+
+impl FromArg for usize {
+    const TYPE_NAME: &'static str = "Integer";
+
+    fn from_arg(value: Option<&Value>) -> Result<Self, String> {
+        let integer = i64::from_arg(value)?;
+        usize::try_from(integer).map_err(|_| format!("应为非负整数，实际为 {}", integer))
+    }
+}
+
+impl FromArg for bool {
+    const TYPE_NAME: &'static str = "Bool";
+
+    fn from_arg(value: Option<&Value>) -> Result<Self, String> {
+        match value {
+            Some(Value::Bool(b)) => Ok(*b),
+            other => expected(<Self as FromArg>::TYPE_NAME, other),
+        }
+    }
+}
+
+impl<T: FromArg> FromArg for Vec<T> {
+    const TYPE_NAME: &'static str = "Array";
+
+    fn from_arg(value: Option<&Value>) -> Result<Self, String> {
+        match value {
+            Some(Value::Array(items)) => items.iter().enumerate()
+                .map(|(i, item)| T::from_arg(Some(item)).map_err(|e| format!("的第 {} 个元素{}", i + 1, e)))
+                .collect(),
+            other => expected(<Self as FromArg>::TYPE_NAME, other),
+        }
+    }
+}
+
+impl FromArg for Map<String, Value> {
+    const TYPE_NAME: &'static str = "Object";
+
+    fn from_arg(value: Option<&Value>) -> Result<Self, String> {
+        match value {
+            Some(Value::Object(map)) => Ok(map.clone()),
+            other => expected(<Self as FromArg>::TYPE_NAME, other),
+        }
+    }
+}
+
+impl<T: FromArg> FromArg for Option<T> {
+    const TYPE_NAME: &'static str = T::TYPE_NAME;
+    const OPTIONAL: bool = true;
+
+    fn from_arg(value: Option<&Value>) -> Result<Self, String> {
+        match value {
+            None | Some(Value::Null) => Ok(None),
+            value => T::from_arg(value).map(Some),
+        }
+    }
+}
+
+impl IntoValue for Value {
+    const TYPE_NAME: &'static str = "Any";
+
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for () {
+    const TYPE_NAME: &'static str = "Null";
+
+    fn into_value(self) -> Value {
+        Value::Null
+    }
+}
+
+impl IntoValue for String {
+    const TYPE_NAME: &'static str = "String";
+
+    fn into_value(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoValue for &str {
+    const TYPE_NAME: &'static str = "String";
+
+    fn into_value(self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl IntoValue for f64 {
+    const TYPE_NAME: &'static str = "Number";
+
+    fn into_value(self) -> Value {
+        Number::from_f64(self).map(Value::Number).unwrap_or(Value::Null)
+    }
+}
+
+impl IntoValue for i64 {
+    const TYPE_NAME: &'static str = "Integer";
+
+    fn into_value(self) -> Value {
+        Value::from(self)
+    }
+}
+
+impl IntoValue for u64 {
+    const TYPE_NAME: &'static str = "Integer";
+
+    fn into_value(self) -> Value {
+        Value::from(self)
+    }
+}
+
+impl IntoValue for usize {
+    const TYPE_NAME: &'static str = "Integer";
+
+    fn into_value(self) -> Value {
+        Value::from(self)
+    }
+}
+
+impl IntoValue for bool {
+    const TYPE_NAME: &'static str = "Bool";
+
+    fn into_value(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    const TYPE_NAME: &'static str = "Array";
+
+    fn into_value(self) -> Value {
+        Value::Array(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+impl IntoValue for Map<String, Value> {
+    const TYPE_NAME: &'static str = "Object";
+
+    fn into_value(self) -> Value {
+        Value::Object(self)
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    const TYPE_NAME: &'static str = T::TYPE_NAME;
+
+    fn into_value(self) -> Value {
+        self.map(IntoValue::into_value).unwrap_or(Value::Null)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use jilang_macros::{jilang_module, jilang_fn};
+    use crate::modules::Module;
+
+    fn context() -> Context {
+        Context::new(json!({"program": {}}), Vec::new()).unwrap()
+    }
+
+    fn convert<T: FromArg>(value: Value) -> Result<T, String> {
+        T::from_arg(Some(&value))
+    }
+
+    #[test]
+    fn from_arg_converts_each_type() {
+        assert_eq!(convert::<Value>(json!({"a": 1})), Ok(json!({"a": 1})));
+        assert_eq!(Value::from_arg(None), Err("没有提供".to_string()));
+
+        assert_eq!(convert::<String>(json!("ab")), Ok("ab".to_string()));
+        assert_eq!(convert::<String>(json!(1.5)), Ok("1.5".to_string()));
+        assert_eq!(convert::<String>(json!(true)), Ok("true".to_string()));
+        assert_eq!(convert::<String>(json!([1])), Err("应为String，实际为 [1]".to_string()));
+        assert_eq!(String::from_arg(None), Err("应为String，但没有提供".to_string()));
+
+        assert_eq!(convert::<f64>(json!(2)), Ok(2.0));
+        assert_eq!(convert::<f64>(json!(" 2.5 ")), Ok(2.5));
+        assert_eq!(convert::<f64>(json!("x")), Err("应为Number，实际为 \"x\"".to_string()));
+        assert_eq!(convert::<f64>(json!(null)), Err("应为Number，实际为 null".to_string()));
+
+        assert_eq!(convert::<i64>(json!(-3)), Ok(-3));
+        assert_eq!(convert::<i64>(json!(4.0)), Ok(4));
+        assert_eq!(convert::<i64>(json!("7")), Ok(7));
+        assert_eq!(convert::<i64>(json!(1.5)), Err("应为整数，实际为 1.5".to_string()));
+        assert_eq!(convert::<i64>(json!(1e300)), Err("应为整数，实际为 1e300".to_string()));
+        assert_eq!(convert::<i64>(json!("1.5")), Err("应为整数，实际为 \"1.5\"".to_string()));
+
+        assert_eq!(convert::<usize>(json!(3)), Ok(3));
+        assert_eq!(convert::<usize>(json!(-1)), Err("应为非负整数，实际为 -1".to_string()));
+
+        assert_eq!(convert::<bool>(json!(false)), Ok(false));
+        assert_eq!(convert::<bool>(json!("true")), Err("应为Bool，实际为 \"true\"".to_string()));
+
+        assert_eq!(convert::<Vec<i64>>(json!([1, "2"])), Ok(vec![1, 2]));
+        assert_eq!(convert::<Vec<i64>>(json!([1, "x"])), Err("的第 2 个元素应为整数，实际为 \"x\"".to_string()));
+        assert_eq!(convert::<Vec<i64>>(json!({})), Err("应为Array，实际为 {}".to_string()));
+
+        let map = json!({"k": 1}).as_object().unwrap().clone();
+        assert_eq!(convert::<Map<String, Value>>(json!({"k": 1})), Ok(map));
+        assert_eq!(convert::<Map<String, Value>>(json!([])), Err("应为Object，实际为 []".to_string()));
+
+        assert_eq!(Option::<i64>::from_arg(None), Ok(None));
+        assert_eq!(convert::<Option<i64>>(json!(null)), Ok(None));
+        assert_eq!(convert::<Option<i64>>(json!(5)), Ok(Some(5)));
+        assert_eq!(convert::<Option<i64>>(json!("x")), Err("应为整数，实际为 \"x\"".to_string()));
+    }
+
+    #[test]
+    fn type_names_for_metadata() {
+        assert_eq!(<Option<Vec<String>> as FromArg>::TYPE_NAME, "Array");
+        assert_eq!([<Option<bool> as FromArg>::OPTIONAL, <bool as FromArg>::OPTIONAL], [true, false]);
+        assert_eq!(<usize as FromArg>::TYPE_NAME, "Integer");
+        assert_eq!(<Option<f64> as IntoValue>::TYPE_NAME, "Number");
+        assert_eq!(<() as IntoValue>::TYPE_NAME, "Null");
+    }
+
+    #[test]
+    fn into_value_converts_each_type() {
+        assert_eq!(json!({"a": 1}).into_value(), json!({"a": 1}));
+        assert_eq!(().into_value(), Value::Null);
+        assert_eq!("ab".to_string().into_value(), json!("ab"));
+        assert_eq!("ab".into_value(), json!("ab"));
+        assert_eq!(1.5f64.into_value(), json!(1.5));
+        assert_eq!(f64::NAN.into_value(), Value::Null);
+        assert_eq!((-2i64).into_value(), json!(-2));
+        assert_eq!(u64::MAX.into_value(), json!(u64::MAX));
+        assert_eq!(3usize.into_value(), json!(3));
+        assert_eq!(true.into_value(), json!(true));
+        assert_eq!(vec![Some(1i64), None].into_value(), json!([1, null]));
+        assert_eq!(json!({"k": 1}).as_object().unwrap().clone().into_value(), json!({"k": 1}));
+    }
+
+    #[test]
+    fn arg_resolves_references_and_names_the_parameter() {
+        let mut context = context();
+        context.set_variable("n".to_string(), json!(4)).unwrap();
+        let args = [json!("@var.n"), json!("x")];
+        assert_eq!(arg::<i64>(&args, 0, "count", &context), Ok(4));
+        assert_eq!(arg::<i64>(&args, 1, "count", &context), Err("参数 'count' 应为整数，实际为 \"x\"".to_string()));
+        assert_eq!(arg::<i64>(&args, 2, "count", &context), Err("参数 'count' 应为Integer，但没有提供".to_string()));
+        assert_eq!(arg::<Option<i64>>(&args, 2, "count", &context), Ok(None));
+    }
+
+    struct SampleModule;
+
+    /// 测试用的模块
+    #[jilang_module(version = "1.2.3")]
+    impl SampleModule {
+        /// 把字符串重复若干次
+        ///
+        /// # 参数
+        /// - text: 要重复的字符串
+        /// - times: 重复次数，
+        ///   默认为2
+        ///
+        /// # 返回
+        /// 重复后的字符串
+        ///
+        /// # 示例
+        /// ```json
+        /// {"sample.repeat": ["ab", 3]}
+        /// ```
+        #[jilang_fn]
+        fn repeat(text: String, times: Option<usize>) -> String {
+            text.repeat(times.unwrap_or(2))
+        }
+
+        /// 整数除法
+        #[jilang_fn(name = "div")]
+        fn divide(a: i64, b: i64) -> Result<i64, String> {
+            a.checked_div(b).ok_or_else(|| "除数不能为0".to_string())
+        }
+
+        /// 设置变量
+        #[jilang_fn]
+        fn set(context: &mut Context, name: String, value: Value) {
+            context.set_variable(name, value).unwrap();
+        }
+
+        /// 参数个数
+        ///
+        /// # 参数
+        /// - values (Array, 可选): 任意参数
+        #[jilang_fn]
+        fn count(args: &[Value], _context: &mut Context) -> Value {
+            json!(args.len())
+        }
+
+        // 没有 #[jilang_fn] 的函数不导出
+        #[allow(dead_code)]
+        fn helper() {}
+    }
+
+    fn call(name: &str, args: &[Value], context: &mut Context) -> Value {
+        let functions = SampleModule.get_functions();
+        let (_, function) = functions.iter().find(|(n, _)| *n == name).unwrap();
+        function(args, context)
+    }
+
+    #[test]
+    fn generated_functions_convert_arguments() {
+        let mut context = context();
+        assert_eq!(SampleModule.get_name(), "sample");
+        let mut names: Vec<_> = SampleModule.get_functions().into_iter().map(|(name, _)| name).collect();
+        names.sort();
+        assert_eq!(names, ["count", "div", "repeat", "set"]);
+
+        assert_eq!(call("repeat", &[json!("ab"), json!(3)], &mut context), json!("ababab"));
+        // 可选参数缺省或为null时使用函数中的默认值
+        assert_eq!(call("repeat", &[json!("ab")], &mut context), json!("abab"));
+        assert_eq!(call("repeat", &[json!("ab"), json!(null)], &mut context), json!("abab"));
+        // 转换失败、参数过多和Err都返回null
+        assert_eq!(call("repeat", &[json!("ab"), json!(-1)], &mut context), Value::Null);
+        assert_eq!(call("repeat", &[], &mut context), Value::Null);
+        assert_eq!(call("repeat", &[json!("a"), json!(1), json!(2)], &mut context), Value::Null);
+        assert_eq!(call("div", &[json!(7), json!(2)], &mut context), json!(3));
+        assert_eq!(call("div", &[json!(7), json!(0)], &mut context), Value::Null);
+
+        // Context参数不占用参数位置
+        assert_eq!(call("set", &[json!("x"), json!([1])], &mut context), Value::Null);
+        assert_eq!(context.get_value("@var.x"), Some(json!([1])));
+        assert_eq!(call("count", &[json!(1), json!(2), json!(3)], &mut context), json!(3));
+    }
+
+    #[test]
+    fn generated_metadata_comes_from_docs_and_signature() {
+        let metadata = SampleModule::module_metadata();
+        assert_eq!(metadata.name, "sample");
+        assert_eq!(metadata.version, "1.2.3");
+        assert_eq!(metadata.author, "JiLang");
+        assert_eq!(metadata.description, "测试用的模块");
+
+        let repeat = &metadata.functions["repeat"];
+        assert_eq!(repeat.description, "把字符串重复若干次");
+        assert_eq!(repeat.example, "{\"sample.repeat\": [\"ab\", 3]}");
+        assert_eq!(repeat.return_type, "String（重复后的字符串）");
+        let params: Vec<_> = repeat.parameters.iter()
+            .map(|p| (p.name.as_str(), p.type_description.as_str(), p.optional, p.description.as_str()))
+            .collect();
+        assert_eq!(params, [("text", "String", false, "要重复的字符串"), ("times", "Integer", true, "重复次数， 默认为2")]);
+
+        let div = &metadata.functions["div"];
+        assert_eq!(div.name, "div");
+        assert_eq!(div.return_type, "Integer");
+        assert_eq!(div.example, "");
+        assert!(div.parameters.iter().all(|p| p.type_description == "Integer" && !p.optional && p.description.is_empty()));
+
+        let set = &metadata.functions["set"];
+        assert_eq!(set.return_type, "Null");
+        assert_eq!(set.parameters.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), ["name", "value"]);
+        assert_eq!(set.parameters[1].type_description, "Any");
+
+        // 原始签名的函数只使用文档中的参数
+        let count = &metadata.functions["count"];
+        assert_eq!(count.return_type, "Any");
+        assert_eq!(count.parameters.len(), 1);
+        assert_eq!((count.parameters[0].type_description.as_str(), count.parameters[0].optional), ("Array", true));
+        assert!(!metadata.functions.contains_key("helper"));
+    }
+}
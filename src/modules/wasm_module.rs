@@ -0,0 +1,836 @@
+// WebAssembly模块加载器
+//
+// .wasm文件由wasmi执行，导出的函数成为模块函数：
+// - 参数和返回值都是数字的函数直接调用，JSON数字按参数类型转换；
+// - 模块导出 jilang_alloc(len) -> ptr 时，签名为 (ptr: i32, len: i32) -> i64 的函数使用JSON内存约定：
+//   参数数组序列化为JSON写入jilang_alloc分配的内存（模块必须导出名为memory的内存），返回值的高32位是
+//   结果JSON的指针、低32位是长度，结果为 {"$error": 消息} 时调用失败。导出 jilang_free(ptr, len) 时
+//   调用结束后释放这两块内存；
+// - 可选的 jilang_metadata() -> i64 按同样的方式返回模块和函数的元数据JSON。
+// WASI的文件系统和环境变量由模块选项 allow_filesystem 和 env_vars 控制（见wasm_wasi）；
+// 沙箱中（sandbox: true，或模块位于项目目录之外）未指定的内存和执行时间使用与Lua模块相同的默认限制。
+// 指令数限制和执行时间限制都用wasmi的燃料计量实现：wasmi不能在执行中途按时间中断，
+// 时间限制按启动时测得的执行速度折算成燃料，调用WASI函数时也会检查是否超时。
+use std::fs;
+use std::cell::{RefCell, RefMut};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use wasmi::core::{TrapCode, ValType};
+use wasmi::{Config, Engine, Extern, ExternType, Func, FuncType, Linker, Store, StoreLimits, StoreLimitsBuilder, Val};
+use crate::interpreter::context::Context;
+use crate::interpreter::error::{InterpreterError, Result};
+use super::Module;
+use super::external_module::{ExternalModule, ModuleLoader, ExternalModuleType, ModuleMetadata, FunctionMetadata, ParameterMetadata, ExternalModuleOptions};
+use super::wasm_wasi::{self, Wasi};
+use super::module_cache;
+
+const ALLOC_EXPORT: &str = "jilang_alloc";
+const FREE_EXPORT: &str = "jilang_free";
+const METADATA_EXPORT: &str = "jilang_metadata";
+const MEMORY_EXPORT: &str = "memory";
+// 结果JSON中表示错误的键
+const ERROR_KEY: &str = "$error";
+// 表的最大元素个数，防止畸形的模块声明巨大的表耗尽内存
+const MAX_TABLE_ELEMENTS: u32 = 10_000_000;
+
+/// WASM模块加载器 - 用于加载.wasm文件模块
+pub struct WasmModuleLoader;
+
+impl ModuleLoader for WasmModuleLoader {
+    fn can_load(&self, path: &str) -> bool {
+        std::path::Path::new(path).exists() && path.ends_with(".wasm")
+    }
+
+    fn load(&self, name: &str, path: &str, options: Option<ExternalModuleOptions>) -> Result<Box<dyn ExternalModule>> {
+        if crate::is_debug_mode() {
+            println!("加载WASM模块: {} 从文件: {}", name, path);
+        }
+
+        let module = decode(name, path)?;
+        let options = options.unwrap_or_default();
+        let mut instance = WasmInstance::new(name, path, &module, &options)?;
+        let (metadata, module_meta) = instance.metadata(name)?;
+
+        if crate::is_debug_mode() {
+            println!("成功加载WASM模块: {}", name);
+            for (fname, meta) in metadata.functions.iter() {
+                println!("  - {} -> {}", fname, meta.return_type);
+            }
+        }
+
+        Ok(Box::new(WasmModule {
+            name: name.to_string(),
+            path: path.to_string(),
+            module,
+            metadata,
+            module_meta,
+            options,
+            instance: RefCell::new(Some(instance)),
+        }))
+    }
+
+    fn get_supported_extensions(&self) -> Vec<&'static str> {
+        vec!["wasm"]
+    }
+
+    fn get_loader_name(&self) -> &'static str {
+        "WASM模块加载器"
+    }
+}
+
+// 所有WASM模块共用的引擎，开启燃料计量
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        Engine::new(&config)
+    })
+}
+
+// 每毫秒消耗的燃料：第一次需要时用一个空循环测量，用于把时间限制折算成燃料
+fn fuel_per_ms() -> u64 {
+    static FUEL_PER_MS: OnceLock<u64> = OnceLock::new();
+    *FUEL_PER_MS.get_or_init(|| {
+        // (func (export "spin") (loop (br 0)))
+        const SPIN: &[u8] = b"\0asm\x01\0\0\0\x01\x04\x01\x60\x00\x00\x03\x02\x01\x00\x07\x08\x01\x04spin\x00\x00\x0a\x09\x01\x07\x00\x03\x40\x0c\x00\x0b\x0b";
+        const SAMPLE_FUEL: u64 = 1_000_000;
+        let measure = || -> Option<Duration> {
+            let module = wasmi::Module::new(engine(), SPIN).ok()?;
+            let mut store = Store::new(engine(), ());
+            let instance = Linker::new(engine()).instantiate(&mut store, &module).ok()?.start(&mut store).ok()?;
+            let spin = instance.get_func(&store, "spin")?;
+            store.set_fuel(SAMPLE_FUEL).ok()?;
+            let start = Instant::now();
+            let _ = spin.call(&mut store, &[], &mut []);
+            Some(start.elapsed())
+        };
+        let elapsed = measure().map(|d| d.as_micros() as u64).unwrap_or(0).max(1);
+        (SAMPLE_FUEL * 1000 / elapsed).max(1)
+    })
+}
+
+// 读取并解码WASM文件，文件没有修改时使用缓存的解码结果
+fn decode(name: &str, path: &str) -> Result<Arc<wasmi::Module>> {
+    module_cache::load(path, || {
+        let bytes = fs::read(path)
+            .map_err(|e| InterpreterError::ModuleError(format!("无法读取WASM文件 '{}': {}", path, e)))?;
+        wasmi::Module::new(engine(), &bytes[..])
+            .map_err(|e| InterpreterError::ModuleError(format!("WASM模块 '{}' 解码失败: {}", name, e)))
+    })
+}
+
+// 实例的宿主状态，WASI函数通过它访问调用中的上下文
+struct HostState {
+    wasi: Wasi,
+    limits: StoreLimits,
+    // 只在调用期间指向当前的上下文
+    context: Option<*mut Context>,
+    deadline: Option<Instant>,
+    timeout_ms: Option<u64>,
+    instruction_limit: Option<u64>,
+}
+
+impl HostState {
+    // 燃料上限：指令数限制和由时间限制折算的燃料中较小的一个
+    fn fuel_limit(&self) -> Option<u64> {
+        let timeout_fuel = self.timeout_ms.map(|ms| ms.saturating_mul(fuel_per_ms()));
+        match (self.instruction_limit, timeout_fuel) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (limit, None) | (None, limit) => limit,
+        }
+    }
+
+    // 把wasmi的错误转换为中文消息，燃料耗尽时报告是哪一个限制
+    fn describe(&self, error: wasmi::Error) -> String {
+        let Some(code) = error.as_trap_code() else {
+            return error.to_string();
+        };
+        match code {
+            TrapCode::OutOfFuel => match (self.instruction_limit, self.timeout_ms) {
+                (Some(limit), _) if Some(limit) == self.fuel_limit() => format!("WASM代码超过指令数限制（{} 条）", limit),
+                (_, Some(ms)) => format!("WASM代码执行超时（限制为 {} 毫秒）", ms),
+                _ => "WASM代码的燃料耗尽".to_string(),
+            },
+            TrapCode::UnreachableCodeReached => "执行到了unreachable指令".to_string(),
+            TrapCode::MemoryOutOfBounds => "内存访问越界".to_string(),
+            TrapCode::TableOutOfBounds => "表访问越界".to_string(),
+            TrapCode::IndirectCallToNull => "间接调用了空的表元素".to_string(),
+            TrapCode::IntegerDivisionByZero => "整数除以零".to_string(),
+            TrapCode::IntegerOverflow => "整数溢出".to_string(),
+            TrapCode::BadConversionToInteger => "浮点数无法转换为整数".to_string(),
+            TrapCode::StackOverflow => "调用栈溢出".to_string(),
+            TrapCode::BadSignature => "间接调用的函数签名不匹配".to_string(),
+            TrapCode::GrowthOperationLimited => "内存或表的增长超过了限制".to_string(),
+        }
+    }
+}
+
+// 每次调用重新开始计算执行限制
+fn begin_call(store: &mut Store<HostState>, context: Option<&mut Context>) {
+    let fuel = store.data().fuel_limit().unwrap_or(u64::MAX);
+    // 引擎开启了燃料计量，set_fuel不会失败
+    let _ = store.set_fuel(fuel);
+    let state = store.data_mut();
+    state.context = context.map(|context| context as *mut Context);
+    state.deadline = state.timeout_ms.map(|ms| Instant::now() + Duration::from_millis(ms));
+}
+
+// 实例化的模块
+struct WasmInstance {
+    store: Store<HostState>,
+    instance: wasmi::Instance,
+    // 是否导出了jilang_alloc，即是否使用JSON内存约定
+    json_abi: bool,
+}
+
+impl WasmInstance {
+    fn new(name: &str, path: &str, module: &wasmi::Module, options: &ExternalModuleOptions) -> Result<Self> {
+        let error = |stage: &str, message: String| InterpreterError::ModuleError(format!("WASM模块 '{}' {}: {}", name, stage, message));
+        // 允许访问文件系统时预打开项目目录
+        let root = if options.allow_filesystem {
+            super::get_registry().get_base_path().map(PathBuf::from).or_else(|| std::env::current_dir().ok())
+        } else {
+            None
+        };
+        // 与Lua模块相同，沙箱中未指定的内存和时间限制使用默认值
+        let sandboxed = options.is_sandboxed(path);
+        let mut limits = StoreLimitsBuilder::new().table_elements(MAX_TABLE_ELEMENTS);
+        if let Some(mb) = options.memory_limit_mb(sandboxed) {
+            limits = limits.memory_size(mb.saturating_mul(1024 * 1024));
+        }
+        let state = HostState {
+            wasi: Wasi::new(name, &options.env_vars, root),
+            limits: limits.build(),
+            context: None,
+            deadline: None,
+            timeout_ms: options.execution_timeout_ms(sandboxed),
+            instruction_limit: options.instruction_limit,
+        };
+        let mut store = Store::new(engine(), state);
+        store.limiter(|state| &mut state.limits);
+
+        let mut linker = Linker::new(engine());
+        for import in module.imports() {
+            let ExternType::Func(ty) = import.ty() else {
+                return Err(error("实例化失败", format!("不支持导入 {}.{}，只能导入函数", import.module(), import.name())));
+            };
+            if import.module() != wasm_wasi::WASI_MODULE {
+                return Err(error("实例化失败", format!("不支持导入 {}.{}，只能导入 {} 中的函数", import.module(), import.name(), wasm_wasi::WASI_MODULE)));
+            }
+            let func = wasi_function(&mut store, import.name().to_string(), ty.clone());
+            linker.define(import.module(), import.name(), func).map_err(|e| error("实例化失败", e.to_string()))?;
+        }
+
+        // start函数在实例化时执行，同样受执行限制
+        begin_call(&mut store, None);
+        let instance = linker.instantiate(&mut store, module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| error("实例化失败", store.data().describe(e)))?;
+        let mut instance = WasmInstance { store, instance, json_abi: false };
+
+        // WASI reactor模块的初始化函数
+        if let Some(initialize) = instance.instance.get_func(&instance.store, "_initialize") {
+            instance.invoke(initialize, &[], None).map_err(|e| error("初始化失败", e))?;
+        }
+        instance.json_abi = instance.function(ALLOC_EXPORT).is_some();
+        Ok(instance)
+    }
+
+    fn function(&self, name: &str) -> Option<Func> {
+        self.instance.get_func(&self.store, name)
+    }
+
+    // 导出的模块函数，jilang_开头的约定函数和WASI的入口函数除外
+    fn functions(&self) -> Vec<(String, FuncType)> {
+        let mut functions: Vec<(String, FuncType)> = self.instance.exports(&self.store)
+            .filter(|e| !e.name().starts_with("jilang_") && e.name() != "_initialize" && e.name() != "_start")
+            .filter_map(|e| Some((e.name().to_string(), e.into_func()?)))
+            .map(|(name, func)| (name, func.ty(&self.store)))
+            .collect();
+        functions.sort_by(|a, b| a.0.cmp(&b.0));
+        functions
+    }
+
+    fn is_json_function(&self, ty: &FuncType) -> bool {
+        self.json_abi && ty.params() == [ValType::I32, ValType::I32] && ty.results() == [ValType::I64]
+    }
+
+    // 根据导出函数的签名生成元数据，再用jilang_metadata返回的信息补充
+    fn metadata(&mut self, name: &str) -> Result<(ModuleMetadata, Option<Value>)> {
+        let mut metadata = ModuleMetadata {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            description: "WASM模块".to_string(),
+            author: "未知".to_string(),
+            functions: HashMap::new(),
+        };
+
+        for (func_name, ty) in self.functions() {
+            let json = self.is_json_function(&ty);
+            let parameters = if json {
+                Vec::new()
+            } else {
+                ty.params().iter().enumerate().map(|(i, param)| ParameterMetadata {
+                    name: format!("arg{}", i + 1),
+                    description: String::new(),
+                    type_description: type_name(*param).to_string(),
+                    optional: false,
+                    default_value: None,
+                }).collect()
+            };
+            let return_type = match ty.results() {
+                _ if json => "Any".to_string(),
+                [] => "Null".to_string(),
+                results => results.iter().map(|r| type_name(*r)).collect::<Vec<_>>().join(", "),
+            };
+            metadata.functions.insert(func_name.clone(), FunctionMetadata {
+                description: format!("WASM函数 {}", func_name),
+                name: func_name,
+                parameters,
+                return_type,
+                example: String::new(),
+            });
+        }
+
+        let Some(function) = self.function(METADATA_EXPORT) else {
+            return Ok((metadata, None));
+        };
+        let module_meta = self.call_json(function, None, None)
+            .map_err(|e| InterpreterError::ModuleError(format!("读取WASM模块 '{}' 的元数据失败: {}", name, e)))?;
+
+        let text = |value: &Value, key: &str| value.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+        if let Some(version) = text(&module_meta, "version") {
+            metadata.version = version;
+        }
+        if let Some(description) = text(&module_meta, "description") {
+            metadata.description = description;
+        }
+        if let Some(author) = text(&module_meta, "author") {
+            metadata.author = author;
+        }
+        if let Some(functions) = module_meta.get("functions").and_then(|f| f.as_object()) {
+            for (func_name, info) in functions {
+                let Some(function) = metadata.functions.get_mut(func_name) else {
+                    continue;
+                };
+                if let Some(description) = text(info, "description") {
+                    function.description = description;
+                }
+                if let Some(return_type) = text(info, "return_type") {
+                    function.return_type = return_type;
+                }
+                if let Some(example) = text(info, "example") {
+                    function.example = example;
+                }
+                if let Some(parameters) = info.get("parameters").and_then(|p| p.as_array()) {
+                    function.parameters = parameters.iter().map(|param| ParameterMetadata {
+                        name: text(param, "name").unwrap_or_default(),
+                        description: text(param, "description").unwrap_or_default(),
+                        type_description: text(param, "type").unwrap_or_else(|| "any".to_string()),
+                        optional: param.get("optional").and_then(|o| o.as_bool()).unwrap_or(false),
+                        default_value: param.get("default").cloned(),
+                    }).collect();
+                }
+            }
+        }
+
+        Ok((metadata, Some(module_meta)))
+    }
+
+    fn invoke(&mut self, function: Func, args: &[Val], context: Option<&mut Context>) -> std::result::Result<Vec<Val>, String> {
+        let ty = function.ty(&self.store);
+        if args.len() != ty.params().len() {
+            return Err(format!("函数需要 {} 个参数，实际传入 {} 个", ty.params().len(), args.len()));
+        }
+        let mut results: Vec<Val> = ty.results().iter().map(|ty| Val::default(*ty)).collect();
+        begin_call(&mut self.store, context);
+        let result = function.call(&mut self.store, args, &mut results);
+        self.store.data_mut().context = None;
+        result.map_err(|e| self.store.data().describe(e))?;
+        Ok(results)
+    }
+
+    fn memory(&self) -> std::result::Result<wasmi::Memory, String> {
+        self.instance.get_memory(&self.store, MEMORY_EXPORT).ok_or_else(|| "模块没有导出名为 memory 的内存".to_string())
+    }
+
+    // 按JSON内存约定调用函数，input为None时函数没有参数
+    fn call_json(&mut self, function: Func, input: Option<&[u8]>, mut context: Option<&mut Context>) -> std::result::Result<Value, String> {
+        let memory = self.memory()?;
+        let free = self.function(FREE_EXPORT);
+        let mut args = Vec::new();
+        if let Some(bytes) = input {
+            let alloc = self.function(ALLOC_EXPORT).ok_or("模块没有导出 jilang_alloc")?;
+            let ptr = self.invoke(alloc, &[Val::I32(bytes.len() as i32)], context.as_deref_mut())?
+                .first().and_then(Val::i32).ok_or("jilang_alloc 没有返回指针")?;
+            wasm_wasi::Memory(memory.data_mut(&mut self.store)).write(ptr as u32, bytes)?;
+            args = vec![Val::I32(ptr), Val::I32(bytes.len() as i32)];
+        }
+
+        let packed = self.invoke(function, &args, context.as_deref_mut())?
+            .first().and_then(Val::i64).ok_or("函数没有返回结果")? as u64;
+        let (ptr, len) = ((packed >> 32) as u32, packed as u32);
+        let output = wasm_wasi::Memory(memory.data_mut(&mut self.store)).read(ptr, len)?.to_vec();
+
+        if let Some(free) = free {
+            if !args.is_empty() {
+                self.invoke(free, &args, context.as_deref_mut())?;
+            }
+            if len > 0 {
+                self.invoke(free, &[Val::I32(ptr as i32), Val::I32(len as i32)], context)?;
+            }
+        }
+
+        if output.is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_slice(&output).map_err(|e| format!("函数返回的不是有效的JSON: {}", e))
+    }
+
+    fn call(&mut self, name: &str, args: &[Value], context: &mut Context) -> std::result::Result<Value, String> {
+        let function = self.function(name).ok_or_else(|| format!("模块没有导出函数 '{}'", name))?;
+        let ty = function.ty(&self.store);
+
+        if self.is_json_function(&ty) {
+            let input = serde_json::to_vec(&Value::Array(args.to_vec())).map_err(|e| e.to_string())?;
+            let result = self.call_json(function, Some(&input), Some(context))?;
+            if let Some(error) = result.as_object().filter(|obj| obj.len() == 1).and_then(|obj| obj.get(ERROR_KEY)) {
+                return Err(error.as_str().map(|s| s.to_string()).unwrap_or_else(|| error.to_string()));
+            }
+            return Ok(result);
+        }
+
+        if args.len() != ty.params().len() {
+            return Err(format!("需要 {} 个参数，实际传入 {} 个", ty.params().len(), args.len()));
+        }
+        let raw_args = args.iter().zip(ty.params()).enumerate()
+            .map(|(i, (arg, param))| to_val(arg, *param).ok_or_else(|| format!("第 {} 个参数应为{}，实际为 {}", i + 1, type_name(*param), arg)))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let results: Vec<Value> = self.invoke(function, &raw_args, Some(context))?
+            .iter().map(from_val).collect();
+        Ok(match results.len() {
+            0 => Value::Null,
+            1 => results.into_iter().next().unwrap(),
+            _ => Value::Array(results),
+        })
+    }
+}
+
+// 导入的WASI函数：参数转换为原始位交给wasm_wasi，返回值是WASI错误码
+fn wasi_function(store: &mut Store<HostState>, name: String, ty: FuncType) -> Func {
+    Func::new(store, ty, move |mut caller, params, results| {
+        if caller.data().deadline.is_some_and(|deadline| Instant::now() > deadline) {
+            let ms = caller.data().timeout_ms.unwrap_or(0);
+            return Err(wasmi::Error::new(format!("WASM代码执行超时（限制为 {} 毫秒）", ms)));
+        }
+        let args: Vec<u64> = params.iter().map(|param| match param {
+            Val::I32(v) => *v as u32 as u64,
+            Val::I64(v) => *v as u64,
+            _ => 0,
+        }).collect();
+        let memory = caller.get_export(MEMORY_EXPORT).and_then(Extern::into_memory);
+        let (data, state) = match memory {
+            Some(memory) => memory.data_and_store_mut(&mut caller),
+            None => (&mut [][..], caller.data_mut()),
+        };
+        // 上下文只在调用期间设置，调用返回前不会失效
+        let context = state.context.map(|context| unsafe { &mut *context });
+        let errno = state.wasi.host(context).call(&name, &mut wasm_wasi::Memory(data), &args)
+            .map_err(wasmi::Error::new)?;
+        if let Some(result) = results.first_mut() {
+            *result = Val::I32(errno as i32);
+        }
+        Ok(())
+    })
+}
+
+fn type_name(ty: ValType) -> &'static str {
+    match ty {
+        ValType::I32 => "i32",
+        ValType::I64 => "i64",
+        ValType::F32 => "f32",
+        ValType::F64 => "f64",
+        ValType::FuncRef => "funcref",
+        ValType::ExternRef => "externref",
+    }
+}
+
+// JSON值按参数类型转换为WASM值，布尔值可以作为i32传入
+fn to_val(value: &Value, ty: ValType) -> Option<Val> {
+    match ty {
+        ValType::I32 => value.as_i64()
+            .filter(|v| *v >= i32::MIN as i64 && *v <= u32::MAX as i64)
+            .map(|v| Val::I32(v as u32 as i32))
+            .or_else(|| value.as_bool().map(|b| Val::I32(b as i32))),
+        ValType::I64 => value.as_i64().or_else(|| value.as_u64().map(|v| v as i64)).map(Val::I64),
+        ValType::F32 => value.as_f64().map(|v| Val::F32((v as f32).into())),
+        ValType::F64 => value.as_f64().map(|v| Val::F64(v.into())),
+        ValType::FuncRef | ValType::ExternRef => None,
+    }
+}
+
+fn from_val(value: &Val) -> Value {
+    let float = |v: f64| serde_json::Number::from_f64(v).map(Value::Number).unwrap_or(Value::Null);
+    match value {
+        Val::I32(v) => json!(v),
+        Val::I64(v) => json!(v),
+        Val::F32(v) => float(f32::from(*v) as f64),
+        Val::F64(v) => float(f64::from(*v)),
+        Val::FuncRef(_) | Val::ExternRef(_) => Value::Null,
+    }
+}
+
+/// WASM模块实现
+pub struct WasmModule {
+    name: String,
+    path: String,
+    module: Arc<wasmi::Module>, // 解码后的模块，重新实例化时使用
+    metadata: ModuleMetadata,
+    module_meta: Option<Value>, // jilang_metadata返回的元数据
+    options: ExternalModuleOptions,
+    // 修改选项后丢弃，下次调用时重新实例化
+    instance: RefCell<Option<WasmInstance>>,
+}
+
+impl WasmModule {
+    fn instance(&self) -> Result<RefMut<'_, WasmInstance>> {
+        let mut slot = self.instance.try_borrow_mut()
+            .map_err(|_| InterpreterError::RuntimeError(format!("WASM模块 '{}' 正在被调用", self.name)))?;
+        if slot.is_none() {
+            *slot = Some(WasmInstance::new(&self.name, &self.path, &self.module, &self.options)?);
+        }
+        Ok(RefMut::map(slot, |slot| slot.as_mut().unwrap()))
+    }
+}
+
+impl Module for WasmModule {
+    fn get_name(&self) -> &'static str {
+        Box::leak(self.name.clone().into_boxed_str())
+    }
+
+    fn get_functions(&self) -> Vec<(&'static str, Box<dyn Fn(&[Value], &mut Context) -> Value + Send + Sync + 'static>)> {
+        Vec::new() // 使用自定义调用机制
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl ExternalModule for WasmModule {
+    fn get_module_type(&self) -> ExternalModuleType {
+        ExternalModuleType::External("WASM".to_string())
+    }
+
+    fn get_metadata(&self) -> &ModuleMetadata {
+        &self.metadata
+    }
+
+    fn get_options(&self) -> &ExternalModuleOptions {
+        &self.options
+    }
+
+    fn set_options(&mut self, options: ExternalModuleOptions) -> Result<()> {
+        self.options = options;
+        // WASI的目录和环境变量在实例化时确定，使用新选项重新实例化
+        self.instance.replace(None);
+        Ok(())
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        self.module = decode(&self.name, &self.path)?;
+        let mut instance = WasmInstance::new(&self.name, &self.path, &self.module, &self.options)?;
+        let (metadata, module_meta) = instance.metadata(&self.name)?;
+        self.metadata = metadata;
+        self.module_meta = module_meta;
+        self.instance.replace(Some(instance));
+        Ok(())
+    }
+
+    fn source_path(&self) -> Option<&str> {
+        Some(&self.path)
+    }
+
+    fn call_function(&self, name: &str, args: &[Value], context: &mut Context) -> Result<Value> {
+        if !self.has_function(name) {
+            return Err(InterpreterError::FunctionError(
+                format!("WASM模块 '{}' 中未找到函数 '{}'", self.name, name)
+            ));
+        }
+        self.instance()?.call(name, args, context)
+            .map_err(|message| InterpreterError::RuntimeError(format!("WASM函数 '{}' 调用失败: {}", name, message)))
+    }
+
+    fn get_function_metadata(&self, name: &str) -> Option<&FunctionMetadata> {
+        self.metadata.functions.get(name)
+    }
+
+    fn has_function(&self, name: &str) -> bool {
+        self.metadata.functions.contains_key(name)
+    }
+
+    fn get_all_function_metadata(&self) -> Vec<&FunctionMetadata> {
+        self.metadata.functions.values().collect()
+    }
+
+    fn initialize(&mut self) -> Result<()> {
+        self.instance()?;
+        Ok(())
+    }
+
+    fn destroy(&mut self) -> Result<()> {
+        self.instance.replace(None);
+        Ok(())
+    }
+
+    fn get_jlang_function(&self, _name: &str) -> Option<Value> {
+        None // WASM模块不支持该功能
+    }
+
+    fn get_module_meta_value(&self) -> Option<&Value> {
+        self.module_meta.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const I32: u8 = 0x7F;
+    const I64: u8 = 0x7E;
+    const ERROR_OUTPUT: &str = r#"{"$error":"坏了"}"#;
+
+    /// 测试用的函数定义，函数体不含结尾的 end
+    struct TestFunc {
+        name: &'static str,
+        params: Vec<u8>,
+        results: Vec<u8>,
+        body: Vec<u8>,
+    }
+
+    fn func(name: &'static str, params: &[u8], results: &[u8], body: &[u8]) -> TestFunc {
+        TestFunc { name, params: params.to_vec(), results: results.to_vec(), body: body.to_vec() }
+    }
+
+    fn uleb(mut value: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return out;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    fn sleb(mut value: i64) -> Vec<u8> {
+        let mut out = Vec::new();
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+                out.push(byte);
+                return out;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    fn section(out: &mut Vec<u8>, id: u8, items: Vec<Vec<u8>>) {
+        let mut content = uleb(items.len() as u64);
+        for item in items {
+            content.extend(item);
+        }
+        out.push(id);
+        out.extend(uleb(content.len() as u64));
+        out.extend(content);
+    }
+
+    fn name(text: &str) -> Vec<u8> {
+        let mut out = uleb(text.len() as u64);
+        out.extend(text.as_bytes());
+        out
+    }
+
+    /// 汇编一个只包含函数、一个导出为memory的内存（最小页数）和主动数据段的模块
+    fn assemble(funcs: &[TestFunc], memory: Option<u32>, data: &[(u32, &[u8])]) -> wasmi::Module {
+        let mut out = b"\0asm\x01\0\0\0".to_vec();
+        section(&mut out, 1, funcs.iter().map(|f| {
+            let mut ty = vec![0x60];
+            ty.extend(uleb(f.params.len() as u64));
+            ty.extend(&f.params);
+            ty.extend(uleb(f.results.len() as u64));
+            ty.extend(&f.results);
+            ty
+        }).collect());
+        section(&mut out, 3, (0..funcs.len()).map(|i| uleb(i as u64)).collect());
+        if let Some(min) = memory {
+            let mut limits = vec![0x00];
+            limits.extend(uleb(min as u64));
+            section(&mut out, 5, vec![limits]);
+        }
+        let mut exports: Vec<Vec<u8>> = funcs.iter().enumerate().map(|(i, f)| {
+            let mut export = name(f.name);
+            export.push(0x00);
+            export.extend(uleb(i as u64));
+            export
+        }).collect();
+        if memory.is_some() {
+            let mut export = name(MEMORY_EXPORT);
+            export.extend([0x02, 0x00]);
+            exports.push(export);
+        }
+        section(&mut out, 7, exports);
+        section(&mut out, 10, funcs.iter().map(|f| {
+            let mut body = vec![0x00];
+            body.extend(&f.body);
+            body.push(0x0B);
+            let mut code = uleb(body.len() as u64);
+            code.extend(body);
+            code
+        }).collect());
+        if !data.is_empty() {
+            section(&mut out, 11, data.iter().map(|(offset, bytes)| {
+                let mut segment = vec![0x00, 0x41];
+                segment.extend(sleb(*offset as i64));
+                segment.push(0x0B);
+                segment.extend(uleb(bytes.len() as u64));
+                segment.extend(*bytes);
+                segment
+            }).collect());
+        }
+        wasmi::Module::new(engine(), &out[..]).expect("模块应该能解码")
+    }
+
+    fn spin() -> wasmi::Module {
+        assemble(&[func("spin", &[], &[], &[0x03, 0x40, 0x0C, 0x00, 0x0B])], None, &[])
+    }
+
+    // 按JSON内存约定导出函数的模块：输入固定写到地址1024，echo原样返回输入，fail返回数据段中的错误
+    fn json_module() -> wasmi::Module {
+        let mut fail = vec![0x42];
+        fail.extend(sleb(ERROR_OUTPUT.len() as i64));
+        assemble(&[
+            func("jilang_alloc", &[I32], &[I32], &[0x41, 0x80, 0x08]),
+            func("echo", &[I32, I32], &[I64], &[
+                0x20, 0x00, 0xAD, 0x42, 0x20, 0x86, // ptr << 32
+                0x20, 0x01, 0xAD, 0x84, // | len
+            ]),
+            func("fail", &[I32, I32], &[I64], &fail),
+            func("add", &[I32, I32], &[I32], &[0x20, 0x00, 0x20, 0x01, 0x6A]),
+            func("div", &[I32, I32], &[I32], &[0x20, 0x00, 0x20, 0x01, 0x6D]),
+            func("trap", &[], &[], &[0x00]),
+        ], Some(1), &[(0, ERROR_OUTPUT.as_bytes())])
+    }
+
+    fn instance() -> WasmInstance {
+        WasmInstance::new("json", "json.wasm", &json_module(), &ExternalModuleOptions::default()).unwrap()
+    }
+
+    fn context() -> Context {
+        Context::new(json!({"program": {}}), Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn json_abi_passes_arguments_through_memory() {
+        let mut instance = instance();
+        assert!(instance.json_abi);
+        let names: Vec<String> = instance.functions().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["add", "div", "echo", "fail", "trap"]);
+
+        let mut context = context();
+        let result = instance.call("echo", &[json!("你好"), json!({"n": [1, 2.5]}), Value::Null], &mut context);
+        assert_eq!(result, Ok(json!(["你好", {"n": [1, 2.5]}, null])));
+        let input = serde_json::to_vec(&json!(["你好", {"n": [1, 2.5]}, null])).unwrap();
+        let memory = instance.memory().unwrap();
+        assert_eq!(&memory.data(&instance.store)[1024..1024 + input.len()], &input[..]);
+    }
+
+    #[test]
+    fn json_abi_error_results_become_errors() {
+        let mut instance = instance();
+        assert_eq!(instance.call("fail", &[], &mut context()), Err("坏了".to_string()));
+    }
+
+    #[test]
+    fn plain_functions_convert_numbers() {
+        let mut instance = instance();
+        let mut context = context();
+        assert_eq!(instance.call("add", &[json!(-5), json!(true)], &mut context), Ok(json!(-4)));
+        assert_eq!(instance.call("add", &[json!(1)], &mut context), Err("需要 2 个参数，实际传入 1 个".to_string()));
+        assert!(instance.call("add", &[json!("1"), json!(2)], &mut context).unwrap_err().starts_with("第 1 个参数应为"));
+        assert!(instance.call("missing", &[], &mut context).is_err());
+    }
+
+    #[test]
+    fn traps_become_errors() {
+        let mut instance = instance();
+        let mut context = context();
+        assert_eq!(instance.call("div", &[json!(1), json!(0)], &mut context), Err("整数除以零".to_string()));
+        assert_eq!(instance.call("div", &[json!(i32::MIN), json!(-1)], &mut context), Err("整数溢出".to_string()));
+        assert_eq!(instance.call("trap", &[], &mut context), Err("执行到了unreachable指令".to_string()));
+        // 陷入之后实例仍然可以使用
+        assert_eq!(instance.call("div", &[json!(7), json!(2)], &mut context), Ok(json!(3)));
+    }
+
+    #[test]
+    fn value_conversion() {
+        assert_eq!(to_val(&json!(-1), ValType::I32).and_then(|v| v.i32()), Some(-1));
+        assert_eq!(to_val(&json!(u32::MAX), ValType::I32).and_then(|v| v.i32()), Some(-1));
+        assert!(to_val(&json!(1u64 << 32), ValType::I32).is_none());
+        assert_eq!(to_val(&json!(1.5), ValType::F64).and_then(|v| v.f64()).map(f64::from), Some(1.5));
+        assert_eq!(from_val(&Val::I64(-1)), json!(-1));
+        assert_eq!(from_val(&Val::F64(2.5.into())), json!(2.5));
+        assert_eq!(from_val(&Val::F64(f64::NAN.into())), Value::Null);
+    }
+
+    #[test]
+    fn instruction_limit_stops_infinite_loops() {
+        let options = ExternalModuleOptions { instruction_limit: Some(10_000), ..Default::default() };
+        let mut instance = WasmInstance::new("spin", "spin.wasm", &spin(), &options).unwrap();
+        let mut context = context();
+        assert_eq!(instance.call("spin", &[], &mut context), Err("WASM代码超过指令数限制（10000 条）".to_string()));
+        // 每次调用重新计算限制
+        assert_eq!(instance.call("spin", &[], &mut context), Err("WASM代码超过指令数限制（10000 条）".to_string()));
+    }
+
+    #[test]
+    fn sandboxed_modules_get_default_limits() {
+        let options = ExternalModuleOptions { sandbox: Some(true), execution_timeout_ms: Some(50), ..Default::default() };
+        let mut instance = WasmInstance::new("spin", "spin.wasm", &spin(), &options).unwrap();
+        assert_eq!(instance.call("spin", &[], &mut context()), Err("WASM代码执行超时（限制为 50 毫秒）".to_string()));
+
+        // 沙箱默认限制 64MB 内存
+        let large = assemble(&[], Some(2048), &[]);
+        let options = ExternalModuleOptions { sandbox: Some(true), ..Default::default() };
+        assert!(WasmInstance::new("large", "large.wasm", &large, &options).is_err());
+        let options = ExternalModuleOptions { sandbox: Some(false), ..Default::default() };
+        assert!(WasmInstance::new("large", "large.wasm", &large, &options).is_ok());
+    }
+
+    #[test]
+    fn oversized_tables_are_rejected() {
+        let huge = b"\0asm\x01\0\0\0\x04\x08\x01\x70\x00\xff\xff\xff\xff\x0f";
+        // 解码时或实例化时拒绝，都不能先分配再失败
+        let rejected = match wasmi::Module::new(engine(), &huge[..]) {
+            Ok(module) => WasmInstance::new("huge", "huge.wasm", &module, &ExternalModuleOptions::default()).is_err(),
+            Err(_) => true,
+        };
+        assert!(rejected);
+    }
+
+    #[test]
+    fn only_wasi_imports_are_allowed() {
+        // (import "env" "f" (func))
+        let bytes = b"\0asm\x01\0\0\0\x01\x04\x01\x60\x00\x00\x02\x09\x01\x03env\x01f\x00\x00";
+        let module = wasmi::Module::new(engine(), &bytes[..]).unwrap();
+        let error = WasmInstance::new("env", "env.wasm", &module, &ExternalModuleOptions::default()).err().unwrap();
+        assert!(error.to_string().contains("不支持导入 env.f"));
+    }
+}
@@ -0,0 +1,449 @@
+// WASM模块的WASI（wasi_snapshot_preview1）宿主函数
+//
+// 标准输入输出、时钟、随机数和命令行参数始终可用，时钟和随机数经过录制/回放。环境变量只包含
+// 模块选项env_vars中的变量。文件系统只在allow_filesystem时可用：项目目录被预打开为"."，
+// 路径不能通过..、绝对路径或符号链接离开这个目录。未实现的WASI函数返回ENOSYS。
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use rand::RngCore;
+use serde_json::{json, Value};
+use crate::interpreter::context::Context;
+use crate::interpreter::trace::{self, TraceKind};
+
+pub const WASI_MODULE: &str = "wasi_snapshot_preview1";
+
+// WASI错误码
+const SUCCESS: u32 = 0;
+const EACCES: u32 = 2;
+const EBADF: u32 = 8;
+const EEXIST: u32 = 20;
+const EFAULT: u32 = 21;
+const EINVAL: u32 = 28;
+const EIO: u32 = 29;
+const EISDIR: u32 = 31;
+const ENOENT: u32 = 44;
+const ENOSYS: u32 = 52;
+const ENOTDIR: u32 = 54;
+const ENOTEMPTY: u32 = 55;
+const ESPIPE: u32 = 70;
+const ENOTCAPABLE: u32 = 76;
+
+// 文件类型
+const FILETYPE_CHARACTER_DEVICE: u8 = 2;
+const FILETYPE_DIRECTORY: u8 = 3;
+const FILETYPE_REGULAR_FILE: u8 = 4;
+
+// path_open的标志和权限位
+const OFLAGS_CREAT: u64 = 1;
+const OFLAGS_DIRECTORY: u64 = 2;
+const OFLAGS_EXCL: u64 = 4;
+const OFLAGS_TRUNC: u64 = 8;
+const FDFLAGS_APPEND: u64 = 1;
+const RIGHTS_FD_READ: u64 = 1 << 1;
+const RIGHTS_FD_WRITE: u64 = 1 << 6;
+
+// 预打开的项目目录的文件描述符，0-2是标准输入输出
+const PREOPEN_FD: u32 = 3;
+
+enum Handle {
+    File(File),
+    Dir(PathBuf),
+}
+
+/// 一个WASM模块实例的WASI状态
+pub struct Wasi {
+    args: Vec<String>,
+    env: Vec<String>,
+    // 预打开的目录（已规范化），不允许访问文件系统时为None
+    root: Option<PathBuf>,
+    handles: HashMap<u32, Handle>,
+    next_fd: u32,
+}
+
+/// 宿主函数访问的WASM线性内存，越界时返回错误
+pub struct Memory<'a>(pub &'a mut [u8]);
+
+impl Memory<'_> {
+    fn range(&self, address: u32, len: u32) -> Result<std::ops::Range<usize>, String> {
+        let start = address as usize;
+        start.checked_add(len as usize)
+            .filter(|end| *end <= self.0.len())
+            .map(|end| start..end)
+            .ok_or_else(|| format!("内存访问越界（地址 {}，长度 {}，内存大小 {}）", address, len, self.0.len()))
+    }
+
+    pub fn read(&self, address: u32, len: u32) -> Result<&[u8], String> {
+        let range = self.range(address, len)?;
+        Ok(&self.0[range])
+    }
+
+    pub fn write(&mut self, address: u32, bytes: &[u8]) -> Result<(), String> {
+        let range = self.range(address, bytes.len() as u32)?;
+        self.0[range].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn read_u32(&self, address: u32) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.read(address, 4)?.try_into().unwrap()))
+    }
+
+    fn write_u32(&mut self, address: u32, value: u32) -> Result<(), String> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, address: u32, value: u64) -> Result<(), String> {
+        self.write(address, &value.to_le_bytes())
+    }
+}
+
+impl Wasi {
+    pub fn new(name: &str, env_vars: &HashMap<String, String>, root: Option<PathBuf>) -> Self {
+        let mut env: Vec<String> = env_vars.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        env.sort();
+        let root = root.and_then(|root| root.canonicalize().ok());
+        let mut handles = HashMap::new();
+        if let Some(root) = &root {
+            handles.insert(PREOPEN_FD, Handle::Dir(root.clone()));
+        }
+        Wasi { args: vec![name.to_string()], env, root, handles, next_fd: PREOPEN_FD + 1 }
+    }
+
+    /// 在调用期间使用的宿主，有context时标准输出写入JiLang的输出、随机数使用JiLang的随机数生成器
+    pub fn host<'a>(&'a mut self, context: Option<&'a mut Context>) -> WasiHost<'a> {
+        WasiHost { wasi: self, context }
+    }
+}
+
+pub struct WasiHost<'a> {
+    wasi: &'a mut Wasi,
+    context: Option<&'a mut Context>,
+}
+
+impl WasiHost<'_> {
+    /// 调用名为name的WASI函数，参数是原始位，返回WASI错误码；proc_exit返回错误结束执行
+    pub fn call(&mut self, name: &str, memory: &mut Memory, args: &[u64]) -> Result<u32, String> {
+        let arg = |i: usize| args.get(i).copied().unwrap_or(0);
+        if name == "proc_exit" {
+            return Err(format!("WASM模块调用了proc_exit({})", arg(0) as u32 as i32));
+        }
+        Ok(match self.dispatch(name, memory, &arg) {
+            Ok(()) => SUCCESS,
+            Err(errno) => errno,
+        })
+    }
+}
+
+// 内存访问越界时返回EFAULT
+fn fault<T>(result: Result<T, String>) -> Result<T, u32> {
+    result.map_err(|_| EFAULT)
+}
+
+fn io_errno(error: std::io::Error) -> u32 {
+    match error.kind() {
+        ErrorKind::NotFound => ENOENT,
+        ErrorKind::PermissionDenied => EACCES,
+        ErrorKind::AlreadyExists => EEXIST,
+        ErrorKind::DirectoryNotEmpty => ENOTEMPTY,
+        ErrorKind::NotADirectory => ENOTDIR,
+        ErrorKind::IsADirectory => EISDIR,
+        ErrorKind::InvalidInput => EINVAL,
+        _ => EIO,
+    }
+}
+
+fn nanos(time: std::io::Result<SystemTime>) -> u64 {
+    time.ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
+
+impl WasiHost<'_> {
+    fn dispatch(&mut self, name: &str, memory: &mut Memory, arg: &dyn Fn(usize) -> u64) -> Result<(), u32> {
+        let u32_arg = |i: usize| arg(i) as u32;
+        match name {
+            "args_sizes_get" => strings_sizes(memory, &self.wasi.args, u32_arg(0), u32_arg(1)),
+            "args_get" => strings_get(memory, &self.wasi.args, u32_arg(0), u32_arg(1)),
+            "environ_sizes_get" => strings_sizes(memory, &self.wasi.env, u32_arg(0), u32_arg(1)),
+            "environ_get" => strings_get(memory, &self.wasi.env, u32_arg(0), u32_arg(1)),
+            "clock_res_get" => fault(memory.write_u64(u32_arg(1), 1000)),
+            "clock_time_get" => {
+                let time = trace::capture(TraceKind::Time, "wasi", || json!(nanos(Ok(SystemTime::now()))));
+                fault(memory.write_u64(u32_arg(2), time.as_u64().unwrap_or(0)))
+            }
+            "random_get" => {
+                let len = u32_arg(1) as usize;
+                let context = self.context.as_deref_mut();
+                let bytes = trace::capture(TraceKind::Random, "wasi", || {
+                    let mut bytes = vec![0u8; len];
+                    match context {
+                        Some(context) => context.with_rng(|rng| rng.fill_bytes(&mut bytes)),
+                        None => rand::thread_rng().fill_bytes(&mut bytes),
+                    }
+                    Value::Array(bytes.into_iter().map(Value::from).collect())
+                });
+                let bytes: Vec<u8> = bytes.as_array().map(|items| items.iter().map(|b| b.as_u64().unwrap_or(0) as u8).collect()).unwrap_or_default();
+                fault(memory.write(u32_arg(0), &bytes))
+            }
+            "sched_yield" => Ok(()),
+            "fd_write" => {
+                let data = gather(memory, u32_arg(1), u32_arg(2))?;
+                match u32_arg(0) {
+                    1 => {
+                        let text = String::from_utf8_lossy(&data);
+                        match self.context.as_deref_mut() {
+                            Some(context) => context.write_output(&text),
+                            None => print!("{}", text),
+                        }
+                    }
+                    2 => eprint!("{}", String::from_utf8_lossy(&data)),
+                    fd => match self.wasi.handles.get_mut(&fd) {
+                        Some(Handle::File(file)) => file.write_all(&data).map_err(io_errno)?,
+                        Some(Handle::Dir(_)) => return Err(EISDIR),
+                        None => return Err(EBADF),
+                    },
+                }
+                fault(memory.write_u32(u32_arg(3), data.len() as u32))
+            }
+            "fd_read" => {
+                let (iovs, count) = (u32_arg(1), u32_arg(2));
+                // 一次最多读取1MiB，读取的字节数可以少于请求的
+                let mut buffer = vec![0u8; iovec_total(memory, iovs, count)?.min(1 << 20)];
+                let read = match u32_arg(0) {
+                    0 => std::io::stdin().read(&mut buffer).map_err(io_errno)?,
+                    fd => match self.wasi.handles.get_mut(&fd) {
+                        Some(Handle::File(file)) => file.read(&mut buffer).map_err(io_errno)?,
+                        Some(Handle::Dir(_)) => return Err(EISDIR),
+                        None => return Err(EBADF),
+                    },
+                };
+                scatter(memory, iovs, count, &buffer[..read])?;
+                fault(memory.write_u32(u32_arg(3), read as u32))
+            }
+            "fd_close" => match u32_arg(0) {
+                0..=2 => Ok(()),
+                fd => self.wasi.handles.remove(&fd).map(|_| ()).ok_or(EBADF),
+            },
+            "fd_seek" | "fd_tell" => {
+                let (position, result) = if name == "fd_seek" {
+                    let offset = arg(1) as i64;
+                    let position = match arg(2) as u8 {
+                        0 => SeekFrom::Start(offset.max(0) as u64),
+                        1 => SeekFrom::Current(offset),
+                        2 => SeekFrom::End(offset),
+                        _ => return Err(EINVAL),
+                    };
+                    (position, u32_arg(3))
+                } else {
+                    (SeekFrom::Current(0), u32_arg(1))
+                };
+                match self.wasi.handles.get_mut(&u32_arg(0)) {
+                    Some(Handle::File(file)) => {
+                        let offset = file.seek(position).map_err(io_errno)?;
+                        fault(memory.write_u64(result, offset))
+                    }
+                    Some(Handle::Dir(_)) => Err(EISDIR),
+                    None if u32_arg(0) <= 2 => Err(ESPIPE),
+                    None => Err(EBADF),
+                }
+            }
+            "fd_fdstat_get" => {
+                let filetype = match u32_arg(0) {
+                    0..=2 => FILETYPE_CHARACTER_DEVICE,
+                    fd => match self.wasi.handles.get(&fd) {
+                        Some(Handle::File(_)) => FILETYPE_REGULAR_FILE,
+                        Some(Handle::Dir(_)) => FILETYPE_DIRECTORY,
+                        None => return Err(EBADF),
+                    },
+                };
+                let ptr = u32_arg(1);
+                fault(memory.write(ptr, &[0; 24]))?;
+                fault(memory.write(ptr, &[filetype]))?;
+                fault(memory.write_u64(ptr + 8, u64::MAX))?;
+                fault(memory.write_u64(ptr + 16, u64::MAX))
+            }
+            "fd_fdstat_set_flags" | "fd_sync" | "fd_datasync" | "fd_advise" => Ok(()),
+            "fd_prestat_get" => {
+                if u32_arg(0) != PREOPEN_FD || self.wasi.root.is_none() {
+                    return Err(EBADF);
+                }
+                // 标签0表示目录，名称为"."
+                fault(memory.write_u32(u32_arg(1), 0))?;
+                fault(memory.write_u32(u32_arg(1) + 4, 1))
+            }
+            "fd_prestat_dir_name" => {
+                if u32_arg(0) != PREOPEN_FD || self.wasi.root.is_none() {
+                    return Err(EBADF);
+                }
+                fault(memory.write(u32_arg(1), b"."))
+            }
+            "fd_filestat_get" => {
+                let stat = match u32_arg(0) {
+                    0..=2 => None,
+                    fd => match self.wasi.handles.get(&fd) {
+                        Some(Handle::File(file)) => Some(file.metadata().map_err(io_errno)?),
+                        Some(Handle::Dir(path)) => Some(fs::metadata(path).map_err(io_errno)?),
+                        None => return Err(EBADF),
+                    },
+                };
+                write_filestat(memory, u32_arg(1), stat.as_ref())
+            }
+            "path_filestat_get" => {
+                let path = self.path(memory, u32_arg(0), u32_arg(2), u32_arg(3))?;
+                let stat = fs::metadata(path).map_err(io_errno)?;
+                write_filestat(memory, u32_arg(4), Some(&stat))
+            }
+            "path_open" => {
+                let path = self.path(memory, u32_arg(0), u32_arg(2), u32_arg(3))?;
+                let (oflags, rights, fdflags) = (arg(4), arg(5), arg(7));
+                let write = rights & RIGHTS_FD_WRITE != 0 || oflags & (OFLAGS_CREAT | OFLAGS_TRUNC) != 0;
+                let handle = if path.is_dir() && !write {
+                    Handle::Dir(path)
+                } else if oflags & OFLAGS_DIRECTORY != 0 {
+                    return Err(ENOTDIR);
+                } else {
+                    let mut options = OpenOptions::new();
+                    options
+                        .read(rights & RIGHTS_FD_READ != 0 || !write)
+                        .write(write && fdflags & FDFLAGS_APPEND == 0)
+                        .append(fdflags & FDFLAGS_APPEND != 0)
+                        .create(oflags & OFLAGS_CREAT != 0)
+                        .create_new(oflags & OFLAGS_CREAT != 0 && oflags & OFLAGS_EXCL != 0)
+                        .truncate(oflags & OFLAGS_TRUNC != 0);
+                    // 检查路径之后文件可能被替换为符号链接，打开时同样不跟随
+                    #[cfg(unix)]
+                    std::os::unix::fs::OpenOptionsExt::custom_flags(&mut options, libc::O_NOFOLLOW);
+                    let file = options.open(&path).map_err(io_errno)?;
+                    Handle::File(file)
+                };
+                let fd = self.wasi.next_fd;
+                self.wasi.next_fd += 1;
+                self.wasi.handles.insert(fd, handle);
+                fault(memory.write_u32(u32_arg(8), fd))
+            }
+            "path_create_directory" => fs::create_dir(self.path(memory, u32_arg(0), u32_arg(1), u32_arg(2))?).map_err(io_errno),
+            "path_remove_directory" => fs::remove_dir(self.path(memory, u32_arg(0), u32_arg(1), u32_arg(2))?).map_err(io_errno),
+            "path_unlink_file" => fs::remove_file(self.path(memory, u32_arg(0), u32_arg(1), u32_arg(2))?).map_err(io_errno),
+            "path_rename" => {
+                let from = self.path(memory, u32_arg(0), u32_arg(1), u32_arg(2))?;
+                let to = self.path(memory, u32_arg(3), u32_arg(4), u32_arg(5))?;
+                fs::rename(from, to).map_err(io_errno)
+            }
+            "fd_readdir" => {
+                let Some(Handle::Dir(dir)) = self.wasi.handles.get(&u32_arg(0)) else {
+                    return Err(ENOTDIR);
+                };
+                let mut names: Vec<String> = fs::read_dir(dir).map_err(io_errno)?
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect();
+                names.sort();
+                names.splice(0..0, [".".to_string(), "..".to_string()]);
+                let (buf, len) = (u32_arg(1), u32_arg(2) as usize);
+                // 每项是24字节的dirent（下一项的cookie、inode、名称长度、类型）加名称，放不下时截断
+                let mut bytes = Vec::new();
+                for (index, name) in names.iter().enumerate().skip(arg(3) as usize) {
+                    if bytes.len() >= len {
+                        break;
+                    }
+                    let filetype = if index < 2 || dir.join(name).is_dir() { FILETYPE_DIRECTORY } else { FILETYPE_REGULAR_FILE };
+                    bytes.extend_from_slice(&(index as u64 + 1).to_le_bytes());
+                    bytes.extend_from_slice(&0u64.to_le_bytes());
+                    bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                    bytes.extend_from_slice(&[filetype, 0, 0, 0]);
+                    bytes.extend_from_slice(name.as_bytes());
+                }
+                bytes.truncate(len);
+                fault(memory.write(buf, &bytes))?;
+                fault(memory.write_u32(u32_arg(4), bytes.len() as u32))
+            }
+            _ => Err(ENOSYS),
+        }
+    }
+
+    // 解析相对于目录fd的路径，不能离开预打开的目录
+    fn path(&self, memory: &Memory, fd: u32, ptr: u32, len: u32) -> Result<PathBuf, u32> {
+        let (Some(root), Some(Handle::Dir(dir))) = (&self.wasi.root, self.wasi.handles.get(&fd)) else {
+            return Err(if self.wasi.root.is_none() { ENOTCAPABLE } else { EBADF });
+        };
+        let path = String::from_utf8(fault(memory.read(ptr, len))?.to_vec()).map_err(|_| EINVAL)?;
+        let mut resolved = dir.clone();
+        for component in Path::new(&path).components() {
+            match component {
+                Component::Normal(part) => resolved.push(part),
+                Component::CurDir => {}
+                _ => return Err(ENOTCAPABLE),
+            }
+            // 不跟随符号链接：指向目录外（包括目标不存在）的链接都可能离开预打开的目录
+            if fs::symlink_metadata(&resolved).map(|meta| meta.file_type().is_symlink()).unwrap_or(false) {
+                return Err(ENOTCAPABLE);
+            }
+        }
+        if !resolved.starts_with(root) {
+            return Err(ENOTCAPABLE);
+        }
+        Ok(resolved)
+    }
+}
+
+fn strings_sizes(memory: &mut Memory, strings: &[String], count_ptr: u32, size_ptr: u32) -> Result<(), u32> {
+    let size: usize = strings.iter().map(|s| s.len() + 1).sum();
+    fault(memory.write_u32(count_ptr, strings.len() as u32))?;
+    fault(memory.write_u32(size_ptr, size as u32))
+}
+
+// 写入字符串指针数组和以\0结尾的字符串
+fn strings_get(memory: &mut Memory, strings: &[String], pointers: u32, buffer: u32) -> Result<(), u32> {
+    let mut offset = buffer;
+    for (i, string) in strings.iter().enumerate() {
+        fault(memory.write_u32(pointers + 4 * i as u32, offset))?;
+        fault(memory.write(offset, string.as_bytes()))?;
+        fault(memory.write(offset + string.len() as u32, &[0]))?;
+        offset += string.len() as u32 + 1;
+    }
+    Ok(())
+}
+
+// iovec数组中每项是 (指针, 长度)
+fn iovecs(memory: &Memory, iovs: u32, count: u32) -> Result<Vec<(u32, u32)>, u32> {
+    (0..count).map(|i| {
+        let entry = iovs + 8 * i;
+        Ok((fault(memory.read_u32(entry))?, fault(memory.read_u32(entry + 4))?))
+    }).collect()
+}
+
+fn iovec_total(memory: &Memory, iovs: u32, count: u32) -> Result<usize, u32> {
+    Ok(iovecs(memory, iovs, count)?.iter().map(|(_, len)| *len as usize).sum())
+}
+
+fn gather(memory: &Memory, iovs: u32, count: u32) -> Result<Vec<u8>, u32> {
+    let mut data = Vec::new();
+    for (ptr, len) in iovecs(memory, iovs, count)? {
+        data.extend_from_slice(fault(memory.read(ptr, len))?);
+    }
+    Ok(data)
+}
+
+fn scatter(memory: &mut Memory, iovs: u32, count: u32, mut data: &[u8]) -> Result<(), u32> {
+    for (ptr, len) in iovecs(memory, iovs, count)? {
+        let take = data.len().min(len as usize);
+        fault(memory.write(ptr, &data[..take]))?;
+        data = &data[take..];
+    }
+    Ok(())
+}
+
+// filestat：设备、inode、类型、链接数、大小和三个时间戳，共64字节
+fn write_filestat(memory: &mut Memory, ptr: u32, stat: Option<&fs::Metadata>) -> Result<(), u32> {
+    fault(memory.write(ptr, &[0; 64]))?;
+    let Some(stat) = stat else {
+        return fault(memory.write(ptr + 16, &[FILETYPE_CHARACTER_DEVICE]));
+    };
+    let filetype = if stat.is_dir() { FILETYPE_DIRECTORY } else { FILETYPE_REGULAR_FILE };
+    fault(memory.write(ptr + 16, &[filetype]))?;
+    fault(memory.write_u64(ptr + 24, 1))?;
+    fault(memory.write_u64(ptr + 32, stat.len()))?;
+    fault(memory.write_u64(ptr + 40, nanos(stat.accessed())))?;
+    fault(memory.write_u64(ptr + 48, nanos(stat.modified())))?;
+    fault(memory.write_u64(ptr + 56, nanos(stat.created().or_else(|_| stat.modified()))))
+}
@@ -0,0 +1,603 @@
+// JiLang程序和模块的JSON Schema
+//
+// 描述程序的整体结构（include、const、program、module_meta）和每个内置语句的参数格式，
+// 可以用包含模块的函数元数据和程序中的用户函数扩展。
+// 同时提供一个只支持本Schema用到的关键字的简单校验器，用于运行前校验程序。
+use serde_json::{json, Map, Value};
+use crate::interpreter::source_map::escape_segment;
+use crate::modules::{builtin_metadata, get_registry};
+use crate::modules::external_module::FunctionMetadata;
+
+const SCHEMA_ID: &str = "https://github.com/HelloAIXIAOJI/JiLang/schema/program.json";
+
+// 条件表达式支持的运算符
+const CONDITION_OPS: &[&str] = &["eq", "neq", "gt", "lt", "gte", "lte", "and", "or"];
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/definitions/{}", name) })
+}
+
+// 数组形式的参数：至少min_items个元素
+fn positional(description: &str, min_items: usize) -> Value {
+    json!({ "description": description, "type": "array", "minItems": min_items })
+}
+
+// 每个内置语句的参数格式
+fn builtin_statement_schemas() -> Vec<(&'static str, Value)> {
+    let output = json!({ "type": "string", "description": "保存结果的变量名" });
+    vec![
+        ("var", json!({
+            "description": "定义或修改变量，键为变量名",
+            "type": "object",
+        })),
+        ("echo", json!({
+            "description": "输出文本，数组元素依次输出",
+            "type": ["array", "object"],
+            "properties": { "output": output },
+        })),
+        ("concat", json!({
+            "description": "拼接字符串",
+            "type": ["array", "object"],
+            "properties": {
+                "target": { "type": "string" },
+                "parts": { "type": "array" },
+                "output": output,
+            },
+        })),
+        ("comment", json!({
+            "description": "注释，不执行任何操作",
+            "type": ["string", "array", "object"],
+        })),
+        ("return", json!({
+            "description": "从函数返回值",
+        })),
+        ("if", json!({
+            "description": "条件分支",
+            "type": "object",
+            "properties": {
+                "condition": reference("condition"),
+                "then": reference("block"),
+                "else": reference("block"),
+            },
+            "required": ["condition", "then"],
+        })),
+        ("while", json!({
+            "description": "条件循环",
+            "type": "object",
+            "properties": {
+                "condition": reference("condition"),
+                "body": reference("block"),
+            },
+            "required": ["condition", "body"],
+        })),
+        ("for", json!({
+            "description": "遍历数组（in）或数值范围（range 或 from/to）",
+            "type": "object",
+            "properties": {
+                "var": { "type": "string" },
+                "in": {},
+                "range": { "type": "array", "minItems": 2, "maxItems": 2 },
+                "from": {},
+                "to": {},
+                "step": {},
+                "body": reference("block"),
+            },
+            "required": ["var", "body"],
+            "anyOf": [
+                { "required": ["in"] },
+                { "required": ["range"] },
+                { "required": ["from", "to"] },
+            ],
+        })),
+        ("exec", json!({
+            "description": "执行外部命令",
+            "type": "object",
+            "properties": {
+                "cmd": { "type": "string" },
+                "args": { "type": "array" },
+                "output": output,
+            },
+            "required": ["cmd"],
+        })),
+        ("lua", json!({
+            "description": "执行内联Lua代码块，代码中可以使用args和jilang表",
+            "type": "object",
+            "properties": {
+                "code": {
+                    "description": "Lua代码，也可以是按行分开的字符串数组",
+                    "type": ["string", "array"],
+                    "items": { "type": "string" },
+                },
+                "args": { "type": "object", "description": "传给代码块的参数，值可以是变量引用" },
+                "output": output,
+            },
+            "required": ["code"],
+        })),
+        ("switch", json!({
+            "description": "多分支选择",
+            "type": "object",
+            "properties": {
+                "value": {},
+                "cases": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "case": {},
+                            "do": reference("block"),
+                        },
+                        "required": ["case", "do"],
+                    },
+                },
+                "default": reference("block"),
+            },
+            "required": ["value", "cases"],
+        })),
+        ("try", json!({
+            "description": "捕获错误",
+            "type": "object",
+            "properties": {
+                "try": reference("block"),
+                "catch": reference("block"),
+                "error": { "type": "string", "description": "保存错误信息的变量名" },
+            },
+            "required": ["try", "catch"],
+        })),
+        ("get_property", json!({
+            "description": "按路径获取对象或数组中的值",
+            "type": "object",
+            "properties": {
+                "object": {},
+                "path": { "type": ["string", "array"] },
+                "output": output,
+            },
+            "required": ["object", "path"],
+        })),
+        ("array.create", json!({
+            "description": "创建数组：直接给出元素，或使用 size/initial",
+            "type": ["array", "object"],
+            "properties": {
+                "size": {},
+                "initial": {},
+                "output": output,
+            },
+        })),
+        ("array.push", positional("[数组, 元素...]", 2)),
+        ("array.pop", positional("[数组]", 1)),
+        ("array.get", positional("[数组, 索引]", 2)),
+        ("array.set", positional("[数组, 索引, 值]", 3)),
+        ("array.length", positional("[数组]", 1)),
+        ("array.slice", positional("[数组, 开始, 结束?]", 2)),
+        ("object.create", json!({
+            "description": "创建对象，键值对即对象内容",
+            "type": "object",
+        })),
+        ("object.get", positional("[对象, 键]", 2)),
+        ("object.set", positional("[对象, 键, 值]", 3)),
+        ("object.has", positional("[对象, 键]", 2)),
+        ("object.keys", positional("[对象]", 1)),
+        ("object.values", positional("[对象]", 1)),
+        ("object.delete", positional("[对象, 键]", 2)),
+        ("regex.match", positional("[模式, 文本]", 2)),
+        ("regex.test", positional("[模式, 文本]", 2)),
+        ("regex.replace", positional("[模式, 文本, 替换]", 3)),
+        ("regex.split", positional("[模式, 文本]", 2)),
+        ("assert.equal", positional("[实际值, 期望值, 说明?]", 2)),
+        ("assert.deep_equal", positional("[实际值, 期望值, 说明?]", 2)),
+        ("assert.not_equal", positional("[实际值, 不期望的值, 说明?]", 2)),
+        ("assert.true", positional("[条件或值, 说明?]", 1)),
+        ("assert.throws", json!({
+            "description": "断言语句块执行时出错",
+            "type": "object",
+            "properties": {
+                "do": reference("block"),
+                "error": { "type": "string", "description": "错误信息应包含的文本" },
+                "message": { "type": "string" },
+                "output": output,
+            },
+            "required": ["do"],
+        })),
+        ("assert.matches", positional("[文本, 正则表达式, 说明?]", 2)),
+        ("assert.approx", positional("[实际值, 期望值, 误差?, 说明?]", 2)),
+        ("assert.output", json!({
+            "description": "断言测试中捕获的输出：[期望输出, 说明?] 或 {equals|contains|matches, message}",
+            "type": ["array", "object"],
+            "properties": {
+                "equals": { "type": "string" },
+                "contains": { "type": "string" },
+                "matches": { "type": "string" },
+                "message": { "type": "string" },
+            },
+        })),
+    ]
+}
+
+/// 生成程序和模块的基础Schema
+pub fn program_schema() -> Value {
+    let mut statement_properties = Map::new();
+    let mut definitions = Map::new();
+    for (name, schema) in builtin_statement_schemas() {
+        let definition = format!("statement.{}", name);
+        statement_properties.insert(name.to_string(), reference(&definition));
+        definitions.insert(definition, schema);
+    }
+    statement_properties.insert("output".to_string(), json!({
+        "type": "string",
+        "description": "保存模块函数结果的变量名",
+    }));
+
+    definitions.insert("block".to_string(), json!({
+        "description": "语句列表",
+        "type": "array",
+        "items": reference("statement"),
+    }));
+    definitions.insert("statement".to_string(), json!({
+        "description": "语句：键为语句类型（内置语句、用户函数或 模块.函数），值为参数",
+        "type": "object",
+        "minProperties": 1,
+        "properties": statement_properties,
+    }));
+    definitions.insert("condition".to_string(), json!({
+        "description": "条件表达式",
+        "type": "object",
+        "properties": {
+            "left": {},
+            "op": { "enum": CONDITION_OPS },
+            "right": {},
+        },
+        "required": ["left", "op", "right"],
+    }));
+    definitions.insert("function".to_string(), json!({
+        "description": "用户函数定义",
+        "type": "object",
+        "properties": {
+            "params": { "type": "object", "description": "参数名到类型描述的映射" },
+            "body": reference("block"),
+        },
+        "required": ["body"],
+    }));
+    definitions.insert("module_meta".to_string(), json!({
+        "description": "模块元数据",
+        "type": "object",
+        "properties": {
+            "version": { "type": "string" },
+            "description": { "type": "string" },
+            "author": { "type": "string" },
+        },
+    }));
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "$id": SCHEMA_ID,
+        "title": "JiLang 程序",
+        "type": "object",
+        "properties": {
+            "include": {
+                "description": "要加载的模块",
+                "type": "array",
+                "items": {
+                    "oneOf": [
+                        { "type": "string" },
+                        {
+                            "type": "object",
+                            "required": ["module"],
+                            "properties": {
+                                "module": { "type": "string" },
+                                "options": {
+                                    "description": "外部模块的执行选项",
+                                    "type": "object",
+                                    "properties": {
+                                        "sandbox": { "type": "boolean" },
+                                        "allow_filesystem": { "type": "boolean" },
+                                        "allow_network": { "type": "boolean" },
+                                        "memory_limit_mb": { "type": "integer", "minimum": 0 },
+                                        "execution_timeout_ms": { "type": "integer", "minimum": 0 },
+                                        "instruction_limit": { "type": "integer", "minimum": 0 },
+                                        "env_vars": { "type": "object", "additionalProperties": { "type": "string" } },
+                                    },
+                                    "additionalProperties": false,
+                                },
+                            },
+                            "additionalProperties": false,
+                        },
+                    ],
+                },
+            },
+            "const": {
+                "description": "常量定义",
+                "type": "object",
+            },
+            "module_meta": reference("module_meta"),
+            "program": {
+                "description": "函数定义，main为程序入口",
+                "type": "object",
+                "properties": {
+                    "main": reference("function"),
+                },
+                "additionalProperties": reference("function"),
+            },
+            "tests": {
+                "description": "测试用例，由 jlang test 运行",
+                "type": "object",
+                "additionalProperties": reference("function"),
+            },
+        },
+        "required": ["program"],
+        "definitions": definitions,
+    })
+}
+
+// 模块函数的参数格式：按位置传参的数组，或带output的对象
+fn module_function_schema(module_name: &str, func: &FunctionMetadata) -> Value {
+    let required = func.parameters.iter().filter(|p| !p.optional).count();
+    let items: Vec<Value> = func.parameters.iter()
+        .map(|p| json!({ "description": format!("{}: {} ({})", p.name, p.description, p.type_description) }))
+        .collect();
+    let mut description = format!("{}.{}", module_name, func.name);
+    if !func.description.is_empty() {
+        description.push_str(&format!(" - {}", func.description));
+    }
+    let mut array_form = json!({ "type": "array", "minItems": required });
+    if !items.is_empty() {
+        array_form["items"] = Value::Array(items);
+    }
+    json!({
+        "description": description,
+        "anyOf": [array_form, { "not": { "type": "array" } }],
+    })
+}
+
+/// 获取模块的函数元数据，按函数名排序
+pub fn module_functions(name: &str) -> Option<Vec<FunctionMetadata>> {
+    let metadata = match builtin_metadata(name) {
+        Some(metadata) => metadata,
+        None => get_registry().load_module(name, None).ok()?.get_metadata().clone(),
+    };
+    let mut functions: Vec<FunctionMetadata> = metadata.functions.into_values().collect();
+    functions.sort_by(|a, b| a.name.cmp(&b.name));
+    Some(functions)
+}
+
+/// 用程序包含的模块和用户函数扩展Schema
+///
+/// 扩展后语句类型限定为已知名称，拼错的语句类型会被报告。
+pub fn extend_schema(schema: &mut Value, program: &Value) {
+    let mut names: Vec<String> = builtin_statement_schemas().iter().map(|(n, _)| n.to_string()).collect();
+    names.push("output".to_string());
+
+    let statement = &mut schema["definitions"]["statement"];
+
+    if let Some(includes) = program.get("include").and_then(|i| i.as_array()) {
+        for module_name in includes.iter().filter_map(crate::modules::include_name) {
+            for func in module_functions(module_name).unwrap_or_default() {
+                let key = format!("{}.{}", module_name, func.name);
+                statement["properties"][&key] = module_function_schema(module_name, &func);
+                names.push(key);
+            }
+        }
+    }
+
+    if let Some(functions) = program.get("program").and_then(|p| p.as_object()) {
+        for (func_name, func) in functions.iter().filter(|(k, _)| *k != "main") {
+            let params: Vec<String> = func.get("params")
+                .and_then(|p| p.as_object())
+                .map(|p| p.keys().cloned().collect())
+                .unwrap_or_default();
+            statement["properties"][func_name] = json!({
+                "description": format!("用户函数 {}({})", func_name, params.join(", ")),
+            });
+            names.push(func_name.clone());
+        }
+    }
+
+    statement["propertyNames"] = json!({ "enum": names });
+}
+
+/// 校验错误：出错位置（JSON Pointer）和原因
+pub struct ValidationError {
+    pub path: String,
+    pub message: String,
+}
+
+/// 按Schema校验值，返回所有错误
+pub fn validate(schema: &Value, value: &Value) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    validate_node(schema, schema, value, "", &mut errors);
+    errors
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema.get("$ref").and_then(|r| r.as_str()).and_then(|r| r.strip_prefix('#')) {
+        Some(pointer) => root.pointer(pointer).unwrap_or(&Value::Null),
+        None => schema,
+    }
+}
+
+fn validate_node(root: &Value, schema: &Value, value: &Value, path: &str, errors: &mut Vec<ValidationError>) {
+    let schema = resolve(root, schema);
+    let mut report = |message: String| errors.push(ValidationError { path: path.to_string(), message });
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| type_matches(t, value)) {
+            report(format!("类型应为 {}，实际为 {}", types.join(" 或 "), type_name(value)));
+            return;
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !options.contains(value) {
+            let names: Vec<String> = options.iter().map(|o| o.to_string()).collect();
+            report(format!("值 {} 不在允许的范围内: {}", value, names.join(", ")));
+        }
+    }
+
+    if let Some(not) = schema.get("not") {
+        let mut inner = Vec::new();
+        validate_node(root, not, value, path, &mut inner);
+        if inner.is_empty() {
+            report("值不应匹配被排除的格式".to_string());
+        }
+    }
+
+    if let Some(options) = schema.get("anyOf").and_then(|a| a.as_array()) {
+        let mut option_errors = Vec::new();
+        let matched = options.iter().any(|option| {
+            let mut inner = Vec::new();
+            validate_node(root, option, value, path, &mut inner);
+            let ok = inner.is_empty();
+            option_errors.push(inner);
+            ok
+        });
+        if !matched {
+            // 数组参数通常匹配第一种格式，直接报告其错误；否则列出每种格式的第一个问题
+            if value.is_array() || option_errors.len() == 1 {
+                errors.extend(option_errors.swap_remove(0));
+            } else {
+                let reasons: Vec<String> = option_errors.iter()
+                    .filter_map(|inner| inner.first().map(|e| e.message.clone()))
+                    .collect();
+                errors.push(ValidationError { path: path.to_string(), message: reasons.join("；或 ") });
+            }
+        }
+    }
+
+    match value {
+        Value::Object(map) => validate_object(root, schema, map, path, errors),
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(|m| m.as_u64()) {
+                if (items.len() as u64) < min {
+                    errors.push(ValidationError { path: path.to_string(), message: format!("至少需要 {} 个元素，实际为 {} 个", min, items.len()) });
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(|m| m.as_u64()) {
+                if (items.len() as u64) > max {
+                    errors.push(ValidationError { path: path.to_string(), message: format!("最多允许 {} 个元素，实际为 {} 个", max, items.len()) });
+                }
+            }
+            match schema.get("items") {
+                Some(Value::Array(tuple)) => {
+                    for (i, (item_schema, item)) in tuple.iter().zip(items).enumerate() {
+                        validate_node(root, item_schema, item, &format!("{}/{}", path, i), errors);
+                    }
+                },
+                Some(item_schema) => {
+                    for (i, item) in items.iter().enumerate() {
+                        validate_node(root, item_schema, item, &format!("{}/{}", path, i), errors);
+                    }
+                },
+                None => {},
+            }
+        },
+        _ => {},
+    }
+}
+
+fn validate_object(root: &Value, schema: &Value, map: &Map<String, Value>, path: &str, errors: &mut Vec<ValidationError>) {
+    if let Some(min) = schema.get("minProperties").and_then(|m| m.as_u64()) {
+        if (map.len() as u64) < min {
+            errors.push(ValidationError { path: path.to_string(), message: format!("至少需要 {} 个成员", min) });
+        }
+    }
+    if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+        for name in required.iter().filter_map(|r| r.as_str()) {
+            if !map.contains_key(name) {
+                errors.push(ValidationError { path: path.to_string(), message: format!("缺少必需的字段 '{}'", name) });
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(|p| p.as_object());
+    let allowed_names = schema.get("propertyNames").and_then(|p| p.get("enum")).and_then(|e| e.as_array());
+    for (key, item) in map {
+        let child = format!("{}/{}", path, escape_segment(key));
+        if let Some(allowed) = allowed_names {
+            if !allowed.iter().any(|a| a.as_str() == Some(key)) {
+                errors.push(ValidationError { path: child.clone(), message: format!("未知的名称 '{}'", key) });
+                continue;
+            }
+        }
+        match properties.and_then(|p| p.get(key)) {
+            Some(property_schema) => validate_node(root, property_schema, item, &child, errors),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    errors.push(ValidationError { path: child, message: format!("不允许的字段 '{}'", key) });
+                },
+                Some(additional) if additional.is_object() => validate_node(root, additional, item, &child, errors),
+                _ => {},
+            },
+        }
+    }
+}
+
+/// 运行前校验程序，在标准错误输出所有问题，校验通过时返回true
+pub fn validate_program(path: &str, source: &str, program: &Value) -> bool {
+    let mut schema = program_schema();
+    extend_schema(&mut schema, program);
+    let errors = validate(&schema, program);
+    if errors.is_empty() {
+        return true;
+    }
+
+    // 只有JSON源码可以把路径映射回行号
+    let source_map = match crate::source_format::SourceFormat::from_path(path) {
+        crate::source_format::SourceFormat::Json => Some(crate::interpreter::source_map::SourceMap::from_source(source)),
+        _ => None,
+    };
+    eprintln!("程序校验失败 ({}):", path);
+    for error in &errors {
+        let location = source_map.as_ref()
+            .and_then(|map| map.position(&error.path))
+            .map(|pos| format!("第 {} 行第 {} 列 ", pos.line, pos.column))
+            .unwrap_or_default();
+        let pointer = if error.path.is_empty() { "/" } else { &error.path };
+        eprintln!("  {}{}: {}", location, pointer, error.message);
+    }
+    false
+}
+
+/// 执行 `jlang schema [程序文件]`，将Schema输出到标准输出，返回进程退出码
+///
+/// 指定程序文件时，Schema会包含该程序包含的模块函数和用户函数。
+pub fn run_schema(filename: Option<&str>, extra_module_paths: Vec<String>) -> i32 {
+    let mut schema = program_schema();
+    if let Some(filename) = filename {
+        match crate::load_program(filename, extra_module_paths) {
+            Ok(loaded) => extend_schema(&mut schema, &loaded.program),
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        }
+    }
+    println!("{}", serde_json::to_string_pretty(&schema).unwrap_or_default());
+    0
+}
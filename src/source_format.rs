@@ -0,0 +1,263 @@
+// 程序源码格式：JSON（JSONC/JSON5）、YAML和TOML
+//
+// 三种格式都解析为同一个 serde_json::Value 程序结构，
+// 格式由文件扩展名决定：`.jl.yaml`/`.jl.yml` 为YAML，`.jl.toml` 为TOML，其余为JSON。
+use serde_json::Value;
+use crate::jsonc::{self, ParseError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl SourceFormat {
+    /// 根据文件路径判断源码格式
+    pub fn from_path(path: &str) -> Self {
+        let lower = path.to_lowercase();
+        if lower.ends_with(".yaml") || lower.ends_with(".yml") {
+            SourceFormat::Yaml
+        } else if lower.ends_with(".toml") {
+            SourceFormat::Toml
+        } else {
+            SourceFormat::Json
+        }
+    }
+
+    /// 根据格式名称获取格式（用于命令行参数）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "json" | "jsonc" | "json5" | "jl" => Some(SourceFormat::Json),
+            "yaml" | "yml" => Some(SourceFormat::Yaml),
+            "toml" => Some(SourceFormat::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SourceFormat::Json => "JSON",
+            SourceFormat::Yaml => "YAML",
+            SourceFormat::Toml => "TOML",
+        }
+    }
+}
+
+// JiLang源文件的扩展名
+const SOURCE_EXTENSIONS: &[&str] = &[".jl.yaml", ".jl.yml", ".jl.toml", ".jl", ".jil"];
+
+/// 检查文件名是否是JiLang源文件
+pub fn is_source_file(path: &str) -> bool {
+    strip_source_extension(path).is_some()
+}
+
+/// 去掉JiLang源文件的扩展名，例如 `util.jl.yaml` -> `util`
+pub fn strip_source_extension(file_name: &str) -> Option<&str> {
+    let lower = file_name.to_lowercase();
+    SOURCE_EXTENSIONS.iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map(|ext| &file_name[..file_name.len() - ext.len()])
+}
+
+/// 按文件扩展名解析源码
+pub fn parse_file(path: &str, source: &str) -> Result<Value, ParseError> {
+    parse(source, SourceFormat::from_path(path))
+}
+
+/// 按指定格式解析源码
+pub fn parse(source: &str, format: SourceFormat) -> Result<Value, ParseError> {
+    match format {
+        SourceFormat::Json => jsonc::parse(source),
+        SourceFormat::Yaml => parse_yaml(source),
+        SourceFormat::Toml => parse_toml(source),
+    }
+}
+
+fn parse_yaml(source: &str) -> Result<Value, ParseError> {
+    serde_yaml::from_str::<Value>(source).map_err(|e| {
+        let (line, column) = e.location()
+            .map(|location| (location.line(), location.column()))
+            .unwrap_or((1, 1));
+        ParseError::new(source, line, column, &e.to_string())
+    })
+}
+
+fn parse_toml(source: &str) -> Result<Value, ParseError> {
+    let value: toml::Value = toml::from_str(source).map_err(|e| {
+        let offset = e.span().map(|span| span.start).unwrap_or(0);
+        let (line, column) = line_column(source, offset);
+        ParseError::new(source, line, column, e.message().trim_end())
+    })?;
+    Ok(toml_to_json(value))
+}
+
+// 字节偏移量转换为行号和列号（均从1开始，列号按字符计算）
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => serde_json::Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(table.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect()),
+    }
+}
+
+// TOML没有null，遇到null时报告其路径
+fn json_to_toml(value: &Value, path: &str) -> Result<toml::Value, String> {
+    match value {
+        Value::Null => Err(format!("TOML 不支持 null 值 (位于 {})", if path.is_empty() { "/" } else { path })),
+        Value::Bool(b) => Ok(toml::Value::Boolean(*b)),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Ok(toml::Value::Integer(i))
+            } else if n.is_u64() {
+                Err(format!("整数 {} 超出 TOML 的取值范围 (位于 {})", n, path))
+            } else {
+                Ok(toml::Value::Float(n.as_f64().unwrap_or(0.0)))
+            }
+        },
+        Value::String(s) => Ok(toml::Value::String(s.clone())),
+        Value::Array(items) => items.iter().enumerate()
+            .map(|(i, item)| json_to_toml(item, &format!("{}/{}", path, i)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(toml::Value::Array),
+        Value::Object(map) => {
+            let mut table = toml::map::Map::new();
+            for (key, item) in map {
+                let child = format!("{}/{}", path, crate::interpreter::source_map::escape_segment(key));
+                table.insert(key.clone(), json_to_toml(item, &child)?);
+            }
+            Ok(toml::Value::Table(table))
+        },
+    }
+}
+
+/// 将程序结构输出为指定格式的源码
+pub fn serialize(value: &Value, format: SourceFormat) -> Result<String, String> {
+    match format {
+        SourceFormat::Json => {
+            let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+            crate::formatter::format_source(&text)
+        },
+        SourceFormat::Yaml => serde_yaml::to_string(value).map_err(|e| format!("YAML 输出错误: {}", e)),
+        SourceFormat::Toml => {
+            if !value.is_object() {
+                return Err("TOML 文档的顶层必须是对象".to_string());
+            }
+            let table = json_to_toml(value, "")?;
+            toml::to_string_pretty(&table).map_err(|e| format!("TOML 输出错误: {}", e))
+        },
+    }
+}
+
+/// 执行 `jlang convert 输入文件 [--to 格式] [-o 输出文件]`，返回进程退出码
+pub fn run_convert(args: &[String]) -> i32 {
+    let usage = "用法: jlang convert 输入文件 [--to json|yaml|toml] [-o 输出文件]";
+    let mut input = None;
+    let mut output: Option<String> = None;
+    let mut target = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--to" | "-o" | "--output" if i + 1 >= args.len() => {
+                eprintln!("错误: {} 需要提供参数", args[i]);
+                eprintln!("{}", usage);
+                return 2;
+            },
+            "--to" => {
+                i += 1;
+                match SourceFormat::from_name(&args[i]) {
+                    Some(format) => target = Some(format),
+                    None => {
+                        eprintln!("错误: 不支持的格式 '{}'，可用格式: json, yaml, toml", args[i]);
+                        return 2;
+                    }
+                }
+            },
+            "-o" | "--output" => {
+                i += 1;
+                output = Some(args[i].clone());
+            },
+            _ => input = Some(args[i].clone()),
+        }
+        i += 1;
+    }
+
+    let input = match input {
+        Some(input) => input,
+        None => {
+            eprintln!("错误: 请指定要转换的JiLang文件");
+            eprintln!("{}", usage);
+            return 2;
+        }
+    };
+    // 未指定--to时根据输出文件扩展名判断目标格式
+    let target = match (target, &output) {
+        (Some(format), _) => format,
+        (None, Some(path)) => SourceFormat::from_path(path),
+        (None, None) => {
+            eprintln!("错误: 请使用 --to 指定目标格式，或使用 -o 指定输出文件");
+            eprintln!("{}", usage);
+            return 2;
+        }
+    };
+
+    let source = match std::fs::read_to_string(&input) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("错误: 无法读取文件 '{}': {}", input, e);
+            return 2;
+        }
+    };
+    let program = match parse_file(&input, &source) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("错误: 无法解析 '{}': {}", input, e);
+            return 1;
+        }
+    };
+
+    let converted = match serialize(&program, target) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("错误: 无法转换为 {}: {}", target.name(), e);
+            return 1;
+        }
+    };
+
+    // 转换不能改变程序的含义
+    match parse(&converted, target) {
+        Ok(value) if value == program => {},
+        Ok(_) => {
+            eprintln!("错误: 转换为 {} 后程序结构发生了变化", target.name());
+            return 1;
+        },
+        Err(e) => {
+            eprintln!("错误: 转换结果无法解析: {}", e);
+            return 1;
+        }
+    }
+
+    match output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, converted) {
+                eprintln!("错误: 无法写入文件 '{}': {}", path, e);
+                return 2;
+            }
+            println!("已转换: {} -> {} ({})", input, path, target.name());
+        },
+        None => print!("{}", converted),
+    }
+    0
+}
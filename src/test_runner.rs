@@ -0,0 +1,232 @@
+// JiLang测试运行器（jlang test）
+//
+// 测试用例来自两处：任意源文件顶层的 `tests` 部分（测试名到函数定义的映射），
+// 以及 `*_test.jl` 文件中名称以 test 开头的函数。
+// 每个测试都重新加载程序并使用全新的Context运行，echo等输出会被捕获，
+// 可以用 assert.output 断言，测试失败时连同失败信息一起显示。
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use serde_json::Value;
+use crate::interpreter::Interpreter;
+use crate::interpreter::source_map::escape_segment;
+use crate::source_format;
+use crate::{load_program, LoadedProgram};
+
+// 遍历目录时跳过的目录
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+// 一个测试用例
+struct TestCase {
+    name: String,
+    // 函数定义在源文件中的JSON路径
+    path: String,
+}
+
+// 测试结果
+enum Outcome {
+    Passed,
+    Failed { message: String, output: String },
+}
+
+// 递归收集目录中的JiLang源文件
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(e) => {
+            eprintln!("警告: 无法读取目录 '{}': {}", dir.display(), e);
+            return;
+        }
+    };
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        if path.is_dir() {
+            if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+                collect_files(&path, files);
+            }
+        } else if source_format::is_source_file(&name) {
+            files.push(path);
+        }
+    }
+}
+
+// 是否是 *_test 测试文件
+fn is_test_file(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    source_format::strip_source_extension(&name).is_some_and(|stem| stem.ends_with("_test"))
+}
+
+// 查找文件中的测试用例
+fn find_tests(path: &Path, program: &Value) -> Vec<TestCase> {
+    let mut tests = Vec::new();
+    if let Some(section) = program.get("tests").and_then(|t| t.as_object()) {
+        for name in section.keys() {
+            tests.push(TestCase { name: name.clone(), path: format!("/tests/{}", escape_segment(name)) });
+        }
+    }
+    if is_test_file(path) {
+        if let Some(functions) = program.get("program").and_then(|p| p.as_object()) {
+            for name in functions.keys().filter(|name| name.starts_with("test")) {
+                tests.push(TestCase { name: name.clone(), path: format!("/program/{}", escape_segment(name)) });
+            }
+        }
+    }
+    tests
+}
+
+// 在全新的Context中运行一个测试，程序定义了setup函数时先运行它
+fn run_test(file: &str, test: &TestCase, extra_module_paths: &[String]) -> Outcome {
+    let failed = |message: String, output: &str| Outcome::Failed { message, output: output.to_string() };
+
+    let LoadedProgram { path, program, modules, .. } = match load_program(file, extra_module_paths.to_vec()) {
+        Ok(loaded) => loaded,
+        Err(e) => return failed(e, ""),
+    };
+    let func_def = match program.pointer(&test.path) {
+        Some(def) if def.get("body").is_some_and(|b| b.is_array()) => def.clone(),
+        _ => return failed(format!("测试 '{}' 必须是带 body 数组的函数定义", test.name), ""),
+    };
+    let setup = program.pointer("/program/setup").cloned();
+
+    let mut interpreter = match Interpreter::new(program, modules) {
+        Ok(interpreter) => interpreter,
+        Err(e) => return failed(format!("初始化错误: {}", e), ""),
+    };
+    interpreter.set_source_path(&path);
+    interpreter.start_output_capture();
+
+    if let Some(setup) = setup {
+        if let Err(e) = interpreter.run_function("setup", &setup, "/program/setup") {
+            let output = interpreter.captured_output().unwrap_or_default().to_string();
+            return failed(format!("setup 出错: {}", e), &output);
+        }
+    }
+    let result = interpreter.run_function(&test.name, &func_def, &test.path);
+    let output = interpreter.captured_output().unwrap_or_default().to_string();
+    match result {
+        Ok(_) => Outcome::Passed,
+        Err(e) => failed(e.to_string(), &output),
+    }
+}
+
+// 多行文本的每一行加上缩进
+fn indent(text: &str, prefix: &str) -> String {
+    text.lines().map(|line| format!("{}{}", prefix, line)).collect::<Vec<_>>().join("\n")
+}
+
+/// 执行 `jlang test [路径...] [--filter 文本] [--module-path 路径]`，返回进程退出码
+pub fn run_tests(args: &[String]) -> i32 {
+    let mut targets = Vec::new();
+    let mut filter: Option<String> = None;
+    let mut extra_module_paths = Vec::new();
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--filter" | "--module-path" if i + 1 >= args.len() => {
+                eprintln!("错误: {} 需要提供参数", args[i]);
+                eprintln!("用法: jlang test [文件或目录...] [--filter 文本] [--module-path 路径]");
+                return 2;
+            },
+            "--filter" => {
+                i += 1;
+                filter = Some(args[i].clone());
+            },
+            "--module-path" => {
+                i += 1;
+                extra_module_paths.push(args[i].clone());
+            },
+            _ => targets.push(args[i].clone()),
+        }
+        i += 1;
+    }
+    if targets.is_empty() {
+        targets.push(".".to_string());
+    }
+
+    // 明确指定的文件总是检查，目录中只检查有tests部分的文件和*_test文件
+    let mut files = Vec::new();
+    for target in &targets {
+        let path = Path::new(target);
+        if path.is_dir() {
+            collect_files(path, &mut files);
+        } else if path.exists() {
+            files.push(path.to_path_buf());
+        } else {
+            eprintln!("错误: 找不到 '{}'", target);
+            return 2;
+        }
+    }
+
+    let mut passed = 0;
+    let mut failures: Vec<(String, String)> = Vec::new();
+    let started = Instant::now();
+
+    for file in files {
+        let display = file.to_string_lossy().to_string();
+        let source = match std::fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("警告: 无法读取文件 '{}': {}", display, e);
+                continue;
+            }
+        };
+        let program = match source_format::parse_file(&display, &source) {
+            Ok(program) => program,
+            Err(e) => {
+                // 测试文件解析失败算作失败，其他文件可能根本不是程序
+                if is_test_file(&file) {
+                    println!("{}\n  ✗ 无法解析: {}", display, e);
+                    failures.push((display.clone(), "解析失败".to_string()));
+                }
+                continue;
+            }
+        };
+
+        let tests: Vec<TestCase> = find_tests(&file, &program).into_iter()
+            .filter(|test| filter.as_ref().is_none_or(|f| test.name.contains(f.as_str())))
+            .collect();
+        if tests.is_empty() {
+            continue;
+        }
+
+        println!("{}", display);
+        for test in &tests {
+            let test_started = Instant::now();
+            let outcome = run_test(&display, test, &extra_module_paths);
+            let elapsed = test_started.elapsed().as_secs_f64() * 1000.0;
+            match outcome {
+                Outcome::Passed => {
+                    println!("  ✓ {} ({:.1} ms)", test.name, elapsed);
+                    passed += 1;
+                },
+                Outcome::Failed { message, output } => {
+                    println!("  ✗ {} ({:.1} ms)", test.name, elapsed);
+                    println!("{}", indent(&message, "      "));
+                    if !output.is_empty() {
+                        println!("      捕获的输出:");
+                        println!("{}", indent(&output, "        "));
+                    }
+                    failures.push((display.clone(), test.name.clone()));
+                },
+            }
+        }
+    }
+
+    let total = passed + failures.len();
+    if total == 0 {
+        println!("未找到测试");
+        return 0;
+    }
+    println!();
+    if !failures.is_empty() {
+        println!("失败的测试:");
+        for (file, name) in &failures {
+            println!("  {} :: {}", file, name);
+        }
+    }
+    println!("结果: {} 通过, {} 失败 (共 {} 个测试, {:.1} ms)",
+        passed, failures.len(), total, started.elapsed().as_secs_f64() * 1000.0);
+
+    if failures.is_empty() { 0 } else { 1 }
+}